@@ -0,0 +1,173 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::style::{extract_decimal, extract_number, extract_string, PropertyValue};
+
+// Elements can be animated when their slide is shown: they start out faded, moved away or
+// scaled, and ease into their place over a short time. Rendered slides show every element
+// where its animation ends, so animations only play while presenting.
+
+/// How an animation speeds up and slows down over its duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "linear" => Easing::Linear,
+            "ease-in" => Easing::EaseIn,
+            "ease-in-out" => Easing::EaseInOut,
+            // `ease-out`, as other easings are reported when the style is parsed
+            _ => Easing::EaseOut,
+        }
+    }
+
+    /// How far along an animation is when a fraction `t` of its duration has passed.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+        }
+    }
+}
+
+/// An animation from the way an element is drawn when its slide is shown to the way it is
+/// drawn normally.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Animation {
+    pub opacity: f32,
+    /// Where the element starts, in pixels from its place.
+    pub offset: (f32, f32),
+    pub scale: f32,
+    pub delay: Duration,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+/// How an animated element is drawn at some moment of its animation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    pub opacity: f32,
+    pub offset: (f32, f32),
+    pub scale: f32,
+}
+
+impl Animation {
+    /// The animation that the `enter-` properties of a style ask for, if they ask for any.
+    pub fn from_style(style: &HashMap<String, PropertyValue>) -> Option<Self> {
+        let decimal = |property: &str| {
+            style
+                .contains_key(property)
+                .then(|| extract_decimal(style, property))
+        };
+        let number = |property: &str, default: u32| {
+            if style.contains_key(property) {
+                extract_number(style, property)
+            } else {
+                default
+            }
+        };
+
+        let opacity = decimal("enter-opacity");
+        let scale = decimal("enter-scale");
+        let from = style
+            .contains_key("enter-from")
+            .then(|| extract_string(style, "enter-from"));
+        if opacity.is_none() && scale.is_none() && from.is_none() {
+            return None;
+        }
+
+        let distance = number("enter-distance", 100) as f32;
+        // other sides are reported when the style is parsed
+        let offset = match from.as_deref() {
+            Some("left") => (-distance, 0.0),
+            Some("right") => (distance, 0.0),
+            Some("top") => (0.0, -distance),
+            Some("bottom") => (0.0, distance),
+            _ => (0.0, 0.0),
+        };
+        let easing = if style.contains_key("enter-easing") {
+            Easing::from_name(&extract_string(style, "enter-easing"))
+        } else {
+            Easing::EaseOut
+        };
+
+        Some(Animation {
+            opacity: opacity.unwrap_or(1.0).clamp(0.0, 1.0),
+            offset,
+            scale: scale.unwrap_or(1.0),
+            delay: Duration::from_millis(number("enter-delay", 0) as u64),
+            duration: Duration::from_millis(number("enter-duration", 500) as u64),
+            easing,
+        })
+    }
+
+    pub fn is_finished(&self, elapsed: Duration) -> bool {
+        elapsed >= self.delay + self.duration
+    }
+
+    /// How the element is drawn when `elapsed` has passed since its slide was shown.
+    pub fn frame_at(&self, elapsed: Duration) -> Frame {
+        let progress = if self.is_finished(elapsed) {
+            1.0
+        } else {
+            let running = elapsed.saturating_sub(self.delay);
+            self.easing
+                .apply(running.as_secs_f32() / self.duration.as_secs_f32())
+        };
+        let towards = |from: f32, to: f32| from + (to - from) * progress;
+
+        Frame {
+            opacity: towards(self.opacity, 1.0),
+            offset: (towards(self.offset.0, 0.0), towards(self.offset.1, 0.0)),
+            scale: towards(self.scale, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn animation_frames() {
+        let style = HashMap::from([
+            (String::from("enter-opacity"), PropertyValue::Number(0)),
+            (
+                String::from("enter-from"),
+                PropertyValue::String(String::from("bottom")),
+            ),
+            (
+                String::from("enter-easing"),
+                PropertyValue::String(String::from("linear")),
+            ),
+            (String::from("enter-delay"), PropertyValue::Number(100)),
+        ]);
+        let animation = Animation::from_style(&style).unwrap();
+
+        let start = animation.frame_at(Duration::ZERO);
+        assert_eq!(start.opacity, 0.0);
+        assert_eq!(start.offset, (0.0, 100.0));
+        assert_eq!(start.scale, 1.0);
+
+        // halfway through the animation, after the delay
+        let halfway = animation.frame_at(Duration::from_millis(350));
+        assert!((halfway.opacity - 0.5).abs() < 1e-3);
+        assert!((halfway.offset.1 - 50.0).abs() < 1e-3);
+
+        assert!(animation.is_finished(Duration::from_millis(600)));
+        let end = animation.frame_at(Duration::from_millis(600));
+        assert_eq!(end.opacity, 1.0);
+        assert_eq!(end.offset, (0.0, 0.0));
+
+        assert_eq!(Animation::from_style(&HashMap::new()), None);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    }
+}
@@ -1,16 +1,30 @@
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use strum::EnumIter;
 
+use crate::chart::{ChartData, PlotData};
 use crate::error::FoliumError;
-use crate::style::StyleMap;
+use crate::image;
+use crate::layout::SizeSpec;
+use crate::style::{Palette, PropertyValue, StyleMap};
 
 #[derive(Clone, Debug)]
 pub struct GlobalState {
     unassigned_id: RefCell<AbstractElementID>,
     pub slides: RefCell<Vec<Slide>>,
     elements: RefCell<Vec<AbstractElement>>,
+    definitions: RefCell<HashMap<String, AbstractElementID>>,
+    style_classes: RefCell<HashMap<String, HashMap<String, PropertyValue>>>,
+    deck: RefCell<DeckSettings>,
+    profiles: RefCell<Vec<String>>,
+    /// The variant of the presentation that is shown, like `dark`, if any.
+    variant: RefCell<Option<String>>,
+    palettes: RefCell<HashMap<String, Palette>>,
+    /// The factor by which every size in pixels is multiplied, see [`GlobalState::set_scale`].
+    scale: RefCell<f32>,
+    image_sizes: RefCell<HashMap<PathBuf, Option<(u32, u32)>>>,
 }
 
 impl GlobalState {
@@ -19,9 +33,27 @@ impl GlobalState {
             unassigned_id: RefCell::new(AbstractElementID(0)),
             slides: RefCell::new(Vec::new()),
             elements: RefCell::new(Vec::new()),
+            definitions: RefCell::new(HashMap::new()),
+            style_classes: RefCell::new(HashMap::new()),
+            deck: RefCell::new(DeckSettings::default()),
+            profiles: RefCell::new(Vec::new()),
+            variant: RefCell::new(None),
+            palettes: RefCell::new(HashMap::new()),
+            scale: RefCell::new(1.0),
+            image_sizes: RefCell::new(HashMap::new()),
         }
     }
 
+    /// The width and height of an image file, if it can be read. Only the header of the file is
+    /// looked at, once per file, so that layout knows the aspect ratio of images.
+    pub fn image_size(&self, path: &Path) -> Option<(u32, u32)> {
+        *self
+            .image_sizes
+            .borrow_mut()
+            .entry(path.to_path_buf())
+            .or_insert_with(|| image::image_size(path))
+    }
+
     pub fn push_slide(&self, slide: Slide) {
         let mut slides = self.slides.borrow_mut();
         slides.push(slide);
@@ -44,6 +76,84 @@ impl GlobalState {
         id
     }
 
+    /// Registers a top-level `def` so that it can later be referenced with `use(name)`.
+    pub fn define(&self, name: String, id: AbstractElementID) {
+        self.definitions.borrow_mut().insert(name, id);
+    }
+
+    pub fn get_definition(&self, name: &str) -> Option<AbstractElementID> {
+        self.definitions.borrow().get(name).copied()
+    }
+
+    /// Registers a top-level `style` block, whose properties can be applied to a target with `class: name`.
+    pub fn define_style_class(&self, name: String, properties: HashMap<String, PropertyValue>) {
+        self.style_classes.borrow_mut().insert(name, properties);
+    }
+
+    pub fn get_style_class(&self, name: &str) -> Option<HashMap<String, PropertyValue>> {
+        self.style_classes.borrow().get(name).cloned()
+    }
+
+    pub fn deck(&self) -> DeckSettings {
+        self.deck.borrow().clone()
+    }
+
+    pub fn set_deck(&self, deck: DeckSettings) {
+        *self.deck.borrow_mut() = deck;
+    }
+
+    /// Sets the build profiles that are active, which decide what `@if profile("name")` sections are included.
+    pub fn set_profiles(&self, profiles: Vec<String>) {
+        *self.profiles.borrow_mut() = profiles;
+    }
+
+    pub fn has_profile(&self, profile: &str) -> bool {
+        self.profiles.borrow().iter().any(|p| p == profile)
+    }
+
+    /// Sets the variant of the presentation that is shown, whose palette recolours the slides.
+    pub fn set_variant(&self, variant: Option<String>) {
+        *self.variant.borrow_mut() = variant;
+    }
+
+    /// Registers a top-level `variant` block, with the palette of that variant.
+    pub fn define_palette(&self, name: String, palette: Palette) {
+        self.palettes.borrow_mut().insert(name, palette);
+    }
+
+    /// The palette of the variant that is shown: the one the presentation defines for it, or
+    /// for `dark`, the builtin one. Without a variant, or with one that has no palette, the
+    /// colours are kept as they are.
+    pub fn palette(&self) -> Option<Palette> {
+        let variant = self.variant.borrow();
+        let variant = variant.as_deref()?;
+        match self.palettes.borrow().get(variant) {
+            Some(palette) => Some(palette.clone()),
+            None => (variant == "dark").then(Palette::dark),
+        }
+    }
+
+    /// Makes every slide that is loaded from now on larger by a factor, with everything on it:
+    /// the sizes in its styles and those given to elements like `place` and `sized`.
+    pub fn set_scale(&self, scale: f32) {
+        *self.scale.borrow_mut() = scale;
+    }
+
+    pub fn scale(&self) -> f32 {
+        *self.scale.borrow()
+    }
+
+    /// A size in pixels, at the scale that slides are loaded at.
+    pub fn scaled(&self, pixels: u32) -> u32 {
+        (pixels as f32 * self.scale()).round() as u32
+    }
+
+    /// The variant that is shown, if it has no palette.
+    pub fn unknown_variant(&self) -> Option<String> {
+        let variant = self.variant.borrow().clone()?;
+        self.palette().is_none().then_some(variant)
+    }
+
     /// Because the first value returned by this function is AbstractElementID(1),
     /// an AbstractElementID of 0 is ALWAYS invalid and is used for a dummy referent.
     fn generate_id(&self) -> AbstractElementID {
@@ -60,35 +170,92 @@ impl GlobalState {
             .cloned()
     }
 
+    pub fn all_elements(&self) -> Vec<AbstractElement> {
+        self.elements.borrow().clone()
+    }
+
     pub fn traverse(&self, id: AbstractElementID) -> Vec<AbstractElementID> {
         let elem = self
             .get_element_by_id(id)
             .unwrap_or_else(|| panic!("{id} is not present"));
         let all_children = match elem.data {
-            AbstractElementData::Row(children) | AbstractElementData::Col(children) => children
+            AbstractElementData::Row(children)
+            | AbstractElementData::Col(children)
+            | AbstractElementData::List(children)
+            | AbstractElementData::Grid(_, children)
+            | AbstractElementData::Stack(children)
+            | AbstractElementData::Flow(children) => children
                 .into_iter()
                 .flat_map(|child| self.traverse(child))
                 .collect(),
             AbstractElementData::Centre(child)
             | AbstractElementData::Padding(child)
-            | AbstractElementData::Sized(child) => self.traverse(child),
+            | AbstractElementData::Sized(_, child)
+            | AbstractElementData::Rect(Some(child))
+            | AbstractElementData::Ellipse(Some(child))
+            | AbstractElementData::Circle(Some(child))
+            | AbstractElementData::Rotate(_, child)
+            | AbstractElementData::Opacity(_, child)
+            | AbstractElementData::Frame(child)
+            | AbstractElementData::Shadow(child)
+            | AbstractElementData::Place(_, _, child) => self.traverse(child),
             AbstractElementData::Text(_)
             | AbstractElementData::Code(_)
             | AbstractElementData::Image(_)
+            | AbstractElementData::Math(_)
+            | AbstractElementData::Video(_)
+            | AbstractElementData::Rect(None)
+            | AbstractElementData::Ellipse(None)
+            | AbstractElementData::Circle(None)
+            | AbstractElementData::Line(..)
+            | AbstractElementData::Arrow(..)
+            | AbstractElementData::BarChart(_)
+            | AbstractElementData::LineChart(_)
+            | AbstractElementData::PieChart(_)
+            | AbstractElementData::Plot(_)
+            | AbstractElementData::Spacer(_)
+            | AbstractElementData::SlideNumber
+            | AbstractElementData::Progress
+            | AbstractElementData::Link(..)
+            | AbstractElementData::Icon(_)
+            | AbstractElementData::Heading(..)
+            | AbstractElementData::Clock
+            | AbstractElementData::Audio(_)
             | AbstractElementData::None => Vec::new(),
         };
 
         [[id].as_slice(), all_children.as_slice()].concat()
     }
 
+    /// All elements that can appear on a slide, including the deck's header and footer.
     pub fn get_slide_elements(&self, slide: &Slide) -> Vec<AbstractElement> {
         let slide_root_id = slide.content;
+        let deck = self.deck();
+        let header_and_footer = [deck.header, deck.footer]
+            .into_iter()
+            .flatten()
+            .flat_map(|id| self.traverse(id))
+            .collect::<Vec<_>>();
+
         self.traverse(slide_root_id)
             .iter()
+            .chain(slide.connectors.iter())
+            .chain(header_and_footer.iter())
             .filter_map(|id| self.get_element_by_id(*id))
             .collect()
     }
 
+    /// The level and text of every heading on a slide, in the order they appear in.
+    pub fn slide_headings(&self, slide: &Slide) -> Vec<(u8, String)> {
+        self.traverse(slide.content)
+            .iter()
+            .filter_map(|id| match self.get_element_by_id(*id)?.data() {
+                AbstractElementData::Heading(level, text) => Some((*level, text.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn number_of_slides(&self) -> usize {
         self.slides.borrow().len()
     }
@@ -106,6 +273,7 @@ impl std::fmt::Display for GlobalState {
             self.slides.borrow().len(),
             self.elements.borrow().len()
         )?;
+        writeln!(f, "Deck: {:?}", self.deck.borrow())?;
         writeln!(f, "Elements:")?;
         for elem in self.elements.borrow().iter() {
             writeln!(f, "    {elem:?}")?;
@@ -114,13 +282,31 @@ impl std::fmt::Display for GlobalState {
         for elem in self.slides.borrow().iter() {
             writeln!(f, "    {elem:?}")?;
         }
+        writeln!(f, "Slide metadata:")?;
+        for (idx, slide) in self.slides.borrow().iter().enumerate() {
+            if slide.metadata() != &SlideMetadata::default() {
+                writeln!(f, "    slide {}: {}", idx + 1, slide.metadata())?;
+            }
+        }
+        writeln!(f, "Outline:")?;
+        for (idx, slide) in self.slides.borrow().iter().enumerate() {
+            let headings = self.slide_headings(slide);
+            if !headings.is_empty() {
+                writeln!(f, "    slide {}:", idx + 1)?;
+            }
+            for (level, text) in headings {
+                writeln!(f, "{}{text}", "    ".repeat(level as usize + 1))?;
+            }
+        }
         Ok(())
     }
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum AbstractElementData {
-    Sized(AbstractElementID),
+    /// Content with a size of its own, which is given inline as in `sized(300, _, child)` or
+    /// else taken from the `size` style.
+    Sized(Option<SizeSpec>, AbstractElementID),
     Row(Vec<AbstractElementID>),
     Col(Vec<AbstractElementID>),
     Centre(AbstractElementID),
@@ -128,9 +314,68 @@ pub enum AbstractElementData {
     Text(String),
     Code(String),
     Image(PathBuf),
+    List(Vec<AbstractElementID>),
+    Math(String),
+    Video(PathBuf),
+    /// A sound that is played when presenting, which is not drawn.
+    Audio(PathBuf),
+    /// A rectangle, optionally with content on top of it.
+    Rect(Option<AbstractElementID>),
+    /// An ellipse filling the bounding box, optionally with content on top of it.
+    Ellipse(Option<AbstractElementID>),
+    /// A circle centred in the bounding box, optionally with content on top of it.
+    Circle(Option<AbstractElementID>),
+    /// Content with a border around it.
+    Frame(AbstractElementID),
+    /// Content with a soft shadow behind it.
+    Shadow(AbstractElementID),
+    /// Content that is drawn rotated clockwise by a number of degrees around its centre.
+    Rotate(u32, AbstractElementID),
+    /// Content that is drawn with its alpha multiplied by the given amount out of 255.
+    Opacity(u8, AbstractElementID),
+    /// Content that is pinned at a position on the slide, in pixels from its top left corner,
+    /// instead of taking part in the layout around it.
+    Place(u32, u32, AbstractElementID),
+    /// A connector between two endpoints, which is drawn after the rest of the slide.
+    Line(Endpoint, Endpoint),
+    Arrow(Endpoint, Endpoint),
+    BarChart(ChartData),
+    LineChart(ChartData),
+    PieChart(ChartData),
+    /// Points read from two columns of a CSV file.
+    Plot(PlotData),
+    /// A number of columns and the cells, which are filled row by row.
+    Grid(u32, Vec<AbstractElementID>),
+    /// Children that are laid out on top of each other, in the same area.
+    Stack(Vec<AbstractElementID>),
+    /// Children at their natural size, left to right, wrapping to a new line when one is full.
+    Flow(Vec<AbstractElementID>),
+    /// The number of the slide it is on, formatted with the `format` style.
+    SlideNumber,
+    /// A glyph from an icon font.
+    Icon(char),
+    /// A label and the URL it links to.
+    Link(String, String),
+    /// A bar that shows how far along the presentation the slide it is on is.
+    Progress,
+    /// Empty space, optionally of a fixed size in pixels.
+    Spacer(Option<u32>),
+    /// The current time, formatted with the `format` style.
+    Clock,
+    /// A heading of the given level, starting at 1 for the most important one.
+    Heading(u8, String),
     None,
 }
 
+/// One end of a line or an arrow.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Endpoint {
+    /// The edge of the element with this name.
+    Element(String),
+    /// A point on the slide, in pixels.
+    Point(u32, u32),
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, EnumIter)]
 pub enum ElementType {
     Sized,
@@ -141,6 +386,36 @@ pub enum ElementType {
     Text,
     Code,
     Image,
+    List,
+    Math,
+    Video,
+    Rect,
+    Ellipse,
+    Circle,
+    Line,
+    Arrow,
+    BarChart,
+    LineChart,
+    PieChart,
+    Plot,
+    Spacer,
+    Grid,
+    Stack,
+    SlideNumber,
+    Progress,
+    Link,
+    Icon,
+    H1,
+    H2,
+    H3,
+    Clock,
+    Audio,
+    Rotate,
+    Opacity,
+    Frame,
+    Shadow,
+    Place,
+    Flow,
     ElNone, // preferred naming over just None, which causes confusion with Option::None
 }
 
@@ -155,6 +430,36 @@ impl ElementType {
             ElementType::Text => "text",
             ElementType::Code => "code",
             ElementType::Image => "image",
+            ElementType::List => "olist",
+            ElementType::Math => "math",
+            ElementType::Video => "video",
+            ElementType::Rect => "rect",
+            ElementType::Ellipse => "ellipse",
+            ElementType::Circle => "circle",
+            ElementType::Line => "line",
+            ElementType::Arrow => "arrow",
+            ElementType::BarChart => "barchart",
+            ElementType::LineChart => "linechart",
+            ElementType::PieChart => "piechart",
+            ElementType::Plot => "plot",
+            ElementType::Spacer => "spacer",
+            ElementType::Grid => "grid",
+            ElementType::Stack => "stack",
+            ElementType::SlideNumber => "slidenumber",
+            ElementType::Progress => "progress",
+            ElementType::Link => "link",
+            ElementType::Icon => "icon",
+            ElementType::H1 => "h1",
+            ElementType::H2 => "h2",
+            ElementType::H3 => "h3",
+            ElementType::Clock => "clock",
+            ElementType::Audio => "audio",
+            ElementType::Rotate => "rotate",
+            ElementType::Opacity => "opacity",
+            ElementType::Frame => "frame",
+            ElementType::Shadow => "shadow",
+            ElementType::Place => "place",
+            ElementType::Flow => "flow",
             ElementType::ElNone => "none",
         }
     }
@@ -176,6 +481,36 @@ impl<'a> TryFrom<&'a str> for ElementType {
             "text" | "t" => Ok(ElementType::Text),
             "code" => Ok(ElementType::Code),
             "img" => Ok(ElementType::Image),
+            "olist" | "enum" => Ok(ElementType::List),
+            "math" => Ok(ElementType::Math),
+            "video" => Ok(ElementType::Video),
+            "rect" => Ok(ElementType::Rect),
+            "ellipse" => Ok(ElementType::Ellipse),
+            "circle" => Ok(ElementType::Circle),
+            "line" => Ok(ElementType::Line),
+            "arrow" => Ok(ElementType::Arrow),
+            "barchart" => Ok(ElementType::BarChart),
+            "linechart" => Ok(ElementType::LineChart),
+            "piechart" => Ok(ElementType::PieChart),
+            "plot" => Ok(ElementType::Plot),
+            "spacer" => Ok(ElementType::Spacer),
+            "grid" => Ok(ElementType::Grid),
+            "stack" => Ok(ElementType::Stack),
+            "slidenumber" => Ok(ElementType::SlideNumber),
+            "progress" => Ok(ElementType::Progress),
+            "link" => Ok(ElementType::Link),
+            "icon" => Ok(ElementType::Icon),
+            "h1" => Ok(ElementType::H1),
+            "h2" => Ok(ElementType::H2),
+            "h3" => Ok(ElementType::H3),
+            "clock" => Ok(ElementType::Clock),
+            "audio" => Ok(ElementType::Audio),
+            "rotate" => Ok(ElementType::Rotate),
+            "opacity" => Ok(ElementType::Opacity),
+            "frame" => Ok(ElementType::Frame),
+            "shadow" => Ok(ElementType::Shadow),
+            "place" => Ok(ElementType::Place),
+            "flow" => Ok(ElementType::Flow),
             "none" => Ok(ElementType::ElNone),
             "padding" => Ok(ElementType::Padding),
             "centre" => Ok(ElementType::Centre),
@@ -227,22 +562,118 @@ impl PartialEq for AbstractElement {
     }
 }
 
+/// Deck-wide settings, set with a `deck` block before the first slide.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeckSettings {
+    /// The size of the presentation window, and the default size of every slide.
+    pub width: u32,
+    pub height: u32,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Definitions that are placed at the top and the bottom of every slide.
+    pub header: Option<AbstractElementID>,
+    pub footer: Option<AbstractElementID>,
+    /// The default `scaling` of images.
+    pub image_scaling: String,
+    pub watermark: Option<Watermark>,
+}
+
+/// Text or an image that is drawn faded over every slide, like a mark that a presentation is
+/// confidential.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watermark {
+    pub mark: WatermarkMark,
+    /// The alpha that the mark is drawn with.
+    pub alpha: u8,
+    /// Where on the slide the mark is: `centre`, a side like `top` or a corner like
+    /// `bottom-right`.
+    pub position: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatermarkMark {
+    Text { text: String, size: u32 },
+    Image(PathBuf),
+}
+
+impl Default for DeckSettings {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            title: None,
+            author: None,
+            header: None,
+            footer: None,
+            image_scaling: String::from("linear"),
+            watermark: None,
+        }
+    }
+}
+
+/// Information about a slide that is not rendered, set with a `meta` block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SlideMetadata {
+    pub title: Option<String>,
+    pub id: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl std::fmt::Display for SlideMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "title: {}, id: {}, tags: [{}]",
+            self.title.as_deref().unwrap_or("-"),
+            self.id.as_deref().unwrap_or("-"),
+            self.tags.join(", ")
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Slide {
     id: AbstractElementID,
     content: AbstractElementID,
     styles: StyleMap,
+    metadata: SlideMetadata,
+    notes: Option<String>,
+    connectors: Vec<AbstractElementID>,
 }
 
 impl Slide {
-    pub fn new(global: &GlobalState, content: AbstractElementID, styles: StyleMap) -> Self {
+    pub fn new(
+        global: &GlobalState,
+        content: AbstractElementID,
+        styles: StyleMap,
+        metadata: SlideMetadata,
+        notes: Option<String>,
+        connectors: Vec<AbstractElementID>,
+    ) -> Self {
         Self {
             content,
             styles,
+            metadata,
+            notes,
+            connectors,
             id: global.generate_id(),
         }
     }
 
+    pub fn metadata(&self) -> &SlideMetadata {
+        &self.metadata
+    }
+
+    /// The speaker notes of this slide. These are never shown on the slide itself.
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    /// The lines and arrows on this slide, which are drawn on top of its content.
+    pub fn connectors(&self) -> &[AbstractElementID] {
+        &self.connectors
+    }
+
     pub fn style_map(&self) -> &StyleMap {
         &self.styles
     }
@@ -0,0 +1,61 @@
+use std::{
+    path::Path,
+    process::{Child, Command, Stdio},
+};
+
+// Audio is played by the `ffplay` command line tool, which comes with ffmpeg like the tools
+// that are used for videos. It is started without a window and quits when the audio ends.
+
+pub struct AudioPlayer {
+    player: Child,
+    paused: bool,
+}
+
+impl AudioPlayer {
+    pub fn start(path: &Path) -> Result<Self, String> {
+        let player = Command::new("ffplay")
+            .args(["-nodisp", "-autoexit", "-loglevel", "error"])
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("could not run ffplay: {err}"))?;
+
+        Ok(Self {
+            player,
+            paused: false,
+        })
+    }
+
+    /// Pauses or resumes the audio by stopping and continuing the player. Processes can only
+    /// be stopped on Unix; elsewhere, pausing ends the audio.
+    pub fn toggle_pause(&mut self) {
+        #[cfg(unix)]
+        {
+            let signal = if self.paused {
+                libc::SIGCONT
+            } else {
+                libc::SIGSTOP
+            };
+            // SAFETY: sending a signal has no memory safety requirements, and the player
+            // is only reaped when this is dropped, so the pid cannot have been reused
+            unsafe {
+                libc::kill(self.player.id() as libc::pid_t, signal);
+            }
+        }
+        #[cfg(not(unix))]
+        if !self.paused {
+            let _ = self.player.kill();
+        }
+
+        self.paused = !self.paused;
+    }
+}
+
+impl Drop for AudioPlayer {
+    fn drop(&mut self) {
+        let _ = self.player.kill();
+        let _ = self.player.wait();
+    }
+}
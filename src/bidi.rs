@@ -0,0 +1,166 @@
+// fontdue lays text out from left to right in the order it is written. Hebrew and Arabic are
+// read from right to left, so the characters of every line are put in the order they are read
+// with a simplified version of the Unicode bidirectional algorithm: every character gets an
+// embedding level from its own direction and that of its neighbours, and runs of characters
+// at odd levels are reversed. Explicit embeddings and isolates are not supported.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    Left,
+    Right,
+    Number,
+    Neutral,
+}
+
+fn class(character: char) -> Class {
+    match character {
+        '\u{200e}' => Class::Left,
+        '\u{200f}' => Class::Right,
+        '0'..='9' | '\u{0660}'..='\u{0669}' | '\u{06f0}'..='\u{06f9}' => Class::Number,
+        // Hebrew, Arabic, Syriac, Thaana, N'Ko and the like, with their presentation forms
+        '\u{0590}'..='\u{08ff}'
+        | '\u{fb1d}'..='\u{fdff}'
+        | '\u{fe70}'..='\u{feff}'
+        | '\u{10800}'..='\u{10fff}'
+        | '\u{1e800}'..='\u{1efff}' => Class::Right,
+        c if c.is_alphabetic() => Class::Left,
+        _ => Class::Neutral,
+    }
+}
+
+/// Whether a piece of text contains any characters that are read from right to left.
+pub fn has_right_to_left(chars: &[char]) -> bool {
+    chars.iter().any(|c| class(*c) == Class::Right)
+}
+
+/// The embedding level of every character of a piece of text, whose paragraphs are read from
+/// right to left if `rtl` is set. Characters at odd levels are read from right to left.
+pub fn levels(chars: &[char], rtl: bool) -> Vec<u8> {
+    let paragraph_level = u8::from(rtl);
+    let left_level = if rtl { 2 } else { 0 };
+
+    let mut levels = Vec::with_capacity(chars.len());
+    for paragraph in chars.split_inclusive(|c| *c == '\n') {
+        // numbers follow the direction of the text before them, and count as that direction
+        // when the direction of the characters between two runs is decided
+        let mut last_strong = rtl;
+        let directions = paragraph
+            .iter()
+            .map(|c| match class(*c) {
+                Class::Left => {
+                    last_strong = false;
+                    Some(false)
+                }
+                Class::Right => {
+                    last_strong = true;
+                    Some(true)
+                }
+                Class::Number => Some(last_strong),
+                Class::Neutral => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (idx, c) in paragraph.iter().enumerate() {
+            let level = match (class(*c), directions[idx]) {
+                (Class::Right, _) => 1,
+                (Class::Number, Some(true)) => 2,
+                (_, Some(_)) => left_level,
+                (_, None) => {
+                    // characters between runs of the same direction take that direction
+                    let before = directions[..idx].iter().rev().find_map(|d| *d);
+                    let after = directions[idx + 1..].iter().find_map(|d| *d);
+                    match (before.unwrap_or(rtl), after.unwrap_or(rtl)) {
+                        (true, true) => 1,
+                        (false, false) => left_level,
+                        _ => paragraph_level,
+                    }
+                }
+            };
+            levels.push(level);
+        }
+    }
+    levels
+}
+
+/// The order in which the characters of a single line are shown from left to right, as
+/// indices into the line. Whitespace at the end of the line stays where it is.
+pub fn line_order(chars: &[char], levels: &[u8]) -> Vec<usize> {
+    let content_len = chars
+        .iter()
+        .rposition(|c| !c.is_whitespace())
+        .map_or(0, |idx| idx + 1);
+    let mut order = (0..chars.len()).collect::<Vec<_>>();
+
+    let highest = levels[..content_len].iter().copied().max().unwrap_or(0);
+    let lowest_odd = levels[..content_len]
+        .iter()
+        .copied()
+        .filter(|level| level % 2 == 1)
+        .min()
+        .unwrap_or(highest + 1);
+
+    // from the highest level down, every run at that level or higher is reversed
+    for level in (lowest_odd..=highest).rev() {
+        let mut start = 0;
+        while start < content_len {
+            if levels[order[start]] < level {
+                start += 1;
+                continue;
+            }
+            let end = (start..content_len)
+                .find(|idx| levels[order[*idx]] < level)
+                .unwrap_or(content_len);
+            order[start..end].reverse();
+            start = end;
+        }
+    }
+    order
+}
+
+/// Brackets and similar characters are drawn mirrored in text that is read from right to left,
+/// so that they still open and close the right way.
+pub fn mirrored(character: char) -> char {
+    match character {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visual(text: &str, rtl: bool) -> String {
+        let chars = text.chars().collect::<Vec<_>>();
+        let levels = levels(&chars, rtl);
+        line_order(&chars, &levels)
+            .into_iter()
+            .map(|idx| chars[idx])
+            .collect()
+    }
+
+    #[test]
+    fn bidi_ordering() {
+        assert_eq!(visual("plain text", false), "plain text");
+        assert_eq!(visual("שלום", true), "םולש");
+        // a Hebrew word in English text
+        assert_eq!(visual("say שלום now", false), "say םולש now");
+        // English words and numbers in Hebrew text keep their order
+        assert_eq!(visual("אב abc 12 גד", true), "דג abc 12 בא");
+        assert_eq!(visual("אב 12", false), "12 בא");
+        // trailing whitespace is not moved
+        assert_eq!(visual("אב ", true), "בא ");
+        assert_eq!(levels(&['א', '\n', 'a'], false), vec![1, 0, 0]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::hash::{Hash, Hasher};
+
+/// The data shown by a chart: one value for every label.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartData {
+    pub labels: Vec<String>,
+    pub values: Vec<f64>,
+}
+
+// values are never NaN, since they are either whole numbers from the source or parsed from a
+// CSV file, where NaN is rejected
+impl Eq for ChartData {}
+
+impl Hash for ChartData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.labels.hash(state);
+        for value in &self.values {
+            value.to_bits().hash(state);
+        }
+    }
+}
+
+impl ChartData {
+    pub fn largest_value(&self) -> f64 {
+        self.values.iter().copied().fold(0.0, f64::max)
+    }
+}
+
+/// The points of a plot, read from two columns of a CSV file, and the names of those columns.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotData {
+    pub x_name: String,
+    pub y_name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+// like chart data, the points are never NaN
+impl Eq for PlotData {}
+
+impl Hash for PlotData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.x_name.hash(state);
+        self.y_name.hash(state);
+        for (x, y) in &self.points {
+            x.to_bits().hash(state);
+            y.to_bits().hash(state);
+        }
+    }
+}
+
+impl PlotData {
+    /// The smallest and largest x and y values, as `((min_x, max_x), (min_y, max_y))`.
+    pub fn ranges(&self) -> ((f64, f64), (f64, f64)) {
+        let range = |values: &mut dyn Iterator<Item = f64>| {
+            values.fold((f64::MAX, f64::MIN), |(min, max), value| {
+                (min.min(value), max.max(value))
+            })
+        };
+        (
+            range(&mut self.points.iter().map(|(x, _)| *x)),
+            range(&mut self.points.iter().map(|(_, y)| *y)),
+        )
+    }
+}
+
+/// Reads chart data from CSV: the first column holds the labels and the second the values.
+/// A first line whose second column is not a number is taken to be a header and skipped.
+pub fn parse_csv(source: &str) -> Result<ChartData, String> {
+    let mut data = ChartData {
+        labels: Vec::new(),
+        values: Vec::new(),
+    };
+
+    for (idx, line) in source.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut fields = line.split(',').map(|field| field.trim().trim_matches('"'));
+        let (Some(label), Some(value)) = (fields.next(), fields.next()) else {
+            return Err(format!("line {} has fewer than two columns", idx + 1));
+        };
+
+        match value.parse::<f64>() {
+            Ok(value) if value.is_finite() => {
+                data.labels.push(label.to_string());
+                data.values.push(value);
+            }
+            _ if idx == 0 => {}
+            _ => return Err(format!("{value:?} on line {} is not a number", idx + 1)),
+        }
+    }
+
+    if data.values.is_empty() {
+        return Err(String::from("there are no values"));
+    }
+
+    Ok(data)
+}
+
+/// Reads the points of a plot from CSV with a header line, taking the x and y values from the
+/// columns with the given names.
+pub fn parse_csv_columns(source: &str, x_name: &str, y_name: &str) -> Result<PlotData, String> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let split = |line: &str| {
+        line.split(',')
+            .map(|field| field.trim().trim_matches('"').to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let Some((_, header)) = lines.next() else {
+        return Err(String::from("the file is empty"));
+    };
+    let columns = split(header);
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| format!("there is no column named {name:?}"))
+    };
+    let (x_column, y_column) = (column(x_name)?, column(y_name)?);
+
+    let mut points = Vec::new();
+    for (idx, line) in lines {
+        let fields = split(line);
+        let value = |column: usize| {
+            let field = fields
+                .get(column)
+                .ok_or_else(|| format!("line {} has too few columns", idx + 1))?;
+            field
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("{field:?} on line {} is not a number", idx + 1))
+        };
+        points.push((value(x_column)?, value(y_column)?));
+    }
+
+    if points.is_empty() {
+        return Err(String::from("there are no values"));
+    }
+
+    Ok(PlotData {
+        x_name: x_name.to_string(),
+        y_name: y_name.to_string(),
+        points,
+    })
+}
+
+/// Evenly spaced values for the axis of a chart, from 0 up to at least `max`. The spacing is
+/// 1, 2 or 5 times a power of ten, so that there are at most six steps.
+pub fn axis_ticks(max: f64) -> Vec<f64> {
+    axis_ticks_between(0.0, max)
+}
+
+/// Like `axis_ticks`, but for an axis that covers the values from `min` to `max`. The ticks
+/// start at the multiple of the spacing just below `min`.
+pub fn axis_ticks_between(min: f64, max: f64) -> Vec<f64> {
+    if max <= min {
+        return vec![min, min + 1.0];
+    }
+
+    let span = max - min;
+    let magnitude = 10f64.powf((span / 6.0).log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| span / step <= 6.0)
+        .unwrap_or(10.0 * magnitude);
+
+    let first = (min / step).floor();
+    let steps = (max / step).ceil() - first;
+    (0..=steps as usize)
+        .map(|idx| (first + idx as f64) * step)
+        .collect()
+}
+
+/// Formats a value on an axis without trailing zeroes.
+pub fn format_tick(value: f64) -> String {
+    let formatted = format!("{value:.3}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The standard library has no notion of time zones, so the local time comes from the C
+// library where there is one. Elsewhere, clocks show UTC.
+
+/// The time of day, as hours, minutes and seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeOfDay {
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+}
+
+#[cfg(unix)]
+pub fn local_time() -> TimeOfDay {
+    // SAFETY: `time` accepts a null pointer, and `localtime_r` only writes to the `tm` it is
+    // given, which is fully initialised when it succeeds
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return utc_time();
+        }
+        TimeOfDay {
+            hours: tm.tm_hour as u32,
+            minutes: tm.tm_min as u32,
+            seconds: tm.tm_sec as u32,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn local_time() -> TimeOfDay {
+    utc_time()
+}
+
+fn utc_time() -> TimeOfDay {
+    let seconds_today = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        % (24 * 60 * 60);
+    TimeOfDay {
+        hours: (seconds_today / 3600) as u32,
+        minutes: (seconds_today / 60 % 60) as u32,
+        seconds: (seconds_today % 60) as u32,
+    }
+}
+
+/// Formats a time like `strftime` does, for the fields that make sense on a clock: `%H` and
+/// `%I` for the hour on a 24 and 12 hour clock, `%M` for the minutes, `%S` for the seconds,
+/// `%p` for AM or PM and `%%` for a percent sign. Anything else is kept as it is.
+pub fn format_time(format: &str, time: TimeOfDay) -> String {
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(character) = chars.next() {
+        if character != '%' {
+            formatted.push(character);
+            continue;
+        }
+
+        match chars.next() {
+            Some('H') => formatted.push_str(&format!("{:02}", time.hours)),
+            Some('I') => formatted.push_str(&format!("{:02}", (time.hours + 11) % 12 + 1)),
+            Some('M') => formatted.push_str(&format!("{:02}", time.minutes)),
+            Some('S') => formatted.push_str(&format!("{:02}", time.seconds)),
+            Some('p') => formatted.push_str(if time.hours < 12 { "AM" } else { "PM" }),
+            Some('%') => formatted.push('%'),
+            Some(other) => {
+                formatted.push('%');
+                formatted.push(other);
+            }
+            None => formatted.push('%'),
+        }
+    }
+    formatted
+}
+
+/// How long it is until a clock with this format shows a different time: until the next
+/// second if it shows seconds, until the next minute otherwise.
+pub fn time_until_next_tick(format: &str) -> Duration {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let into_second = Duration::from_nanos(since_epoch.subsec_nanos() as u64);
+
+    if format.contains("%S") {
+        Duration::from_secs(1) - into_second
+    } else {
+        Duration::from_secs(60 - since_epoch.as_secs() % 60) - into_second
+    }
+}
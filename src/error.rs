@@ -1,6 +1,6 @@
 use crate::interpreter::{Token, TokenLocation};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FoliumError<'a> {
     UnknownType {
         location: TokenLocation,
@@ -28,6 +28,63 @@ pub enum FoliumError<'a> {
         location: TokenLocation,
         expected: &'a str,
     },
+    UndefinedReference {
+        location: TokenLocation,
+        name: &'a str,
+    },
+    UnreadableFile {
+        location: TokenLocation,
+        path: String,
+    },
+    UndefinedVariable {
+        location: TokenLocation,
+        name: String,
+    },
+    UndefinedLoopVariable {
+        location: TokenLocation,
+        name: String,
+    },
+    UndefinedStyleClass {
+        location: TokenLocation,
+        name: String,
+    },
+    UndefinedDefinition {
+        location: TokenLocation,
+        name: String,
+    },
+    UndefinedEndpoint {
+        location: TokenLocation,
+        name: String,
+    },
+    UnbalancedParentheses {
+        location: TokenLocation,
+    },
+    UnclosedBracket {
+        location: TokenLocation,
+        bracket: Token<'a>,
+    },
+    SizeTooLarge {
+        location: TokenLocation,
+    },
+    UnknownSetting {
+        location: TokenLocation,
+        block: &'static str,
+        key: String,
+    },
+    InvalidFormula {
+        location: TokenLocation,
+        reason: String,
+    },
+    InvalidChartData {
+        location: TokenLocation,
+        reason: String,
+    },
+    InvalidKeyword {
+        location: TokenLocation,
+        key: String,
+        value: String,
+        expected: String,
+    },
 }
 
 impl<'a> std::fmt::Display for FoliumError<'a> {
@@ -39,6 +96,20 @@ impl<'a> std::fmt::Display for FoliumError<'a> {
             FoliumError::ExpectedReason { location, expected, got } => write!(f, "at {location}: Expected {expected}, got {got:?}."),
             FoliumError::UnexpectedFileEndWithToken { location, expected } => write!(f, "at {location}: Expected {expected:?} but the file ended abruptly."),
             FoliumError::UnexpectedFileEndWithReason { location, expected } => write!(f, "at {location}: Expected {expected:?} but the file ended abruptly."),
+            FoliumError::UndefinedReference { location, name } => write!(f, "at {location}: Reference to {name}, which was not defined with a def statement before this point."),
+            FoliumError::UnreadableFile { location, path } => write!(f, "at {location}: Could not read file {path}."),
+            FoliumError::UndefinedVariable { location, name } => write!(f, "at {location}: Environment variable {name} is not set."),
+            FoliumError::UndefinedLoopVariable { location, name } => write!(f, "at {location}: {name} is not a loop variable, so it has no value here."),
+            FoliumError::UndefinedStyleClass { location, name } => write!(f, "at {location}: Style class {name}, which was not defined with a style block before this point."),
+            FoliumError::UndefinedDefinition { location, name } => write!(f, "at {location}: {name} was not defined with a def statement before this point."),
+            FoliumError::UndefinedEndpoint { location, name } => write!(f, "at {location}: The line or arrow connects to {name}, which is not an element on this slide."),
+            FoliumError::UnbalancedParentheses { location } => write!(f, "at {location}: This parenthesis has no matching one."),
+            FoliumError::UnclosedBracket { location, bracket } => write!(f, "at {location}: {bracket:?} is never closed."),
+            FoliumError::SizeTooLarge { location } => write!(f, "at {location}: The size that follows from the aspect ratio is too large."),
+            FoliumError::UnknownSetting { location, block, key } => write!(f, "at {location}: {key} is not a setting of a {block} block, or its value is invalid."),
+            FoliumError::InvalidFormula { location, reason } => write!(f, "at {location}: Invalid formula: {reason}."),
+            FoliumError::InvalidChartData { location, reason } => write!(f, "at {location}: Invalid chart data: {reason}."),
+            FoliumError::InvalidKeyword { location, key, value, expected } => write!(f, "at {location}: {value} is not a valid {key}, expected {expected}."),
         }
     }
 }
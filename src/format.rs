@@ -0,0 +1,410 @@
+use itertools::Itertools;
+
+use crate::interpreter::{tokenize, FatToken, Token, Token::*};
+use crate::style::PropertyValue;
+
+const INDENT: &str = "    ";
+
+/// Re-emits a .flm source file in canonical form: four spaces of indentation, spaces around
+/// `::` and after `:`, one child element per line and one style property per line.
+pub fn format_source(source: &str) -> String {
+    let mut formatted = format_items(tokenize(source)).join("\n\n");
+    formatted.push('\n');
+    formatted
+}
+
+/// Formats a sequence of top-level items, each with the comments that precede it.
+fn format_items(tokens: Vec<FatToken>) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    let mut pending_comments = String::new();
+    let mut iter = tokens.into_iter().peekable();
+
+    while let Some(fat_token) = iter.next() {
+        let item = match fat_token.token {
+            Comment(comment) => {
+                pending_comments.push_str(&comment);
+                pending_comments.push('\n');
+                continue;
+            }
+            OpeningSlideParen => format_slide(&take_slide(&mut iter), 0),
+            Ident("def") => {
+                format!("def {}", format_content(&take_element(&mut iter), 0))
+            }
+            Ident("for") => {
+                let header = iter
+                    .by_ref()
+                    .take_while(|token| token.token != OpeningSlideParen)
+                    .map(|token| token_text(&token.token))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let values = take_slide(&mut iter)
+                    .iter()
+                    .filter(|token| token.token != ListSeparator)
+                    .map(|token| token_text(&token.token))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                iter.next_if(|token| token.token == OpeningSlideParen);
+                let slide = format_slide(&take_slide(&mut iter), 0);
+
+                format!("for {header} [{values}] {slide}")
+            }
+            Ident("@if") => {
+                let condition = join_inline(
+                    &iter
+                        .by_ref()
+                        .take_while(|token| token.token != OpeningSlideParen)
+                        .collect::<Vec<_>>(),
+                );
+                let section = take_slide(&mut iter);
+                // like in the interpreter, the section either contains top-level items or is a slide
+                let contains_items = section
+                    .iter()
+                    .find(|token| !matches!(token.token, Comment(_)))
+                    .is_some_and(|token| {
+                        matches!(
+                            token.token,
+                            OpeningSlideParen
+                                | Ident("deck" | "style" | "variant" | "def" | "for" | "@if")
+                        )
+                    });
+                let body = if contains_items {
+                    let items = format_items(section)
+                        .iter()
+                        .map(|item| {
+                            item.lines()
+                                .map(|line| {
+                                    if line.is_empty() {
+                                        String::new()
+                                    } else {
+                                        format!("{INDENT}{line}")
+                                    }
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    format!("[\n{items}\n]")
+                } else {
+                    format_slide(&section, 0)
+                };
+
+                format!("@if {condition} {body}")
+            }
+            Ident(keyword @ ("style" | "variant" | "deck")) => {
+                let name_and_block = iter
+                    .by_ref()
+                    .take_while_inclusive(|token| token.token != ClosingParamsParen)
+                    .collect::<Vec<_>>();
+                format!("{keyword} {}", format_style_blocks(&name_and_block, 0))
+            }
+            other => token_text(&other),
+        };
+
+        items.push(format!("{pending_comments}{item}"));
+        pending_comments.clear();
+    }
+
+    if !pending_comments.is_empty() {
+        items.push(pending_comments.trim_end().to_string());
+    }
+
+    items
+}
+
+/// Takes the tokens up to the closing bracket of the slide that was just opened.
+fn take_slide<'a, I: Iterator<Item = FatToken<'a>>>(iter: &mut I) -> Vec<FatToken<'a>> {
+    let mut depth: usize = 1;
+    iter.take_while(|token| {
+        match token.token {
+            OpeningSlideParen => depth += 1,
+            ClosingSlideParen => depth -= 1,
+            _ => {}
+        }
+        depth > 0
+    })
+    .collect()
+}
+
+/// Takes the tokens of a single element definition, up to and including its closing parenthesis.
+fn take_element<'a, I: Iterator<Item = FatToken<'a>>>(iter: &mut I) -> Vec<FatToken<'a>> {
+    let mut taken_a_bracket = false;
+    let mut brackets: usize = 0;
+
+    iter.take_while_inclusive(|token| match token.token {
+        OpeningArgsParen => {
+            taken_a_bracket = true;
+            brackets += 1;
+            true
+        }
+        ClosingArgsParen => {
+            brackets -= 1;
+            brackets != 0
+        }
+        _ => !taken_a_bracket || brackets != 0,
+    })
+    .collect()
+}
+
+fn format_slide(tokens: &[FatToken], depth: usize) -> String {
+    let inner_indent = INDENT.repeat(depth + 1);
+    let mut iter = tokens.iter().cloned().peekable();
+    let mut sections = Vec::new();
+
+    let mut leading_comments = String::new();
+    while let Some(FatToken {
+        token: Comment(comment),
+        ..
+    }) = iter.next_if(|token| matches!(token.token, Comment(_)))
+    {
+        leading_comments.push_str(&format!("{inner_indent}{comment}\n"));
+    }
+
+    let content = take_element(&mut iter);
+    if !content.is_empty() {
+        sections.push(format!(
+            "{leading_comments}{inner_indent}{}",
+            format_content(&content, depth + 1)
+        ));
+    }
+
+    let rest = iter.collect::<Vec<_>>();
+    if !rest.is_empty() {
+        sections.push(format_style_blocks(&rest, depth + 1));
+    }
+
+    format!("[\n{}\n{}]", sections.join("\n\n"), INDENT.repeat(depth))
+}
+
+/// Formats a single element, like `name :: row ( text("a"), text("b") )`. The first line
+/// is not indented, so that the caller can prefix it.
+fn format_content(tokens: &[FatToken], depth: usize) -> String {
+    let Some(paren_idx) = tokens.iter().position(|t| t.token == OpeningArgsParen) else {
+        return join_inline(tokens);
+    };
+
+    let head = join_inline(&tokens[..paren_idx]);
+    let args = match tokens.last() {
+        Some(FatToken {
+            token: ClosingArgsParen,
+            ..
+        }) => &tokens[paren_idx + 1..tokens.len() - 1],
+        _ => &tokens[paren_idx + 1..],
+    };
+
+    let children = split_arguments(args);
+    let has_child_elements = children
+        .iter()
+        .any(|child| child.iter().any(|t| t.token == OpeningArgsParen));
+
+    if has_child_elements {
+        let child_indent = INDENT.repeat(depth + 1);
+        let children = children
+            .iter()
+            .map(|child| {
+                let comments = child
+                    .iter()
+                    .filter_map(|t| match &t.token {
+                        Comment(comment) => Some(format!("{child_indent}{comment}\n")),
+                        _ => None,
+                    })
+                    .collect::<String>();
+                let child = child
+                    .iter()
+                    .filter(|t| !matches!(t.token, Comment(_)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                format!(
+                    "{comments}{child_indent}{}",
+                    format_content(&child, depth + 1)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        format!("{head} (\n{children}\n{})", INDENT.repeat(depth))
+    } else {
+        format!("{head}({})", join_inline(args))
+    }
+}
+
+/// Splits the arguments of an element on the commas that are not nested in another element.
+fn split_arguments<'a>(tokens: &[FatToken<'a>]) -> Vec<Vec<FatToken<'a>>> {
+    let mut groups = vec![Vec::new()];
+    let mut depth: usize = 0;
+
+    for token in tokens {
+        match token.token {
+            OpeningArgsParen | OpeningSlideParen => depth += 1,
+            ClosingArgsParen | ClosingSlideParen => depth = depth.saturating_sub(1),
+            ListSeparator if depth == 0 => {
+                groups.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        groups.last_mut().unwrap().push(token.clone());
+    }
+
+    groups.retain(|group| !group.is_empty());
+    groups
+}
+
+/// Formats a sequence of `target { key: value, ... }` blocks, one property per line.
+fn format_style_blocks(tokens: &[FatToken], depth: usize) -> String {
+    let indent = INDENT.repeat(depth);
+    let property_indent = INDENT.repeat(depth + 1);
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+    let mut properties: Vec<Vec<FatToken>> = Vec::new();
+    let mut comments: Vec<(usize, String)> = Vec::new();
+    // tokens outside of a block, e.g. a style target or a call like `notes("...")`
+    let mut inline: Vec<FatToken> = Vec::new();
+    let mut in_block = false;
+    let mut list_depth: usize = 0;
+
+    let flush_inline = |current: &mut String, inline: &mut Vec<FatToken>| {
+        if !inline.is_empty() {
+            if current.is_empty() || current.ends_with('\n') {
+                current.push_str(&indent);
+            }
+            current.push_str(&join_inline(inline));
+            inline.clear();
+        }
+    };
+
+    for fat_token in tokens {
+        match &fat_token.token {
+            Comment(comment) if in_block => comments.push((properties.len(), comment.clone())),
+            Comment(comment) => {
+                flush_inline(&mut current, &mut inline);
+                if !current.is_empty() && !current.ends_with('\n') {
+                    current.push('\n');
+                }
+                current.push_str(&format!("{indent}{comment}\n"))
+            }
+            OpeningParamsParen => {
+                flush_inline(&mut current, &mut inline);
+                in_block = true;
+                properties = vec![Vec::new()];
+            }
+            ClosingParamsParen => {
+                in_block = false;
+                let properties = properties
+                    .iter()
+                    .filter(|property| !property.is_empty())
+                    .collect::<Vec<_>>();
+                let mut body = String::new();
+                for (idx, property) in properties.iter().enumerate() {
+                    for (_, comment) in comments.iter().filter(|(before, _)| *before == idx + 1) {
+                        body.push_str(&format!("{property_indent}{comment}\n"));
+                    }
+                    let separator = if idx + 1 < properties.len() { "," } else { "" };
+                    body.push_str(&format!(
+                        "{property_indent}{}{separator}\n",
+                        join_inline(property)
+                    ));
+                }
+
+                let opening = if current.is_empty() { "{" } else { " {" };
+                current.push_str(&format!("{opening}\n{body}{indent}}}"));
+                blocks.push(std::mem::take(&mut current));
+                comments.clear();
+            }
+            // a call outside of a block, like `notes("...")`, stands on its own
+            ClosingArgsParen if !in_block => {
+                inline.push(fat_token.clone());
+                flush_inline(&mut current, &mut inline);
+                blocks.push(std::mem::take(&mut current));
+            }
+            ListSeparator if in_block && list_depth == 0 => properties.push(Vec::new()),
+            _ if in_block => {
+                match fat_token.token {
+                    OpeningSlideParen => list_depth += 1,
+                    ClosingSlideParen => list_depth = list_depth.saturating_sub(1),
+                    _ => {}
+                }
+                properties.last_mut().unwrap().push(fat_token.clone())
+            }
+            _ => inline.push(fat_token.clone()),
+        }
+    }
+
+    flush_inline(&mut current, &mut inline);
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks.join("\n\n")
+}
+
+/// Joins tokens on a single line, with canonical spacing around punctuation.
+fn join_inline(tokens: &[FatToken]) -> String {
+    let mut ret = String::new();
+
+    for fat_token in tokens {
+        match &fat_token.token {
+            Definition => ret.push_str(" :: "),
+            ValueAssignment => ret.push_str(": "),
+            ListSeparator => ret.push_str(", "),
+            Concatenation => ret.push_str(" + "),
+            other => ret.push_str(&token_text(other)),
+        }
+    }
+
+    ret
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        OpeningSlideParen => String::from("["),
+        ClosingSlideParen => String::from("]"),
+        Definition => String::from("::"),
+        ValueAssignment => String::from(":"),
+        ListSeparator => String::from(","),
+        StringDelim => String::from("\""),
+        OpeningArgsParen => String::from("("),
+        ClosingArgsParen => String::from(")"),
+        OpeningParamsParen => String::from("{"),
+        ClosingParamsParen => String::from("}"),
+        Concatenation => String::from("+"),
+        Value(PropertyValue::String(s)) => format!("\"{s}\""),
+        Value(value) => value.to_string(),
+        Ident(ident) => ident.to_string(),
+        Comment(comment) => comment.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_is_canonical_and_idempotent() {
+        let source = r#"// title slide
+[ col(title::text("Diatitel"),row(text( "a" ),img("in.jpg")))
+title{size:80,fill:#2e1128} ]"#;
+
+        let formatted = format_source(source);
+        assert_eq!(
+            formatted,
+            r#"// title slide
+[
+    col (
+        title :: text("Diatitel"),
+        row (
+            text("a"),
+            img("in.jpg")
+        )
+    )
+
+    title {
+        size: 80,
+        fill: #2e1128
+    }
+]
+"#
+        );
+        assert_eq!(format_source(&formatted), formatted);
+    }
+}
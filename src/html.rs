@@ -0,0 +1,135 @@
+//! Writes presentations to a single HTML file that can be shared and shown in any browser. The
+//! slides are embedded as the images they are drawn as, with their links on top of them, and a
+//! small script goes through them with the keyboard or by clicking.
+
+use std::fmt::Write;
+
+use image::ImageResult;
+
+use crate::{
+    layout::Rect,
+    raster,
+    svg::{data_uri, escape},
+};
+
+/// A slide as it was drawn, as RGBA pixels row by row, with the areas of its links.
+pub struct Slide {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    pub links: Vec<(Rect, String)>,
+}
+
+const STYLE: &str = "html, body { margin: 0; height: 100%; overflow: hidden; background: #000; }
+section { display: none; position: absolute; inset: 0; margin: auto; }
+section.current { display: block; }
+section img { display: block; width: 100%; height: 100%; user-select: none; }
+section a { position: absolute; }";
+
+/// Right, down, space and page down go to the next slide, left, up, backspace and page up to
+/// the one before, and home and end to the first and the last one. Clicking on the left third
+/// of the window goes back and anywhere else forward. The number of the slide that is shown is
+/// kept in the address, so that it can be linked to.
+const SCRIPT: &str = r##"const slides = document.querySelectorAll("section");
+let current = 0;
+function show(idx) {
+  idx = Math.max(0, Math.min(slides.length - 1, idx));
+  slides[current].classList.remove("current");
+  current = idx;
+  slides[current].classList.add("current");
+  history.replaceState(null, "", "#" + (current + 1));
+}
+addEventListener("keydown", (event) => {
+  switch (event.key) {
+    case "ArrowRight": case "ArrowDown": case " ": case "PageDown": show(current + 1); break;
+    case "ArrowLeft": case "ArrowUp": case "Backspace": case "PageUp": show(current - 1); break;
+    case "Home": show(0); break;
+    case "End": show(slides.length - 1); break;
+    default: return;
+  }
+  event.preventDefault();
+});
+addEventListener("click", (event) => {
+  if (!event.target.closest("a")) {
+    show(current + (event.clientX < innerWidth / 3 ? -1 : 1));
+  }
+});
+addEventListener("hashchange", () => show(parseInt(location.hash.slice(1), 10) - 1 || 0));
+show(parseInt(location.hash.slice(1), 10) - 1 || 0);"##;
+
+/// A page that shows the slides one at a time, as large as they fit in the window.
+pub fn presentation(title: &str, slides: &[Slide]) -> ImageResult<String> {
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>",
+        escape(title)
+    )
+    .unwrap();
+
+    for (idx, slide) in slides.iter().enumerate() {
+        let (width, height) = (slide.width.max(1), slide.height.max(1));
+        // the slide is as large as it can be while keeping its aspect ratio
+        writeln!(
+            html,
+            "<section style=\"width: min(100vw, calc(100vh * {width} / {height})); height: min(100vh, calc(100vw * {height} / {width}));\">\n<img src=\"{}\" alt=\"Slide {}\">",
+            data_uri(
+                "image/png",
+                &raster::encode_png(slide.width, slide.height, &slide.rgba)?
+            ),
+            idx + 1
+        )
+        .unwrap();
+        for (area, url) in &slide.links {
+            let percent = |value: u32, of: u32| value as f32 * 100.0 / of as f32;
+            writeln!(
+                html,
+                "<a href=\"{}\" style=\"left: {:.3}%; top: {:.3}%; width: {:.3}%; height: {:.3}%;\"></a>",
+                escape(url),
+                percent(area.x, width),
+                percent(area.y, height),
+                percent(area.w, width),
+                percent(area.h, height)
+            )
+            .unwrap();
+        }
+        html.push_str("</section>\n");
+    }
+
+    write!(html, "<script>\n{SCRIPT}\n</script>\n</body>\n</html>\n").unwrap();
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presentation_page() {
+        let slide = |links| Slide {
+            width: 4,
+            height: 2,
+            rgba: [255, 255, 255, 255].repeat(8),
+            links,
+        };
+        let link = (
+            Rect {
+                x: 1,
+                y: 0,
+                w: 2,
+                h: 1,
+            },
+            String::from("https://example.com/?a=1&b=2"),
+        );
+        let html = presentation("Q&A", &[slide(vec![]), slide(vec![link])]).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Q&amp;A</title>"));
+        assert_eq!(html.matches("<section ").count(), 2);
+        assert_eq!(html.matches("src=\"data:image/png;base64,").count(), 2);
+        assert!(html.contains(
+            "<a href=\"https://example.com/?a=1&amp;b=2\" style=\"left: 25.000%; top: 0.000%; width: 50.000%; height: 50.000%;\"></a>"
+        ));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
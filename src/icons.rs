@@ -0,0 +1,48 @@
+// Icons are drawn as glyphs from an icon font. The names below refer to the codepoints of
+// Font Awesome, which has to be installed separately; any other icon font can be used by
+// setting the `font` of the icon and giving the codepoint directly, like `icon("U+E87C")`.
+
+const ICONS: &[(&str, char)] = &[
+    // brands
+    ("github", '\u{f09b}'),
+    ("twitter", '\u{f099}'),
+    ("linkedin", '\u{f08c}'),
+    ("youtube", '\u{f167}'),
+    // solid and regular
+    ("arrow-left", '\u{f060}'),
+    ("arrow-right", '\u{f061}'),
+    ("calendar", '\u{f133}'),
+    ("check", '\u{f00c}'),
+    ("circle-info", '\u{f05a}'),
+    ("clock", '\u{f017}'),
+    ("code", '\u{f121}'),
+    ("download", '\u{f019}'),
+    ("envelope", '\u{f0e0}'),
+    ("gear", '\u{f013}'),
+    ("globe", '\u{f0ac}'),
+    ("heart", '\u{f004}'),
+    ("house", '\u{f015}'),
+    ("lightbulb", '\u{f0eb}'),
+    ("link", '\u{f0c1}'),
+    ("magnifying-glass", '\u{f002}'),
+    ("pause", '\u{f04c}'),
+    ("phone", '\u{f095}'),
+    ("play", '\u{f04b}'),
+    ("star", '\u{f005}'),
+    ("triangle-exclamation", '\u{f071}'),
+    ("user", '\u{f007}'),
+    ("xmark", '\u{f00d}'),
+];
+
+/// Looks up the character for an icon, which is either one of the names above or a
+/// codepoint written as `U+` followed by hexadecimal digits.
+pub fn icon_character(name: &str) -> Option<char> {
+    if let Some(hex) = name.strip_prefix("U+") {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+
+    ICONS
+        .iter()
+        .find(|(icon_name, _)| *icon_name == name)
+        .map(|(_, character)| *character)
+}
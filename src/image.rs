@@ -0,0 +1,96 @@
+use std::{fs, path::Path};
+
+// Images are only decoded when they are drawn, but layout needs their aspect ratio, so their
+// size is read from the header of the file. PNG, JPEG, GIF and BMP files are understood.
+
+pub fn image_size(path: &Path) -> Option<(u32, u32)> {
+    header_size(&fs::read(path).ok()?)
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_i32(bytes: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Reads the width and height from the contents of an image file. Images without any pixels
+/// have no size, since they have no aspect ratio either.
+fn header_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let size = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // the IHDR chunk always comes first
+        (be_u32(bytes, 16)?, be_u32(bytes, 20)?)
+    } else if bytes.starts_with(b"GIF8") {
+        (le_u16(bytes, 6)?, le_u16(bytes, 8)?)
+    } else if bytes.starts_with(b"BM") {
+        // the height is negative for images that are stored top to bottom
+        (
+            le_i32(bytes, 18)?.unsigned_abs(),
+            le_i32(bytes, 22)?.unsigned_abs(),
+        )
+    } else if bytes.starts_with(&[0xff, 0xd8]) {
+        jpeg_size(bytes)?
+    } else {
+        return None;
+    };
+
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+/// Walks the segments of a JPEG file up to the start of frame segment, which has the size.
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // padding before a marker
+            0xff => at += 1,
+            // start of frame, apart from the markers that share the range
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                return Some((be_u16(bytes, at + 7)?, be_u16(bytes, at + 5)?));
+            }
+            _ => at += 2 + be_u16(bytes, at + 2)? as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_header_sizes() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        assert_eq!(header_size(&png), Some((640, 480)));
+
+        let gif = b"GIF89a\x20\x03\x58\x02";
+        assert_eq!(header_size(gif), Some((800, 600)));
+
+        let mut bmp = b"BM".to_vec();
+        bmp.resize(18, 0);
+        bmp.extend(100i32.to_le_bytes());
+        bmp.extend((-50i32).to_le_bytes());
+        assert_eq!(header_size(&bmp), Some((100, 50)));
+
+        // an APP0 segment, followed by the start of frame
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00];
+        jpeg.extend([0xff, 0xc0, 0x00, 0x11, 0x08, 0x01, 0x2c, 0x01, 0x90]);
+        assert_eq!(header_size(&jpeg), Some((400, 300)));
+
+        assert_eq!(header_size(b"not an image"), None);
+    }
+}
@@ -1,16 +1,24 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::ast::ElementType::*;
-use crate::ast::{AbstractElementData, AbstractElementID, ElementType, GlobalState, Slide};
+use crate::ast::{
+    AbstractElementData, AbstractElementID, DeckSettings, ElementType, Endpoint, GlobalState,
+    Slide, SlideMetadata, Watermark, WatermarkMark,
+};
+use crate::chart::{self, ChartData, PlotData};
 use crate::error::FoliumError;
+use crate::icons;
 use crate::layout::SizeSpec;
-use crate::style::{PropertyValue, StyleMap, StyleTarget};
+use crate::style::{
+    check_keyword, parse_aspect_ratio, size_for_aspect_ratio, Length, Palette, PropertyValue,
+    StyleMap, StyleTarget,
+};
 
 use itertools::Itertools;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Token<'a> {
     /// in source code: token [
     OpeningSlideParen,
@@ -32,10 +40,14 @@ pub enum Token<'a> {
     OpeningParamsParen,
     /// in source code: token }
     ClosingParamsParen,
-    /// in source code: token numbers, string literals, bool
+    /// in source code: token +
+    Concatenation,
+    /// in source code: token numbers (optionally with a unit suffix), string literals, bool
     Value(PropertyValue),
     /// in source code: token all other values
     Ident(&'a str),
+    /// in source code: any line starting with //
+    Comment(String),
 }
 use Token::*;
 
@@ -52,9 +64,9 @@ impl std::fmt::Display for TokenLocation {
 }
 
 #[derive(Clone, Debug, PartialEq)]
-struct FatToken<'a> {
-    token: Token<'a>,
-    location: TokenLocation,
+pub struct FatToken<'a> {
+    pub token: Token<'a>,
+    pub location: TokenLocation,
 }
 
 #[derive(Clone, Debug)]
@@ -102,14 +114,45 @@ fn split_off_string_delims(mut s: &str) -> Vec<&str> {
     ret
 }
 
+/// Replaces every `${NAME}` in a string literal with the value of the environment variable NAME.
+/// If a variable is not set, its name is returned as the error.
+fn interpolate_environment(mut s: &str) -> Result<String, String> {
+    let mut ret = String::new();
+
+    while let Some(start) = s.find("${") {
+        let Some(length) = s[start..].find('}') else {
+            break;
+        };
+
+        let name = &s[start + 2..start + length];
+        ret.push_str(&s[..start]);
+        ret.push_str(&std::env::var(name).map_err(|_| name.to_string())?);
+        s = &s[start + length + 1..];
+    }
+
+    ret.push_str(s);
+    Ok(ret)
+}
+
 /// Takes an iterator of tokens and returns the defined AbstractElement
+///
+/// `location` is where the content is expected, which is where an error is reported when
+/// there is none.
 fn parse_content_definition<'a, I: std::fmt::Debug + Iterator<Item = FatToken<'a>>>(
     mut iter: I,
+    location: TokenLocation,
     global: &'a GlobalState,
-) -> Result<AbstractElementID, FoliumError> {
+) -> Result<AbstractElementID, FoliumError<'a>> {
     let content_name_or_type = iter
         .next()
-        .expect("could not parse name of following content item");
+        .ok_or(FoliumError::UnexpectedFileEndWithReason {
+            location,
+            expected: "a content type or name",
+        })?;
+
+    if content_name_or_type.token == Ident("use") {
+        return parse_reference(iter, content_name_or_type.location, global);
+    }
 
     let (maybe_name, element_type, should_check_opening_paren): (
         Option<String>,
@@ -262,59 +305,388 @@ fn parse_content_definition<'a, I: std::fmt::Debug + Iterator<Item = FatToken<'a
 
     Ok(match element_type {
         ElNone => global.push_element(AbstractElementData::None, element_type, maybe_name),
-        Sized => global.push_element(
-            AbstractElementData::Sized(
-                parse_content_definition(content_tokens.into_iter(), global)
-                    .unwrap_or_else(|err| panic!("{err}")),
+        SlideNumber => {
+            global.push_element(AbstractElementData::SlideNumber, element_type, maybe_name)
+        }
+        Progress => global.push_element(AbstractElementData::Progress, element_type, maybe_name),
+        Clock => global.push_element(AbstractElementData::Clock, element_type, maybe_name),
+        Icon => {
+            let name = parse_text_argument(&content_tokens, content_name_or_type.location)?;
+            let Some(character) = icons::icon_character(&name) else {
+                return Err(FoliumError::ExpectedReason {
+                    location: content_name_or_type.location,
+                    expected: "the name of an icon or a codepoint like U+F09B",
+                    got: Value(PropertyValue::String(name)),
+                });
+            };
+            global.push_element(
+                AbstractElementData::Icon(character),
+                element_type,
+                maybe_name,
+            )
+        }
+        Link => match &content_tokens[..] {
+            [FatToken {
+                token: Value(PropertyValue::String(label)),
+                ..
+            }, FatToken {
+                token: ListSeparator,
+                ..
+            }, FatToken {
+                token: Value(PropertyValue::String(url)),
+                ..
+            }] => global.push_element(
+                AbstractElementData::Link(label.clone(), url.clone()),
+                element_type,
+                maybe_name,
             ),
-            element_type,
-            maybe_name,
-        ),
+            [FatToken { token, location }, ..] => {
+                return Err(FoliumError::ExpectedReason {
+                    location: *location,
+                    expected: "a label and a URL, like link(\"label\", \"https://...\")",
+                    got: token.clone(),
+                })
+            }
+            [] => {
+                return Err(FoliumError::ExpectedReason {
+                    location: content_name_or_type.location,
+                    expected: "a label and a URL, like link(\"label\", \"https://...\")",
+                    got: ClosingArgsParen,
+                })
+            }
+        },
+        Spacer => {
+            let amount = match &content_tokens[..] {
+                [] => None,
+                [FatToken {
+                    token: Value(PropertyValue::Number(amount)),
+                    ..
+                }] => Some(global.scaled(*amount)),
+                [FatToken { token, location }, ..] => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: *location,
+                        expected: "a size in pixels",
+                        got: token.clone(),
+                    })
+                }
+            };
+            global.push_element(
+                AbstractElementData::Spacer(amount),
+                element_type,
+                maybe_name,
+            )
+        }
+        Sized => {
+            // the size can be given before the content, like `sized(300, _, text("a"))`, where
+            // `_` leaves a dimension free
+            let dimension = |token: &Token| match token {
+                Value(PropertyValue::Number(pixels)) => Some(Some(global.scaled(*pixels))),
+                Ident("_") => Some(None),
+                _ => None,
+            };
+            let (size, content_tokens) = match &content_tokens[..] {
+                [width, FatToken {
+                    token: ListSeparator,
+                    ..
+                }, height, FatToken {
+                    token: ListSeparator,
+                    ..
+                }, ..] => match (dimension(&width.token), dimension(&height.token)) {
+                    (Some(width), Some(height)) => (
+                        Some(SizeSpec { width, height }),
+                        content_tokens[4..].to_vec(),
+                    ),
+                    _ => (None, content_tokens),
+                },
+                _ => (None, content_tokens),
+            };
+            global.push_element(
+                AbstractElementData::Sized(
+                    size,
+                    parse_content_definition(
+                        content_tokens.into_iter(),
+                        content_name_or_type.location,
+                        global,
+                    )?,
+                ),
+                element_type,
+                maybe_name,
+            )
+        }
         Text => global.push_element(
-            AbstractElementData::Text(match content_tokens[0].token {
-                Value(PropertyValue::String(ref s)) => s.clone(),
-                _ => panic!("text content did not contain text value token"),
-            }),
+            AbstractElementData::Text(parse_text_argument(
+                &content_tokens,
+                content_name_or_type.location,
+            )?),
             element_type,
             maybe_name,
         ),
+        H1 | H2 | H3 => {
+            let level = match element_type {
+                H1 => 1,
+                H2 => 2,
+                _ => 3,
+            };
+            global.push_element(
+                AbstractElementData::Heading(
+                    level,
+                    parse_text_argument(&content_tokens, content_name_or_type.location)?,
+                ),
+                element_type,
+                maybe_name,
+            )
+        }
         Code => global.push_element(
-            AbstractElementData::Code(match content_tokens[0].token {
-                Value(PropertyValue::String(ref s)) => s.clone(),
-                _ => panic!("code content did not contain text value token"),
-            }),
+            AbstractElementData::Code(parse_text_argument(
+                &content_tokens,
+                content_name_or_type.location,
+            )?),
             element_type,
             maybe_name,
         ),
+        Math => {
+            let formula = parse_text_argument(&content_tokens, content_name_or_type.location)?;
+            // formulas are only typeset when rendering, but mistakes should be reported when loading
+            crate::math::parse_formula(&formula).map_err(|reason| FoliumError::InvalidFormula {
+                location: content_name_or_type.location,
+                reason,
+            })?;
+            global.push_element(AbstractElementData::Math(formula), element_type, maybe_name)
+        }
         Image => global.push_element(
-            AbstractElementData::Image(match content_tokens[0].token {
-                Value(PropertyValue::String(ref s)) => s.clone().into(),
-                _ => panic!("img content did not contain text value token"),
-            }),
+            AbstractElementData::Image(parse_path_argument(
+                &content_tokens,
+                content_name_or_type.location,
+                "a path to an image",
+            )?),
             element_type,
             maybe_name,
         ),
-        Centre => global.push_element(
-            AbstractElementData::Centre(
-                parse_content_definition(content_tokens.into_iter(), global)
-                    .map_err(|err| {
-                        eprintln!("{err}");
-                        panic!();
-                    })
-                    .unwrap(),
+        Video => global.push_element(
+            AbstractElementData::Video(parse_path_argument(
+                &content_tokens,
+                content_name_or_type.location,
+                "a path to a video",
+            )?),
+            element_type,
+            maybe_name,
+        ),
+        Audio => global.push_element(
+            AbstractElementData::Audio(parse_path_argument(
+                &content_tokens,
+                content_name_or_type.location,
+                "a path to an audio file",
+            )?),
+            element_type,
+            maybe_name,
+        ),
+        Rect | Ellipse | Circle => {
+            let content = if content_tokens.is_empty() {
+                None
+            } else {
+                Some(parse_content_definition(
+                    content_tokens.into_iter(),
+                    content_name_or_type.location,
+                    global,
+                )?)
+            };
+            let data = match element_type {
+                Rect => AbstractElementData::Rect(content),
+                Ellipse => AbstractElementData::Ellipse(content),
+                _ => AbstractElementData::Circle(content),
+            };
+            global.push_element(data, element_type, maybe_name)
+        }
+        BarChart | LineChart | PieChart => {
+            let data = parse_chart_data(&content_tokens, content_name_or_type.location)?;
+            let data = match element_type {
+                BarChart => AbstractElementData::BarChart(data),
+                LineChart => AbstractElementData::LineChart(data),
+                _ => AbstractElementData::PieChart(data),
+            };
+            global.push_element(data, element_type, maybe_name)
+        }
+        Plot => {
+            let data = parse_plot_data(&content_tokens, content_name_or_type.location)?;
+            global.push_element(AbstractElementData::Plot(data), element_type, maybe_name)
+        }
+        Line => {
+            let (from, to) =
+                parse_endpoints(&content_tokens, content_name_or_type.location, global)?;
+            global.push_element(
+                AbstractElementData::Line(from, to),
+                element_type,
+                maybe_name,
+            )
+        }
+        Arrow => {
+            let (from, to) =
+                parse_endpoints(&content_tokens, content_name_or_type.location, global)?;
+            global.push_element(
+                AbstractElementData::Arrow(from, to),
+                element_type,
+                maybe_name,
+            )
+        }
+        Rotate => match &content_tokens[..] {
+            // the angle comes first, like `rotate(30, text("DRAFT"))`
+            [FatToken {
+                token: Value(PropertyValue::Number(angle)),
+                ..
+            }, FatToken {
+                token: ListSeparator,
+                ..
+            }, ..] => global.push_element(
+                AbstractElementData::Rotate(
+                    angle % 360,
+                    parse_content_definition(
+                        content_tokens.into_iter().skip(2),
+                        content_name_or_type.location,
+                        global,
+                    )?,
+                ),
+                element_type,
+                maybe_name,
+            ),
+            [FatToken { token, location }, ..] => {
+                return Err(FoliumError::ExpectedReason {
+                    location: *location,
+                    expected: "an angle in degrees, followed by content",
+                    got: token.clone(),
+                })
+            }
+            [] => {
+                return Err(FoliumError::ExpectedReason {
+                    location: content_name_or_type.location,
+                    expected: "an angle in degrees, followed by content",
+                    got: ClosingArgsParen,
+                })
+            }
+        },
+        Place => match &content_tokens[..] {
+            // the position comes first, like `place(1600, 80, text("new!"))`
+            [FatToken {
+                token: Value(PropertyValue::Number(x)),
+                ..
+            }, FatToken {
+                token: ListSeparator,
+                ..
+            }, FatToken {
+                token: Value(PropertyValue::Number(y)),
+                ..
+            }, FatToken {
+                token: ListSeparator,
+                ..
+            }, ..] => global.push_element(
+                AbstractElementData::Place(
+                    global.scaled(*x),
+                    global.scaled(*y),
+                    parse_content_definition(
+                        content_tokens.into_iter().skip(4),
+                        content_name_or_type.location,
+                        global,
+                    )?,
+                ),
+                element_type,
+                maybe_name,
             ),
+            [FatToken { token, location }, ..] => {
+                return Err(FoliumError::ExpectedReason {
+                    location: *location,
+                    expected: "a position on the slide, followed by content",
+                    got: token.clone(),
+                })
+            }
+            [] => {
+                return Err(FoliumError::ExpectedReason {
+                    location: content_name_or_type.location,
+                    expected: "a position on the slide, followed by content",
+                    got: ClosingArgsParen,
+                })
+            }
+        },
+        Opacity => {
+            // the opacity comes first, like `opacity(0.5, text("later"))`
+            let opacity = match &content_tokens[..] {
+                [FatToken {
+                    token: Value(PropertyValue::Decimal(opacity)),
+                    ..
+                }, FatToken {
+                    token: ListSeparator,
+                    ..
+                }, ..]
+                    if *opacity <= 1.0 =>
+                {
+                    *opacity
+                }
+                [FatToken {
+                    token: Value(PropertyValue::Number(opacity @ (0 | 1))),
+                    ..
+                }, FatToken {
+                    token: ListSeparator,
+                    ..
+                }, ..] => *opacity as f32,
+                [FatToken { token, location }, ..] => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: *location,
+                        expected: "an opacity between 0 and 1, followed by content",
+                        got: token.clone(),
+                    })
+                }
+                [] => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: content_name_or_type.location,
+                        expected: "an opacity between 0 and 1, followed by content",
+                        got: ClosingArgsParen,
+                    })
+                }
+            };
+            global.push_element(
+                AbstractElementData::Opacity(
+                    (opacity * 255.0).round() as u8,
+                    parse_content_definition(
+                        content_tokens.into_iter().skip(2),
+                        content_name_or_type.location,
+                        global,
+                    )?,
+                ),
+                element_type,
+                maybe_name,
+            )
+        }
+        Centre => global.push_element(
+            AbstractElementData::Centre(parse_content_definition(
+                content_tokens.into_iter(),
+                content_name_or_type.location,
+                global,
+            )?),
+            element_type,
+            maybe_name,
+        ),
+        Frame => global.push_element(
+            AbstractElementData::Frame(parse_content_definition(
+                content_tokens.into_iter(),
+                content_name_or_type.location,
+                global,
+            )?),
+            element_type,
+            maybe_name,
+        ),
+        Shadow => global.push_element(
+            AbstractElementData::Shadow(parse_content_definition(
+                content_tokens.into_iter(),
+                content_name_or_type.location,
+                global,
+            )?),
             element_type,
             maybe_name,
         ),
         Padding => global.push_element(
-            AbstractElementData::Padding(
-                parse_content_definition(content_tokens.into_iter(), global)
-                    .map_err(|err| {
-                        eprintln!("{err}");
-                        panic!();
-                    })
-                    .unwrap(),
-            ),
+            AbstractElementData::Padding(parse_content_definition(
+                content_tokens.into_iter(),
+                content_name_or_type.location,
+                global,
+            )?),
             element_type,
             maybe_name,
         ),
@@ -325,11 +697,13 @@ fn parse_content_definition<'a, I: std::fmt::Debug + Iterator<Item = FatToken<'a
             let children_ids = children_tokens
                 .into_iter()
                 .map(|tokens| {
-                    parse_content_definition(tokens.iter().cloned(), global)
-                        .map_err(|err| panic!("{err}"))
-                        .unwrap()
+                    parse_content_definition(
+                        tokens.iter().cloned(),
+                        content_name_or_type.location,
+                        global,
+                    )
                 })
-                .collect();
+                .collect::<Result<_, _>>()?;
             global.push_element(
                 AbstractElementData::Row(children_ids),
                 element_type,
@@ -341,20 +715,307 @@ fn parse_content_definition<'a, I: std::fmt::Debug + Iterator<Item = FatToken<'a
             let children_ids = children_tokens
                 .into_iter()
                 .map(|tokens| {
-                    parse_content_definition(tokens.iter().cloned(), global)
-                        .map_err(|err| panic!("{err}"))
-                        .unwrap()
+                    parse_content_definition(
+                        tokens.iter().cloned(),
+                        content_name_or_type.location,
+                        global,
+                    )
                 })
-                .collect();
+                .collect::<Result<_, _>>()?;
             global.push_element(
                 AbstractElementData::Col(children_ids),
                 element_type,
                 maybe_name,
             )
         }
+        Grid => {
+            // the number of columns comes first, like `grid(cols: 3, ...)`
+            let columns = match &content_tokens[..] {
+                [FatToken {
+                    token: Ident("cols"),
+                    ..
+                }, FatToken {
+                    token: ValueAssignment,
+                    ..
+                }, FatToken {
+                    token: Value(PropertyValue::Number(columns)),
+                    ..
+                }, ..]
+                    if *columns > 0 =>
+                {
+                    *columns
+                }
+                [FatToken { token, location }, ..] => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: *location,
+                        expected: "the number of columns, like cols: 3",
+                        got: token.clone(),
+                    })
+                }
+                [] => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: content_name_or_type.location,
+                        expected: "the number of columns, like cols: 3",
+                        got: ClosingArgsParen,
+                    })
+                }
+            };
+            let children_tokens = split_child_elements(content_tokens.into_iter().skip(4));
+
+            let children_ids = children_tokens
+                .into_iter()
+                .map(|tokens| {
+                    parse_content_definition(
+                        tokens.iter().cloned(),
+                        content_name_or_type.location,
+                        global,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            global.push_element(
+                AbstractElementData::Grid(columns, children_ids),
+                element_type,
+                maybe_name,
+            )
+        }
+        Stack => {
+            let children_tokens = split_child_elements(content_tokens.iter().cloned());
+            let children_ids = children_tokens
+                .into_iter()
+                .map(|tokens| {
+                    parse_content_definition(
+                        tokens.iter().cloned(),
+                        content_name_or_type.location,
+                        global,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            global.push_element(
+                AbstractElementData::Stack(children_ids),
+                element_type,
+                maybe_name,
+            )
+        }
+        Flow => {
+            let children_tokens = split_child_elements(content_tokens.iter().cloned());
+            let children_ids = children_tokens
+                .into_iter()
+                .map(|tokens| {
+                    parse_content_definition(
+                        tokens.iter().cloned(),
+                        content_name_or_type.location,
+                        global,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            global.push_element(
+                AbstractElementData::Flow(children_ids),
+                element_type,
+                maybe_name,
+            )
+        }
+        List => {
+            let children_tokens = split_child_elements(content_tokens.iter().cloned());
+            let children_ids = children_tokens
+                .into_iter()
+                .map(|tokens| {
+                    parse_content_definition(
+                        tokens.iter().cloned(),
+                        content_name_or_type.location,
+                        global,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            global.push_element(
+                AbstractElementData::List(children_ids),
+                element_type,
+                maybe_name,
+            )
+        }
     })
 }
 
+/// Parses the argument of a text-like element, which is either a string literal
+/// or a `file: "path"` argument whose contents are used instead.
+fn parse_text_argument<'a>(
+    content_tokens: &[FatToken<'a>],
+    location: TokenLocation,
+) -> Result<String, FoliumError<'a>> {
+    if content_tokens.iter().any(|t| t.token == Concatenation) {
+        return concatenate(content_tokens);
+    }
+
+    match content_tokens {
+        [FatToken {
+            token: Value(PropertyValue::String(s)),
+            ..
+        }] => Ok(s.clone()),
+        [FatToken {
+            token: Ident("file"),
+            ..
+        }, FatToken {
+            token: ValueAssignment,
+            ..
+        }, FatToken {
+            token: Value(PropertyValue::String(path)),
+            location,
+        }] => fs::read_to_string(path).map_err(|_| FoliumError::UnreadableFile {
+            location: *location,
+            path: path.clone(),
+        }),
+        [FatToken { token, location }, ..] => Err(FoliumError::ExpectedReason {
+            location: *location,
+            expected: "a string or a file argument",
+            got: token.clone(),
+        }),
+        [] => Err(FoliumError::ExpectedReason {
+            location,
+            expected: "a string or a file argument",
+            got: ClosingArgsParen,
+        }),
+    }
+}
+
+/// Parses the argument of an element that refers to a file, like `img("photo.jpg")`.
+fn parse_path_argument<'a>(
+    content_tokens: &[FatToken<'a>],
+    location: TokenLocation,
+    expected: &'a str,
+) -> Result<PathBuf, FoliumError<'a>> {
+    match content_tokens.first() {
+        Some(FatToken {
+            token: Value(PropertyValue::String(s)),
+            ..
+        }) => Ok(s.clone().into()),
+        Some(FatToken { token, location }) => Err(FoliumError::ExpectedReason {
+            location: *location,
+            expected,
+            got: token.clone(),
+        }),
+        None => Err(FoliumError::ExpectedReason {
+            location,
+            expected,
+            got: ClosingArgsParen,
+        }),
+    }
+}
+
+/// Joins values separated by `+` into a single string, like `"Chapter " + 3`. Loop variables
+/// have already been substituted at this point, so any identifier left over is undefined.
+fn concatenate<'a>(tokens: &[FatToken<'a>]) -> Result<String, FoliumError<'a>> {
+    let mut result = String::new();
+
+    for (idx, fat_token) in tokens.iter().enumerate() {
+        let expecting_operand = idx % 2 == 0;
+        match &fat_token.token {
+            Value(PropertyValue::String(s)) if expecting_operand => result.push_str(s),
+            Value(value) if expecting_operand => result.push_str(&value.to_string()),
+            Ident(name) if expecting_operand => {
+                return Err(FoliumError::UndefinedLoopVariable {
+                    location: fat_token.location,
+                    name: name.to_string(),
+                })
+            }
+            Concatenation if !expecting_operand => {}
+            other => {
+                return Err(FoliumError::ExpectedReason {
+                    location: fat_token.location,
+                    expected: if expecting_operand {
+                        "a value to concatenate"
+                    } else {
+                        "a + between the values to concatenate"
+                    },
+                    got: other.clone(),
+                })
+            }
+        }
+    }
+
+    match tokens.last() {
+        Some(FatToken {
+            token: Concatenation,
+            location,
+        }) => Err(FoliumError::ExpectedReason {
+            location: *location,
+            expected: "a value to concatenate",
+            got: ClosingArgsParen,
+        }),
+        _ => Ok(result),
+    }
+}
+
+/// Parses the remainder of a `use(name)` reference and resolves it to the ID of the
+/// element that was declared earlier with `def name :: ...`.
+fn parse_reference<'a, I: Iterator<Item = FatToken<'a>>>(
+    mut iter: I,
+    location: TokenLocation,
+    global: &GlobalState,
+) -> Result<AbstractElementID, FoliumError<'a>> {
+    match iter.next() {
+        Some(FatToken {
+            token: OpeningArgsParen,
+            ..
+        }) => {}
+        Some(FatToken {
+            token: other_token,
+            location,
+        }) => {
+            return Err(FoliumError::ExpectedToken {
+                location,
+                expected: OpeningArgsParen,
+                got: other_token,
+            })
+        }
+        None => {
+            return Err(FoliumError::UnexpectedFileEndWithToken {
+                location,
+                expected: OpeningArgsParen,
+            })
+        }
+    }
+
+    let referenced_id = match iter.next() {
+        Some(FatToken {
+            token: Ident(name),
+            location,
+        }) => global
+            .get_definition(name)
+            .ok_or(FoliumError::UndefinedReference { location, name })?,
+        Some(FatToken {
+            token: other_token,
+            location,
+        }) => {
+            return Err(FoliumError::ExpectedReason {
+                location,
+                expected: "the name of a definition",
+                got: other_token,
+            })
+        }
+        None => {
+            return Err(FoliumError::UnexpectedFileEndWithReason {
+                location,
+                expected: "the name of a definition",
+            })
+        }
+    };
+
+    match iter.next() {
+        Some(FatToken {
+            token: ClosingArgsParen,
+            ..
+        }) => Ok(referenced_id),
+        None => Err(FoliumError::UnbalancedParentheses { location }),
+        Some(FatToken {
+            token: other_token,
+            location,
+        }) => Err(FoliumError::ExpectedToken {
+            location,
+            expected: ClosingArgsParen,
+            got: other_token,
+        }),
+    }
+}
+
 fn split_child_elements<'a, I: std::fmt::Debug + Iterator<Item = FatToken<'a>>>(
     mut iter: I,
 ) -> Vec<Vec<FatToken<'a>>> {
@@ -402,12 +1063,27 @@ fn split_child_elements<'a, I: std::fmt::Debug + Iterator<Item = FatToken<'a>>>(
 pub fn load_from_file<'a, P: AsRef<Path> + 'a>(
     global: &'a GlobalState,
     path: P,
-) -> Result<(), FoliumError<'a>> {
+) -> Result<(), Vec<FoliumError<'a>>> {
     let source = fs::read_to_string(path.as_ref()).expect("could not open file");
     load(global, source)
 }
 
-pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>> {
+/// Splits source code into tokens. Comments are kept as tokens, so that the
+/// token stream contains everything that is needed to reconstruct the source.
+pub fn tokenize(source: &str) -> Vec<FatToken<'static>> {
+    let comments = source
+        .split_inclusive("\n")
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("//"))
+        .map(|(line_idx, line)| FatToken {
+            token: Comment(line.trim_end().to_string()),
+            location: TokenLocation {
+                line: line_idx,
+                col: 0,
+            },
+        })
+        .collect::<Vec<_>>();
+
     let mut all_characters = source
         .split_inclusive("\n")
         .enumerate()
@@ -464,6 +1140,11 @@ pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>>
                 col_idx: col,
                 value: ListSeparator,
             },
+            '+' => RawToken::AlreadyParsed {
+                line_idx: line,
+                col_idx: col,
+                value: Concatenation,
+            },
             ':' => {
                 if all_characters.next_if(|&(_, _, c)| c == ':').is_some() {
                     RawToken::AlreadyParsed {
@@ -528,7 +1209,8 @@ pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>>
                             ClosingArgsParen => ")",
                             OpeningParamsParen => "{",
                             ClosingParamsParen => "}",
-                            StringDelim | Value(_) | Ident(_) => unreachable!(),
+                            Concatenation => "+",
+                            StringDelim | Value(_) | Ident(_) | Comment(_) => unreachable!(),
                         }
                         .chars()
                         .collect(),
@@ -599,6 +1281,27 @@ pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>>
                         },
                         token: Value(PropertyValue::Number(number)),
                     });
+                } else if let Some(decimal) = working_value
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == '.')
+                    .then(|| working_value.parse::<f32>().ok())
+                    .flatten()
+                {
+                    contiguous_tokens.push(FatToken {
+                        location: TokenLocation {
+                            line: line_idx,
+                            col: col_idx,
+                        },
+                        token: Value(PropertyValue::Decimal(decimal)),
+                    });
+                } else if let Some(length) = Length::parse(&working_value) {
+                    contiguous_tokens.push(FatToken {
+                        location: TokenLocation {
+                            line: line_idx,
+                            col: col_idx,
+                        },
+                        token: Value(PropertyValue::Length(length)),
+                    });
                 } else if let Ok(boolean) = working_value.parse::<bool>() {
                     contiguous_tokens.push(FatToken {
                         location: TokenLocation {
@@ -619,6 +1322,17 @@ pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>>
                         let g = u8::from_str_radix(&colour[3..5], 16).unwrap();
                         let b = u8::from_str_radix(&colour[5..7], 16).unwrap();
 
+                        Value(PropertyValue::Colour(r, g, b))
+                    } else if working_value.starts_with('#')
+                        && working_value.len() == 4
+                        && working_value.chars().skip(1).all(|c| c.is_ascii_hexdigit())
+                    {
+                        // parseable as shorthand colour: every digit is doubled, so #0af is #00aaff
+
+                        let [r, g, b] = [1, 2, 3].map(|idx| {
+                            u8::from_str_radix(&working_value[idx..idx + 1], 16).unwrap() * 0x11
+                        });
+
                         Value(PropertyValue::Colour(r, g, b))
                     } else if working_value.starts_with('<') {
                         // parseable as size spec:   <w, h> where w and h may be one of '_' or Number
@@ -626,7 +1340,7 @@ pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>>
                         // TODO: add error handling here
                         // dbg!(&working_value);
                         let (width, height) = working_value
-                            .trim_matches(&['<', '>'])
+                            .trim_matches(['<', '>'])
                             .split_once(';')
                             .unwrap();
                         let width_val = if width == "_" {
@@ -668,240 +1382,3509 @@ pub fn load(global: &GlobalState, source: String) -> Result<(), FoliumError<'_>>
         }
     }
 
-    // group tokens by slide
-    let mut grouped_tokens: Vec<Vec<FatToken>> = Vec::new();
-    let mut current_slide_tokens: Vec<FatToken> = Vec::new();
-
+    // tokens are produced in source order, so the comments can be merged back in by location
+    let mut tokens = Vec::with_capacity(contiguous_tokens.len() + comments.len());
+    let mut comments = comments.into_iter().peekable();
     for fat_token in contiguous_tokens {
-        match fat_token {
-            FatToken {
-                token: OpeningSlideParen,
-                ..
-            } => {}
+        while let Some(comment) = comments.next_if(|c| c.location < fat_token.location) {
+            tokens.push(comment);
+        }
+        tokens.push(fat_token);
+    }
+    tokens.extend(comments);
+
+    tokens
+}
+
+pub fn load(global: &GlobalState, source: String) -> Result<(), Vec<FoliumError<'_>>> {
+    let contiguous_tokens = tokenize(&source)
+        .into_iter()
+        .filter(|fat_token| !matches!(fat_token.token, Comment(_)))
+        .map(|fat_token| match fat_token.token {
+            Value(PropertyValue::String(string)) => Ok(FatToken {
+                token: Value(PropertyValue::String(
+                    interpolate_environment(&string).map_err(|name| {
+                        FoliumError::UndefinedVariable {
+                            location: fat_token.location,
+                            name,
+                        }
+                    })?,
+                )),
+                location: fat_token.location,
+            }),
+            _ => Ok(fat_token),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| vec![err])?;
+    check_brackets(&contiguous_tokens).map_err(|err| vec![err])?;
+
+    // group tokens into top-level items: the deck preamble, slides, loops, definitions and style classes.
+    // When an item contains an error, parsing continues with the next item so that all errors are reported.
+    let mut top_level_tokens = contiguous_tokens.into_iter().peekable();
+    let mut errors = Vec::new();
+
+    while let Some(fat_token) = top_level_tokens.next() {
+        if let Err(err) = parse_top_level_item(fat_token, &mut top_level_tokens, global) {
+            errors.push(err);
+
+            while top_level_tokens
+                .next_if(|token| !starts_top_level_item(&token.token))
+                .is_some()
+            {}
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Checks that every bracket that is opened is closed again, by a bracket of the same kind,
+/// before anything is parsed: everything after an unclosed bracket would otherwise be taken
+/// to be inside of it. Closing brackets that were never opened are reported by the parser.
+fn check_brackets<'a>(tokens: &[FatToken<'a>]) -> Result<(), FoliumError<'a>> {
+    let mut open: Vec<&FatToken> = Vec::new();
+    for fat_token in tokens {
+        let opening = match fat_token.token {
+            OpeningSlideParen | OpeningArgsParen | OpeningParamsParen => {
+                open.push(fat_token);
+                continue;
+            }
+            ClosingSlideParen => OpeningSlideParen,
+            ClosingArgsParen => OpeningArgsParen,
+            ClosingParamsParen => OpeningParamsParen,
+            _ => continue,
+        };
+        match open.pop() {
+            Some(last) if last.token != opening => {
+                return Err(FoliumError::UnclosedBracket {
+                    location: last.location,
+                    bracket: last.token.clone(),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    match open.last() {
+        Some(last) => Err(FoliumError::UnclosedBracket {
+            location: last.location,
+            bracket: last.token.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Takes the tokens up to the closing bracket of a slide that was just opened,
+/// skipping over the brackets of any lists inside of it.
+fn take_slide_tokens<'a, I: Iterator<Item = FatToken<'a>>>(iter: &mut I) -> Vec<FatToken<'a>> {
+    let mut depth: usize = 1;
+    iter.take_while(|token| {
+        match token.token {
+            OpeningSlideParen => depth += 1,
+            ClosingSlideParen => depth -= 1,
+            _ => {}
+        }
+        depth > 0
+    })
+    .collect()
+}
+
+/// Takes the tokens of a loop that follow its `for`: the loop variable, `in`, and the list of
+/// values and the slide template, which are the first two groups of tokens in brackets.
+fn take_loop_tokens<'a, I: Iterator<Item = FatToken<'a>>>(iter: &mut I) -> Vec<FatToken<'a>> {
+    let mut depth: usize = 0;
+    let mut groups = 0;
+    let mut tokens = Vec::new();
+    for token in iter.by_ref() {
+        match token.token {
+            OpeningSlideParen => depth += 1,
+            ClosingSlideParen if depth == 1 => {
+                depth = 0;
+                groups += 1;
+            }
+            ClosingSlideParen => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        tokens.push(token);
+        if groups == 2 {
+            break;
+        }
+    }
+    tokens
+}
+
+fn starts_top_level_item(token: &Token) -> bool {
+    matches!(
+        token,
+        OpeningSlideParen | Ident("deck" | "style" | "variant" | "def" | "for" | "@if")
+    )
+}
+
+/// Parses a single top-level item, of which `fat_token` is the first token.
+fn parse_top_level_item<'a, I: Iterator<Item = FatToken<'a>>>(
+    fat_token: FatToken<'a>,
+    top_level_tokens: &mut I,
+    global: &'a GlobalState,
+) -> Result<(), FoliumError<'a>> {
+    match fat_token.token {
+        OpeningSlideParen => {
+            let slide_tokens = take_slide_tokens(top_level_tokens);
+            parse_slide(slide_tokens, fat_token.location, global)?;
+        }
+        Ident("@if") => {
+            let location = expect_token(top_level_tokens, Ident("profile"), fat_token.location)?;
+            let location = expect_token(top_level_tokens, OpeningArgsParen, location)?;
+            let profile = match top_level_tokens.next() {
+                Some(FatToken {
+                    token: Value(PropertyValue::String(profile)),
+                    ..
+                }) => profile,
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedReason {
+                        location,
+                        expected: "the name of a profile",
+                        got: token,
+                    })
+                }
+                None => {
+                    return Err(FoliumError::UnexpectedFileEndWithReason {
+                        location,
+                        expected: "the name of a profile",
+                    })
+                }
+            };
+            let location = expect_token(top_level_tokens, ClosingArgsParen, location)?;
+            let location = expect_token(top_level_tokens, OpeningSlideParen, location)?;
+
+            // the section either contains top-level items, or is a single slide itself
+            let section_tokens = take_slide_tokens(top_level_tokens);
+            if global.has_profile(&profile) {
+                if section_tokens
+                    .first()
+                    .is_some_and(|token| starts_top_level_item(&token.token))
+                {
+                    let mut section_tokens = section_tokens.into_iter();
+                    while let Some(fat_token) = section_tokens.next() {
+                        parse_top_level_item(fat_token, &mut section_tokens, global)?;
+                    }
+                } else {
+                    parse_slide(section_tokens, location, global)?;
+                }
+            }
+        }
+        Ident("for") => {
+            // the loop is taken as a whole first, so that after an error in it, its slide
+            // template is not left behind to be parsed as a slide of its own
+            let top_level_tokens = &mut take_loop_tokens(top_level_tokens).into_iter();
+            let variable = match top_level_tokens.next() {
+                Some(FatToken {
+                    token: Ident(variable),
+                    ..
+                }) => variable,
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedReason {
+                        location,
+                        expected: "a loop variable name",
+                        got: token,
+                    })
+                }
+                None => {
+                    return Err(FoliumError::UnexpectedFileEndWithReason {
+                        location: fat_token.location,
+                        expected: "a loop variable name",
+                    })
+                }
+            };
+
+            let location = expect_token(top_level_tokens, Ident("in"), fat_token.location)?;
+            let location = expect_token(top_level_tokens, OpeningSlideParen, location)?;
+
+            let values = top_level_tokens
+                .by_ref()
+                .take_while(|token| token.token != ClosingSlideParen)
+                .filter(|token| token.token != ListSeparator)
+                .map(|token| match token.token {
+                    Value(value) => Ok(value),
+                    other_token => Err(FoliumError::ExpectedReason {
+                        location: token.location,
+                        expected: "a value to loop over",
+                        got: other_token,
+                    }),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let location = expect_token(top_level_tokens, OpeningSlideParen, location)?;
+            let template_tokens = take_slide_tokens(top_level_tokens);
+
+            for value in values {
+                parse_slide(
+                    substitute_variable(&template_tokens, variable, &value),
+                    location,
+                    global,
+                )?;
+            }
+        }
+        Ident("deck") => {
+            if global.number_of_slides() > 0 {
+                return Err(FoliumError::ExpectedReason {
+                    location: fat_token.location,
+                    expected: "the deck block to come before the first slide",
+                    got: fat_token.token,
+                });
+            }
+
+            expect_token(top_level_tokens, OpeningParamsParen, fat_token.location)?;
+            let deck_tokens = top_level_tokens
+                .by_ref()
+                .take_while(|token| token.token != ClosingParamsParen)
+                .collect::<Vec<_>>();
+
+            let mut deck = DeckSettings::default();
+            let (mut width, mut height, mut aspect_ratio) = (None, None, None);
+            let (mut watermark_text, mut watermark_image, mut watermark_size) = (None, None, 96);
+            let (mut watermark_opacity, mut watermark_position) = (0.2, String::from("centre"));
+            for (key, value) in parse_properties(&deck_tokens)? {
+                match (key.as_str(), value) {
+                    ("width", PropertyValue::Number(value)) => width = Some(value),
+                    ("height", PropertyValue::Number(value)) => height = Some(value),
+                    ("aspect", PropertyValue::String(ratio))
+                        if parse_aspect_ratio(&ratio).is_some() =>
+                    {
+                        aspect_ratio = parse_aspect_ratio(&ratio)
+                    }
+                    ("title", value) => deck.title = Some(value.to_string()),
+                    ("scaling", PropertyValue::String(scaling))
+                        if matches!(scaling.as_str(), "nearest" | "linear" | "best") =>
+                    {
+                        deck.image_scaling = scaling
+                    }
+                    ("author", value) => deck.author = Some(value.to_string()),
+                    (slot @ ("header" | "footer"), PropertyValue::String(name)) => {
+                        let id = global.get_definition(&name).ok_or_else(|| {
+                            FoliumError::UndefinedDefinition {
+                                location: fat_token.location,
+                                name: name.clone(),
+                            }
+                        })?;
+                        if slot == "header" {
+                            deck.header = Some(id);
+                        } else {
+                            deck.footer = Some(id);
+                        }
+                    }
+                    ("watermark", PropertyValue::String(text)) => watermark_text = Some(text),
+                    ("watermark-image", PropertyValue::String(path)) => {
+                        watermark_image = Some(PathBuf::from(path))
+                    }
+                    ("watermark-size", PropertyValue::Number(size)) => watermark_size = size,
+                    ("watermark-opacity", PropertyValue::Decimal(opacity))
+                        if (0.0..=1.0).contains(&opacity) =>
+                    {
+                        watermark_opacity = opacity
+                    }
+                    ("watermark-opacity", PropertyValue::Number(opacity @ (0 | 1))) => {
+                        watermark_opacity = opacity as f32
+                    }
+                    ("watermark-position", PropertyValue::String(position))
+                        if matches!(
+                            position.as_str(),
+                            "centre"
+                                | "top"
+                                | "bottom"
+                                | "left"
+                                | "right"
+                                | "top-left"
+                                | "top-right"
+                                | "bottom-left"
+                                | "bottom-right"
+                        ) =>
+                    {
+                        watermark_position = position
+                    }
+                    (other, _) => {
+                        return Err(FoliumError::UnknownSetting {
+                            location: fat_token.location,
+                            block: "deck",
+                            key: other.to_string(),
+                        })
+                    }
+                }
+            }
+            // an image takes the place of text
+            let watermark_mark = match (watermark_image, watermark_text) {
+                (Some(path), _) => Some(WatermarkMark::Image(path)),
+                (None, Some(text)) => Some(WatermarkMark::Text {
+                    text,
+                    size: global.scaled(watermark_size),
+                }),
+                (None, None) => None,
+            };
+            deck.watermark = watermark_mark.map(|mark| Watermark {
+                mark,
+                alpha: (watermark_opacity * 255.0).round() as u8,
+                position: watermark_position,
+            });
+            (deck.width, deck.height) = aspect_size(
+                aspect_ratio,
+                width,
+                height,
+                (deck.width, deck.height),
+                fat_token.location,
+            )?;
+            global.set_deck(deck);
+        }
+        Ident("style") => {
+            let name = match top_level_tokens.next() {
+                Some(FatToken {
+                    token: Ident(name), ..
+                }) => name,
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedReason {
+                        location,
+                        expected: "a name for the style class",
+                        got: token,
+                    })
+                }
+                None => {
+                    return Err(FoliumError::UnexpectedFileEndWithReason {
+                        location: fat_token.location,
+                        expected: "a name for the style class",
+                    })
+                }
+            };
+
+            expect_token(top_level_tokens, OpeningParamsParen, fat_token.location)?;
+            let class_tokens = top_level_tokens
+                .by_ref()
+                .take_while(|token| token.token != ClosingParamsParen)
+                .collect::<Vec<_>>();
+
+            global.define_style_class(name.to_string(), parse_properties(&class_tokens)?);
+        }
+        Ident("variant") => {
+            let name = match top_level_tokens.next() {
+                Some(FatToken {
+                    token: Ident(name), ..
+                }) => name,
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedReason {
+                        location,
+                        expected: "a name for the variant",
+                        got: token,
+                    })
+                }
+                None => {
+                    return Err(FoliumError::UnexpectedFileEndWithReason {
+                        location: fat_token.location,
+                        expected: "a name for the variant",
+                    })
+                }
+            };
+
+            expect_token(top_level_tokens, OpeningParamsParen, fat_token.location)?;
+            let variant_tokens = top_level_tokens
+                .by_ref()
+                .take_while(|token| token.token != ClosingParamsParen)
+                .collect::<Vec<_>>();
+
+            let mut palette = Palette::default();
+            for (key, value) in parse_properties(&variant_tokens)? {
+                match (key.as_str(), value) {
+                    ("invert", PropertyValue::Boolean(invert)) => palette.invert = invert,
+                    ("swap", PropertyValue::List(swaps)) => {
+                        for swap in swaps {
+                            let pair = match &swap {
+                                PropertyValue::List(pair) => pair.as_slice(),
+                                _ => &[],
+                            };
+                            match pair {
+                                [PropertyValue::Colour(r1, g1, b1), PropertyValue::Colour(r2, g2, b2)] =>
+                                {
+                                    palette.swaps.insert((*r1, *g1, *b1), (*r2, *g2, *b2));
+                                }
+                                _ => {
+                                    return Err(FoliumError::ExpectedReason {
+                                        location: fat_token.location,
+                                        expected: "a pair of colours to swap",
+                                        got: Value(swap.clone()),
+                                    })
+                                }
+                            }
+                        }
+                    }
+                    (other, _) => {
+                        return Err(FoliumError::UnknownSetting {
+                            location: fat_token.location,
+                            block: "variant",
+                            key: other.to_string(),
+                        })
+                    }
+                }
+            }
+            global.define_palette(name.to_string(), palette);
+        }
+        Ident("def") => {
+            let mut first_bracket = None;
+            let mut stray_bracket = None;
+            let mut brackets: usize = 0;
+
+            let definition_tokens = top_level_tokens
+                .by_ref()
+                .take_while_inclusive(|token| match token.token {
+                    OpeningArgsParen => {
+                        first_bracket.get_or_insert(token.location);
+                        brackets += 1;
+                        true
+                    }
+                    ClosingArgsParen if brackets == 0 => {
+                        stray_bracket = Some(token.location);
+                        false
+                    }
+                    ClosingArgsParen => {
+                        brackets -= 1;
+                        brackets != 0
+                    }
+                    _ => first_bracket.is_none() || brackets != 0,
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(location) = stray_bracket {
+                return Err(FoliumError::UnbalancedParentheses { location });
+            }
+            if let Some(location) = first_bracket.filter(|_| brackets != 0) {
+                return Err(FoliumError::UnbalancedParentheses { location });
+            }
+
+            // a definition must always be named, otherwise it can never be referenced
+            match definition_tokens.first() {
+                Some(FatToken {
+                    token: Ident(name), ..
+                }) if ElementType::try_from(*name).is_err() => {}
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: *location,
+                        expected: "a name for the definition",
+                        got: token.clone(),
+                    })
+                }
+                None => {
+                    return Err(FoliumError::UnexpectedFileEndWithReason {
+                        location: fat_token.location,
+                        expected: "a name for the definition",
+                    })
+                }
+            }
+
+            let id = parse_content_definition(
+                definition_tokens.into_iter(),
+                fat_token.location,
+                global,
+            )?;
+            let name = global
+                .get_element_by_id(id)
+                .and_then(|elem| elem.name().clone())
+                .expect("named definition did not produce a named element");
+            global.define(name, id);
+        }
+        other_token => {
+            return Err(FoliumError::ExpectedReason {
+                location: fat_token.location,
+                expected: "a slide or a definition",
+                got: other_token,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+/// Consumes the next token, making sure it is the expected one. Returns the location of
+/// the consumed token, or the given location if the file ended before that.
+fn expect_token<'a, I: Iterator<Item = FatToken<'a>>>(
+    iter: &mut I,
+    expected: Token<'a>,
+    location: TokenLocation,
+) -> Result<TokenLocation, FoliumError<'a>> {
+    match iter.next() {
+        Some(FatToken { token, location }) if token == expected => Ok(location),
+        Some(FatToken { token, location }) => Err(FoliumError::ExpectedToken {
+            location,
+            expected,
+            got: token,
+        }),
+        None => Err(FoliumError::UnexpectedFileEndWithToken { location, expected }),
+    }
+}
+
+/// Replaces every occurrence of a loop variable in a slide template with the given value.
+/// Bare identifiers are replaced by the value itself, whereas `$variable` inside string
+/// literals is replaced by its textual representation. Identifiers that are used as a
+/// name (i.e. followed by `::` or `:`) are left alone.
+fn substitute_variable<'a>(
+    tokens: &[FatToken<'a>],
+    variable: &str,
+    value: &PropertyValue,
+) -> Vec<FatToken<'a>> {
+    let placeholder = format!("${variable}");
+
+    tokens
+        .iter()
+        .enumerate()
+        .map(|(idx, fat_token)| {
+            let is_name = matches!(
+                tokens.get(idx + 1),
+                Some(FatToken {
+                    token: Definition | ValueAssignment,
+                    ..
+                })
+            );
+
+            let token = match &fat_token.token {
+                Ident(ident) if *ident == variable && !is_name => Value(value.clone()),
+                Value(PropertyValue::String(string)) => Value(PropertyValue::String(
+                    string.replace(&placeholder, &value.to_string()),
+                )),
+                other => other.clone(),
+            };
+
             FatToken {
-                token: ClosingSlideParen,
+                token,
+                location: fat_token.location,
+            }
+        })
+        .collect()
+}
+
+/// Parses a single property value, of which `fat_token` is the first token.
+/// Lists are written between square brackets, like `[4, 2]`.
+fn parse_value<'a, I: Iterator<Item = FatToken<'a>>>(
+    fat_token: FatToken<'a>,
+    iter: &mut I,
+) -> Result<PropertyValue, FoliumError<'a>> {
+    match fat_token.token {
+        Value(pv) => Ok(pv),
+        // bare words like `left` or `bold` are shorthand for strings
+        Ident(word) => Ok(PropertyValue::String(word.to_string())),
+        OpeningSlideParen => {
+            let mut values = Vec::new();
+            loop {
+                match iter.next() {
+                    Some(FatToken {
+                        token: ClosingSlideParen,
+                        ..
+                    }) => break,
+                    Some(FatToken {
+                        token: ListSeparator,
+                        ..
+                    }) => {}
+                    Some(element_token) => values.push(parse_value(element_token, iter)?),
+                    None => {
+                        return Err(FoliumError::UnexpectedFileEndWithToken {
+                            location: fat_token.location,
+                            expected: ClosingSlideParen,
+                        })
+                    }
+                }
+            }
+            Ok(PropertyValue::List(values))
+        }
+        other_token => Err(FoliumError::ExpectedReason {
+            location: fat_token.location,
+            expected: "a parameter value",
+            got: other_token,
+        }),
+    }
+}
+
+/// The width and height of a deck or a slide. With an aspect ratio, the one that is not given
+/// follows from the one that is, see [`size_for_aspect_ratio`], and if both are given, they take
+/// precedence over it; without one, whatever is not given keeps its default.
+fn aspect_size<'a>(
+    aspect_ratio: Option<(u32, u32)>,
+    width: Option<u32>,
+    height: Option<u32>,
+    default: (u32, u32),
+    location: TokenLocation,
+) -> Result<(u32, u32), FoliumError<'a>> {
+    match (aspect_ratio, width, height) {
+        (Some(_), Some(width), Some(height)) => Ok((width, height)),
+        (Some(ratio), width, height) => size_for_aspect_ratio(ratio, width, height, default.1)
+            .ok_or(FoliumError::SizeTooLarge { location }),
+        (None, width, height) => Ok((width.unwrap_or(default.0), height.unwrap_or(default.1))),
+    }
+}
+
+/// Parses a list of `key: value` pairs, optionally separated by commas,
+/// as found between the braces of a style block.
+fn parse_properties<'a>(
+    tokens: &[FatToken<'a>],
+) -> Result<HashMap<String, PropertyValue>, FoliumError<'a>> {
+    let mut properties = HashMap::new();
+    let mut iter = tokens.iter().cloned().peekable();
+
+    while let Some(fat_token) = iter.next() {
+        let key = match fat_token.token {
+            Ident(key) => key.to_string(),
+            ListSeparator => continue,
+            other_token => {
+                return Err(FoliumError::ExpectedReason {
+                    location: fat_token.location,
+                    expected: "a style directive",
+                    got: other_token,
+                })
+            }
+        };
+
+        let location = expect_token(&mut iter, ValueAssignment, fat_token.location)?;
+
+        let value_location = iter.peek().map_or(location, |token| token.location);
+        let mut value = match iter.next() {
+            Some(first_token) => parse_value(first_token, &mut iter)?,
+            None => {
+                return Err(FoliumError::UnexpectedFileEndWithReason {
+                    location,
+                    expected: "a parameter value",
+                })
+            }
+        };
+
+        // a ratio like `16:9` is two numbers with a colon between them, which is kept as text
+        if let PropertyValue::Number(numerator) = value {
+            if let Some(colon) = iter.next_if(|token| token.token == ValueAssignment) {
+                value = match iter.next() {
+                    Some(FatToken {
+                        token: Value(PropertyValue::Number(denominator)),
+                        ..
+                    }) => PropertyValue::String(format!("{numerator}:{denominator}")),
+                    Some(FatToken { token, location }) => {
+                        return Err(FoliumError::ExpectedReason {
+                            location,
+                            expected: "the second number of a ratio",
+                            got: token,
+                        })
+                    }
+                    None => {
+                        return Err(FoliumError::UnexpectedFileEndWithReason {
+                            location: colon.location,
+                            expected: "the second number of a ratio",
+                        })
+                    }
+                };
+            }
+        }
+
+        if let Err(expected) = check_keyword(&key, &value) {
+            return Err(FoliumError::InvalidKeyword {
+                location: value_location,
+                key,
+                value: value.to_string(),
+                expected,
+            });
+        }
+        properties.insert(key, value);
+    }
+
+    Ok(properties)
+}
+
+/// Makes sure that the elements a connector refers to are part of the slide.
+fn check_connector_endpoints<'a>(
+    global: &GlobalState,
+    content_root_id: AbstractElementID,
+    connector: AbstractElementID,
+    location: TokenLocation,
+) -> Result<(), FoliumError<'a>> {
+    let (from, to) = match global.get_element_by_id(connector).unwrap().data() {
+        AbstractElementData::Line(from, to) | AbstractElementData::Arrow(from, to) => {
+            (from.clone(), to.clone())
+        }
+        _ => unreachable!("connector was not a line or an arrow"),
+    };
+
+    let slide_elements = global.traverse(content_root_id);
+    for endpoint in [from, to] {
+        if let Endpoint::Element(name) = endpoint {
+            let is_on_slide = slide_elements.iter().any(|id| {
+                global
+                    .get_element_by_id(*id)
+                    .is_some_and(|elem| elem.name().as_deref() == Some(name.as_str()))
+            });
+            if !is_on_slide {
+                return Err(FoliumError::UndefinedEndpoint { location, name });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the data of a chart, which is either given inline as `labels: [...], values: [...]`
+/// or read from a CSV file with `file: "path"`.
+fn parse_chart_data<'a>(
+    content_tokens: &[FatToken<'a>],
+    location: TokenLocation,
+) -> Result<ChartData, FoliumError<'a>> {
+    let mut arguments = parse_properties(content_tokens)?;
+
+    if let Some(file) = arguments.remove("file") {
+        let PropertyValue::String(path) = file else {
+            return Err(FoliumError::ExpectedReason {
+                location,
+                expected: "a path to a CSV file",
+                got: Value(file),
+            });
+        };
+        let source = fs::read_to_string(&path)
+            .map_err(|_| FoliumError::UnreadableFile { location, path })?;
+        return chart::parse_csv(&source)
+            .map_err(|reason| FoliumError::InvalidChartData { location, reason });
+    }
+
+    let labels = match arguments.remove("labels") {
+        Some(PropertyValue::List(labels)) => labels
+            .into_iter()
+            .map(|label| match label {
+                PropertyValue::String(label) => label,
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>(),
+        Some(other) => {
+            return Err(FoliumError::ExpectedReason {
+                location,
+                expected: "a list of labels",
+                got: Value(other),
+            })
+        }
+        None => {
+            return Err(FoliumError::ExpectedReason {
+                location,
+                expected: "labels",
+                got: ClosingArgsParen,
+            })
+        }
+    };
+
+    let values = match arguments.remove("values") {
+        Some(PropertyValue::List(values)) => values
+            .into_iter()
+            .map(|value| match value {
+                PropertyValue::Number(value) => Ok(value as f64),
+                other => Err(FoliumError::ExpectedReason {
+                    location,
+                    expected: "a number",
+                    got: Value(other),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(other) => {
+            return Err(FoliumError::ExpectedReason {
+                location,
+                expected: "a list of values",
+                got: Value(other),
+            })
+        }
+        None => {
+            return Err(FoliumError::ExpectedReason {
+                location,
+                expected: "values",
+                got: ClosingArgsParen,
+            })
+        }
+    };
+
+    if labels.len() != values.len() {
+        return Err(FoliumError::InvalidChartData {
+            location,
+            reason: format!(
+                "there are {} labels but {} values",
+                labels.len(),
+                values.len()
+            ),
+        });
+    }
+
+    Ok(ChartData { labels, values })
+}
+
+/// Parses the arguments of a plot: a CSV file and the names of the columns with the x and y
+/// values, which are read right away.
+fn parse_plot_data<'a>(
+    content_tokens: &[FatToken<'a>],
+    location: TokenLocation,
+) -> Result<PlotData, FoliumError<'a>> {
+    let mut arguments = parse_properties(content_tokens)?;
+    let mut string_argument = |name: &'a str, expected: &'a str| match arguments.remove(name) {
+        Some(PropertyValue::String(value)) => Ok(value),
+        Some(other) => Err(FoliumError::ExpectedReason {
+            location,
+            expected,
+            got: Value(other),
+        }),
+        None => Err(FoliumError::ExpectedReason {
+            location,
+            expected: name,
+            got: ClosingArgsParen,
+        }),
+    };
+
+    let path = string_argument("file", "a path to a CSV file")?;
+    let x = string_argument("x", "the name of a column")?;
+    let y = string_argument("y", "the name of a column")?;
+
+    let source =
+        fs::read_to_string(&path).map_err(|_| FoliumError::UnreadableFile { location, path })?;
+    chart::parse_csv_columns(&source, &x, &y)
+        .map_err(|reason| FoliumError::InvalidChartData { location, reason })
+}
+
+/// Parses the `from` and `to` arguments of a line or an arrow. Each is either the name
+/// of an element on the slide or a point like `[100, 200]`, which is scaled like other sizes.
+fn parse_endpoints<'a>(
+    content_tokens: &[FatToken<'a>],
+    location: TokenLocation,
+    global: &GlobalState,
+) -> Result<(Endpoint, Endpoint), FoliumError<'a>> {
+    let mut arguments = parse_properties(content_tokens)?;
+
+    let mut endpoint = |key: &'a str| match arguments.remove(key) {
+        Some(PropertyValue::String(name)) => Ok(Endpoint::Element(name)),
+        Some(PropertyValue::List(point)) => match point[..] {
+            [PropertyValue::Number(x), PropertyValue::Number(y)] => {
+                Ok(Endpoint::Point(global.scaled(x), global.scaled(y)))
+            }
+            _ => Err(FoliumError::ExpectedReason {
+                location,
+                expected: "a point like [x, y]",
+                got: Value(PropertyValue::List(point)),
+            }),
+        },
+        Some(other) => Err(FoliumError::ExpectedReason {
+            location,
+            expected: "the name of an element or a point like [x, y]",
+            got: Value(other),
+        }),
+        None => Err(FoliumError::ExpectedReason {
+            location,
+            expected: key,
+            got: ClosingArgsParen,
+        }),
+    };
+
+    Ok((endpoint("from")?, endpoint("to")?))
+}
+
+/// Parses the tokens of a slide, without its brackets. `location` is that of its opening bracket.
+fn parse_slide<'a>(
+    slide_tokens: Vec<FatToken<'a>>,
+    location: TokenLocation,
+    global: &'a GlobalState,
+) -> Result<(), FoliumError<'a>> {
+    let first_location = slide_tokens
+        .first()
+        .map(|token| token.location)
+        .unwrap_or(location);
+    let mut iter = slide_tokens.into_iter();
+    let content_root_id = parse_content_definition(&mut iter, location, global)?;
+
+    // lines and arrows are not part of the layout, so they can only be given after the content
+    if let Some(connector) = global
+        .traverse(content_root_id)
+        .iter()
+        .flat_map(|id| global.get_element_by_id(*id))
+        .find(|elem| matches!(elem.el_type(), ElementType::Line | ElementType::Arrow))
+    {
+        return Err(FoliumError::UseOfContentTypeName {
+            location: first_location,
+            word: connector.el_type().string_rep(),
+        });
+    }
+
+    // speaker notes and connectors can be given anywhere between the style blocks
+    let mut iter = iter.peekable();
+    let mut notes = None;
+    let mut connectors = Vec::new();
+    let mut remaining_style_tokens = Vec::new();
+    while let Some(fat_token) = iter.next() {
+        let is_connector = |token: Option<&FatToken>| {
+            matches!(token.map(|t| &t.token), Some(Ident("line" | "arrow")))
+        };
+        let starts_connector = (is_connector(Some(&fat_token))
+            && iter.peek().is_some_and(|t| t.token == OpeningArgsParen))
+            || (matches!(fat_token.token, Ident(_))
+                && iter.peek().is_some_and(|t| t.token == Definition));
+        if starts_connector {
+            let mut connector_tokens = vec![fat_token];
+            connector_tokens.extend(
+                iter.by_ref()
+                    .take_while_inclusive(|t| t.token != ClosingArgsParen),
+            );
+
+            if connector_tokens.len() > 2
+                && !is_connector(connector_tokens.get(2))
+                && connector_tokens[1].token == Definition
+            {
+                return Err(FoliumError::ExpectedReason {
+                    location: connector_tokens[2].location,
+                    expected: "a line or an arrow after the content of a slide",
+                    got: connector_tokens[2].token.clone(),
+                });
+            }
+
+            let connector =
+                parse_content_definition(connector_tokens.into_iter(), first_location, global)?;
+            check_connector_endpoints(global, content_root_id, connector, first_location)?;
+            connectors.push(connector);
+            continue;
+        }
+
+        if fat_token.token == Ident("notes")
+            && iter.next_if(|t| t.token == OpeningArgsParen).is_some()
+        {
+            let text = match iter.next() {
+                Some(FatToken {
+                    token: Value(PropertyValue::String(text)),
+                    ..
+                }) => text,
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedReason {
+                        expected: "the text of the speaker notes",
+                        location,
+                        got: token,
+                    })
+                }
+                None => {
+                    return Err(FoliumError::UnexpectedFileEndWithReason {
+                        location: fat_token.location,
+                        expected: "the text of the speaker notes",
+                    })
+                }
+            };
+            expect_token(&mut iter, ClosingArgsParen, fat_token.location)?;
+            notes = Some(text);
+            continue;
+        }
+        remaining_style_tokens.push(fat_token);
+    }
+
+    let mut metadata = SlideMetadata::default();
+
+    let mut style_map: StyleMap = if !remaining_style_tokens.is_empty() {
+        let individual_styles = remaining_style_tokens
+            .split(|token| token.token == ClosingParamsParen)
+            .filter(|slice| !slice.is_empty());
+        let mut style_map = StyleMap::new();
+
+        for individual_style in individual_styles {
+            // every style target is followed by its properties between braces
+            match individual_style.get(1) {
+                Some(FatToken {
+                    token: OpeningParamsParen,
+                    ..
+                }) => {}
+                Some(FatToken { token, location }) => {
+                    return Err(FoliumError::ExpectedToken {
+                        location: *location,
+                        expected: OpeningParamsParen,
+                        got: token.clone(),
+                    })
+                }
+                None => {
+                    return Err(FoliumError::ExpectedReason {
+                        location: individual_style[0].location,
+                        expected: "a style target followed by its properties in braces",
+                        got: individual_style[0].token.clone(),
+                    })
+                }
+            }
+
+            if individual_style[0].token == Ident("meta") {
+                for (key, value) in parse_properties(&individual_style[2..])? {
+                    match key.as_str() {
+                        "title" => metadata.title = Some(value.to_string()),
+                        "id" => metadata.id = Some(value.to_string()),
+                        "tags" => {
+                            metadata.tags = match value {
+                                PropertyValue::List(tags) => {
+                                    tags.iter().map(|tag| tag.to_string()).collect()
+                                }
+                                other => other
+                                    .to_string()
+                                    .split(',')
+                                    .map(|tag| tag.trim().to_string())
+                                    .filter(|tag| !tag.is_empty())
+                                    .collect(),
+                            }
+                        }
+                        other => {
+                            return Err(FoliumError::UnknownSetting {
+                                location: individual_style[0].location,
+                                block: "meta",
+                                key: other.to_string(),
+                            })
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let target = match &individual_style[0] {
+                &FatToken {
+                    token: Ident(ident_val),
+                    ..
+                } => {
+                    if let Ok(el_type) = ElementType::try_from(ident_val) {
+                        StyleTarget::Anonymous(el_type)
+                    } else if ident_val == "slide" {
+                        StyleTarget::Slide
+                    } else {
+                        StyleTarget::Named(ident_val.to_owned())
+                    }
+                }
+                FatToken {
+                    token: other_token,
+                    location,
+                } => {
+                    return Err(FoliumError::ExpectedReason {
+                        expected: "a style target identifier",
+                        location: *location,
+                        got: other_token.clone(),
+                    })
+                }
+            };
+
+            let mut properties = parse_properties(&individual_style[2..])?;
+
+            if let Some(class_value) = properties.get("class") {
+                let class_name = class_value.to_string();
+                let class_properties = global.get_style_class(&class_name).ok_or_else(|| {
+                    FoliumError::UndefinedStyleClass {
+                        location: individual_style[0].location,
+                        name: class_name.clone(),
+                    }
+                })?;
+
+                // properties set on the target itself take precedence over those of the class
+                for (prop_name, prop_value) in class_properties {
+                    properties.entry(prop_name).or_insert(prop_value);
+                }
+            }
+
+            // the size of a slide with an aspect ratio follows from its width or its height
+            if target == StyleTarget::Slide {
+                if let Some(ratio) = properties.get("aspect") {
+                    let location = individual_style[0].location;
+                    let pixels = |property: &str| match properties.get(property) {
+                        Some(PropertyValue::Number(pixels)) => Some(*pixels),
+                        _ => None,
+                    };
+                    match parse_aspect_ratio(&ratio.to_string()) {
+                        Some(ratio) => {
+                            let deck = global.deck();
+                            let (width, height) = aspect_size(
+                                Some(ratio),
+                                pixels("width"),
+                                pixels("height"),
+                                (deck.width, deck.height),
+                                location,
+                            )?;
+                            properties.insert(String::from("width"), PropertyValue::Number(width));
+                            properties
+                                .insert(String::from("height"), PropertyValue::Number(height));
+                        }
+                        None => {
+                            return Err(FoliumError::UnknownSetting {
+                                location,
+                                block: "slide",
+                                key: String::from("aspect"),
+                            })
+                        }
+                    }
+                }
+            }
+
+            style_map.add_style(target, properties);
+        }
+
+        // make sure that properties like height and width are present if the user hasn't overridden them
+        style_map.fill_in(StyleMap::for_deck(&global.deck()));
+        style_map.resolve_units();
+
+        style_map
+    } else {
+        StyleMap::for_deck(&global.deck())
+    };
+    if let Some(palette) = global.palette() {
+        style_map.recolour(&palette);
+    }
+    style_map.scale(global.scale());
+
+    let slide = Slide::new(
+        global,
+        content_root_id,
+        style_map,
+        metadata,
+        notes,
+        connectors,
+    );
+    global.push_slide(slide);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn none_slide() {
+        let global = GlobalState::new();
+        let source = String::from("[ none() ]");
+        assert_eq!(Ok(()), load(&global, source));
+        let none_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(none_el.data(), &AbstractElementData::None);
+        assert_eq!(*none_el.name(), None);
+    }
+
+    #[test]
+    fn named_none_slide() {
+        let global = GlobalState::new();
+        let source = String::from("[ joop :: none() ]");
+        assert_eq!(Ok(()), load(&global, source));
+        let none_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(none_el.data(), &AbstractElementData::None);
+        assert_eq!(*none_el.name(), Some(String::from("joop")));
+    }
+
+    #[test]
+    fn text_slide() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("jakob") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(
+            text_el.data(),
+            &AbstractElementData::Text(String::from("jakob"))
+        );
+    }
+
+    #[test]
+    fn named_text_slide() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ joop :: text("jakob") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(
+            text_el.data(),
+            &AbstractElementData::Text(String::from("jakob"))
+        );
+    }
+
+    #[test]
+    fn named_text_slide_with_space() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ joop :: text("jakob en zonen") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(
+            text_el.data(),
+            &AbstractElementData::Text(String::from("jakob en zonen"))
+        );
+    }
+
+    #[test]
+    fn styled_slide() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ padding ( text ("joop") ) padding { amount: 10, } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let slide = &slides[0];
+
+        let padding_style = slide
+            .style_map()
+            .styles_for_target(&StyleTarget::Anonymous(Padding))
+            .unwrap();
+        let padding_amount = padding_style.get(&String::from("amount")).unwrap();
+        assert_eq!(padding_amount, &PropertyValue::Number(10))
+    }
+
+    #[test]
+    fn partial_style_override() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ none () slide { height: 500 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let slide = &slides[0];
+
+        let slide_style = slide
+            .style_map()
+            .styles_for_target(&StyleTarget::Slide)
+            .unwrap();
+        let height = slide_style.get(&String::from("height")).unwrap();
+        let width = slide_style.get(&String::from("width")).unwrap();
+        assert_eq!(height, &PropertyValue::Number(500));
+        assert_eq!(width, &PropertyValue::Number(1920));
+    }
+
+    #[test]
+    fn col_in_row() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row ( text("joop"), col ( text("in kolom"), text("in kolom 2") ) ) ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        println!("{}", global);
+
+        let row = global.get_element_by_id(AbstractElementID(5)).unwrap();
+        let data = match row.data() {
+            AbstractElementData::Row(val) => val,
+            _ => panic!(),
+        };
+        assert_eq!(data.len(), 2);
+    }
+
+    #[test]
+    fn definition_used_on_two_slides() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"def logo :: img("logo.png") [ use(logo) ] [ row ( text("joop"), use(logo) ) ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let logo_id = global.get_definition("logo").unwrap();
+        let slides = global.slides.borrow();
+        assert_eq!(slides[0].content(), logo_id);
+
+        let row = global.get_element_by_id(slides[1].content()).unwrap();
+        assert_eq!(
+            row.data(),
+            &AbstractElementData::Row(vec![AbstractElementID(3), logo_id])
+        );
+    }
+
+    #[test]
+    fn unbalanced_parentheses_in_definitions() {
+        let global = GlobalState::new();
+        let source = String::from("def x :: ) [ none() ]");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnbalancedParentheses { .. }]
+        ));
+
+        // unclosed parentheses are found before anything is parsed
+        let global = GlobalState::new();
+        let source = String::from(r#"def x :: row ( text("a") "#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnclosedBracket { .. }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from(r#"def logo :: img("logo.png") [ use(logo ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnclosedBracket { .. }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from(r#"def logo :: img("logo.png")"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let location = TokenLocation::default();
+        let reference = |source| parse_reference(tokenize(source).into_iter(), location, &global);
+        assert!(reference("(logo)").is_ok());
+        assert_eq!(
+            reference("(logo"),
+            Err(FoliumError::UnbalancedParentheses { location })
+        );
+    }
+
+    #[test]
+    fn use_of_undefined_reference() {
+        let global = GlobalState::new();
+        let source = String::from("[ use(logo) ]");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UndefinedReference { name: "logo", .. }]
+        ));
+    }
+
+    #[test]
+    fn for_loop_expands_slides() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"for path in ["a.jpg", "b.jpg"] [ col ( img(path), text("showing $path") ) ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(global.number_of_slides(), 2);
+
+        let slides = global.slides.borrow();
+        let second_col = global.get_element_by_id(slides[1].content()).unwrap();
+        let children = match second_col.data() {
+            AbstractElementData::Col(children) => children.clone(),
+            _ => panic!(),
+        };
+        assert_eq!(
+            global.get_element_by_id(children[0]).unwrap().data(),
+            &AbstractElementData::Image("b.jpg".into())
+        );
+        assert_eq!(
+            global.get_element_by_id(children[1]).unwrap().data(),
+            &AbstractElementData::Text(String::from("showing b.jpg"))
+        );
+    }
+
+    #[test]
+    fn errors_in_loops_skip_the_whole_loop() {
+        let global = GlobalState::new();
+        let source = String::from(
+            "for chapter in [\"one\", two, \"three\"] [ text(\"Chapter \" + chapter) ]\n[ text(\"fine\") ]\n[ circle(\"inside\") ]",
+        );
+        let errors = load(&global, source).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            FoliumError::ExpectedReason {
+                location: TokenLocation { line: 0, col: 23 },
+                expected: "a value to loop over",
+                ..
+            }
+        ));
+        assert!(matches!(
+            errors[1],
+            FoliumError::ExpectedReason {
+                location: TokenLocation { line: 2, .. },
+                ..
+            }
+        ));
+        assert_eq!(global.number_of_slides(), 1);
+    }
+
+    #[test]
+    fn unit_suffixes_are_resolved() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ padding ( text("joop") ) text { size: 24pt } padding { amount: 0.5em } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let styles = slides[0].style_map();
+        let text_size = styles
+            .styles_for_target(&StyleTarget::Anonymous(Text))
+            .unwrap()
+            .get("size")
+            .unwrap();
+        let padding_amount = styles
+            .styles_for_target(&StyleTarget::Anonymous(Padding))
+            .unwrap()
+            .get("amount")
+            .unwrap();
+        assert_eq!(text_size, &PropertyValue::Number(32));
+        assert_eq!(padding_amount, &PropertyValue::Number(16));
+    }
+
+    #[test]
+    fn text_from_file() {
+        let path = std::env::temp_dir().join("folium_text_from_file.txt");
+        fs::write(&path, "een lange alinea").unwrap();
+
+        let global = GlobalState::new();
+        let source = format!(r#"[ text(file: "{}") ]"#, path.display());
+        assert_eq!(Ok(()), load(&global, source));
+        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(
+            text_el.data(),
+            &AbstractElementData::Text(String::from("een lange alinea"))
+        );
+    }
+
+    #[test]
+    fn style_class_applied_to_target() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"style emphasised { fill: #ff0000, size: 48 } [ title :: text("joop") title { class: emphasised, size: 60 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let title_style = slides[0]
+            .style_map()
+            .styles_for_target(&StyleTarget::Named(String::from("title")))
+            .unwrap();
+        assert_eq!(
+            title_style.get("fill"),
+            Some(&PropertyValue::Colour(255, 0, 0))
+        );
+        assert_eq!(title_style.get("size"), Some(&PropertyValue::Number(60)));
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ title :: text("joop") title { class: missing } ]"#);
+        assert!(matches!(
+            &load(&global, source).unwrap_err()[..],
+            [FoliumError::UndefinedStyleClass { name, .. }] if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn slide_metadata() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ none() meta { title: "Results", id: results, tags: "data, final" } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        assert_eq!(
+            slides[0].metadata(),
+            &SlideMetadata {
+                title: Some(String::from("Results")),
+                id: Some(String::from("results")),
+                tags: vec![String::from("data"), String::from("final")],
+            }
+        );
+        drop(slides);
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ none() meta { titel: "Results" } ]"#);
+        assert!(matches!(
+            &load(&global, source).unwrap_err()[..],
+            [FoliumError::UnknownSetting { block: "meta", key, .. }] if key == "titel"
+        ));
+    }
+
+    #[test]
+    fn deck_preamble_sets_slide_size() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"deck { width: 1280, height: 720, author: "Simeon" } [ none() ] [ none() slide { height: 500 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(global.deck().author, Some(String::from("Simeon")));
+        assert!(matches!(
+            &load(&GlobalState::new(), String::from("deck { colour: #fff } [ none() ]"))
+                .unwrap_err()[..],
+            [FoliumError::UnknownSetting { block: "deck", key, .. }] if key == "colour"
+        ));
+
+        let slides = global.slides.borrow();
+        let first = slides[0]
+            .style_map()
+            .styles_for_target(&StyleTarget::Slide)
+            .unwrap();
+        let second = slides[1]
+            .style_map()
+            .styles_for_target(&StyleTarget::Slide)
+            .unwrap();
+        assert_eq!(first.get("width"), Some(&PropertyValue::Number(1280)));
+        assert_eq!(first.get("height"), Some(&PropertyValue::Number(720)));
+        assert_eq!(second.get("width"), Some(&PropertyValue::Number(1280)));
+        assert_eq!(second.get("height"), Some(&PropertyValue::Number(500)));
+    }
+
+    #[test]
+    fn environment_variable_interpolation() {
+        std::env::set_var("FOLIUM_TEST_PRESENTER", "Simeon");
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("presented by ${FOLIUM_TEST_PRESENTER}") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(
+            text_el.data(),
+            &AbstractElementData::Text(String::from("presented by Simeon"))
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("${FOLIUM_TEST_SURELY_NOT_SET}") ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UndefinedVariable { .. }]
+        ));
+    }
+
+    #[test]
+    fn named_styles_cascade_to_anonymous_and_defaults() {
+        let global = GlobalState::new();
+        let source =
+            String::from(r#"[ title :: text("joop") text { size: 40 } title { fill: #ff0000 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let title = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        let styles = slides[0].style_map().styles_for_element(&title);
+        assert_eq!(styles.get("fill"), Some(&PropertyValue::Colour(255, 0, 0)));
+        assert_eq!(styles.get("size"), Some(&PropertyValue::Number(40)));
+        assert_eq!(
+            styles.get("font"),
+            Some(&PropertyValue::String(String::from("Liberation Serif")))
+        );
+    }
+
+    #[test]
+    fn errors_in_several_slides_are_all_reported() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ text("ok") ] [ row ( text("a"), blah("b") ) ] [ use(logo) ] [ none() ]"#,
+        );
+        let errors = load(&global, source).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [
+                FoliumError::ExpectedToken { .. },
+                FoliumError::UndefinedReference { .. }
+            ]
+        ));
+        assert_eq!(global.number_of_slides(), 2);
+    }
+
+    #[test]
+    fn list_valued_property() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ box :: none() box { dashes: [4, 2], stops: [#ffffff, #000000], sizes: [1em, 24pt] } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let styles = slides[0]
+            .style_map()
+            .styles_for_target(&StyleTarget::Named(String::from("box")))
+            .unwrap();
+        assert_eq!(
+            styles.get("dashes"),
+            Some(&PropertyValue::List(vec![
+                PropertyValue::Number(4),
+                PropertyValue::Number(2)
+            ]))
+        );
+        assert_eq!(
+            styles.get("stops"),
+            Some(&PropertyValue::List(vec![
+                PropertyValue::Colour(255, 255, 255),
+                PropertyValue::Colour(0, 0, 0)
+            ]))
+        );
+        assert_eq!(
+            crate::style::extract_number_list(styles, "sizes"),
+            vec![32, 32]
+        );
+    }
+
+    #[test]
+    fn three_digit_colours_are_expanded() {
+        let tokens = tokenize("#fff #0af");
+        assert_eq!(
+            tokens.iter().map(|t| t.token.clone()).collect::<Vec<_>>(),
+            vec![
+                Value(PropertyValue::Colour(255, 255, 255)),
+                Value(PropertyValue::Colour(0, 170, 255))
+            ]
+        );
+    }
+
+    #[test]
+    fn speaker_notes() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ title :: text("Results") notes("Mention the error bars.") title { size: 60 } ]
+               [ none() ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        let slides = global.slides.borrow();
+        assert_eq!(slides[0].notes(), Some("Mention the error bars."));
+        assert_eq!(slides[1].notes(), None);
+        assert_eq!(
+            crate::style::extract_number(
+                slides[0]
+                    .style_map()
+                    .styles_for_target(&StyleTarget::Named(String::from("title")))
+                    .unwrap(),
+                "size"
+            ),
+            60
+        );
+    }
+
+    #[test]
+    fn string_concatenation() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"for chapter in ["Introduction", "Method"] [ text("Chapter " + chapter + ", part " + 1) ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Text(String::from("Chapter Introduction, part 1"))
+        );
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(3))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Text(String::from("Chapter Method, part 1"))
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("Chapter " + chapter) ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UndefinedLoopVariable { .. }]
+        ));
+    }
+
+    #[test]
+    fn profile_sections() {
+        let source = r#"[ text("always") ]
+            @if profile("handout") [ text("handout only") ]
+            @if profile("live") [
+                [ text("live one") ]
+                [ text("live two") ]
+            ]"#;
+
+        let global = GlobalState::new();
+        assert_eq!(Ok(()), load(&global, String::from(source)));
+        assert_eq!(global.number_of_slides(), 1);
+
+        let global = GlobalState::new();
+        global.set_profiles(vec![String::from("live")]);
+        assert_eq!(Ok(()), load(&global, String::from(source)));
+        assert_eq!(global.number_of_slides(), 3);
+
+        let global = GlobalState::new();
+        global.set_profiles(vec![String::from("handout")]);
+        assert_eq!(Ok(()), load(&global, String::from(source)));
+        assert_eq!(global.number_of_slides(), 2);
+    }
+
+    #[test]
+    fn numbered_list_layout() {
+        let global = GlobalState::new();
+        let source =
+            String::from(r#"[ enum(text("a"), text("b")) olist { indent: 40, gap: 20 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 220,
+        };
+        let bounds = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.element.0, layout_element.max_bounds))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            bounds,
+            vec![
+                (3, area),
+                (
+                    1,
+                    crate::layout::Rect {
+                        x: 40,
+                        y: 0,
+                        w: 360,
+                        h: 100
+                    }
+                ),
+                (
+                    2,
+                    crate::layout::Rect {
+                        x: 40,
+                        y: 120,
+                        w: 360,
+                        h: 100
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn math_formulas_are_checked_when_loading() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ math("\frac{a^2 + b_1}{\sqrt{\pi}}") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Math(String::from(r"\frac{a^2 + b_1}{\sqrt{\pi}}"))
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ math("\frac{a}{\foo}") ]"#);
+        assert!(matches!(
+            &load(&global, source).unwrap_err()[..],
+            [FoliumError::InvalidFormula { reason, .. }] if reason == r"unknown command \foo"
+        ));
+    }
+
+    #[test]
+    fn video_slide() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ demo :: video("clip.mp4") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let video_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(
+            video_el.data(),
+            &AbstractElementData::Video(PathBuf::from("clip.mp4"))
+        );
+    }
+
+    #[test]
+    fn rect_is_laid_out_behind_its_content() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ panel :: rect(text("callout")) panel { fill: none, stroke-width: 4, radius: 16 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let order = slides[0]
+            .layout(&global, None)
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.element.0)
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![2, 1]);
+
+        let panel_styles = slides[0]
+            .style_map()
+            .styles_for_target(&StyleTarget::Named(String::from("panel")))
+            .unwrap();
+        assert_eq!(
+            crate::style::extract_optional_colour(panel_styles, "fill"),
+            None
+        );
+    }
+
+    #[test]
+    fn connectors() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row ( a :: text("A"), b :: text("B") ) arrow(from: a, to: b) hint :: line(from: [10, 20], to: b) hint { stroke: #f00 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        let slides = global.slides.borrow();
+        let connectors = slides[0]
+            .connectors()
+            .iter()
+            .map(|id| global.get_element_by_id(*id).unwrap().data().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            connectors,
+            vec![
+                AbstractElementData::Arrow(
+                    Endpoint::Element(String::from("a")),
+                    Endpoint::Element(String::from("b"))
+                ),
+                AbstractElementData::Line(
+                    Endpoint::Point(10, 20),
+                    Endpoint::Element(String::from("b"))
+                ),
+            ]
+        );
+
+        let layout = slides[0].layout(&global, None).unwrap();
+        let segments = slides[0].layout_connectors(&global, &layout);
+        let a = layout[0].max_bounds;
+        assert_eq!(
+            segments[0].from,
+            (a.x as f32 + a.w as f32, a.y as f32 + a.h as f32 / 2.0)
+        );
+        assert_eq!(segments[1].from, (10.0, 20.0));
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ a :: text("A") arrow(from: a, to: c) ]"#);
+        assert!(matches!(
+            &load(&global, source).unwrap_err()[..],
+            [FoliumError::UndefinedEndpoint { name, .. }] if name == "c"
+        ));
+    }
+
+    #[test]
+    fn bar_chart_data() {
+        let path = std::env::temp_dir().join("folium_bar_chart.csv");
+        fs::write(&path, "year,sales\n2022,3.5\n2023,7\n").unwrap();
+
+        let global = GlobalState::new();
+        let source = format!(
+            r#"[ row ( barchart(labels: ["a", "b"], values: [3, 5]), barchart(file: "{}") ) ]"#,
+            path.display()
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::BarChart(ChartData {
+                labels: vec![String::from("a"), String::from("b")],
+                values: vec![3.0, 5.0],
+            })
+        );
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(2))
+                .unwrap()
+                .data(),
+            &AbstractElementData::BarChart(ChartData {
+                labels: vec![String::from("2022"), String::from("2023")],
+                values: vec![3.5, 7.0],
+            })
+        );
+        assert_eq!(chart::axis_ticks(7.0), vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ barchart(labels: ["a", "b"], values: [3]) ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::InvalidChartData { .. }]
+        ));
+    }
+
+    #[test]
+    fn line_and_pie_charts() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ col ( linechart(labels: [q1, q2], values: [1, 2]), shares :: piechart(labels: [a, b], values: [3, 1]) )
+                 shares { fill: [#f00, #0f0], legend: false } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        let data = ChartData {
+            labels: vec![String::from("q1"), String::from("q2")],
+            values: vec![1.0, 2.0],
+        };
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::LineChart(data)
+        );
+
+        let slides = global.slides.borrow();
+        let shares = slides[0]
+            .style_map()
+            .styles_for_element(&global.get_element_by_id(AbstractElementID(2)).unwrap());
+        assert_eq!(
+            crate::style::extract_colour_list(&shares, "fill"),
+            vec![(255, 0, 0), (0, 255, 0)]
+        );
+        assert!(!crate::style::extract_boolean(&shares, "legend"));
+    }
+
+    #[test]
+    fn spacers_take_up_room_in_rows() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row ( text("a"), spacer(), spacer(40), text("b") ) row { gap: 0 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 50,
+        };
+        let xs = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.max_bounds.x, layout_element.max_bounds.w))
+            .collect::<Vec<_>>();
+
+        assert_eq!(xs, vec![(0, 120), (120, 120), (240, 40), (280, 120)]);
+    }
+
+    #[test]
+    fn grid_layout() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ grid(cols: 2, text("a"), text("b"), text("c")) grid { widths: [2, 1], row-gap: 20, col-gap: 30 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 10,
+            y: 10,
+            w: 330,
+            h: 220,
+        };
+        let bounds = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| {
+                let rect = layout_element.max_bounds;
+                (rect.x, rect.y, rect.w, rect.h)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            bounds,
+            vec![(10, 10, 200, 100), (240, 10, 100, 100), (10, 130, 200, 100)]
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ grid(text("a")) ]"#);
+        assert!(load(&global, source).is_err());
+    }
+
+    #[test]
+    fn stacked_elements_share_their_area() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ stack(img("photo.jpg"), padding(text("caption"))) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 300,
+        };
+        let layout = slides[0].layout(&global, Some(area)).unwrap();
+        assert_eq!(layout.len(), 2);
+        assert_eq!(layout[0].max_bounds, area);
+        assert_eq!(layout[1].max_bounds, area.with_margin(12));
+    }
+
+    #[test]
+    fn deck_footer() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"def foot :: text("folium")
+               deck { width: 1000, height: 600, footer: foot }
+               [ text("with footer") slide { margin: 0, footer-height: 100 } ]
+               [ text("without footer") slide { margin: 0, footer: false } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let bounds = |slide: &Slide| {
+            slide
+                .layout(&global, None)
+                .unwrap()
+                .iter()
+                .map(|layout_element| (layout_element.element.0, layout_element.max_bounds))
+                .collect::<Vec<_>>()
+        };
+        let rect = |y, h| crate::layout::Rect {
+            x: 0,
+            y,
+            w: 1000,
+            h,
+        };
+
+        assert_eq!(
+            bounds(&slides[0]),
+            vec![(2, rect(0, 500)), (1, rect(500, 100))]
+        );
+        assert_eq!(bounds(&slides[1]), vec![(4, rect(0, 600))]);
+
+        let global = GlobalState::new();
+        let source = String::from(r#"deck { header: missing } [ none() ]"#);
+        assert!(matches!(
+            &load(&global, source).unwrap_err()[..],
+            [FoliumError::UndefinedDefinition { name, .. }] if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn slide_number_format() {
+        let global = GlobalState::new();
+        let source =
+            String::from(r#"[ page :: slidenumber() page { format: "page $slide of $total" } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let page = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        assert_eq!(page.data(), &AbstractElementData::SlideNumber);
+        assert_eq!(
+            crate::style::extract_string(
+                &global.slides.borrow()[0]
+                    .style_map()
+                    .styles_for_element(&page),
+                "format"
+            ),
+            "page $slide of $total"
+        );
+    }
+
+    #[test]
+    fn links() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ col ( text("Sources"), link("folium on GitHub", "https://github.com/WalrusGumboot/folium") ) ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(2))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Link(
+                String::from("folium on GitHub"),
+                String::from("https://github.com/WalrusGumboot/folium")
+            )
+        );
+
+        let links = crate::render::slide_links(&global, 0, None);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].1, "https://github.com/WalrusGumboot/folium");
+        assert!(links[0].0.y > 64);
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ link("no url") ]"#);
+        assert!(load(&global, source).is_err());
+    }
+
+    #[test]
+    fn icons_by_name_and_codepoint() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ row ( icon("github"), icon("U+E87C") ) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Icon('\u{f09b}')
+        );
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(2))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Icon('\u{e87c}')
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ icon("no-such-icon") ]"#);
+        assert!(load(&global, source).is_err());
+    }
+
+    #[test]
+    fn headings() {
+        let global = GlobalState::new();
+        let source =
+            String::from(r#"[ col(h1("Results"), h2("Q3"), text("up 4%")) ] [ h3("Questions?") ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        assert_eq!(
+            global.slide_headings(&slides[0]),
+            vec![(1, String::from("Results")), (2, String::from("Q3"))]
+        );
+        assert_eq!(
+            global.slide_headings(&slides[1]),
+            vec![(3, String::from("Questions?"))]
+        );
+
+        let h1_size = slides[0]
+            .style_map()
+            .styles_for_target(&StyleTarget::Anonymous(H1))
+            .unwrap()
+            .get("size")
+            .cloned();
+        assert_eq!(h1_size, Some(PropertyValue::Number(72)));
+    }
+
+    #[test]
+    fn clock_format() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ clock() clock { format: "%I:%M %p" } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Clock
+        );
+
+        let time = crate::clock::TimeOfDay {
+            hours: 14,
+            minutes: 5,
+            seconds: 9,
+        };
+        assert_eq!(crate::clock::format_time("%I:%M %p", time), "02:05 PM");
+        assert_eq!(
+            crate::clock::format_time("%H:%M:%S (100%%)", time),
+            "14:05:09 (100%)"
+        );
+    }
+
+    #[test]
+    fn audio() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(text("listen"), audio("intro.ogg")) audio { autoplay: false } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(2))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Audio(PathBuf::from("intro.ogg"))
+        );
+
+        // audio is not drawn, so the text gets the whole row, apart from the gap
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 50,
+        };
+        let xs = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.max_bounds.x, layout_element.max_bounds.w))
+            .collect::<Vec<_>>();
+
+        assert_eq!(xs, vec![(0, 368), (400, 0)]);
+    }
+
+    #[test]
+    fn plot_from_csv_columns() {
+        let path = std::env::temp_dir().join("folium_plot.csv");
+        fs::write(&path, "time,load,value\n0.5,9,-2\n1.5,4,3\n2,1,12.5\n").unwrap();
+
+        let global = GlobalState::new();
+        let source = format!(
+            r#"[ plot(file: "{}", x: "time", y: "value") ]"#,
+            path.display()
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Plot(PlotData {
+                x_name: String::from("time"),
+                y_name: String::from("value"),
+                points: vec![(0.5, -2.0), (1.5, 3.0), (2.0, 12.5)],
+            })
+        );
+        assert_eq!(
+            chart::axis_ticks_between(-2.0, 12.5),
+            vec![-5.0, 0.0, 5.0, 10.0, 15.0]
+        );
+
+        let global = GlobalState::new();
+        let source = format!(
+            r#"[ plot(file: "{}", x: "time", y: "speed") ]"#,
+            path.display()
+        );
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::InvalidChartData { .. }]
+        ));
+    }
+
+    #[test]
+    fn rotated_content() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ rotate(390, text("DRAFT")) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(2))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Rotate(30, AbstractElementID(1))
+        );
+
+        // the child is laid out in the area of the rotation, right after it
+        let layout = global.slides.borrow()[0].layout(&global, None).unwrap();
+        assert_eq!(
+            layout.iter().map(|elem| elem.element).collect::<Vec<_>>(),
+            vec![AbstractElementID(2), AbstractElementID(1)]
+        );
+        assert_eq!(layout[0].max_bounds, layout[1].max_bounds);
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ rotate(text("DRAFT")) ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::ExpectedReason { .. }]
+        ));
+    }
+
+    #[test]
+    fn opacity_wrapper() {
+        let global = GlobalState::new();
+        let source =
+            String::from(r#"[ row(opacity(0.5, text("later")), opacity(1, text("now"))) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(2))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Opacity(128, AbstractElementID(1))
+        );
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(4))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Opacity(255, AbstractElementID(3))
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ opacity(1.5, text("too much")) ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::ExpectedReason { .. }]
+        ));
+    }
+
+    #[test]
+    fn frame_surrounds_its_content() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ frame(img("in.jpg")) frame { width: 6, gap: 4 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 300,
+        };
+        let layout = global.slides.borrow()[0]
+            .layout(&global, Some(area))
+            .unwrap();
+        let bounds = layout
+            .iter()
+            .map(|elem| (elem.element, elem.max_bounds))
+            .collect::<Vec<_>>();
+
+        // the border is drawn after the image, around it
+        assert_eq!(
+            bounds,
+            vec![
+                (AbstractElementID(1), area.with_margin(10)),
+                (AbstractElementID(2), area)
+            ]
+        );
+    }
+
+    #[test]
+    fn shadow_is_drawn_behind_its_content() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ shadow(rect()) shadow { opacity: 0.25, blur: 0 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let layout = slides[0].layout(&global, None).unwrap();
+        assert_eq!(
+            layout.iter().map(|elem| elem.element).collect::<Vec<_>>(),
+            vec![AbstractElementID(2), AbstractElementID(1)]
+        );
+        assert_eq!(layout[0].max_bounds, layout[1].max_bounds);
+
+        let styles = slides[0].style_map();
+        let shadow_style = styles
+            .styles_for_target(&StyleTarget::Anonymous(Shadow))
+            .unwrap();
+        assert_eq!(crate::style::extract_decimal(shadow_style, "opacity"), 0.25);
+        assert_eq!(crate::style::extract_number(shadow_style, "blur"), 0);
+    }
+
+    #[test]
+    fn image_fit_modes() {
+        let area = crate::layout::Rect {
+            x: 100,
+            y: 100,
+            w: 400,
+            h: 200,
+        };
+        let rect = |x, y, w, h| crate::layout::Rect { x, y, w, h };
+        let image = (200, 200);
+
+        assert_eq!(
+            crate::layout::fit_rects("contain", image, area),
+            (rect(0, 0, 200, 200), rect(200, 100, 200, 200))
+        );
+        assert_eq!(
+            crate::layout::fit_rects("cover", image, area),
+            (rect(0, 50, 200, 100), area)
+        );
+        assert_eq!(
+            crate::layout::fit_rects("crop", (800, 100), area),
+            (rect(200, 0, 400, 100), rect(100, 150, 400, 100))
+        );
+        assert_eq!(
+            crate::layout::fit_rects("stretch", image, area),
+            (rect(0, 0, 200, 200), area)
+        );
+    }
+
+    #[test]
+    fn grow_weights_in_rows() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(text("a"), img("b.png"), text("c")) img { grow: 2 } row { gap: 10 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 420,
+            h: 50,
+        };
+        let xs = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.max_bounds.x, layout_element.max_bounds.w))
+            .collect::<Vec<_>>();
+
+        // the image gets twice the share of the texts
+        assert_eq!(xs, vec![(0, 100), (110, 200), (320, 100)]);
+    }
+
+    #[test]
+    fn images_keep_their_aspect_ratio_in_rows() {
+        let path = std::env::temp_dir().join("folium_wide.png");
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(200u32.to_be_bytes());
+        png.extend(100u32.to_be_bytes());
+        fs::write(&path, png).unwrap();
+
+        let global = GlobalState::new();
+        let source = format!(
+            r#"[ row(img("{}"), text("a"), text("b")) row {{ gap: 0 }} ]"#,
+            path.display()
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 600,
+            h: 50,
+        };
+        let xs = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.max_bounds.x, layout_element.max_bounds.w))
+            .collect::<Vec<_>>();
+
+        // at a height of 50, the image is 100 wide, and the texts share the rest
+        assert_eq!(xs, vec![(0, 100), (100, 250), (350, 250)]);
+    }
+
+    #[test]
+    fn placed_content() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"deck { width: 1000, height: 600 }
+               [ row(text("a"), place(800, 50, text("new!"))) slide { margin: 0 } row { gap: 0 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(3))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Place(800, 50, AbstractElementID(2))
+        );
+
+        // the placed text takes no room in the row and gets the rest of the slide from its
+        // position
+        let slides = global.slides.borrow();
+        let bounds = slides[0]
+            .layout(&global, None)
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds)
+            .collect::<Vec<_>>();
+        let rect = |x, y, w, h| crate::layout::Rect { x, y, w, h };
+        assert_eq!(bounds, vec![rect(0, 0, 1000, 600), rect(800, 50, 200, 550)]);
+    }
+
+    #[test]
+    fn z_order() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ stack(rect(text("b")), text("a")) rect { z: 1 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        // the rect is drawn over the text that comes after it, with its content on top of it
+        let slides = global.slides.borrow();
+        let layout = slides[0].layout(&global, None).unwrap();
+        let order = |layout: &[crate::layout::LayoutElement]| {
+            layout
+                .iter()
+                .map(|layout_element| layout_element.element.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(&layout), vec![2, 1, 3]);
+        assert_eq!(
+            order(&slides[0].in_draw_order(&global, layout)),
+            vec![3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn flow_wraps_to_new_lines() {
+        let global = GlobalState::new();
+        let source =
+            String::from(r#"[ flow(spacer(100), spacer(200), spacer(150), spacer(300)) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 500,
+            h: 600,
+        };
+        let bounds = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds)
+            .collect::<Vec<_>>();
+
+        // three spacers fit on the first line, centred on its height, and the last one wraps
+        let rect = |x, y, w, h| crate::layout::Rect { x, y, w, h };
+        assert_eq!(
+            bounds,
+            vec![
+                rect(0, 50, 100, 100),
+                rect(116, 0, 200, 200),
+                rect(332, 25, 150, 150),
+                rect(0, 216, 300, 300)
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_spans() {
+        // a wide cell that does not fit at the end of a row moves on to the next row
+        assert_eq!(
+            crate::layout::grid_cells(3, &[(1, 2), (2, 1), (1, 1), (3, 1), (5, 1)]),
+            vec![
+                (0, 0, 1, 2),
+                (1, 0, 2, 1),
+                (1, 1, 1, 1),
+                (0, 2, 3, 1),
+                (0, 3, 3, 1)
+            ]
+        );
+
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ grid(cols: 2, big :: text("a"), text("b"), text("c"))
+                 big { row-span: 2 } grid { col-gap: 10, row-gap: 10 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 210,
+            h: 110,
+        };
+        let bounds = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds)
+            .collect::<Vec<_>>();
+        let rect = |x, y, w, h| crate::layout::Rect { x, y, w, h };
+        assert_eq!(
+            bounds,
+            vec![
+                rect(0, 0, 100, 110),
+                rect(110, 0, 100, 50),
+                rect(110, 60, 100, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn baseline_alignment_in_rows() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(text("small"), big :: text("big")) row { align: "baseline" } big { size: 64 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let ys = slides[0]
+            .layout(&global, None)
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds.y)
+            .collect::<Vec<_>>();
+
+        // the small text moves down by as much as the big text reaches higher
+        let fonts = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let ascent = |size| crate::measure::ascent(&fonts, size).unwrap();
+        assert_eq!(ys, vec![64 + ascent(64.0) - ascent(32.0), 64]);
+    }
+
+    #[test]
+    fn automatic_text_size() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("hello there") text { size: auto, max-size: 50 } ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let text = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        let styles = slides[0].style_map().styles_for_element(&text);
+        let size = |width, height| crate::measure::font_size(&styles, "hello there", width, height);
+
+        assert_eq!(size(2000, 2000), 50.0);
+        assert_eq!(size(10, 10), 8.0);
+
+        // the text fits at the chosen size, but not at the next one
+        let fonts = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let fits = |font_size: f32| {
+            let (width, height) = crate::measure::text_size(
+                &fonts,
+                "hello there",
+                font_size,
+                300,
+                crate::measure::TextFlow::default(),
+            )
+            .unwrap();
+            width <= 300 && height <= 40
+        };
+        let chosen = size(300, 40);
+        assert!(fits(chosen) && !fits(chosen + 1.0));
+    }
+
+    #[test]
+    fn text_overflow() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"deck { width: 400, height: 300 }
+               [ col(long :: text("far too much text for such a small slide"), text("clipped"))
+                 slide { margin: 0 } text { size: 120 } long { overflow: error } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        // only the text that is not allowed to overflow is reported
+        let slides = global.slides.borrow();
+        let layout = slides[0].layout(&global, None).unwrap();
+        assert_eq!(
+            slides[0].overflowing_elements(&global, &layout),
+            vec![AbstractElementID(1)]
+        );
+
+        // shrinking text makes it smaller than its size to fit
+        let text = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        let mut styles = slides[0].style_map().styles_for_element(&text);
+        styles.insert(
+            String::from("overflow"),
+            PropertyValue::String(String::from("shrink")),
+        );
+        let text = "far too much text for such a small slide";
+        let size = crate::measure::font_size(&styles, text, 400, 134);
+        assert!(size < 120.0);
+        assert!(!crate::measure::overflows(&styles, text, 400, 134));
+    }
+
+    #[test]
+    fn gap_overrides_in_rows() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(text("a"), text("b"), second :: text("c"), text("d"))
+                 row { gap: 10 } second { gap-before: 100 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 430,
+            h: 50,
+        };
+        let xs = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.max_bounds.x, layout_element.max_bounds.w))
+            .collect::<Vec<_>>();
+
+        assert_eq!(xs, vec![(0, 77), (87, 77), (264, 77), (351, 77)]);
+    }
+
+    #[test]
+    fn content_sizing_in_columns() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ col(text("title"), rect(), text("caption")) col { gap: 0, sizing: content } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 600,
+        };
+        let heights = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds.h)
+            .collect::<Vec<_>>();
+
+        // the texts are as high as their lines and the rect gets the rest
+        let fonts = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let line_height = |text| {
+            crate::measure::text_size(&fonts, text, 32.0, 400, Default::default())
+                .unwrap()
+                .1
+        };
+        let (title, caption) = (line_height("title"), line_height("caption"));
+        assert_eq!(heights, vec![title, 600 - title - caption, caption]);
+    }
+
+    #[test]
+    fn right_to_left_rows() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(text("a"), text("b"), text("c")) row { gap: 10, direction: rtl } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 100,
+            y: 0,
+            w: 320,
+            h: 50,
+        };
+        let xs = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds.x)
+            .collect::<Vec<_>>();
+
+        assert_eq!(xs, vec![320, 210, 100]);
+    }
+
+    #[test]
+    fn aspect_ratio_presets() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"deck { aspect: 4:3 }
+               [ none() ]
+               [ none() slide { aspect: 16:9 } ]
+               [ none() slide { aspect: "16:10", width: 1280 } ]
+               [ none() slide { width: 1000, aspect: 2:1 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!((global.deck().width, global.deck().height), (1440, 1080));
+
+        let slides = global.slides.borrow();
+        let sizes = slides
+            .iter()
+            .map(|slide| {
+                let styles = slide
+                    .style_map()
+                    .styles_for_target(&StyleTarget::Slide)
+                    .unwrap();
+                (
+                    crate::style::extract_number(styles, "width"),
+                    crate::style::extract_number(styles, "height"),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![(1440, 1080), (1920, 1080), (1280, 800), (1000, 500)]
+        );
+        drop(slides);
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ none() slide { aspect: 16:wide } ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::ExpectedReason {
+                expected: "the second number of a ratio",
                 ..
-            } => {
-                grouped_tokens.push(current_slide_tokens.clone());
-                current_slide_tokens.clear();
+            }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from("deck { aspect: 16:9, height: 4000000000 }\n[ none() ]");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::SizeTooLarge {
+                location: TokenLocation { line: 0, col: 0 }
+            }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ none() slide { aspect: 16:0 } ]"#);
+        assert!(matches!(
+            &load(&global, source).unwrap_err()[..],
+            [FoliumError::UnknownSetting {
+                location: TokenLocation { line: 0, col: 9 },
+                block: "slide",
+                key,
+            }] if key == "aspect"
+        ));
+    }
+
+    #[test]
+    fn anchored_content() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ stack(logo :: spacer(40), mark :: spacer(20), spacer(10))
+                 logo { anchor: bottom-right } mark { anchor: top } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 100,
+            y: 100,
+            w: 400,
+            h: 300,
+        };
+        let bounds = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            bounds,
+            vec![
+                crate::layout::Rect {
+                    x: 460,
+                    y: 360,
+                    w: 40,
+                    h: 40
+                },
+                crate::layout::Rect {
+                    x: 290,
+                    y: 100,
+                    w: 20,
+                    h: 20
+                },
+                area,
+            ]
+        );
+    }
+
+    #[test]
+    fn overflowing_layout_is_an_error() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ none() ]
+               [ wide :: row(sized(text("a")), sized(text("b")))
+                 wide { gap: 100 } sized { size: <300;_> } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 600,
+            h: 100,
+        };
+        let err = slides[1].layout(&global, Some(area)).unwrap_err();
+        assert_eq!(err.slide, 1);
+        assert_eq!((err.needed, err.available), (700, 600));
+        assert_eq!(
+            err.message(&global),
+            "error on slide 2: the sized content of the row element wide needs 700 pixels, but there are only 600."
+        );
+    }
+
+    #[test]
+    fn inline_sizes_for_anonymous_elements() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(sized(100, _, text("a")), sized(text("b")), sized(50, 20, text("c"))) row { gap: 0 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 100,
+        };
+        let sizes = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| (layout_element.max_bounds.w, layout_element.max_bounds.h))
+            .collect::<Vec<_>>();
+
+        assert_eq!(sizes, vec![(100, 100), (250, 100), (50, 20)]);
+    }
+
+    #[test]
+    fn asymmetric_slide_margins() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"deck { width: 1000, height: 600 }
+               [ none() slide { margin: 20, margin-left: 100, margin-bottom: 80 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let layout = slides[0].layout(&global, None).unwrap();
+        assert_eq!(
+            layout[0].max_bounds,
+            crate::layout::Rect {
+                x: 100,
+                y: 20,
+                w: 880,
+                h: 500
             }
-            other => current_slide_tokens.push(other),
-        }
+        );
     }
 
-    for slide_tokens in grouped_tokens {
-        let mut iter = slide_tokens.into_iter();
-        let content_root_id = parse_content_definition(&mut iter, global)
-            .map_err(|err| {
-                eprintln!("{err}");
-                panic!()
-            })
-            .unwrap();
+    #[test]
+    fn unwrapped_text() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ col(text("a sentence that is too long"),
+                  unwrapped :: text("a sentence that is too long"), rect())
+              col { gap: 0, sizing: content } unwrapped { wrap: none } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
 
-        let remaining_style_tokens = iter.collect::<Vec<_>>();
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 200,
+            h: 600,
+        };
+        let heights = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds.h)
+            .collect::<Vec<_>>();
 
-        let style_map: StyleMap = if !remaining_style_tokens.is_empty() {
-            let individual_styles = remaining_style_tokens
-                .split(|token| token.token == ClosingParamsParen)
-                .filter(|slice| !slice.is_empty());
-            let mut style_map = StyleMap::new();
+        // the unwrapped text stays on a single line
+        let fonts = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let unwrapped = crate::measure::TextFlow {
+            wrap: crate::measure::TextWrap::None,
+            ..Default::default()
+        };
+        let height = crate::measure::text_size(&fonts, "a sentence", 32.0, 200, unwrapped)
+            .unwrap()
+            .1;
+        assert_eq!(heights[1], height);
+        assert!(heights[0] > heights[1]);
+    }
 
-            for individual_style in individual_styles {
-                let target = match &individual_style[0] {
-                    &FatToken {
-                        token: Ident(ident_val),
-                        ..
-                    } => {
-                        if let Ok(el_type) = ElementType::try_from(ident_val) {
-                            StyleTarget::Anonymous(el_type)
-                        } else if ident_val == "slide" {
-                            StyleTarget::Slide
-                        } else {
-                            StyleTarget::Named(ident_val.to_owned())
-                        }
-                    }
-                    FatToken {
-                        token: other_token,
-                        location,
-                    } => {
-                        return Err(FoliumError::ExpectedReason {
-                            expected: "a style target identifier",
-                            location: *location,
-                            got: other_token.clone(),
-                        })
-                    }
-                };
+    #[test]
+    fn line_height() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ col(text("more than one line"), spaced :: text("more than one line"), rect())
+                 col { gap: 0, sizing: content } spaced { line-height: 1.5 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
 
-                let properties: HashMap<String, PropertyValue> = individual_style[2..]
-                    .chunks(4) // we use chunks instead of chunks_exact because it doesn't enfore a comma after the last element
-                    .map(|slice| &slice[0..3])
-                    .map(|def| {
-                        assert_eq!(def[1].token, Token::ValueAssignment);
-                        (
-                            (match &def[0] {
-                                FatToken {
-                                    token: Ident(s), ..
-                                } => Ok(s.to_string()),
-                                FatToken {
-                                    token: other_token,
-                                    location,
-                                } => Err(FoliumError::ExpectedReason {
-                                    location: *location,
-                                    expected: "a style directive",
-                                    got: other_token.clone(),
-                                }),
-                            })
-                            .map_err(|err| panic!("{err}"))
-                            .unwrap(),
-                            match &def[2] {
-                                FatToken {
-                                    token: Value(pv), ..
-                                } => Ok(pv),
-                                FatToken {
-                                    token: other_token,
-                                    location,
-                                } => Err(FoliumError::ExpectedReason {
-                                    location: *location,
-                                    expected: "a parameter value",
-                                    got: other_token.clone(),
-                                }),
-                            }
-                            .map_err(|err| panic!("{err}"))
-                            .unwrap()
-                            .clone(),
-                        )
-                    })
-                    .collect();
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 120,
+            h: 600,
+        };
+        let heights = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds.h)
+            .collect::<Vec<_>>();
 
-                style_map.add_style(target, properties);
-            }
+        // the lines below the first one are half a line further down
+        let fonts = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let single_line = crate::measure::text_size(&fonts, "more", 32.0, 120, Default::default())
+            .unwrap()
+            .1;
+        assert!(heights[0] > single_line);
+        assert!(heights[1] > heights[0]);
+    }
 
-            // make sure that properties like height and width are present if the user hasn't overridden them
-            style_map.fill_in(StyleMap::default());
+    #[test]
+    fn tracking() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ row(text("TITLE"), tracked :: text("TITLE"), rect())
+                 row { gap: 0, sizing: content } tracked { tracking: 10 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
 
-            style_map
-        } else {
-            StyleMap::default()
+        let slides = global.slides.borrow();
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 1000,
+            h: 100,
         };
+        let widths = slides[0]
+            .layout(&global, Some(area))
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds.w)
+            .collect::<Vec<_>>();
+
+        // four gaps between five letters
+        assert_eq!(widths[1], widths[0] + 40);
 
-        let slide = Slide::new(global, content_root_id, style_map);
-        global.push_slide(slide);
+        // lines that are too wide once they are tracked are broken earlier
+        let fonts = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let flow = crate::measure::TextFlow {
+            tracking: 10.0,
+            ..Default::default()
+        };
+        let untracked_width =
+            crate::measure::text_size(&fonts, "tracked text", 32.0, 1000, Default::default())
+                .unwrap()
+                .0;
+        let (width, height) =
+            crate::measure::text_size(&fonts, "tracked text", 32.0, untracked_width, flow).unwrap();
+        assert!(width <= untracked_width);
+        assert!(height > 40);
     }
 
-    Ok(())
-}
+    #[test]
+    fn font_weight_and_style() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ col(text("a"), light :: text("b"), h1("c"))
+                 text { weight: bold, style: italic } light { weight: 300 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let slides = global.slides.borrow();
+        let faces = (1..=3)
+            .map(|id| {
+                let elem = global.get_element_by_id(AbstractElementID(id)).unwrap();
+                let choice = crate::measure::FontChoice::from_style(
+                    &slides[0].style_map().styles_for_element(&elem),
+                    "font",
+                );
+                (choice.weight, choice.style)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            faces,
+            vec![
+                (fontdb::Weight::BOLD, fontdb::Style::Italic),
+                (fontdb::Weight::LIGHT, fontdb::Style::Italic),
+                (fontdb::Weight::NORMAL, fontdb::Style::Normal),
+            ]
+        );
+    }
 
     #[test]
-    fn none_slide() {
+    fn gradient_backgrounds() {
+        use crate::style::{extract_paint, Paint};
+
         let global = GlobalState::new();
-        let source = String::from("[ none() ]");
+        let source = String::from(
+            r#"[ row(a :: code("x"), b :: rect()) a { bg: [#000000, #ffffff] } b { fill: [#ff0000, #0000ff, 45] } ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
-        let none_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
-        assert_eq!(none_el.data(), &AbstractElementData::None);
-        assert_eq!(*none_el.name(), None);
+
+        let slides = global.slides.borrow();
+        let styles = slides[0].style_map();
+        let code_bg = extract_paint(
+            styles
+                .styles_for_target(&StyleTarget::Named(String::from("a")))
+                .unwrap(),
+            "bg",
+        );
+        assert_eq!(
+            code_bg,
+            Some(Paint::Gradient {
+                from: (0, 0, 0),
+                to: (255, 255, 255),
+                angle: 90
+            })
+        );
+        assert_eq!(code_bg.unwrap().colour_at(0.5), (128, 128, 128));
+
+        let rect_fill = extract_paint(
+            styles
+                .styles_for_target(&StyleTarget::Named(String::from("b")))
+                .unwrap(),
+            "fill",
+        );
+        assert_eq!(
+            rect_fill,
+            Some(Paint::Gradient {
+                from: (255, 0, 0),
+                to: (0, 0, 255),
+                angle: 45
+            })
+        );
+        assert_eq!(rect_fill.unwrap().colour_at(1.0), (0, 0, 255));
     }
 
     #[test]
-    fn named_none_slide() {
+    fn image_scaling() {
         let global = GlobalState::new();
-        let source = String::from("[ joop :: none() ]");
+        let source = String::from(
+            r#"deck { scaling: nearest } [ row(img("in.jpg"), photo :: img("in.jpg")) photo { scaling: best } ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
-        let none_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
-        assert_eq!(none_el.data(), &AbstractElementData::None);
-        assert_eq!(*none_el.name(), Some(String::from("joop")));
+
+        let slides = global.slides.borrow();
+        let scalings = global
+            .get_slide_elements(&slides[0])
+            .iter()
+            .filter(|elem| elem.el_type() == ElementType::Image)
+            .map(|elem| {
+                crate::style::extract_string(
+                    &slides[0].style_map().styles_for_element(elem),
+                    "scaling",
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(scalings, vec!["nearest", "best"]);
     }
 
     #[test]
-    fn text_slide() {
+    fn animations_apply_to_everything_inside_an_element() {
         let global = GlobalState::new();
-        let source = String::from(r#"[ text("jakob") ]"#);
+        let source = String::from(
+            r#"[ group :: row(text("a"), late :: text("b")) group { enter-opacity: 0 } late { enter-from: left, enter-delay: 300 } ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
-        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+
+        let slides = global.slides.borrow();
+        let animations = slides[0].animations(&global);
+        assert_eq!(animations.len(), 3);
+        assert_eq!(animations[&AbstractElementID(1)].0, AbstractElementID(3));
+        assert_eq!(animations[&AbstractElementID(1)].1.opacity, 0.0);
+
+        // an element with an animation of its own does not follow the one around it
+        let (animated, animation) = animations[&AbstractElementID(2)];
+        assert_eq!(animated, AbstractElementID(2));
+        assert_eq!(animation.offset, (-100.0, 0.0));
+        assert_eq!(animation.delay, std::time::Duration::from_millis(300));
+    }
+
+    #[test]
+    fn variants_recolour_the_slides() {
+        let source = r#"variant print { swap: [[#ffffff, #101010]] }
+            [ row(text("a"), label :: text("b")) label { fill: #4682b4 } slide { bg: #ffffff } ]"#;
+        let colours = |variant: &str| {
+            let global = GlobalState::new();
+            global.set_variant(Some(variant.to_string()));
+            assert_eq!(Ok(()), load(&global, String::from(source)));
+            assert_eq!(global.unknown_variant(), None);
+
+            let slides = global.slides.borrow();
+            let styles = slides[0].style_map();
+            let fills = global
+                .get_slide_elements(&slides[0])
+                .iter()
+                .filter(|elem| elem.el_type() == ElementType::Text)
+                .map(|elem| crate::style::extract_colour(&styles.styles_for_element(elem), "fill"))
+                .collect::<Vec<_>>();
+            let bg = crate::style::extract_colour(
+                styles.styles_for_target(&StyleTarget::Slide).unwrap(),
+                "bg",
+            );
+            (bg, fills[0], fills[1])
+        };
+
+        // only the swapped colour changes
+        assert_eq!(colours("print"), ((16, 16, 16), (0, 0, 0), (70, 130, 180)));
+        // the builtin dark variant inverts the lightness of every colour
         assert_eq!(
-            text_el.data(),
-            &AbstractElementData::Text(String::from("jakob"))
+            colours("dark"),
+            ((0, 0, 0), (255, 255, 255), (75, 135, 185))
         );
+
+        let global = GlobalState::new();
+        global.set_variant(Some(String::from("sepia")));
+        assert_eq!(Ok(()), load(&global, String::from(source)));
+        assert_eq!(global.unknown_variant(), Some(String::from("sepia")));
+
+        assert!(matches!(
+            &load(
+                &GlobalState::new(),
+                String::from("variant print { hue: 90 } [ none() ]")
+            )
+            .unwrap_err()[..],
+            [FoliumError::UnknownSetting { block: "variant", key, .. }] if key == "hue"
+        ));
+        assert!(matches!(
+            &load(
+                &GlobalState::new(),
+                String::from("variant print { swap: [[#fff]] } [ none() ]")
+            )
+            .unwrap_err()[..],
+            [FoliumError::ExpectedReason {
+                location: TokenLocation { line: 0, col: 0 },
+                expected: "a pair of colours to swap",
+                ..
+            }]
+        ));
     }
 
     #[test]
-    fn named_text_slide() {
+    fn highlighted_code_lines() {
         let global = GlobalState::new();
-        let source = String::from(r#"[ joop :: text("jakob") ]"#);
+        let source = String::from(
+            r#"[ snippet :: code("fn main() {}") snippet { highlight: [3, 7-9, 12] } ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
-        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        let slides = global.slides.borrow();
+        let snippet = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        let style = slides[0].style_map().styles_for_element(&snippet);
         assert_eq!(
-            text_el.data(),
-            &AbstractElementData::Text(String::from("jakob"))
+            crate::style::extract_line_ranges(&style, "highlight"),
+            vec![3..=3, 7..=9, 12..=12]
         );
     }
 
     #[test]
-    fn named_text_slide_with_space() {
+    fn deck_watermark() {
         let global = GlobalState::new();
-        let source = String::from(r#"[ joop :: text("jakob en zonen") ]"#);
+        let source = String::from(
+            r#"deck { watermark: "CONFIDENTIAL", watermark-opacity: 0.5, watermark-position: top-right }
+               [ text("numbers") ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
-        let text_el = global.get_element_by_id(AbstractElementID(1)).unwrap();
         assert_eq!(
-            text_el.data(),
-            &AbstractElementData::Text(String::from("jakob en zonen"))
+            global.deck().watermark,
+            Some(Watermark {
+                mark: WatermarkMark::Text {
+                    text: String::from("CONFIDENTIAL"),
+                    size: 96
+                },
+                alpha: 128,
+                position: String::from("top-right"),
+            })
         );
+
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"deck { watermark: "DRAFT", watermark-image: "logo.png" } [ text("numbers") ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+        let watermark = global.deck().watermark.unwrap();
+        assert_eq!(watermark.mark, WatermarkMark::Image("logo.png".into()));
+        assert_eq!(watermark.position, "centre");
     }
 
     #[test]
-    fn styled_slide() {
+    fn pattern_backgrounds() {
+        use crate::style::{extract_paint, Paint};
+
         let global = GlobalState::new();
-        let source = String::from(r#"[ padding ( text ("joop") ) padding { amount: 10, } ]"#);
+        let source = String::from(
+            r#"[ row(a :: code("x"), b :: rect()) slide { bg: "paper.png" } a { bg: ["dots.png", 0.5] } b { fill: ["dots.png", 2] } ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
 
         let slides = global.slides.borrow();
-        let slide = &slides[0];
-
-        let padding_style = slide
-            .style_map()
-            .styles_for_target(&StyleTarget::Anonymous(Padding))
-            .unwrap();
-        let padding_amount = padding_style.get(&String::from("amount")).unwrap();
-        assert_eq!(padding_amount, &PropertyValue::Number(10))
+        let styles = slides[0].style_map();
+        let paint = |target: StyleTarget, property| {
+            extract_paint(styles.styles_for_target(&target).unwrap(), property)
+        };
+        assert_eq!(
+            paint(StyleTarget::Slide, "bg"),
+            Some(Paint::Pattern {
+                image: "paper.png".into(),
+                scale: 1.0
+            })
+        );
+        assert_eq!(
+            paint(StyleTarget::Named(String::from("a")), "bg"),
+            Some(Paint::Pattern {
+                image: "dots.png".into(),
+                scale: 0.5
+            })
+        );
+        assert_eq!(
+            paint(StyleTarget::Named(String::from("b")), "fill"),
+            Some(Paint::Pattern {
+                image: "dots.png".into(),
+                scale: 2.0
+            })
+        );
     }
 
     #[test]
-    fn partial_style_override() {
+    fn padded_text_backgrounds() {
+        use crate::style::{extract_paint, Paint};
+
         let global = GlobalState::new();
-        let source = String::from(r#"[ none () slide { height: 500 } ]"#);
+        let source = String::from(
+            r#"[ col(plain :: text("New"), pill :: text("New"))
+                 plain { anchor: top-left } pill { anchor: top-left, bg: #204080, padding: 12 } ]"#,
+        );
         assert_eq!(Ok(()), load(&global, source));
 
         let slides = global.slides.borrow();
-        let slide = &slides[0];
+        let styles = slides[0].style_map();
+        let backgrounds = global
+            .get_slide_elements(&slides[0])
+            .iter()
+            .filter(|elem| elem.el_type() == ElementType::Text)
+            .map(|elem| extract_paint(&styles.styles_for_element(elem), "bg"))
+            .collect::<Vec<_>>();
+        // text is not given a background unless it asks for one
+        assert_eq!(backgrounds, vec![None, Some(Paint::Solid((32, 64, 128)))]);
 
-        let slide_style = slide
-            .style_map()
-            .styles_for_target(&StyleTarget::Slide)
-            .unwrap();
-        let height = slide_style.get(&String::from("height")).unwrap();
-        let width = slide_style.get(&String::from("width")).unwrap();
-        assert_eq!(height, &PropertyValue::Number(500));
-        assert_eq!(width, &PropertyValue::Number(1920));
+        // the padding is kept around the text, inside of its background
+        let bounds = slides[0]
+            .layout(&global, None)
+            .unwrap()
+            .iter()
+            .map(|layout_element| layout_element.max_bounds)
+            .collect::<Vec<_>>();
+        assert_eq!(bounds[1].w, bounds[0].w + 24);
+        assert_eq!(bounds[1].h, bounds[0].h + 24);
     }
 
     #[test]
-    fn col_in_row() {
+    fn missing_content_is_reported_where_it_was_expected() {
+        let global = GlobalState::new();
+        let source = String::from("[ text(\"a\") ]\n\n\n  [ ]");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnexpectedFileEndWithReason {
+                location: TokenLocation { line: 3, col: 2 },
+                ..
+            }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from("[\n  row ( text(\"a\"), centre() ) ]");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnexpectedFileEndWithReason {
+                location: TokenLocation { line: 1, col: 19 },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn style_targets_without_properties() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("a") foo ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::ExpectedReason {
+                got: Ident("foo"),
+                ..
+            }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from(r#"[ text("a") foo bar { size: 10 } ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::ExpectedToken {
+                expected: OpeningParamsParen,
+                got: Ident("bar"),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn unclosed_brackets() {
+        let global = GlobalState::new();
+        let source = String::from("[ row( ]");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnclosedBracket {
+                location: TokenLocation { line: 0, col: 5 },
+                bracket: OpeningArgsParen,
+            }]
+        ));
+
+        let global = GlobalState::new();
+        let source = String::from("[ text(\"a\") ]\ntext { size: 10");
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::UnclosedBracket {
+                location: TokenLocation { line: 1, col: 5 },
+                bracket: OpeningParamsParen,
+            }]
+        ));
+        assert_eq!(global.number_of_slides(), 0);
+    }
+
+    #[test]
+    fn font_fallback_lists() {
         let global = GlobalState::new();
         let source = String::from(
-            r#"[ row ( text("joop"), col ( text("in kolom"), text("in kolom 2") ) ) ]"#,
+            r#"[ text("fallback") text { font: ["Not A Real Font", "Liberation Serif", serif] } ]"#,
         );
         assert_eq!(Ok(()), load(&global, source));
 
-        println!("{}", global);
+        let slides = global.slides.borrow();
+        let elem = global.get_element_by_id(AbstractElementID(1)).unwrap();
+        let choice = crate::measure::FontChoice::from_style(
+            &slides[0].style_map().styles_for_element(&elem),
+            "font",
+        );
+        assert_eq!(
+            choice.names,
+            vec!["Not A Real Font", "Liberation Serif", "serif"]
+        );
 
-        let row = global.get_element_by_id(AbstractElementID(5)).unwrap();
-        let data = match row.data() {
-            AbstractElementData::Row(val) => val,
-            _ => panic!(),
+        // the first installed font of the list is the one that text is measured in
+        let alone = crate::measure::FontChoice::regular(vec![String::from("Liberation Serif")]);
+        let measure = |fonts| {
+            crate::measure::text_size(fonts, "fallback", 32.0, 1000, Default::default()).unwrap()
         };
-        assert_eq!(data.len(), 2);
+        assert_eq!(measure(&choice), measure(&alone));
+    }
+
+    #[test]
+    fn ellipses_and_circles() {
+        let global = GlobalState::new();
+        let source = String::from(r#"[ row(ellipse(), circle(text("inside"))) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(1))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Ellipse(None)
+        );
+        assert_eq!(
+            global
+                .get_element_by_id(AbstractElementID(3))
+                .unwrap()
+                .data(),
+            &AbstractElementData::Circle(Some(AbstractElementID(2)))
+        );
+
+        // the shapes are laid out like rectangles, with their content inside of them
+        let area = crate::layout::Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 200,
+        };
+        let layout = global.slides.borrow()[0]
+            .layout(&global, Some(area))
+            .unwrap();
+        let bounds = |id| {
+            layout
+                .iter()
+                .find(|layout_element| layout_element.element == AbstractElementID(id))
+                .unwrap()
+                .max_bounds
+        };
+        assert_eq!(bounds(1).h, 200);
+        assert_eq!(bounds(3).union(&bounds(2)), bounds(3));
+
+        let global = GlobalState::new();
+        // the content of a shape is an element, not text of its own
+        let source = String::from(r#"[ circle("inside") ]"#);
+        assert!(matches!(
+            load(&global, source).unwrap_err()[..],
+            [FoliumError::ExpectedReason {
+                location: TokenLocation { line: 0, col: 9 },
+                expected: "a content type or name",
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn misspelt_keywords_are_reported() {
+        let error_for = |style: &str| {
+            let source = format!("[ none() img {{ {style} }} ]");
+            load(&GlobalState::new(), source).unwrap_err()[0].to_string()
+        };
+        assert_eq!(
+            error_for("fit: contian"),
+            "at line 1, col 21: contian is not a valid fit, expected contain, cover, crop or stretch."
+        );
+        assert_eq!(
+            error_for("align: center"),
+            "at line 1, col 23: center is not a valid align, expected left, centre, right, justify, top or baseline."
+        );
+        assert_eq!(
+            error_for("anchor: top-centre"),
+            "at line 1, col 24: top-centre is not a valid anchor, expected top-left, top, top-right, left, centre, right, bottom-left, bottom or bottom-right."
+        );
+        assert_eq!(
+            error_for("wrap: words"),
+            "at line 1, col 22: words is not a valid wrap, expected word, char or none."
+        );
+        assert_eq!(
+            error_for("weight: heavy"),
+            "at line 1, col 24: heavy is not a valid weight, expected a number from 1 to 1000, thin, extra-light, light, normal, medium, semibold, bold, extra-bold or black."
+        );
+        assert_eq!(
+            error_for("style: slanted"),
+            "at line 1, col 23: slanted is not a valid style, expected normal, italic or oblique."
+        );
+        assert_eq!(
+            error_for("scaling: smooth"),
+            "at line 1, col 25: smooth is not a valid scaling, expected nearest, linear or best."
+        );
+        assert_eq!(
+            error_for("enter-easing: bounce"),
+            "at line 1, col 30: bounce is not a valid enter-easing, expected linear, ease-in, ease-out or ease-in-out."
+        );
+        assert_eq!(
+            error_for("enter-from: above"),
+            "at line 1, col 28: above is not a valid enter-from, expected left, right, top or bottom."
+        );
+        assert_eq!(
+            load(
+                &GlobalState::new(),
+                String::from("[ none() img { fit: cover, weight: 600, style: italic } ]")
+            ),
+            Ok(())
+        );
     }
 }
@@ -1,8 +1,19 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
 use crate::{
+    animation::Animation,
     ast::{
-        AbstractElement, AbstractElementData, AbstractElementID, ElementType, GlobalState, Slide,
+        AbstractElement, AbstractElementData, AbstractElementID, ElementType, Endpoint,
+        GlobalState, Slide,
+    },
+    measure,
+    style::{
+        extract_boolean, extract_decimal, extract_number, extract_number_list,
+        extract_optional_number, extract_size_spec, extract_string, PropertyValue, StyleMap,
+        StyleTarget,
     },
-    style::{extract_number, extract_size_spec, StyleMap, StyleTarget},
 };
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, Default)]
@@ -22,6 +33,82 @@ impl Rect {
             h: self.h - 2 * margin,
         }
     }
+
+    /// The rect grown by `amount` on every side, as far as the top and the left of the slide.
+    pub fn expand(&self, amount: u32) -> Self {
+        let x = self.x.saturating_sub(amount);
+        let y = self.y.saturating_sub(amount);
+        Self {
+            x,
+            y,
+            w: self.x + self.w + amount - x,
+            h: self.y + self.h + amount - y,
+        }
+    }
+
+    /// The part of a slide of the given size that lies inside of its margins. Every side has the
+    /// `margin` of the slide, unless it has a `margin-top`, `margin-right`, `margin-bottom` or
+    /// `margin-left` of its own.
+    pub fn inside_slide_margins(
+        slide_styles: &HashMap<String, PropertyValue>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let margin = extract_number(slide_styles, "margin");
+        let side =
+            |property: &str| extract_optional_number(slide_styles, property).unwrap_or(margin);
+        let (top, right, bottom, left) = (
+            side("margin-top"),
+            side("margin-right"),
+            side("margin-bottom"),
+            side("margin-left"),
+        );
+        Self {
+            x: left,
+            y: top,
+            w: width.saturating_sub(left + right),
+            h: height.saturating_sub(top + bottom),
+        }
+    }
+
+    pub fn union(&self, other: &Rect) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self {
+            x,
+            y,
+            w: (self.x + self.w).max(other.x + other.w) - x,
+            h: (self.y + self.h).max(other.y + other.h) - y,
+        }
+    }
+
+    pub fn centre(&self) -> (f32, f32) {
+        (
+            self.x as f32 + self.w as f32 / 2.0,
+            self.y as f32 + self.h as f32 / 2.0,
+        )
+    }
+
+    /// The point where the line from the centre of the rect towards `target` leaves the rect.
+    pub fn edge_towards(&self, target: (f32, f32)) -> (f32, f32) {
+        let (centre_x, centre_y) = self.centre();
+        let dx = target.0 - centre_x;
+        let dy = target.1 - centre_y;
+
+        let scale_x = if dx == 0.0 {
+            f32::INFINITY
+        } else {
+            self.w as f32 / 2.0 / dx.abs()
+        };
+        let scale_y = if dy == 0.0 {
+            f32::INFINITY
+        } else {
+            self.h as f32 / 2.0 / dy.abs()
+        };
+        let scale = scale_x.min(scale_y).min(1.0);
+
+        (centre_x + dx * scale, centre_y + dy * scale)
+    }
 }
 
 pub fn folium_to_sdl_rect(folium_rect: Rect) -> sdl2::rect::Rect {
@@ -33,7 +120,418 @@ pub fn folium_to_sdl_rect(folium_rect: Rect) -> sdl2::rect::Rect {
     )
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Where an image of the given size is drawn in `area` with the given `fit` mode, as the part
+/// of the image that is drawn and the rect it is drawn to. `contain` scales the image to fit
+/// in the area, `cover` scales it to fill the area and cuts off what sticks out, `crop` keeps
+/// the image at its own size and cuts off what sticks out, and `stretch` fills the area with
+/// the entire image regardless of its aspect ratio. All but `stretch` centre the image.
+pub fn fit_rects(fit: &str, image: (u32, u32), area: Rect) -> (Rect, Rect) {
+    let (image_w, image_h) = image;
+    let full_image = Rect {
+        x: 0,
+        y: 0,
+        w: image_w,
+        h: image_h,
+    };
+    let centred = |outer: Rect, w: u32, h: u32| Rect {
+        x: outer.x + (outer.w - w) / 2,
+        y: outer.y + (outer.h - h) / 2,
+        w,
+        h,
+    };
+    if image_w == 0 || image_h == 0 {
+        return (full_image, area);
+    }
+
+    match fit {
+        "cover" => {
+            let scale = (area.w as f32 / image_w as f32).max(area.h as f32 / image_h as f32);
+            let w = ((area.w as f32 / scale).round() as u32).min(image_w);
+            let h = ((area.h as f32 / scale).round() as u32).min(image_h);
+            (centred(full_image, w, h), area)
+        }
+        "crop" => {
+            let w = image_w.min(area.w);
+            let h = image_h.min(area.h);
+            (centred(full_image, w, h), centred(area, w, h))
+        }
+        "stretch" => (full_image, area),
+        // `contain`, as any other value is reported when the style is parsed
+        _ => {
+            let scale = (area.w as f32 / image_w as f32).min(area.h as f32 / image_h as f32);
+            let w = ((image_w as f32 * scale).round() as u32).min(area.w);
+            let h = ((image_h as f32 * scale).round() as u32).min(area.h);
+            (full_image, centred(area, w, h))
+        }
+    }
+}
+
+/// Splits the area of a numbered list into one row of equal height per item. Each row is split
+/// into the area for the marker on the left, which is `indent` wide, and the area for the item.
+pub fn list_item_rects(area: Rect, items: u32, gap: u32, indent: u32) -> Vec<(Rect, Rect)> {
+    if items == 0 {
+        return Vec::new();
+    }
+
+    let item_height = area.h.saturating_sub(gap * (items - 1)) / items;
+    let indent = indent.min(area.w);
+
+    (0..items)
+        .map(|idx| {
+            let y = area.y + idx * (item_height + gap);
+            (
+                Rect {
+                    x: area.x,
+                    y,
+                    w: indent,
+                    h: item_height,
+                },
+                Rect {
+                    x: area.x + indent,
+                    y,
+                    w: area.w - indent,
+                    h: item_height,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Splits a length into `parts` tracks separated by `gap`, and returns the offset and length
+/// of every track. The tracks are sized in proportion to `weights`; tracks without a weight
+/// get a weight of 1, so without any weights all tracks are equally large.
+pub fn split_by_weights(length: u32, parts: u32, gap: u32, weights: &[u32]) -> Vec<(u32, u32)> {
+    let available = length.saturating_sub(gap * parts.saturating_sub(1));
+    let weights = (0..parts as usize)
+        .map(|idx| weights.get(idx).copied().unwrap_or(1))
+        .collect::<Vec<_>>();
+    let total_weight = weights.iter().sum::<u32>().max(1);
+
+    let mut offset = 0;
+    weights
+        .into_iter()
+        .map(|weight| {
+            let track = (offset, available * weight / total_weight);
+            offset += track.1 + gap;
+            track
+        })
+        .collect()
+}
+
+/// Places the cells of a grid with the given number of columns, given how many columns and rows
+/// every cell spans. Cells are placed row by row, each in the first free spot after the cell
+/// before it where it fits. Returns the column, row, column span and row span of every cell;
+/// spans are at least 1, and cells are never wider than the grid.
+pub fn grid_cells(columns: u32, spans: &[(u32, u32)]) -> Vec<(u32, u32, u32, u32)> {
+    let mut taken: Vec<Vec<bool>> = Vec::new();
+    let is_free = |taken: &mut Vec<Vec<bool>>, column: u32, row: u32| {
+        while taken.len() <= row as usize {
+            taken.push(vec![false; columns as usize]);
+        }
+        !taken[row as usize][column as usize]
+    };
+
+    let mut position = 0;
+    spans
+        .iter()
+        .map(|(column_span, row_span)| {
+            let column_span = (*column_span).clamp(1, columns);
+            let row_span = (*row_span).max(1);
+
+            loop {
+                let (column, row) = (position % columns, position / columns);
+                let fits = column + column_span <= columns
+                    && (row..row + row_span).all(|row| {
+                        (column..column + column_span)
+                            .all(|column| is_free(&mut taken, column, row))
+                    });
+                if fits {
+                    for taken_row in &mut taken[row as usize..(row + row_span) as usize] {
+                        for cell in &mut taken_row[column as usize..(column + column_span) as usize]
+                        {
+                            *cell = true;
+                        }
+                    }
+                    break (column, row, column_span, row_span);
+                }
+                position += 1;
+            }
+        })
+        .collect()
+}
+
+/// Whether an element asks rows and columns for a size of its own, instead of an equal share
+/// of the remaining space.
+fn has_requested_size(elem: &AbstractElement) -> bool {
+    elem.el_type() == ElementType::Sized
+        || matches!(
+            elem.data(),
+            AbstractElementData::Spacer(Some(_))
+                | AbstractElementData::Audio(_)
+                | AbstractElementData::Place(..)
+        )
+}
+
+/// The size an element asks for: the size of a sized element, given inline or else by its
+/// `size`, or the amount of a spacer. Audio is not drawn and placed content is positioned on
+/// its own, so they ask for no room.
+fn requested_size(elem: &AbstractElement, style_map: &StyleMap) -> SizeSpec {
+    match elem.data() {
+        AbstractElementData::Sized(Some(spec), _) => *spec,
+        AbstractElementData::Spacer(Some(amount)) => SizeSpec {
+            width: Some(*amount),
+            height: Some(*amount),
+        },
+        AbstractElementData::Audio(_) | AbstractElementData::Place(..) => SizeSpec {
+            width: Some(0),
+            height: Some(0),
+        },
+        _ => extract_size_spec(&style_map.styles_for_element(elem), "size"),
+    }
+}
+
+/// How large a share of the free space of a row or column an element takes, relative to its
+/// siblings. This is the `grow` of the element, which is 1 when it is not set.
+fn grow_weight(elem: &AbstractElement, style_map: &StyleMap) -> u32 {
+    extract_optional_number(&style_map.styles_for_element(elem), "grow").unwrap_or(1)
+}
+
+/// The gaps between the consecutive children of a row or column. The gap between two children
+/// is the `gap-after` of the first or the `gap-before` of the second, the larger of the two if
+/// both are set, and the `gap` of the row or column if neither is.
+fn gaps_between(children: &[AbstractElement], style_map: &StyleMap, gap: u32) -> Vec<u32> {
+    children
+        .windows(2)
+        .map(|pair| {
+            let after =
+                extract_optional_number(&style_map.styles_for_element(&pair[0]), "gap-after");
+            let before =
+                extract_optional_number(&style_map.styles_for_element(&pair[1]), "gap-before");
+            after.max(before).unwrap_or(gap)
+        })
+        .collect()
+}
+
+/// Divides the free length of a row or column over its elements without a requested size, in
+/// proportion to their `grow`. An image whose aspect ratio warrants less than its share at the
+/// `cross` length of the row or column only takes that much, and the rest is divided over the
+/// other elements. When the row or column is `content_sized`, every element with a natural size
+/// takes that much, images take what their aspect ratio warrants, and only the other elements
+/// share the rest; if that is more than there is room for, it is shrunk to fit. `along_width`
+/// tells whether the lengths are widths or heights.
+fn flexible_lengths<'s>(
+    elems: &[AbstractElement],
+    global: &GlobalState,
+    style_map: &'s StyleMap,
+    free: u32,
+    cross: u32,
+    along_width: bool,
+    content_sized: bool,
+) -> impl Fn(&AbstractElement) -> u32 + 's {
+    let mut total_grow = elems
+        .iter()
+        .map(|elem| grow_weight(elem, style_map))
+        .sum::<u32>()
+        .max(1);
+
+    let mut natural_lengths = HashMap::new();
+    for elem in elems {
+        let natural = match elem.data() {
+            AbstractElementData::Image(path) => global.image_size(path).map(|(width, height)| {
+                let (length, other) = if along_width {
+                    (width, height)
+                } else {
+                    (height, width)
+                };
+                (cross as u64 * length as u64 / other as u64) as u32
+            }),
+            _ if content_sized => {
+                let max_width = if along_width { free } else { cross };
+                natural_size(elem, global, style_map, max_width).map(|(width, height)| {
+                    if along_width {
+                        width
+                    } else {
+                        height
+                    }
+                })
+            }
+            _ => None,
+        };
+        let Some(natural) = natural else {
+            continue;
+        };
+        if content_sized || natural < free * grow_weight(elem, style_map) / total_grow {
+            natural_lengths.insert(elem.id(), natural);
+        }
+    }
+
+    let natural_total = natural_lengths.values().sum::<u32>();
+    if natural_total > free {
+        for length in natural_lengths.values_mut() {
+            *length = (*length as u64 * free as u64 / natural_total as u64) as u32;
+        }
+    }
+
+    let free = free - natural_lengths.values().sum::<u32>();
+    total_grow = (total_grow
+        - elems
+            .iter()
+            .filter(|elem| natural_lengths.contains_key(&elem.id()))
+            .map(|elem| grow_weight(elem, style_map))
+            .sum::<u32>())
+    .max(1);
+
+    move |elem| {
+        natural_lengths
+            .get(&elem.id())
+            .copied()
+            .unwrap_or_else(|| free * grow_weight(elem, style_map) / total_grow)
+    }
+}
+
+/// The size an element takes up by itself, if it has one: the size of a sized element, the
+/// amount of a spacer, the pixel size of an image or the measured size of text, wrapped at
+/// `max_width`. Other content takes whatever room it is given.
+fn natural_size(
+    elem: &AbstractElement,
+    global: &GlobalState,
+    style_map: &StyleMap,
+    max_width: u32,
+) -> Option<(u32, u32)> {
+    let styles = style_map.styles_for_element(elem);
+    let flow = measure::TextFlow::from_style(&styles);
+    // text that is sized automatically is as large as it is allowed to be
+    let measure_text = |text: &str, max_width: u32| {
+        measure::text_size(
+            &measure::FontChoice::from_style(&styles, "font"),
+            text,
+            measure::font_size(&styles, text, max_width, u32::MAX),
+            max_width,
+            flow,
+        )
+    };
+
+    match elem.data() {
+        AbstractElementData::Sized(..) => {
+            let spec = requested_size(elem, style_map);
+            Some((spec.width?, spec.height?))
+        }
+        AbstractElementData::Spacer(amount) => amount.map(|amount| (amount, amount)),
+        AbstractElementData::Image(path) => global.image_size(path),
+        AbstractElementData::Text(text) | AbstractElementData::Heading(_, text) => {
+            let padding = extract_number(&styles, "padding");
+            let (width, height) = measure_text(text, max_width.saturating_sub(2 * padding))?;
+            Some((width + 2 * padding, height + 2 * padding))
+        }
+        AbstractElementData::Code(code) => {
+            let margin = extract_number(&styles, "margin");
+            let max_width = max_width.saturating_sub(2 * margin);
+            let (width, height) = measure::text_size(
+                &measure::FontChoice::from_style(&styles, "font"),
+                code,
+                measure::font_size(&styles, code, max_width, u32::MAX),
+                max_width,
+                flow,
+            )?;
+            Some((width + 2 * margin, height + 2 * margin))
+        }
+        AbstractElementData::Link(label, _) => measure::text_size(
+            &measure::FontChoice::from_style(&styles, "font"),
+            label,
+            extract_number(&styles, "size") as f32,
+            max_width,
+            flow,
+        ),
+        // slide numbers are measured at their widest, with the total as the current number
+        AbstractElementData::SlideNumber => {
+            let total = global.number_of_slides().to_string();
+            measure::text_size(
+                &measure::FontChoice::from_style(&styles, "font"),
+                &extract_string(&styles, "format")
+                    .replace("$slide", &total)
+                    .replace("$total", &total),
+                extract_number(&styles, "size") as f32,
+                max_width,
+                flow,
+            )
+        }
+        AbstractElementData::Icon(_) => {
+            let size = extract_number(&styles, "size");
+            Some((size, size))
+        }
+        _ => None,
+    }
+}
+
+/// The part of its area that a leaf element with an `anchor` takes up: its natural size, pinned
+/// to a corner or an edge of the area, or centred in it. Images are scaled down to fit the area.
+/// Elements without an anchor or without a natural size take up the whole area.
+fn anchored_bounds(
+    elem: &AbstractElement,
+    global: &GlobalState,
+    style_map: &StyleMap,
+    area: Rect,
+) -> Rect {
+    let styles = style_map.styles_for_element(elem);
+    let Some(anchor) = styles.get("anchor").map(|anchor| anchor.to_string()) else {
+        return area;
+    };
+    let Some((width, height)) = natural_size(elem, global, style_map, area.w) else {
+        return area;
+    };
+
+    let (w, h) = match elem.data() {
+        AbstractElementData::Image(_) if width > area.w || height > area.h => {
+            let scale = (area.w as f32 / width as f32).min(area.h as f32 / height as f32);
+            (
+                (width as f32 * scale) as u32,
+                (height as f32 * scale) as u32,
+            )
+        }
+        _ => (width.min(area.w), height.min(area.h)),
+    };
+
+    // `top-left` names both sides, `top` only names one and centres along the other
+    let (vertical, horizontal) = match anchor.split_once('-') {
+        Some(sides) => sides,
+        None if matches!(anchor.as_str(), "left" | "right") => ("centre", anchor.as_str()),
+        None => (anchor.as_str(), "centre"),
+    };
+    // other anchors are reported when the style is parsed, so the rest is `centre`
+    let x = match horizontal {
+        "left" => area.x,
+        "right" => area.x + area.w - w,
+        _ => area.x + (area.w - w) / 2,
+    };
+    let y = match vertical {
+        "top" => area.y,
+        "bottom" => area.y + area.h - h,
+        _ => area.y + (area.h - h) / 2,
+    };
+    Rect { x, y, w, h }
+}
+
+/// How far the first line of a text element reaches above its baseline, which is where the
+/// baseline lies below the top of the element. The size of automatically sized text depends on
+/// the room it gets, so it is not known here.
+fn first_line_ascent(elem: &AbstractElement, style_map: &StyleMap) -> Option<u32> {
+    match elem.data() {
+        AbstractElementData::Text(_) | AbstractElementData::Heading(..) => {
+            let styles = style_map.styles_for_element(elem);
+            if !matches!(styles.get("size"), Some(PropertyValue::Number(_))) {
+                return None;
+            }
+            let ascent = measure::ascent(
+                &measure::FontChoice::from_style(&styles, "font"),
+                extract_number(&styles, "size") as f32,
+            )?;
+            Some(ascent + extract_number(&styles, "padding"))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct SizeSpec {
     pub width: Option<u32>,
     pub height: Option<u32>,
@@ -45,19 +543,85 @@ pub struct LayoutElement {
     pub max_bounds: Rect,
 }
 
+/// A row or column whose sized elements, with the gaps between them, need more room than it
+/// has, so that the slide it is on can not be laid out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutError {
+    /// The slide that could not be laid out, counting from zero. Elements do not know which
+    /// slide they are on, so this is filled in by [`Slide::layout`].
+    pub slide: usize,
+    pub element: AbstractElementID,
+    pub needed: u32,
+    pub available: u32,
+}
+
+impl LayoutError {
+    fn overflow(element: &AbstractElement, needed: u32, available: u32) -> Self {
+        Self {
+            slide: 0,
+            element: element.id(),
+            needed,
+            available,
+        }
+    }
+
+    /// Describes the error, naming the element that overflows.
+    pub fn message(&self, global: &GlobalState) -> String {
+        let elem = global.get_element_by_id(self.element).unwrap();
+        let description = match elem.name() {
+            Some(name) => format!("{} element {name}", elem.el_type()),
+            None => format!("{} element {}", elem.el_type(), self.element.0),
+        };
+        format!(
+            "error on slide {}: the sized content of the {description} needs {} pixels, but there are only {}.",
+            self.slide + 1,
+            self.needed,
+            self.available
+        )
+    }
+}
+
 impl AbstractElement {
     pub fn layout(
         &self,
         global: &GlobalState,
         style_map: &StyleMap,
         area: Rect,
-    ) -> Vec<LayoutElement> {
-        let own_target = StyleTarget::reify(self);
+    ) -> Result<Vec<LayoutElement>, LayoutError> {
+        let mut layout_elements = self.layout_contents(global, style_map, area)?;
+
+        // containers are not drawn themselves, but a border around them is, over their content
+        let is_container = matches!(
+            self.data(),
+            AbstractElementData::Row(_)
+                | AbstractElementData::Col(_)
+                | AbstractElementData::Padding(_)
+                | AbstractElementData::Grid(..)
+                | AbstractElementData::Stack(_)
+                | AbstractElementData::Flow(_)
+        );
+        let border_width =
+            extract_optional_number(&style_map.styles_for_element(self), "border-width");
+        if is_container && border_width.is_some_and(|width| width > 0) {
+            layout_elements.push(LayoutElement {
+                element: self.id(),
+                max_bounds: area,
+            });
+        }
+        Ok(layout_elements)
+    }
+
+    fn layout_contents(
+        &self,
+        global: &GlobalState,
+        style_map: &StyleMap,
+        area: Rect,
+    ) -> Result<Vec<LayoutElement>, LayoutError> {
+        let own_styles = style_map.styles_for_element(self);
 
         match self.data() {
-            AbstractElementData::Sized(elem) => {
-                let size_spec =
-                    extract_size_spec(style_map.styles_for_target(&own_target).unwrap(), "size");
+            AbstractElementData::Sized(_, elem) => {
+                let size_spec = requested_size(self, style_map);
 
                 let used_width = if let Some(width) = size_spec.width {
                     if area.w < width {
@@ -81,7 +645,7 @@ impl AbstractElement {
                     area.h
                 };
 
-                Vec::from(&[LayoutElement {
+                Ok(Vec::from(&[LayoutElement {
                     element: *elem,
                     max_bounds: Rect {
                         x: area.x,
@@ -89,60 +653,78 @@ impl AbstractElement {
                         w: used_width,
                         h: used_height,
                     },
-                }])
+                }]))
             }
             AbstractElementData::Row(elems) => {
-                let row_gap = extract_number(
-                    style_map
-                        .styles_for_target(&own_target)
-                        .expect("no style map for rows was found"),
-                    "gap",
-                );
+                let children = elems
+                    .iter()
+                    .flat_map(|id| global.get_element_by_id(*id))
+                    .collect::<Vec<_>>();
+                let gaps = gaps_between(&children, style_map, extract_number(&own_styles, "gap"));
+                let total_gap = gaps.iter().sum::<u32>();
 
                 let sized_elements = elems
                     .iter()
                     .flat_map(|id| global.get_element_by_id(*id))
-                    .filter(|elem| elem.el_type() == ElementType::Sized)
+                    .filter(has_requested_size)
                     .collect::<Vec<_>>();
 
                 let all_widths = sized_elements
                     .iter()
-                    .flat_map(|elem| {
-                        let currently_applicable_styles = StyleTarget::reify(elem);
-
-                        extract_size_spec(
-                            style_map
-                                .styles_for_target(&currently_applicable_styles)
-                                .unwrap(),
-                            "size",
-                        )
-                        .width
-                    })
+                    .flat_map(|elem| requested_size(elem, style_map).width)
                     .collect::<Vec<_>>();
 
                 let total_sized_width = all_widths.iter().sum::<u32>();
 
-                if total_sized_width + row_gap * (elems.len() - 1) as u32 > area.w {
-                    panic!("The specified layout will always overflow.")
+                if total_sized_width + total_gap > area.w {
+                    return Err(LayoutError::overflow(
+                        self,
+                        total_sized_width + total_gap,
+                        area.w,
+                    ));
                 }
 
                 let remaining_space = area.w - total_sized_width;
 
-                let single_el_width = (remaining_space - (elems.len() - 1) as u32 * row_gap)
-                    / (elems.len() - sized_elements.len()) as u32;
+                let free_width = remaining_space - total_gap;
+                let flexible_elements = elems
+                    .iter()
+                    .flat_map(|id| global.get_element_by_id(*id))
+                    .filter(|elem| !sized_elements.contains(elem))
+                    .collect::<Vec<_>>();
+                let share_of_width = flexible_lengths(
+                    &flexible_elements,
+                    global,
+                    style_map,
+                    free_width,
+                    area.h,
+                    true,
+                    extract_string(&own_styles, "sizing") == "content",
+                );
+
+                // text is moved down so that the first lines of all text in the row share the
+                // baseline of the text that reaches highest above it
+                let ascents = if extract_string(&own_styles, "align") == "baseline" {
+                    elems
+                        .iter()
+                        .flat_map(|id| global.get_element_by_id(*id))
+                        .filter_map(|elem| Some((elem.id(), first_line_ascent(&elem, style_map)?)))
+                        .collect::<HashMap<_, _>>()
+                } else {
+                    HashMap::new()
+                };
+                let baseline = ascents.values().copied().max().unwrap_or(0);
+                // a right-to-left row starts at the right, so it is laid out as usual and then
+                // mirrored inside of its area
+                let right_to_left = extract_string(&own_styles, "direction") == "rtl";
 
                 let mut x_coord = area.x;
-                elems
-                    .iter()
-                    .flat_map(|el| global.get_element_by_id(*el))
-                    .flat_map(|elem| {
-                        let bounds = if sized_elements.contains(&elem) {
-                            let spec = extract_size_spec(
-                                style_map
-                                    .styles_for_target(&StyleTarget::reify(&elem))
-                                    .unwrap(),
-                                "size",
-                            );
+                children
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, elem)| {
+                        let mut bounds = if sized_elements.contains(&elem) {
+                            let spec = requested_size(&elem, style_map);
 
                             if let Some(width) = spec.width {
                                 Rect {
@@ -155,7 +737,7 @@ impl AbstractElement {
                                 Rect {
                                     x: x_coord,
                                     y: area.y,
-                                    w: single_el_width,
+                                    w: share_of_width(&elem),
                                     h: spec.height.unwrap_or(area.h),
                                 }
                             }
@@ -163,71 +745,81 @@ impl AbstractElement {
                             Rect {
                                 x: x_coord,
                                 y: area.y,
-                                w: single_el_width,
+                                w: share_of_width(&elem),
                                 h: area.h,
                             }
                         };
 
-                        x_coord += bounds.w + row_gap;
+                        x_coord += bounds.w + gaps.get(idx).copied().unwrap_or(0);
+
+                        if let Some(ascent) = ascents.get(&elem.id()) {
+                            let shift = (baseline - ascent).min(bounds.h);
+                            bounds.y += shift;
+                            bounds.h -= shift;
+                        }
+                        if right_to_left {
+                            bounds.x = 2 * area.x + area.w - bounds.x - bounds.w;
+                        }
 
                         elem.layout(global, style_map, bounds)
                     })
+                    .flatten_ok()
                     .collect()
             }
             AbstractElementData::Col(elems) => {
-                let col_gap = extract_number(
-                    style_map
-                        .styles_for_target(&own_target)
-                        .expect("no style map for columns was found"),
-                    "gap",
-                );
+                let children = elems
+                    .iter()
+                    .flat_map(|id| global.get_element_by_id(*id))
+                    .collect::<Vec<_>>();
+                let gaps = gaps_between(&children, style_map, extract_number(&own_styles, "gap"));
+                let total_gap = gaps.iter().sum::<u32>();
 
                 let sized_elements = elems
                     .iter()
                     .flat_map(|id| global.get_element_by_id(*id))
-                    .filter(|elem| elem.el_type() == ElementType::Sized)
+                    .filter(has_requested_size)
                     .collect::<Vec<_>>();
 
                 let all_heights = sized_elements
                     .iter()
-                    .flat_map(|elem| {
-                        extract_size_spec(
-                            style_map
-                                .styles_for_target(&StyleTarget::Named(
-                                    elem.name().clone().unwrap(),
-                                ))
-                                .unwrap(),
-                            "size",
-                        )
-                        .height
-                    })
+                    .flat_map(|elem| requested_size(elem, style_map).height)
                     .collect::<Vec<_>>();
 
                 let total_sized_height = all_heights.iter().sum::<u32>();
 
-                if total_sized_height + col_gap * (elems.len() - 1) as u32 > area.h {
-                    panic!("The specified layout will always overflow.")
+                if total_sized_height + total_gap > area.h {
+                    return Err(LayoutError::overflow(
+                        self,
+                        total_sized_height + total_gap,
+                        area.h,
+                    ));
                 }
 
                 let remaining_space = area.h - total_sized_height;
 
-                let single_el_height = (remaining_space - (elems.len() - 1) as u32 * col_gap)
-                    / (elems.len() - sized_elements.len()) as u32;
+                let free_height = remaining_space - total_gap;
+                let flexible_elements = elems
+                    .iter()
+                    .flat_map(|id| global.get_element_by_id(*id))
+                    .filter(|elem| !sized_elements.contains(elem))
+                    .collect::<Vec<_>>();
+                let share_of_height = flexible_lengths(
+                    &flexible_elements,
+                    global,
+                    style_map,
+                    free_height,
+                    area.w,
+                    false,
+                    extract_string(&own_styles, "sizing") == "content",
+                );
 
                 let mut y_coord = area.y;
-                elems
-                    .iter()
-                    .flat_map(|el| global.get_element_by_id(*el))
-                    .flat_map(|elem| {
+                children
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, elem)| {
                         let bounds = if sized_elements.contains(&elem) {
-                            let spec = extract_size_spec(
-                                style_map
-                                    .styles_for_target(&StyleTarget::Named(
-                                        elem.name().clone().unwrap(),
-                                    ))
-                                    .unwrap(),
-                                "size",
-                            );
+                            let spec = requested_size(&elem, style_map);
 
                             if let Some(height) = spec.height {
                                 Rect {
@@ -241,7 +833,7 @@ impl AbstractElement {
                                     x: area.x,
                                     y: y_coord,
                                     w: spec.width.unwrap_or(area.w),
-                                    h: single_el_height,
+                                    h: share_of_height(&elem),
                                 }
                             }
                         } else {
@@ -249,23 +841,228 @@ impl AbstractElement {
                                 x: area.x,
                                 y: y_coord,
                                 w: area.w,
-                                h: single_el_height,
+                                h: share_of_height(&elem),
                             }
                         };
 
-                        y_coord += bounds.h + col_gap;
+                        y_coord += bounds.h + gaps.get(idx).copied().unwrap_or(0);
 
                         elem.layout(global, style_map, bounds)
                     })
+                    .flatten_ok()
                     .collect()
             }
-            AbstractElementData::Padding(elem) => {
-                let padding_amount = extract_number(
-                    style_map
-                        .styles_for_target(&own_target)
-                        .expect("no style map for paddings was found"),
-                    "amount",
+            AbstractElementData::Grid(columns, elems) => {
+                let children = elems
+                    .iter()
+                    .map(|id| global.get_element_by_id(*id).unwrap())
+                    .collect::<Vec<_>>();
+                let spans = children
+                    .iter()
+                    .map(|child| {
+                        let child_styles = style_map.styles_for_element(child);
+                        (
+                            extract_optional_number(&child_styles, "col-span").unwrap_or(1),
+                            extract_optional_number(&child_styles, "row-span").unwrap_or(1),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let cells = grid_cells(*columns, &spans);
+                let rows = cells
+                    .iter()
+                    .map(|(_, row, _, row_span)| row + row_span)
+                    .max()
+                    .unwrap_or(1);
+
+                let column_widths = split_by_weights(
+                    area.w,
+                    *columns,
+                    extract_number(&own_styles, "col-gap"),
+                    &extract_number_list(&own_styles, "widths"),
                 );
+                let row_heights = split_by_weights(
+                    area.h,
+                    rows,
+                    extract_number(&own_styles, "row-gap"),
+                    &extract_number_list(&own_styles, "heights"),
+                );
+
+                // a cell that spans several tracks also covers the gaps between them
+                let span_of = |tracks: &[(u32, u32)], start: u32, span: u32| {
+                    let (offset, _) = tracks[start as usize];
+                    let (last_offset, last_length) = tracks[(start + span - 1) as usize];
+                    (offset, last_offset + last_length - offset)
+                };
+
+                children
+                    .iter()
+                    .zip(cells)
+                    .map(|(child, (column, row, column_span, row_span))| {
+                        let (x, w) = span_of(&column_widths, column, column_span);
+                        let (y, h) = span_of(&row_heights, row, row_span);
+                        child.layout(
+                            global,
+                            style_map,
+                            Rect {
+                                x: area.x + x,
+                                y: area.y + y,
+                                w,
+                                h,
+                            },
+                        )
+                    })
+                    .flatten_ok()
+                    .collect()
+            }
+            AbstractElementData::Stack(elems) => {
+                // later children end up later in the layout, so they are drawn on top
+                elems
+                    .iter()
+                    .map(|id| {
+                        global
+                            .get_element_by_id(*id)
+                            .unwrap()
+                            .layout(global, style_map, area)
+                    })
+                    .flatten_ok()
+                    .collect()
+            }
+            AbstractElementData::Flow(elems) => {
+                let gap = extract_number(&own_styles, "gap");
+                let line_gap = extract_number(&own_styles, "line-gap");
+
+                // content without a natural size takes a line of its own
+                let mut lines: Vec<Vec<(AbstractElement, (u32, u32))>> = Vec::new();
+                let mut line_width = 0;
+                for elem in elems.iter().flat_map(|id| global.get_element_by_id(*id)) {
+                    let (width, height) = natural_size(&elem, global, style_map, area.w)
+                        .map_or((area.w, area.h), |(width, height)| {
+                            (width.min(area.w), height)
+                        });
+                    match lines.last_mut() {
+                        Some(line) if line_width + gap + width <= area.w => {
+                            line_width += gap + width;
+                            line.push((elem, (width, height)));
+                        }
+                        _ => {
+                            line_width = width;
+                            lines.push(vec![(elem, (width, height))]);
+                        }
+                    }
+                }
+
+                // the items on a line are centred vertically on it
+                let mut layout_elements = Vec::new();
+                let mut y_coord = area.y;
+                for line in lines {
+                    let line_height = line.iter().map(|(_, (_, height))| *height).max().unwrap();
+                    let room_left = (area.y + area.h).saturating_sub(y_coord);
+                    let mut x_coord = area.x;
+                    for (elem, (width, height)) in line {
+                        let bounds = Rect {
+                            x: x_coord,
+                            y: y_coord + (line_height - height) / 2,
+                            w: width,
+                            h: height.min(room_left),
+                        };
+                        layout_elements.extend(elem.layout(global, style_map, bounds)?);
+                        x_coord += width + gap;
+                    }
+                    y_coord += line_height + line_gap;
+                }
+                Ok(layout_elements)
+            }
+            AbstractElementData::List(elems) => {
+                // the list itself is laid out too, so that the markers can be drawn in its area
+                let item_rects = list_item_rects(
+                    area,
+                    elems.len() as u32,
+                    extract_number(&own_styles, "gap"),
+                    extract_number(&own_styles, "indent"),
+                );
+
+                std::iter::once(Ok(vec![LayoutElement {
+                    element: self.id(),
+                    max_bounds: area,
+                }]))
+                .chain(elems.iter().zip(item_rects).map(|(id, (_, content_area))| {
+                    global
+                        .get_element_by_id(*id)
+                        .unwrap()
+                        .layout(global, style_map, content_area)
+                }))
+                .flatten_ok()
+                .collect()
+            }
+            AbstractElementData::Rect(content)
+            | AbstractElementData::Ellipse(content)
+            | AbstractElementData::Circle(content) => {
+                // the shape is drawn first, so that its content ends up on top of it
+                let mut layout_elements = vec![LayoutElement {
+                    element: self.id(),
+                    max_bounds: area,
+                }];
+                if let Some(content) = content {
+                    layout_elements.extend(
+                        global
+                            .get_element_by_id(*content)
+                            .unwrap()
+                            .layout(global, style_map, area)?,
+                    );
+                }
+                Ok(layout_elements)
+            }
+            AbstractElementData::Rotate(_, child)
+            | AbstractElementData::Opacity(_, child)
+            | AbstractElementData::Shadow(child) => {
+                // the child is laid out as usual and rotated, faded or given a shadow when it
+                // is drawn
+                let mut layout_elements = vec![LayoutElement {
+                    element: self.id(),
+                    max_bounds: area,
+                }];
+                layout_elements.extend(
+                    global
+                        .get_element_by_id(*child)
+                        .unwrap()
+                        .layout(global, style_map, area)?,
+                );
+                Ok(layout_elements)
+            }
+            AbstractElementData::Frame(child) => {
+                // the border lies around the content and is drawn on top of it, so that
+                // rounded corners cover the corners of images
+                let border =
+                    extract_number(&own_styles, "width") + extract_number(&own_styles, "gap");
+                let mut layout_elements = global.get_element_by_id(*child).unwrap().layout(
+                    global,
+                    style_map,
+                    area.with_margin(border),
+                )?;
+                layout_elements.push(LayoutElement {
+                    element: self.id(),
+                    max_bounds: area,
+                });
+                Ok(layout_elements)
+            }
+            AbstractElementData::Place(x, y, child) => {
+                // the child gets the room from its position to the edges of the slide
+                let slide_styles = style_map
+                    .styles_for_target(&StyleTarget::Slide)
+                    .expect("No default slide style was found.");
+                let placed_area = Rect {
+                    x: *x,
+                    y: *y,
+                    w: extract_number(slide_styles, "width").saturating_sub(*x),
+                    h: extract_number(slide_styles, "height").saturating_sub(*y),
+                };
+                global
+                    .get_element_by_id(*child)
+                    .unwrap()
+                    .layout(global, style_map, placed_area)
+            }
+            AbstractElementData::Padding(elem) => {
+                let padding_amount = extract_number(&own_styles, "amount");
                 let new_bound = area.with_margin(padding_amount);
 
                 global
@@ -277,17 +1074,54 @@ impl AbstractElement {
             | AbstractElementData::Text(_)
             | AbstractElementData::Code(_)
             | AbstractElementData::Image(_)
-            | AbstractElementData::None => Vec::from(&[LayoutElement {
-                max_bounds: area,
+            | AbstractElementData::Math(_)
+            | AbstractElementData::Video(_)
+            | AbstractElementData::BarChart(_)
+            | AbstractElementData::LineChart(_)
+            | AbstractElementData::PieChart(_)
+            | AbstractElementData::Plot(_)
+            | AbstractElementData::Spacer(_)
+            | AbstractElementData::SlideNumber
+            | AbstractElementData::Progress
+            | AbstractElementData::Link(..)
+            | AbstractElementData::Icon(_)
+            | AbstractElementData::Heading(..)
+            | AbstractElementData::Clock
+            | AbstractElementData::Audio(_)
+            | AbstractElementData::None => Ok(Vec::from(&[LayoutElement {
+                max_bounds: anchored_bounds(self, global, style_map, area),
                 element: self.id(),
-            }]),
+            }])),
+            // connectors are positioned after the rest of the slide, see Slide::layout_connectors
+            AbstractElementData::Line(..) | AbstractElementData::Arrow(..) => Ok(Vec::new()),
         }
     }
 }
 
 impl Slide {
     /// Layouting a slide positions elements on the slide.
-    pub fn layout(&self, global: &GlobalState, size_override: Option<Rect>) -> Vec<LayoutElement> {
+    pub fn layout(
+        &self,
+        global: &GlobalState,
+        size_override: Option<Rect>,
+    ) -> Result<Vec<LayoutElement>, LayoutError> {
+        self.layout_elements(global, size_override)
+            .map_err(|err| LayoutError {
+                slide: global
+                    .slides
+                    .borrow()
+                    .iter()
+                    .position(|slide| slide.id() == self.id())
+                    .unwrap_or(0),
+                ..err
+            })
+    }
+
+    fn layout_elements(
+        &self,
+        global: &GlobalState,
+        size_override: Option<Rect>,
+    ) -> Result<Vec<LayoutElement>, LayoutError> {
         let slide_styles = self
             .style_map()
             .styles_for_target(&StyleTarget::Slide)
@@ -295,17 +1129,357 @@ impl Slide {
 
         let slide_content = global.get_element_by_id(self.content()).unwrap();
 
-        let base_width = extract_number(slide_styles, "width");
-        let base_height = extract_number(slide_styles, "height");
-        let slide_margin = extract_number(slide_styles, "margin");
+        let mut area = size_override.unwrap_or(Rect::inside_slide_margins(
+            slide_styles,
+            extract_number(slide_styles, "width"),
+            extract_number(slide_styles, "height"),
+        ));
+
+        // the deck's header and footer take their room from the top and the bottom of the area
+        let deck = global.deck();
+        let mut layout_elements = Vec::new();
+        if let (Some(header), true) = (deck.header, extract_boolean(slide_styles, "header")) {
+            let height = extract_number(slide_styles, "header-height").min(area.h);
+            let header_area = Rect { h: height, ..area };
+            area.y += height;
+            area.h -= height;
+            layout_elements.extend(global.get_element_by_id(header).unwrap().layout(
+                global,
+                self.style_map(),
+                header_area,
+            )?);
+        }
+        let footer_area = match (deck.footer, extract_boolean(slide_styles, "footer")) {
+            (Some(footer), true) => {
+                let height = extract_number(slide_styles, "footer-height").min(area.h);
+                area.h -= height;
+                Some((
+                    footer,
+                    Rect {
+                        y: area.y + area.h,
+                        h: height,
+                        ..area
+                    },
+                ))
+            }
+            _ => None,
+        };
+
+        layout_elements.extend(slide_content.layout(global, self.style_map(), area)?);
+        if let Some((footer, footer_area)) = footer_area {
+            layout_elements.extend(global.get_element_by_id(footer).unwrap().layout(
+                global,
+                self.style_map(),
+                footer_area,
+            )?);
+        }
+        Ok(layout_elements)
+    }
 
-        let area = size_override.unwrap_or(Rect {
-            x: slide_margin,
-            y: slide_margin,
-            w: base_width - 2 * slide_margin,
-            h: base_height - 2 * slide_margin,
+    /// Puts the layout of a slide in the order it is drawn in: by the `z` of the elements,
+    /// lowest first, and in the order of the layout where that is the same. An element without
+    /// a `z` of its own has that of the element around it, so that wrappers and their content
+    /// stay together.
+    pub fn in_draw_order(
+        &self,
+        global: &GlobalState,
+        mut layout: Vec<LayoutElement>,
+    ) -> Vec<LayoutElement> {
+        let deck = global.deck();
+        let roots = [Some(self.content()), deck.header, deck.footer];
+
+        let mut z_levels = HashMap::new();
+        for root in roots.into_iter().flatten() {
+            // ancestors come before their descendants, whose own z overrides theirs
+            for id in global.traverse(root) {
+                let elem = global.get_element_by_id(id).unwrap();
+                if let Some(z) =
+                    extract_optional_number(&self.style_map().styles_for_element(&elem), "z")
+                {
+                    for descendant in global.traverse(id) {
+                        z_levels.insert(descendant, z);
+                    }
+                }
+            }
+        }
+
+        layout.sort_by_key(|layout_element| {
+            z_levels.get(&layout_element.element).copied().unwrap_or(0)
         });
+        layout
+    }
+
+    /// How opaque the elements on a slide are drawn, as an alpha from 0 to 255, for the elements
+    /// that are not fully opaque. The `opacity` of an element also applies to everything inside
+    /// of it. Shadows are left out, because their `opacity` sets how dark they are.
+    pub fn opacities(&self, global: &GlobalState) -> HashMap<AbstractElementID, u8> {
+        let deck = global.deck();
+        let roots = [Some(self.content()), deck.header, deck.footer];
+
+        let mut opacities = HashMap::new();
+        let elements = roots
+            .into_iter()
+            .flatten()
+            .flat_map(|root| global.traverse(root))
+            .chain(self.connectors().iter().copied());
+        for id in elements {
+            let elem = global.get_element_by_id(id).unwrap();
+            let styles = self.style_map().styles_for_element(&elem);
+            if elem.el_type() == ElementType::Shadow || !styles.contains_key("opacity") {
+                continue;
+            }
+
+            let opacity = extract_decimal(&styles, "opacity").clamp(0.0, 1.0);
+            for descendant in global.traverse(id) {
+                *opacities.entry(descendant).or_insert(1.0) *= opacity;
+            }
+        }
+
+        opacities
+            .into_iter()
+            .filter(|(_, opacity)| *opacity < 1.0)
+            .map(|(id, opacity)| (id, (opacity * 255.0).round() as u8))
+            .collect()
+    }
+
+    /// The animation of every element on a slide that is animated when the slide is shown,
+    /// with the element whose animation it is: everything inside of an animated element moves
+    /// with it, unless it has an animation of its own.
+    pub fn animations(
+        &self,
+        global: &GlobalState,
+    ) -> HashMap<AbstractElementID, (AbstractElementID, Animation)> {
+        let deck = global.deck();
+        let roots = [Some(self.content()), deck.header, deck.footer];
+
+        let mut animations = HashMap::new();
+        let elements = roots
+            .into_iter()
+            .flatten()
+            .flat_map(|root| global.traverse(root))
+            .chain(self.connectors().iter().copied());
+        for id in elements {
+            let elem = global.get_element_by_id(id).unwrap();
+            if let Some(animation) =
+                Animation::from_style(&self.style_map().styles_for_element(&elem))
+            {
+                for descendant in global.traverse(id) {
+                    animations.insert(descendant, (id, animation));
+                }
+            }
+        }
+        animations
+    }
+
+    /// The text and code on a slide with `overflow: error` that does not fit in its box.
+    pub fn overflowing_elements(
+        &self,
+        global: &GlobalState,
+        layout: &[LayoutElement],
+    ) -> Vec<AbstractElementID> {
+        layout
+            .iter()
+            .filter(|layout_element| {
+                let elem = global.get_element_by_id(layout_element.element).unwrap();
+                let styles = self.style_map().styles_for_element(&elem);
+                let area = layout_element.max_bounds;
+                match elem.data() {
+                    AbstractElementData::Text(text) | AbstractElementData::Heading(_, text) => {
+                        let text_area = area.with_margin(extract_number(&styles, "padding"));
+                        extract_string(&styles, "overflow") == "error"
+                            && measure::overflows(&styles, text, text_area.w, text_area.h)
+                    }
+                    AbstractElementData::Code(code) => {
+                        let text_area = area.with_margin(extract_number(&styles, "margin"));
+                        extract_string(&styles, "overflow") == "error"
+                            && measure::overflows(&styles, code, text_area.w, text_area.h)
+                    }
+                    _ => false,
+                }
+            })
+            .map(|layout_element| layout_element.element)
+            .collect()
+    }
+
+    /// Positions the lines and arrows of a slide. Endpoints that refer to an element lie on the
+    /// edge of that element's bounding box, on the straight line between the two endpoints.
+    pub fn layout_connectors(
+        &self,
+        global: &GlobalState,
+        layout: &[LayoutElement],
+    ) -> Vec<ConnectorSegment> {
+        self.connectors()
+            .iter()
+            .filter_map(|id| {
+                let (from, to) = match global.get_element_by_id(*id)?.data() {
+                    AbstractElementData::Line(from, to) | AbstractElementData::Arrow(from, to) => {
+                        (from.clone(), to.clone())
+                    }
+                    _ => return None,
+                };
+
+                let from_bounds = self.endpoint_bounds(global, layout, &from)?;
+                let to_bounds = self.endpoint_bounds(global, layout, &to)?;
+                let from_centre = from_bounds.centre();
+                let to_centre = to_bounds.centre();
+
+                Some(ConnectorSegment {
+                    element: *id,
+                    from: from_bounds.edge_towards(to_centre),
+                    to: to_bounds.edge_towards(from_centre),
+                })
+            })
+            .collect()
+    }
+
+    /// The area an endpoint refers to: the union of the layout rects of the named element, or an
+    /// empty rect for a point.
+    fn endpoint_bounds(
+        &self,
+        global: &GlobalState,
+        layout: &[LayoutElement],
+        endpoint: &Endpoint,
+    ) -> Option<Rect> {
+        let name = match endpoint {
+            Endpoint::Point(x, y) => {
+                return Some(Rect {
+                    x: *x,
+                    y: *y,
+                    w: 0,
+                    h: 0,
+                })
+            }
+            Endpoint::Element(name) => name,
+        };
+
+        let named_id = global.traverse(self.content()).into_iter().find(|id| {
+            global
+                .get_element_by_id(*id)
+                .is_some_and(|elem| elem.name().as_deref() == Some(name.as_str()))
+        })?;
+        let descendants = global.traverse(named_id);
+
+        layout
+            .iter()
+            .filter(|layout_elem| descendants.contains(&layout_elem.element))
+            .map(|layout_elem| layout_elem.max_bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectorSegment {
+    pub element: AbstractElementID,
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::load;
+
+    #[test]
+    fn opacity_applies_to_everything_inside_an_element() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ group :: row(text("a"), faint :: text("b")) group { opacity: 0.5 } faint { opacity: 0.5 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let opacities = slides[0].opacities(&global);
+        assert_eq!(opacities.get(&AbstractElementID(1)), Some(&128));
+        assert_eq!(opacities.get(&AbstractElementID(2)), Some(&64));
+        assert_eq!(opacities.get(&AbstractElementID(3)), Some(&128));
+        assert_eq!(opacities.len(), 3);
+    }
+
+    #[test]
+    fn bordered_containers_are_drawn_over_their_content() {
+        let global = GlobalState::new();
+        let source = String::from(
+            r#"[ boxed :: row(rect(), rect()) boxed { border-width: 4, border-colour: #f00 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let area = Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 300,
+        };
+        let layout = global.slides.borrow()[0]
+            .layout(&global, Some(area))
+            .unwrap();
+        assert_eq!(layout.len(), 3);
+        assert_eq!(layout[2].element, AbstractElementID(3));
+        assert_eq!(layout[2].max_bounds, area);
+
+        // without a border, a row has no layout element of its own
+        let global = GlobalState::new();
+        let source = String::from(r#"[ row(rect(), rect()) ]"#);
+        assert_eq!(Ok(()), load(&global, source));
+        let layout = global.slides.borrow()[0]
+            .layout(&global, Some(area))
+            .unwrap();
+        assert_eq!(layout.len(), 2);
+    }
+
+    #[test]
+    fn scaled_slides() {
+        let global = GlobalState::new();
+        global.set_scale(2.0);
+        let source = String::from(
+            r#"[ row(place(100, 40, text("a")), sized(300, _, rect())) line(from: [10, 20], to: [30, 40]) text { size: 30, tracking: 1.5 } slide { margin-left: 12 } ]"#,
+        );
+        assert_eq!(Ok(()), load(&global, source));
+
+        let slides = global.slides.borrow();
+        let styles = slides[0].style_map();
+        let slide_styles = styles.styles_for_target(&StyleTarget::Slide).unwrap();
+        assert_eq!(extract_number(slide_styles, "width"), 3840);
+        assert_eq!(extract_number(slide_styles, "height"), 2160);
+        assert_eq!(extract_number(slide_styles, "margin-left"), 24);
+        // sizes are scaled, but other numbers are not
+        assert_eq!(extract_number(slide_styles, "dpi"), 96);
+
+        let elements = global.get_slide_elements(&slides[0]);
+        let text = elements
+            .iter()
+            .find(|elem| elem.el_type() == ElementType::Text)
+            .unwrap();
+        let text_styles = styles.styles_for_element(text);
+        assert_eq!(extract_number(&text_styles, "size"), 60);
+        assert_eq!(extract_decimal(&text_styles, "tracking"), 3.0);
+
+        assert!(elements
+            .iter()
+            .any(|elem| elem.data() == &AbstractElementData::Place(200, 80, text.id())));
+        assert!(elements.iter().any(|elem| elem.data()
+            == &AbstractElementData::Line(Endpoint::Point(20, 40), Endpoint::Point(60, 80))));
+        assert!(elements.iter().any(|elem| matches!(
+            elem.data(),
+            AbstractElementData::Sized(
+                Some(SizeSpec {
+                    width: Some(600),
+                    height: None
+                }),
+                _
+            )
+        )));
 
-        slide_content.layout(global, self.style_map(), area)
+        // which is what the slide is laid out with
+        let rect = elements
+            .iter()
+            .find(|elem| elem.el_type() == ElementType::Rect)
+            .unwrap();
+        let layout = slides[0].layout(&global, None).unwrap();
+        let bounds = layout
+            .iter()
+            .find(|layout_element| layout_element.element == rect.id())
+            .unwrap()
+            .max_bounds;
+        assert_eq!(bounds.w, 600);
     }
 }
@@ -1,27 +1,57 @@
 #![allow(dead_code)]
 
+mod animation;
 mod ast;
+mod audio;
+mod bidi;
+mod chart;
+mod clock;
 mod error;
+mod format;
+mod html;
+mod icons;
+mod image;
 mod interpreter;
 mod layout;
+mod markup;
+mod math;
+mod measure;
+mod pdf;
+mod raster;
 mod render;
+mod shaping;
 mod style;
+mod svg;
+mod video;
 
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, ops::RangeInclusive, path::PathBuf, str::FromStr};
 
-use sdl2::{event::Event, image::SaveSurface, keyboard::Keycode};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+    mouse::{Cursor, MouseButton, SystemCursor},
+};
 
 use clap::{Parser, Subcommand};
 
-pub const SLIDE_WIDTH: u32 = 1920;
-pub const SLIDE_HEIGHT: u32 = 1080;
-
 #[derive(Parser)]
 #[command(author = "Simeon Duwel", about = "Presentation renderer and viewer")]
 struct FoliumArgs {
     #[arg(long, short, default_value_t = false, global = true)]
     /// Whether or not to draw red 1px rectangles around all elements; useful for debugging layout issues
     rects: bool,
+    /// Draw guides for the layout over the slides: their margins, the gaps in rows and columns and the name and ID of every element
+    #[arg(long, default_value_t = false, global = true)]
+    guides: bool,
+    /// Include the `@if profile("name")` sections for this profile. Can be given multiple times
+    #[arg(long = "profile", value_name = "NAME", global = true)]
+    profiles: Vec<String>,
+    /// Show a variant of the presentation with other colours: `dark`, or one that it defines with a `variant` block
+    #[arg(long, value_name = "NAME", global = true)]
+    variant: Option<String>,
+    /// Draw text for the red, green and blue subpixels of LCD screens, which makes it sharper on them
+    #[arg(long, default_value_t = false, global = true)]
+    subpixel: bool,
     #[command(subcommand)]
     command: FoliumSubcommand,
 }
@@ -34,31 +64,291 @@ enum FoliumSubcommand {
         input: PathBuf,
         /// The directory path to write the files to
         output: PathBuf,
+        /// Render the slides larger by this factor, with everything on them, for print or high resolution screens
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        /// Draw the slides without SDL's renderer, for machines without a video driver, like servers and CI
+        #[arg(long, default_value_t = false)]
+        software: bool,
+        /// The format of the images: png, jpg or webp. JPEG files are smaller for slides with photos
+        #[arg(long, default_value = "png")]
+        format: raster::ImageFormat,
+        /// The quality of JPEG files, from 1 to 100. PNG and WebP files are lossless
+        #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+        quality: u8,
+        /// Only render these slides, counting from 1, like `3,5,10-14`
+        #[arg(long, value_name = "SLIDES")]
+        slides: Option<SlideSelection>,
+    },
+    /// Write the slides to a folder as SVG files, with shapes and text as vectors, to edit further in a vector editor like Inkscape
+    Svg {
+        /// The source .flm file containing your presentation
+        input: PathBuf,
+        /// The directory path to write the files to
+        output: PathBuf,
+    },
+    /// Write the slides to a PDF file, one on every page, or several on every page as a handout to print
+    Pdf {
+        /// The source .flm file containing your presentation
+        input: PathBuf,
+        /// The path of the PDF file to write
+        output: PathBuf,
+        /// Put 2, 4 or 6 slides on every page of paper, as a handout
+        #[arg(long, value_name = "SLIDES")]
+        handout: Option<usize>,
+        /// The paper that a handout is printed on: a4 or letter
+        #[arg(long, default_value = "a4")]
+        paper: pdf::Paper,
+        /// Put lines for notes next to every slide of a handout
+        #[arg(long, default_value_t = false)]
+        note_lines: bool,
+    },
+    /// Write the slides to a single HTML file that presents them in a browser, to share a presentation as a file or a link
+    ExportHtml {
+        /// The source .flm file containing your presentation
+        input: PathBuf,
+        /// The path of the HTML file to write
+        output: PathBuf,
     },
     /// Open a presentation window
     Present {
         /// The source .flm file containing your presentation
         input: PathBuf,
+        /// Keep what is drawn over a slide with the pen when going to another one, to show it again when coming back
+        #[arg(long, default_value_t = false)]
+        keep_drawings: bool,
     },
     /// Inspect a .flm file and print some info. Can also be used as a check for syntax errors
     Inspect {
         /// The source .flm file containing your presentation
         input: PathBuf,
     },
+    /// Reformat a .flm file with canonical indentation and spacing
+    Fmt {
+        /// The source .flm file containing your presentation
+        input: PathBuf,
+        /// Overwrite the file instead of printing the formatted source
+        #[arg(long, default_value_t = false)]
+        in_place: bool,
+    },
     /// Lists all possible font values available for styling.
     #[command(subcommand_negates_reqs = true)]
     ListFonts,
 }
 
+/// Slides picked by their numbers, counting from 1, and ranges of them, like `3,5,10-14`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SlideSelection(Vec<RangeInclusive<usize>>);
+
+impl SlideSelection {
+    fn contains(&self, number: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&number))
+    }
+
+    /// The highest number that is picked.
+    fn last(&self) -> usize {
+        self.0.iter().map(|range| *range.end()).max().unwrap_or(0)
+    }
+}
+
+impl FromStr for SlideSelection {
+    type Err = String;
+
+    fn from_str(selection: &str) -> Result<Self, Self::Err> {
+        let number = |number: &str| match number.trim().parse::<usize>() {
+            Ok(0) => Err(String::from("slides are counted from 1")),
+            Ok(number) => Ok(number),
+            Err(_) => Err(format!("{:?} is not a slide number", number.trim())),
+        };
+        selection
+            .split(',')
+            .map(|part| match part.split_once('-') {
+                Some((start, end)) => {
+                    let (start, end) = (number(start)?, number(end)?);
+                    if start > end {
+                        return Err(format!("the range {} ends before it starts", part.trim()));
+                    }
+                    Ok(start..=end)
+                }
+                None => number(part).map(|number| number..=number),
+            })
+            .collect::<Result<_, _>>()
+            .map(SlideSelection)
+    }
+}
+
+/// The colours of the pen that draws over slides while presenting, which C cycles through.
+const PEN_COLOURS: [(u8, u8, u8); 5] = [
+    (230, 40, 40),
+    (40, 90, 230),
+    (30, 160, 60),
+    (250, 200, 0),
+    (20, 20, 20),
+];
+
+fn report_errors(errors: &[error::FoliumError]) {
+    for err in errors {
+        eprintln!("error {err}");
+    }
+    eprintln!("found {} error(s)", errors.len());
+}
+
+fn load_or_exit(
+    state: &ast::GlobalState,
+    input: PathBuf,
+    profiles: &[String],
+    variant: Option<&String>,
+) {
+    state.set_profiles(profiles.to_vec());
+    state.set_variant(variant.cloned());
+    if let Err(errors) = interpreter::load_from_file(state, input) {
+        report_errors(&errors);
+        std::process::exit(1);
+    }
+    if let Some(variant) = state.unknown_variant() {
+        eprintln!("error: the presentation has no variant {variant}");
+        std::process::exit(1);
+    }
+}
+
+/// Reports the slides that can not be laid out and the text with `overflow: error` that does
+/// not fit in its box, and exits if there are any. Both are only known after layout, so this is
+/// checked after loading. Only exports stop on them: when presenting, the slides that can not
+/// be laid out show their error instead.
+fn exit_on_layout_errors(state: &ast::GlobalState) {
+    let mut overflowing = 0;
+    for (slide_idx, slide) in state.slides.borrow().iter().enumerate() {
+        let layout = match slide.layout(state, None) {
+            Ok(layout) => layout,
+            Err(err) => {
+                eprintln!("{}", err.message(state));
+                overflowing += 1;
+                continue;
+            }
+        };
+        for id in slide.overflowing_elements(state, &layout) {
+            let elem = state.get_element_by_id(id).unwrap();
+            let description = match elem.name() {
+                Some(name) => format!("{} element {name}", elem.el_type()),
+                None => format!("{} element {}", elem.el_type(), id.0),
+            };
+            eprintln!(
+                "error on slide {}: the {description} does not fit in its box.",
+                slide_idx + 1
+            );
+            overflowing += 1;
+        }
+    }
+    if overflowing > 0 {
+        eprintln!("found {overflowing} error(s)");
+        std::process::exit(1);
+    }
+}
+
+/// Encodes the pixels of a slide as a file of the given format, or exits if they do not fit in
+/// it, like a slide that is too large for a WebP file.
+fn encode_or_exit(
+    format: raster::ImageFormat,
+    slide_idx: usize,
+    (width, height): (u32, u32),
+    pixels: &[u8],
+    quality: u8,
+) -> Vec<u8> {
+    raster::encode(format, width, height, pixels, quality).unwrap_or_else(|err| {
+        eprintln!(
+            "error: could not encode slide {} as {}: {err}",
+            slide_idx + 1,
+            format.extension()
+        );
+        std::process::exit(1);
+    })
+}
+
+/// Opens a URL with the default application of the system.
+fn open_url(url: &str) {
+    let result = if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg(url).spawn()
+    } else if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd")
+            .args(["/C", "start", "", url])
+            .spawn()
+    } else {
+        std::process::Command::new("xdg-open").arg(url).spawn()
+    };
+
+    if let Err(err) = result {
+        eprintln!("warning: could not open {url}: {err}");
+    }
+}
+
+/// Starts playing the audio on a slide that either plays as soon as the slide is shown or,
+/// if `autoplay` is false, waits for the space bar.
+fn start_audio(
+    state: &ast::GlobalState,
+    slide_idx: usize,
+    autoplay: bool,
+) -> Vec<audio::AudioPlayer> {
+    let slides = state.slides.borrow();
+    let slide = &slides[slide_idx];
+    let mut players = Vec::new();
+    for elem in state.get_slide_elements(slide) {
+        let ast::AbstractElementData::Audio(path) = elem.data() else {
+            continue;
+        };
+        if style::extract_boolean(&slide.style_map().styles_for_element(&elem), "autoplay")
+            != autoplay
+        {
+            continue;
+        }
+
+        match audio::AudioPlayer::start(path) {
+            Ok(player) => players.push(player),
+            Err(err) => eprintln!("warning: could not play audio {}: {err}", path.display()),
+        }
+    }
+    players
+}
+
 fn main() {
     let args = FoliumArgs::parse();
 
+    let debug = render::DebugDrawing {
+        rects: args.rects,
+        guides: args.guides,
+    };
     match args.command {
-        FoliumSubcommand::Render { input, output } => {
+        FoliumSubcommand::Render {
+            input,
+            output,
+            scale,
+            software,
+            format,
+            quality,
+            slides,
+        } => {
+            if !(scale > 0.0 && scale.is_finite()) {
+                eprintln!("error: the scale has to be a positive number, not {scale}");
+                std::process::exit(1);
+            }
             let state = ast::GlobalState::new();
-            interpreter::load_from_file(&state, input).unwrap();
+            state.set_scale(scale);
+            load_or_exit(&state, input, &args.profiles, args.variant.as_ref());
+            exit_on_layout_errors(&state);
 
             let number_of_slides = state.number_of_slides();
+            if let Some(slides) = slides
+                .as_ref()
+                .filter(|slides| slides.last() > number_of_slides)
+            {
+                eprintln!(
+                    "error: there is no slide {}, the presentation has {number_of_slides}",
+                    slides.last()
+                );
+                std::process::exit(1);
+            }
+            let rendered = (0..number_of_slides)
+                .filter(|i| slides.as_ref().is_none_or(|slides| slides.contains(i + 1)))
+                .collect::<Vec<_>>();
 
             assert!(!output.is_file(), "{} is a file", output.display());
 
@@ -66,38 +356,164 @@ fn main() {
                 fs::create_dir(&output).unwrap();
             }
 
-            for i in 0..number_of_slides {
-                let dimensions = render::generate_slide_data(&state, i, false).dimensions;
-                let surface = sdl2::surface::Surface::new(
-                    dimensions.0,
-                    dimensions.1,
-                    sdl2::pixels::PixelFormatEnum::RGBA32,
-                )
-                .unwrap();
-                let mut canvas = surface.into_canvas().unwrap();
-                canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+            if software {
+                let mut rendering_data = render::initialise_software_rendering_data(&state);
+                if args.subpixel {
+                    rendering_data.use_subpixel_text();
+                }
 
-                let texture_creator = canvas.texture_creator();
-                let rendering_data = render::initialise_rendering_data(&state, &texture_creator);
+                for i in rendered {
+                    let dimensions = render::generate_slide_data(&state, i, None).dimensions;
+                    let mut canvas = render::SoftwareCanvas::new(dimensions.0, dimensions.1);
+                    render::render(&state, &mut canvas, i, None, &rendering_data, debug);
+                    let pixels = render::Painter::pixels(&canvas);
+                    fs::write(
+                        output.join(format!("{}.{}", i + 1, format.extension())),
+                        encode_or_exit(format, i, dimensions, &pixels, quality),
+                    )
+                    .unwrap();
+                }
+            } else {
+                for i in rendered {
+                    let dimensions = render::generate_slide_data(&state, i, None).dimensions;
+                    let surface = sdl2::surface::Surface::new(
+                        dimensions.0,
+                        dimensions.1,
+                        sdl2::pixels::PixelFormatEnum::RGBA32,
+                    )
+                    .unwrap();
+                    let mut canvas = surface.into_canvas().unwrap();
+                    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+
+                    let texture_creator = canvas.texture_creator();
+                    let mut rendering_data =
+                        render::initialise_rendering_data(&state, &texture_creator);
+                    if args.subpixel {
+                        rendering_data.use_subpixel_text();
+                    }
 
-                render::render(&state, &mut canvas, i, false, &rendering_data, args.rects);
-                canvas
-                    .into_surface()
-                    .save(output.join(format!("{}.png", i + 1)))
+                    render::render(&state, &mut canvas, i, None, &rendering_data, debug);
+                    let pixels = render::Painter::pixels(&canvas);
+                    fs::write(
+                        output.join(format!("{}.{}", i + 1, format.extension())),
+                        encode_or_exit(format, i, dimensions, &pixels, quality),
+                    )
                     .unwrap();
+                }
+            }
+        }
+        FoliumSubcommand::Svg { input, output } => {
+            let state = ast::GlobalState::new();
+            load_or_exit(&state, input, &args.profiles, args.variant.as_ref());
+            exit_on_layout_errors(&state);
+
+            assert!(!output.is_file(), "{} is a file", output.display());
+            if !output.exists() {
+                fs::create_dir(&output).unwrap();
+            }
+
+            // what has no vector form is drawn without SDL's renderer and embedded as an image
+            let rendering_data = render::initialise_software_rendering_data(&state);
+            for i in 0..state.number_of_slides() {
+                let svg = render::render_svg(&state, i, &rendering_data);
+                fs::write(output.join(format!("{}.svg", i + 1)), svg).unwrap();
+            }
+        }
+        FoliumSubcommand::Pdf {
+            input,
+            output,
+            handout,
+            paper,
+            note_lines,
+        } => {
+            if handout.is_some_and(|per_page| !matches!(per_page, 2 | 4 | 6)) {
+                eprintln!("error: a handout has 2, 4 or 6 slides on every page");
+                std::process::exit(1);
+            }
+            if note_lines && handout.is_none() {
+                eprintln!("error: only handouts have lines for notes, add --handout");
+                std::process::exit(1);
+            }
+            let state = ast::GlobalState::new();
+            load_or_exit(&state, input, &args.profiles, args.variant.as_ref());
+            exit_on_layout_errors(&state);
+
+            // slides are drawn without SDL's renderer, so that PDFs can be made anywhere
+            let mut rendering_data = render::initialise_software_rendering_data(&state);
+            if args.subpixel {
+                rendering_data.use_subpixel_text();
             }
+            let slides = (0..state.number_of_slides())
+                .map(|i| {
+                    let (width, height) = render::generate_slide_data(&state, i, None).dimensions;
+                    let mut canvas = render::SoftwareCanvas::new(width, height);
+                    render::render(&state, &mut canvas, i, None, &rendering_data, debug);
+                    pdf::SlideImage {
+                        width,
+                        height,
+                        rgba: render::Painter::pixels(&canvas),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let bytes = match handout {
+                Some(per_page) => pdf::handout_pdf(&slides, paper, per_page, note_lines),
+                None => pdf::slides_pdf(&slides),
+            };
+            fs::write(output, bytes).unwrap();
         }
-        FoliumSubcommand::Present { input } => {
+        FoliumSubcommand::ExportHtml { input, output } => {
+            // the page is named after the presentation, or after its file without a title
+            let file_name = input
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
             let state = ast::GlobalState::new();
-            interpreter::load_from_file(&state, input).unwrap();
+            load_or_exit(&state, input, &args.profiles, args.variant.as_ref());
+            exit_on_layout_errors(&state);
+            let title = state.deck().title.unwrap_or(file_name);
+
+            // slides are drawn without SDL's renderer, like for PDF files
+            let mut rendering_data = render::initialise_software_rendering_data(&state);
+            if args.subpixel {
+                rendering_data.use_subpixel_text();
+            }
+            let slides = (0..state.number_of_slides())
+                .map(|i| {
+                    let (width, height) = render::generate_slide_data(&state, i, None).dimensions;
+                    let mut canvas = render::SoftwareCanvas::new(width, height);
+                    render::render(&state, &mut canvas, i, None, &rendering_data, debug);
+                    html::Slide {
+                        width,
+                        height,
+                        rgba: render::Painter::pixels(&canvas),
+                        links: render::slide_links(&state, i, None),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let html = html::presentation(&title, &slides).unwrap_or_else(|err| {
+                eprintln!("error: could not encode the slides: {err}");
+                std::process::exit(1);
+            });
+            fs::write(output, html).unwrap();
+        }
+        FoliumSubcommand::Present {
+            input,
+            keep_drawings,
+        } => {
+            let state = ast::GlobalState::new();
+            load_or_exit(&state, input, &args.profiles, args.variant.as_ref());
 
             let number_of_slides = state.number_of_slides();
+            let deck = state.deck();
 
             let sdl_context = sdl2::init().expect("Could not create SDL2 context");
             let vid_context = sdl_context.video().expect("Could not create video context");
             let window = vid_context
-                .window("folium", SLIDE_WIDTH, SLIDE_HEIGHT)
+                .window("folium", deck.width, deck.height)
                 .position_centered()
+                .resizable()
                 .build()
                 .unwrap();
 
@@ -110,40 +526,127 @@ fn main() {
             canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
 
             let texture_creator = canvas.texture_creator();
-            let rendering_data = render::initialise_rendering_data(&state, &texture_creator);
+            let mut rendering_data = render::initialise_rendering_data(&state, &texture_creator);
+            if args.subpixel {
+                rendering_data.use_subpixel_text();
+            }
+            rendering_data.keep_snapshots();
             let mut slide_idx: usize = 0;
+            rendering_data.start_animations();
 
             let mut window_needs_redraw = true;
+            // when only what changes on its own, like a clock or a video, has to be drawn again
+            let mut changes_need_redraw = false;
+            // when the slide has to be drawn again without any event, e.g. for a clock
+            let mut next_timed_redraw: Option<std::time::Instant> = None;
+            let mut video_players: HashMap<ast::AbstractElementID, video::VideoPlayer> =
+                HashMap::new();
+            let mut audio_players = start_audio(&state, slide_idx, true);
+            // whether the space bar has started the videos and audio on the current slide
+            let mut media_started = false;
+            // whether L or the right mouse button is held down, which shows the laser pointer
+            let mut pointing = false;
+            // whether D has turned on the pen, with which the left mouse button draws over the
+            // slide, and whether it is drawing a line right now
+            let mut pen_on = false;
+            let mut pen_down = false;
+            let mut pen_colour = 0;
+            let crosshair = Cursor::from_system(SystemCursor::Crosshair).unwrap();
+            let arrow = Cursor::from_system(SystemCursor::Arrow).unwrap();
 
-            for event in event_pump.wait_iter() {
-                if window_needs_redraw {
+            loop {
+                for (id, player) in video_players.iter_mut() {
+                    if let Some(frame) = player.poll() {
+                        rendering_data.update_video_frame(*id, &frame);
+                        changes_need_redraw = true;
+                    }
+                }
+
+                if next_timed_redraw.is_some_and(|at| at <= std::time::Instant::now()) {
+                    changes_need_redraw = true;
+                }
+
+                if window_needs_redraw || changes_need_redraw {
                     let tick = std::time::Instant::now();
-                    render::render(
-                        &state,
-                        &mut canvas,
-                        slide_idx,
-                        true,
-                        &rendering_data,
-                        args.rects,
-                    );
+                    let window_size = canvas.output_size().unwrap();
+                    let drew_changes = !window_needs_redraw
+                        && render::render_changes(
+                            &state,
+                            &mut canvas,
+                            slide_idx,
+                            Some(window_size),
+                            &rendering_data,
+                            debug,
+                        );
+                    if !drew_changes {
+                        render::render(
+                            &state,
+                            &mut canvas,
+                            slide_idx,
+                            Some(window_size),
+                            &rendering_data,
+                            debug,
+                        );
+                    }
                     let tock = std::time::Instant::now();
                     println!("rendered slide in {:6} us.", (tock - tick).as_micros());
                     window_needs_redraw = false;
+                    changes_need_redraw = false;
+                    next_timed_redraw = render::time_until_clock_tick(&state, slide_idx)
+                        .into_iter()
+                        .chain(rendering_data.time_until_animation_frame(
+                            &state,
+                            slide_idx,
+                            Some(window_size),
+                        ))
+                        .min()
+                        .map(|until_redraw| tock + until_redraw);
                 }
 
+                // wake up in time for the next frame of a playing video or the next timed redraw
+                let timeout = video_players
+                    .values()
+                    .filter_map(|player| player.time_until_next_frame())
+                    .chain(
+                        next_timed_redraw
+                            .map(|at| at.saturating_duration_since(std::time::Instant::now())),
+                    )
+                    .min();
+                let event = match timeout {
+                    Some(timeout) => {
+                        match event_pump.wait_event_timeout(timeout.as_millis().max(1) as u32) {
+                            Some(event) => event,
+                            None => continue,
+                        }
+                    }
+                    None => event_pump.wait_event(),
+                };
+
                 match event {
                     Event::Quit { .. }
                     | Event::KeyDown {
                         keycode: Some(Keycode::Escape),
                         ..
                     } => break,
+                    // the slide is laid out again for the new size of the window
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                        ..
+                    } => window_needs_redraw = true,
                     Event::KeyDown {
                         keycode: Some(Keycode::Right),
                         ..
                     } => {
                         let new_idx = (number_of_slides - 1).min(slide_idx + 1);
                         if new_idx != slide_idx {
+                            if !keep_drawings {
+                                rendering_data.clear_annotations(slide_idx);
+                            }
                             slide_idx = new_idx;
+                            rendering_data.start_animations();
+                            video_players.clear();
+                            audio_players = start_audio(&state, slide_idx, true);
+                            media_started = false;
                             window_needs_redraw = true;
                         }
                     }
@@ -153,18 +656,169 @@ fn main() {
                     } => {
                         let new_idx = slide_idx.saturating_sub(1);
                         if new_idx != slide_idx {
+                            if !keep_drawings {
+                                rendering_data.clear_annotations(slide_idx);
+                            }
                             slide_idx = new_idx;
+                            rendering_data.start_animations();
+                            video_players.clear();
+                            audio_players = start_audio(&state, slide_idx, true);
+                            media_started = false;
                             window_needs_redraw = true;
                         }
                     }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } if pen_on => {
+                        pen_down = true;
+                        rendering_data.start_stroke(slide_idx, PEN_COLOURS[pen_colour], (x, y));
+                        changes_need_redraw = true;
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } => {
+                        let clicked_link = render::slide_links(
+                            &state,
+                            slide_idx,
+                            Some(canvas.output_size().unwrap()),
+                        )
+                        .into_iter()
+                        .find(|(area, _)| layout::folium_to_sdl_rect(*area).contains_point((x, y)));
+                        if let Some((_, url)) = clicked_link {
+                            open_url(&url);
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::L),
+                        repeat: false,
+                        ..
+                    }
+                    | Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Right,
+                        ..
+                    } => {
+                        pointing = true;
+                        let mouse = event_pump.mouse_state();
+                        rendering_data.set_laser_pointer(Some((mouse.x(), mouse.y())));
+                        sdl_context.mouse().show_cursor(false);
+                        changes_need_redraw = true;
+                    }
+                    Event::KeyUp {
+                        keycode: Some(Keycode::L),
+                        ..
+                    }
+                    | Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Right,
+                        ..
+                    } => {
+                        pointing = false;
+                        rendering_data.set_laser_pointer(None);
+                        sdl_context.mouse().show_cursor(true);
+                        changes_need_redraw = true;
+                    }
+                    Event::MouseMotion { x, y, .. } if pointing || pen_down => {
+                        if pointing {
+                            rendering_data.set_laser_pointer(Some((x, y)));
+                        }
+                        if pen_down {
+                            rendering_data.extend_stroke(slide_idx, (x, y));
+                        }
+                        changes_need_redraw = true;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::D),
+                        repeat: false,
+                        ..
+                    } => {
+                        pen_on = !pen_on;
+                        pen_down = false;
+                        if pen_on {
+                            crosshair.set()
+                        } else {
+                            arrow.set()
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::C),
+                        ..
+                    } if pen_on => pen_colour = (pen_colour + 1) % PEN_COLOURS.len(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::E),
+                        ..
+                    } => {
+                        rendering_data.clear_annotations(slide_idx);
+                        changes_need_redraw = true;
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Left,
+                        ..
+                    } => pen_down = false,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Space),
+                        ..
+                    } => {
+                        // the first press starts the videos and the audio that does not play
+                        // automatically; later presses, or the first if there was nothing to
+                        // start, pause and resume everything that is playing
+                        let mut started_any = false;
+                        if !media_started {
+                            media_started = true;
+                            let keyed_audio = start_audio(&state, slide_idx, false);
+                            started_any |= !keyed_audio.is_empty();
+                            audio_players.extend(keyed_audio);
+
+                            let slide_elements =
+                                state.get_slide_elements(&state.slides.borrow()[slide_idx]);
+                            for elem in slide_elements {
+                                let (ast::AbstractElementData::Video(path), Some(info)) =
+                                    (elem.data(), rendering_data.video_info(elem.id()))
+                                else {
+                                    continue;
+                                };
+                                match video::VideoPlayer::start(path, info) {
+                                    Ok(player) => {
+                                        video_players.insert(elem.id(), player);
+                                        started_any = true;
+                                    }
+                                    Err(err) => eprintln!(
+                                        "warning: could not play video {}: {err}",
+                                        path.display()
+                                    ),
+                                }
+                            }
+                        }
+                        if !started_any {
+                            for player in video_players.values_mut() {
+                                player.toggle_pause();
+                            }
+                            for player in audio_players.iter_mut() {
+                                player.toggle_pause();
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
         }
         FoliumSubcommand::Inspect { input } => {
             let state = ast::GlobalState::new();
-            interpreter::load_from_file(&state, input).unwrap();
-            println!("{state}");
+            load_or_exit(&state, input, &args.profiles, args.variant.as_ref());
+            println!("{state}")
+        }
+        FoliumSubcommand::Fmt { input, in_place } => {
+            let source = fs::read_to_string(&input).expect("could not open file");
+            let formatted = format::format_source(&source);
+            if in_place {
+                fs::write(&input, formatted).unwrap();
+            } else {
+                print!("{formatted}");
+            }
         }
         FoliumSubcommand::ListFonts => {
             let mut database = fontdb::Database::new();
@@ -180,3 +834,21 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slide_selections() {
+        let selection = "3, 5,10-14".parse::<SlideSelection>().unwrap();
+        assert_eq!(selection, SlideSelection(vec![3..=3, 5..=5, 10..=14]));
+        assert!(selection.contains(12) && !selection.contains(4));
+        assert_eq!(selection.last(), 14);
+
+        assert!("0".parse::<SlideSelection>().is_err());
+        assert!("4-2".parse::<SlideSelection>().is_err());
+        assert!("1,,2".parse::<SlideSelection>().is_err());
+        assert!("a-3".parse::<SlideSelection>().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// Text can contain some lightweight markup: `*bold*`, `_italic_` and `` `mono` ``. A marker
+// only counts at the edge of a word, so that identifiers like snake_case are left alone, and
+// markers without a partner are drawn as they are. A backslash in front of a marker escapes it.
+
+/// How a span of text is set, on top of the style of the element it is in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub mono: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+fn is_marker(character: char) -> bool {
+    matches!(character, '*' | '_' | '`')
+}
+
+/// Whether the marker at `idx` can open a span: it is not preceded by a letter or digit and
+/// is followed by something other than whitespace.
+fn can_open(chars: &[char], idx: usize) -> bool {
+    let before_ok = idx == 0 || !chars[idx - 1].is_alphanumeric();
+    let after_ok = chars
+        .get(idx + 1)
+        .is_some_and(|c| !c.is_whitespace() && *c != chars[idx]);
+    before_ok && after_ok
+}
+
+/// Whether the marker at `idx` can close a span, which is the mirror image of `can_open`.
+fn can_close(chars: &[char], idx: usize) -> bool {
+    let before_ok = idx > 0 && !chars[idx - 1].is_whitespace();
+    let after_ok = chars.get(idx + 1).is_none_or(|c| !c.is_alphanumeric());
+    before_ok && after_ok
+}
+
+/// Finds the marker that closes the one at `open`, skipping over escaped characters.
+fn find_closing(chars: &[char], open: usize) -> Option<usize> {
+    let marker = chars[open];
+    let mut idx = open + 1;
+    while idx < chars.len() {
+        match chars[idx] {
+            '\\' if marker != '`' => idx += 1,
+            c if c == marker && can_close(chars, idx) => return Some(idx),
+            _ => {}
+        }
+        idx += 1;
+    }
+    None
+}
+
+/// Splits text into spans with the same style, removing the markup.
+pub fn parse_markup(text: &str) -> Vec<Span> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = SpanStyle::default();
+    // the positions of the markers that close the spans that are currently open
+    let mut closing: Vec<usize> = Vec::new();
+    let mut current = String::new();
+
+    let mut flush = |current: &mut String, style: SpanStyle| {
+        if current.is_empty() {
+            return;
+        }
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.text.push_str(current),
+            _ => spans.push(Span {
+                text: current.clone(),
+                style,
+            }),
+        }
+        current.clear();
+    };
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let character = chars[idx];
+
+        if character == '\\' && !style.mono && chars.get(idx + 1).is_some_and(|c| is_marker(*c)) {
+            current.push(chars[idx + 1]);
+            idx += 2;
+            continue;
+        }
+
+        if closing.last() == Some(&idx) {
+            flush(&mut current, style);
+            closing.pop();
+            match character {
+                '*' => style.bold = false,
+                '_' => style.italic = false,
+                _ => style.mono = false,
+            }
+            idx += 1;
+            continue;
+        }
+
+        let already_open = match character {
+            '*' => style.bold,
+            '_' => style.italic,
+            '`' => style.mono,
+            _ => true,
+        };
+        if !style.mono && !already_open && can_open(&chars, idx) {
+            // a span has to be closed before the span around it is
+            let limit = closing.last().copied().unwrap_or(chars.len());
+            if let Some(end) = find_closing(&chars, idx).filter(|end| *end < limit) {
+                flush(&mut current, style);
+                closing.push(end);
+                match character {
+                    '*' => style.bold = true,
+                    '_' => style.italic = true,
+                    _ => style.mono = true,
+                }
+                idx += 1;
+                continue;
+            }
+        }
+
+        current.push(character);
+        idx += 1;
+    }
+    flush(&mut current, style);
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, bold: bool, italic: bool, mono: bool) -> Span {
+        Span {
+            text: String::from(text),
+            style: SpanStyle { bold, italic, mono },
+        }
+    }
+
+    #[test]
+    fn markup_spans() {
+        assert_eq!(
+            parse_markup("a *bold _and italic_* call to `do_it(*x)`"),
+            vec![
+                span("a ", false, false, false),
+                span("bold ", true, false, false),
+                span("and italic", true, true, false),
+                span(" call to ", false, false, false),
+                span("do_it(*x)", false, false, true),
+            ]
+        );
+        assert_eq!(
+            parse_markup(r"snake_case_name, 2 * 3 * 4 and \*literal\*"),
+            vec![span(
+                "snake_case_name, 2 * 3 * 4 and *literal*",
+                false,
+                false,
+                false
+            )]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+use std::{iter::Peekable, str::Chars};
+
+use fontdue::Font;
+
+/// A formula, parsed from a subset of TeX math syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MathNode {
+    Symbol(char),
+    /// Binary operators and relations, which get some space on either side.
+    Operator(char),
+    Group(Vec<MathNode>),
+    Fraction(Box<MathNode>, Box<MathNode>),
+    Scripts {
+        base: Box<MathNode>,
+        sup: Option<Box<MathNode>>,
+        sub: Option<Box<MathNode>>,
+    },
+    Root(Box<MathNode>),
+}
+
+/// Parses a formula like `\frac{a^2}{\sqrt{b}}`. Supported are groups, super- and subscripts,
+/// `\frac`, `\sqrt`, Greek letters and the most common operators and symbols; the math element
+/// in SPEC.md lists them all.
+pub fn parse_formula(source: &str) -> Result<MathNode, String> {
+    let mut chars = source.chars().peekable();
+    Ok(MathNode::Group(parse_sequence(&mut chars, false)?))
+}
+
+fn parse_sequence(chars: &mut Peekable<Chars>, in_group: bool) -> Result<Vec<MathNode>, String> {
+    let mut nodes = Vec::new();
+
+    loop {
+        let Some(c) = chars.next() else {
+            return if in_group {
+                Err(String::from("a group was opened with { but never closed"))
+            } else {
+                Ok(nodes)
+            };
+        };
+
+        let node = match c {
+            // like in TeX, whitespace has no meaning in a formula
+            c if c.is_whitespace() => continue,
+            '}' if in_group => return Ok(nodes),
+            '}' => return Err(String::from("found a } that does not close a group")),
+            '{' => MathNode::Group(parse_sequence(chars, true)?),
+            '^' | '_' => {
+                let (base, mut sup, mut sub) = match nodes.pop() {
+                    Some(MathNode::Scripts { base, sup, sub }) => (base, sup, sub),
+                    Some(other) => (Box::new(other), None, None),
+                    None => (Box::new(MathNode::Group(Vec::new())), None, None),
+                };
+
+                let slot = if c == '^' { &mut sup } else { &mut sub };
+                if slot.is_some() {
+                    return Err(format!("double {c}, use braces to clarify"));
+                }
+                *slot = Some(Box::new(parse_argument(chars)?));
+
+                MathNode::Scripts { base, sup, sub }
+            }
+            '\\' => parse_command(chars)?,
+            '-' => MathNode::Operator('−'),
+            '+' | '=' | '<' | '>' => MathNode::Operator(c),
+            other => MathNode::Symbol(other),
+        };
+
+        nodes.push(node);
+    }
+}
+
+/// Parses the argument of a command or script, which is either a group or a single symbol.
+fn parse_argument(chars: &mut Peekable<Chars>) -> Result<MathNode, String> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+    match chars.next() {
+        Some('{') => Ok(MathNode::Group(parse_sequence(chars, true)?)),
+        Some('\\') => parse_command(chars),
+        Some('}') | None => Err(String::from("expected an argument")),
+        Some(other) => Ok(MathNode::Symbol(other)),
+    }
+}
+
+fn parse_command(chars: &mut Peekable<Chars>) -> Result<MathNode, String> {
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+        name.push(c);
+    }
+    // commands that are a single non-letter, like \{ or \,
+    if name.is_empty() {
+        name.extend(chars.next());
+    }
+
+    let symbol = match name.as_str() {
+        "frac" => {
+            let numerator = parse_argument(chars)?;
+            let denominator = parse_argument(chars)?;
+            return Ok(MathNode::Fraction(
+                Box::new(numerator),
+                Box::new(denominator),
+            ));
+        }
+        "sqrt" => return Ok(MathNode::Root(Box::new(parse_argument(chars)?))),
+        "," | " " | "quad" => return Ok(MathNode::Symbol(' ')),
+        "cdot" => return Ok(MathNode::Operator('⋅')),
+        "times" => return Ok(MathNode::Operator('×')),
+        "div" => return Ok(MathNode::Operator('÷')),
+        "pm" => return Ok(MathNode::Operator('±')),
+        "mp" => return Ok(MathNode::Operator('∓')),
+        "leq" | "le" => return Ok(MathNode::Operator('≤')),
+        "geq" | "ge" => return Ok(MathNode::Operator('≥')),
+        "neq" | "ne" => return Ok(MathNode::Operator('≠')),
+        "approx" => return Ok(MathNode::Operator('≈')),
+        "equiv" => return Ok(MathNode::Operator('≡')),
+        "to" | "rightarrow" => return Ok(MathNode::Operator('→')),
+        "leftarrow" => return Ok(MathNode::Operator('←')),
+        "Rightarrow" => return Ok(MathNode::Operator('⇒')),
+        "iff" | "Leftrightarrow" => return Ok(MathNode::Operator('⇔')),
+        "in" => return Ok(MathNode::Operator('∈')),
+        "subset" => return Ok(MathNode::Operator('⊂')),
+        "cup" => return Ok(MathNode::Operator('∪')),
+        "cap" => return Ok(MathNode::Operator('∩')),
+        "{" => '{',
+        "}" => '}',
+        "infty" => '∞',
+        "sum" => '∑',
+        "prod" => '∏',
+        "int" => '∫',
+        "partial" => '∂',
+        "nabla" => '∇',
+        "forall" => '∀',
+        "exists" => '∃',
+        "ldots" => '…',
+        "cdots" => '⋯',
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "pi" => 'π',
+        "rho" => 'ρ',
+        "sigma" => 'σ',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        "Gamma" => 'Γ',
+        "Delta" => 'Δ',
+        "Theta" => 'Θ',
+        "Lambda" => 'Λ',
+        "Xi" => 'Ξ',
+        "Pi" => 'Π',
+        "Sigma" => 'Σ',
+        "Phi" => 'Φ',
+        "Psi" => 'Ψ',
+        "Omega" => 'Ω',
+        other => return Err(format!("unknown command \\{other}")),
+    };
+
+    Ok(MathNode::Symbol(symbol))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MathItem {
+    /// A glyph whose baseline starts at (x, y).
+    Glyph {
+        character: char,
+        x: f32,
+        y: f32,
+        size: f32,
+    },
+    /// A filled rectangle, like the bar of a fraction, with (x, y) as its top left corner.
+    Rule { x: f32, y: f32, w: f32, h: f32 },
+}
+
+impl MathItem {
+    fn shifted(self, dx: f32, dy: f32) -> Self {
+        match self {
+            MathItem::Glyph {
+                character,
+                x,
+                y,
+                size,
+            } => MathItem::Glyph {
+                character,
+                x: x + dx,
+                y: y + dy,
+                size,
+            },
+            MathItem::Rule { x, y, w, h } => MathItem::Rule {
+                x: x + dx,
+                y: y + dy,
+                w,
+                h,
+            },
+        }
+    }
+}
+
+/// A laid out formula. Item positions are relative to the start of the baseline,
+/// with y pointing downwards.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MathBox {
+    pub width: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    pub items: Vec<MathItem>,
+}
+
+impl MathBox {
+    fn place(&mut self, other: MathBox, dx: f32, dy: f32) {
+        self.items
+            .extend(other.items.into_iter().map(|item| item.shifted(dx, dy)));
+    }
+}
+
+pub fn layout_formula(node: &MathNode, font: &Font, size: f32) -> MathBox {
+    let (line_ascent, line_descent) = font
+        .horizontal_line_metrics(size)
+        .map(|metrics| (metrics.ascent, -metrics.descent))
+        .unwrap_or((size * 0.8, size * 0.2));
+    let rule_thickness = (size * 0.05).max(1.0);
+
+    match node {
+        MathNode::Symbol(' ') => MathBox {
+            width: size * 0.25,
+            ..Default::default()
+        },
+        MathNode::Symbol(c) | MathNode::Operator(c) => {
+            let padding = if matches!(node, MathNode::Operator(_)) {
+                size * 0.2
+            } else {
+                0.0
+            };
+
+            MathBox {
+                width: font.metrics(*c, size).advance_width + 2.0 * padding,
+                ascent: line_ascent,
+                descent: line_descent,
+                items: vec![MathItem::Glyph {
+                    character: *c,
+                    x: padding,
+                    y: 0.0,
+                    size,
+                }],
+            }
+        }
+        MathNode::Group(nodes) => {
+            let mut group = MathBox::default();
+            for node in nodes {
+                let inner = layout_formula(node, font, size);
+                let x = group.width;
+                group.width += inner.width;
+                group.ascent = group.ascent.max(inner.ascent);
+                group.descent = group.descent.max(inner.descent);
+                group.place(inner, x, 0.0);
+            }
+            group
+        }
+        MathNode::Fraction(numerator, denominator) => {
+            let numerator = layout_formula(numerator, font, size * 0.8);
+            let denominator = layout_formula(denominator, font, size * 0.8);
+
+            // the fraction bar sits on the maths axis, roughly at the height of a minus sign
+            let axis = size * 0.25;
+            let gap = size * 0.1;
+            let padding = size * 0.1;
+            let width = numerator.width.max(denominator.width) + 2.0 * padding;
+
+            let numerator_baseline = -(axis + rule_thickness / 2.0 + gap + numerator.descent);
+            let denominator_baseline = -axis + rule_thickness / 2.0 + gap + denominator.ascent;
+
+            let mut fraction = MathBox {
+                width,
+                ascent: -numerator_baseline + numerator.ascent,
+                descent: denominator_baseline + denominator.descent,
+                items: vec![MathItem::Rule {
+                    x: 0.0,
+                    y: -axis - rule_thickness / 2.0,
+                    w: width,
+                    h: rule_thickness,
+                }],
+            };
+            let numerator_x = (width - numerator.width) / 2.0;
+            let denominator_x = (width - denominator.width) / 2.0;
+            fraction.place(numerator, numerator_x, numerator_baseline);
+            fraction.place(denominator, denominator_x, denominator_baseline);
+            fraction
+        }
+        MathNode::Scripts { base, sup, sub } => {
+            let base = layout_formula(base, font, size);
+            let sup = sup
+                .as_ref()
+                .map(|sup| layout_formula(sup, font, size * 0.7));
+            let sub = sub
+                .as_ref()
+                .map(|sub| layout_formula(sub, font, size * 0.7));
+
+            let sup_shift = size * 0.4;
+            let sub_shift = size * 0.2;
+            let script_x = base.width;
+
+            let mut scripts = MathBox {
+                width: base.width
+                    + sup
+                        .as_ref()
+                        .map_or(0.0, |s| s.width)
+                        .max(sub.as_ref().map_or(0.0, |s| s.width)),
+                ascent: base
+                    .ascent
+                    .max(sup.as_ref().map_or(0.0, |s| sup_shift + s.ascent)),
+                descent: base
+                    .descent
+                    .max(sub.as_ref().map_or(0.0, |s| sub_shift + s.descent)),
+                items: Vec::new(),
+            };
+            scripts.place(base, 0.0, 0.0);
+            if let Some(sup) = sup {
+                scripts.place(sup, script_x, -sup_shift);
+            }
+            if let Some(sub) = sub {
+                scripts.place(sub, script_x, sub_shift);
+            }
+            scripts
+        }
+        MathNode::Root(radicand) => {
+            let radicand = layout_formula(radicand, font, size);
+            let gap = size * 0.1;
+
+            // the radical sign is scaled up to cover the whole radicand
+            let radical_height = radicand.ascent + radicand.descent + gap + rule_thickness;
+            let radical_size = size * (radical_height / (line_ascent + line_descent)).max(1.0);
+            let radical_width = font.metrics('√', radical_size).advance_width;
+
+            let mut root = MathBox {
+                width: radical_width + radicand.width,
+                ascent: radicand.ascent + gap + rule_thickness,
+                descent: radicand.descent,
+                items: vec![
+                    MathItem::Glyph {
+                        character: '√',
+                        x: 0.0,
+                        y: radicand.descent,
+                        size: radical_size,
+                    },
+                    MathItem::Rule {
+                        x: radical_width,
+                        y: -(radicand.ascent + gap + rule_thickness),
+                        w: radicand.width,
+                        h: rule_thickness,
+                    },
+                ],
+            };
+            root.place(radicand, radical_width, 0.0);
+            root
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measure::{self, FontChoice};
+
+    fn symbol(c: char) -> Box<MathNode> {
+        Box::new(MathNode::Symbol(c))
+    }
+
+    fn group(nodes: Vec<MathNode>) -> Box<MathNode> {
+        Box::new(MathNode::Group(nodes))
+    }
+
+    #[test]
+    fn fractions_and_roots() {
+        assert_eq!(
+            parse_formula(r"\frac{a+1}{\sqrt 2}"),
+            Ok(MathNode::Group(vec![MathNode::Fraction(
+                group(vec![
+                    MathNode::Symbol('a'),
+                    MathNode::Operator('+'),
+                    MathNode::Symbol('1'),
+                ]),
+                group(vec![MathNode::Root(symbol('2'))]),
+            )]))
+        );
+        // the arguments of a command can be commands themselves
+        assert_eq!(
+            parse_formula(r"\frac\pi 2"),
+            Ok(MathNode::Group(vec![MathNode::Fraction(
+                symbol('π'),
+                symbol('2')
+            )]))
+        );
+    }
+
+    #[test]
+    fn scripts() {
+        assert_eq!(
+            parse_formula("x_i^{2}"),
+            Ok(MathNode::Group(vec![MathNode::Scripts {
+                base: symbol('x'),
+                sup: Some(group(vec![MathNode::Symbol('2')])),
+                sub: Some(symbol('i')),
+            }]))
+        );
+        // a script without anything before it has an empty base
+        assert_eq!(
+            parse_formula("^a"),
+            Ok(MathNode::Group(vec![MathNode::Scripts {
+                base: group(Vec::new()),
+                sup: Some(symbol('a')),
+                sub: None,
+            }]))
+        );
+    }
+
+    #[test]
+    fn symbols_and_operators() {
+        assert_eq!(
+            parse_formula(r"\alpha - b \leq \infty\,\{"),
+            Ok(MathNode::Group(vec![
+                MathNode::Symbol('α'),
+                MathNode::Operator('−'),
+                MathNode::Symbol('b'),
+                MathNode::Operator('≤'),
+                MathNode::Symbol('∞'),
+                MathNode::Symbol(' '),
+                MathNode::Symbol('{'),
+            ]))
+        );
+    }
+
+    #[test]
+    fn malformed_formulas() {
+        let error = |source| parse_formula(source).unwrap_err();
+        assert_eq!(error("{a"), "a group was opened with { but never closed");
+        assert_eq!(error("a}"), "found a } that does not close a group");
+        assert_eq!(error("x^2^3"), "double ^, use braces to clarify");
+        assert_eq!(error("x_1_2"), "double _, use braces to clarify");
+        assert_eq!(error(r"\frac{a}"), "expected an argument");
+        assert_eq!(error("x^"), "expected an argument");
+        assert_eq!(error(r"\sin x"), r"unknown command \sin");
+    }
+
+    #[test]
+    fn fractions_stack_and_scripts_shift() {
+        let font =
+            measure::font_for(&FontChoice::regular(vec![String::from("sans-serif")])).unwrap();
+        let layout = |source| layout_formula(&parse_formula(source).unwrap(), &font, 40.0);
+
+        let letter = layout("a");
+        let fraction = layout(r"\frac{a}{b}");
+        assert!(fraction.ascent > letter.ascent);
+        assert!(fraction.descent > letter.descent);
+        assert!(fraction
+            .items
+            .iter()
+            .any(|item| matches!(item, MathItem::Rule { .. })));
+
+        let glyph_y = |math_box: &MathBox, character: char| {
+            math_box.items.iter().find_map(|item| match item {
+                MathItem::Glyph {
+                    character: c, y, ..
+                } if *c == character => Some(*y),
+                _ => None,
+            })
+        };
+        let scripts = layout("a^b_c");
+        assert_eq!(glyph_y(&scripts, 'a'), Some(0.0));
+        assert!(glyph_y(&scripts, 'b').unwrap() < 0.0);
+        assert!(glyph_y(&scripts, 'c').unwrap() > 0.0);
+    }
+}
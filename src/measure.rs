@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use fontdue::{
+    layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle, WrapStyle},
+    FontSettings,
+};
+
+use crate::{
+    render::font_family,
+    shaping,
+    style::{extract_decimal, extract_number, extract_string, extract_string_list, PropertyValue},
+};
+
+// Layout happens before the fonts for drawing are loaded, so content that is sized by its text
+// is measured with fonts of its own. Every choice of font is loaded once, the first time it is
+// measured with, and kept for as long as folium runs.
+
+/// Fonts that are loaded the first time they are asked for and then shared by everything that
+/// measures or draws text with them. The cache owns the fonts, so they are dropped with it.
+pub struct FontCache<K> {
+    fonts: Mutex<HashMap<K, Option<Arc<fontdue::Font>>>>,
+}
+
+impl<K> Default for FontCache<K> {
+    fn default() -> Self {
+        Self {
+            fonts: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash> FontCache<K> {
+    /// The font for a key, which `load` is asked for if the font has not been loaded yet. A font
+    /// that could not be loaded is not looked for again.
+    pub fn get(
+        &self,
+        key: K,
+        load: impl FnOnce() -> Option<fontdue::Font>,
+    ) -> Option<Arc<fontdue::Font>> {
+        self.fonts
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| load().map(Arc::new))
+            .clone()
+    }
+}
+
+/// The fonts that text is set in, in order of preference, and the face of them that is used.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FontChoice {
+    pub names: Vec<String>,
+    pub weight: fontdb::Weight,
+    pub style: fontdb::Style,
+}
+
+impl FontChoice {
+    /// The regular face of the named fonts.
+    pub fn regular(names: Vec<String>) -> Self {
+        Self {
+            names,
+            weight: fontdb::Weight::NORMAL,
+            style: fontdb::Style::Normal,
+        }
+    }
+
+    /// The fonts named by the `font_property` style, in the face given by the `weight` and
+    /// `style` styles. Without those, like for a link, the regular face is used.
+    pub fn from_style(styles: &HashMap<String, PropertyValue>, font_property: &str) -> Self {
+        let weight = match styles.get("weight") {
+            None => fontdb::Weight::NORMAL,
+            Some(PropertyValue::Number(weight)) => fontdb::Weight((*weight).clamp(1, 1000) as u16),
+            Some(other) => match other.to_string().as_str() {
+                "thin" => fontdb::Weight::THIN,
+                "extra-light" => fontdb::Weight::EXTRA_LIGHT,
+                "light" => fontdb::Weight::LIGHT,
+                "medium" => fontdb::Weight::MEDIUM,
+                "semibold" => fontdb::Weight::SEMIBOLD,
+                "bold" => fontdb::Weight::BOLD,
+                "extra-bold" => fontdb::Weight::EXTRA_BOLD,
+                "black" => fontdb::Weight::BLACK,
+                // `normal`, as other weights are reported when the style is parsed
+                _ => fontdb::Weight::NORMAL,
+            },
+        };
+        let style = match styles
+            .get("style")
+            .map(|style| style.to_string())
+            .as_deref()
+        {
+            Some("italic") => fontdb::Style::Italic,
+            Some("oblique") => fontdb::Style::Oblique,
+            _ => fontdb::Style::Normal,
+        };
+
+        Self {
+            names: extract_string_list(styles, font_property),
+            weight,
+            style,
+        }
+    }
+
+    /// The query that finds the face in a font database.
+    pub fn query<'a>(&self, families: &'a [fontdb::Family<'a>]) -> fontdb::Query<'a> {
+        fontdb::Query {
+            families,
+            weight: self.weight,
+            style: self.style,
+            ..Default::default()
+        }
+    }
+}
+
+fn load_font(choice: &FontChoice) -> Option<fontdue::Font> {
+    static DB: OnceLock<fontdb::Database> = OnceLock::new();
+    let db = DB.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        db
+    });
+
+    let families = choice
+        .names
+        .iter()
+        .map(|name| font_family(name))
+        .collect::<Vec<_>>();
+    let loaded = db.query(&choice.query(&families)).and_then(|face| {
+        db.with_face_data(face, |data, index| {
+            fontdue::Font::from_bytes(
+                data,
+                FontSettings {
+                    collection_index: index,
+                    ..Default::default()
+                },
+            )
+            .ok()
+        })?
+    });
+
+    // rendering falls back to the builtin font, and warns about it
+    if loaded.is_none() && cfg!(feature = "builtin-fonts") {
+        return fontdue::Font::from_bytes(
+            include_bytes!("assets/newsreader.ttf").as_slice(),
+            FontSettings::default(),
+        )
+        .ok();
+    }
+    loaded
+}
+
+/// The first of the chosen fonts that is installed, or the builtin font if none of them are.
+pub fn font_for(choice: &FontChoice) -> Option<Arc<fontdue::Font>> {
+    static FONTS: OnceLock<FontCache<FontChoice>> = OnceLock::new();
+    FONTS
+        .get_or_init(Default::default)
+        .get(choice.clone(), || load_font(choice))
+}
+
+/// Where the lines of a piece of text are broken, from its `wrap` style.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextWrap {
+    /// Lines are broken between words, and words that are longer than a line between their
+    /// characters.
+    #[default]
+    Word,
+    /// Lines are broken between any two characters.
+    Char,
+    /// Lines are only broken where the text has a line break.
+    None,
+}
+
+impl TextWrap {
+    /// Text without a `wrap` style, like the label of a link, is wrapped between words. Other
+    /// values than `word`, `char` and `none` are reported when the style is parsed.
+    pub fn from_style(styles: &HashMap<String, PropertyValue>) -> Self {
+        match styles.get("wrap").map(|wrap| wrap.to_string()).as_deref() {
+            Some("char") => Self::Char,
+            Some("none") => Self::None,
+            _ => Self::Word,
+        }
+    }
+}
+
+/// How text flows over its lines, from the `wrap`, `line-height` and `tracking` styles, which
+/// measuring and drawing the text have to agree on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextFlow {
+    pub wrap: TextWrap,
+    /// The distance between the baselines of two lines, as a multiple of what the font asks for.
+    pub line_height: f32,
+    /// The extra room between two glyphs, in pixels.
+    pub tracking: f32,
+}
+
+impl Default for TextFlow {
+    fn default() -> Self {
+        Self {
+            wrap: TextWrap::default(),
+            line_height: 1.0,
+            tracking: 0.0,
+        }
+    }
+}
+
+impl TextFlow {
+    /// Text without these styles, like the label of a link, is wrapped between words and set
+    /// at the line height of its font.
+    pub fn from_style(styles: &HashMap<String, PropertyValue>) -> Self {
+        Self {
+            wrap: TextWrap::from_style(styles),
+            line_height: if styles.contains_key("line-height") {
+                extract_decimal(styles, "line-height").max(0.0)
+            } else {
+                1.0
+            },
+            tracking: if styles.contains_key("tracking") {
+                extract_decimal(styles, "tracking")
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// The settings that fontdue lays text out with in an area of the given width. Text that is
+    /// not wrapped is laid out without a width.
+    pub fn layout_settings(self, width: u32) -> LayoutSettings {
+        let (max_width, wrap_style) = match self.wrap {
+            TextWrap::Word => (Some(width as f32), WrapStyle::Word),
+            TextWrap::Char => (Some(width as f32), WrapStyle::Letter),
+            TextWrap::None => (None, WrapStyle::Word),
+        };
+        LayoutSettings {
+            max_width,
+            wrap_style,
+            line_height: self.line_height,
+            ..Default::default()
+        }
+    }
+
+    /// Lays out the text that `append` adds to the layout with the given settings. fontdue has
+    /// no letter spacing of its own, so glyphs are only moved apart after the lines are broken;
+    /// as long as a line is then wider than `settings.max_width`, the text is laid out again
+    /// with less room.
+    pub fn lay_out(
+        self,
+        layout: &mut Layout,
+        settings: &LayoutSettings,
+        append: impl Fn(&mut Layout),
+    ) {
+        let mut attempt = *settings;
+        for _ in 0..8 {
+            layout.reset(&attempt);
+            append(layout);
+
+            let (Some(width), Some(attempt_width)) = (settings.max_width, attempt.max_width) else {
+                return;
+            };
+            let excess = tracked_width(layout, self.tracking) - width;
+            if self.tracking <= 0.0 || excess <= 0.0 || excess >= attempt_width {
+                return;
+            }
+            attempt.max_width = Some(attempt_width - excess);
+        }
+    }
+}
+
+/// How far the glyphs of a laid out line are moved along by the tracking of the glyphs before
+/// them, by their index in the layout.
+fn tracking_offsets(layout: &Layout, tracking: f32) -> Vec<f32> {
+    let mut offsets = vec![0.0; layout.glyphs().len()];
+    for line in layout.lines().into_iter().flatten() {
+        for (rank, idx) in (line.glyph_start..=line.glyph_end).enumerate() {
+            offsets[idx] = rank as f32 * tracking;
+        }
+    }
+    offsets
+}
+
+/// The width of the widest line of a layout once its glyphs are moved apart by the tracking.
+/// Whitespace at the ends of lines does not count.
+fn tracked_width(layout: &Layout, tracking: f32) -> f32 {
+    layout
+        .glyphs()
+        .iter()
+        .zip(tracking_offsets(layout, tracking))
+        .filter(|(glyph, _)| !glyph.parent.is_whitespace())
+        .map(|(glyph, offset)| glyph.x + offset + glyph.width as f32)
+        .fold(0.0, f32::max)
+}
+
+/// The font size that text is set in: its `size`, or with `size: auto`, the largest size from
+/// `min-size` up to `max-size` at which the text fits in the given area when it is wrapped at
+/// its width. With `overflow: shrink`, text that does not fit at its `size` is made smaller in
+/// the same way. Text that does not fit at any size is set at `min-size`.
+pub fn font_size(
+    styles: &HashMap<String, PropertyValue>,
+    text: &str,
+    width: u32,
+    height: u32,
+) -> f32 {
+    let largest_allowed = match styles.get("size") {
+        Some(PropertyValue::String(size)) if size == "auto" => extract_number(styles, "max-size"),
+        _ if extract_string(styles, "overflow") == "shrink" => extract_number(styles, "size"),
+        _ => return extract_number(styles, "size") as f32,
+    };
+
+    let font = FontChoice::from_style(styles, "font");
+    let flow = TextFlow::from_style(styles);
+    let fits = |size: u32| {
+        text_size(&font, text, size as f32, width, flow)
+            .is_some_and(|(text_width, text_height)| text_width <= width && text_height <= height)
+    };
+
+    // the largest size that fits always lies between `smallest` and `largest`
+    let mut smallest = extract_number(styles, "min-size");
+    let mut largest = largest_allowed.max(smallest);
+    while smallest < largest {
+        let middle = (smallest + largest).div_ceil(2);
+        if fits(middle) {
+            smallest = middle;
+        } else {
+            largest = middle - 1;
+        }
+    }
+    smallest as f32
+}
+
+/// Whether text does not fit in the given area at the size it is set in.
+pub fn overflows(
+    styles: &HashMap<String, PropertyValue>,
+    text: &str,
+    width: u32,
+    height: u32,
+) -> bool {
+    let font_size = font_size(styles, text, width, height);
+    text_size(
+        &FontChoice::from_style(styles, "font"),
+        text,
+        font_size,
+        width,
+        TextFlow::from_style(styles),
+    )
+    .is_some_and(|(text_width, text_height)| text_width > width || text_height > height)
+}
+
+/// How far lines of text in the first of the chosen fonts that is installed reach above their
+/// baseline.
+pub fn ascent(font: &FontChoice, font_size: f32) -> Option<u32> {
+    let metrics = font_for(font)?.horizontal_line_metrics(font_size)?;
+    Some(metrics.ascent.round() as u32)
+}
+
+/// The width and height that a piece of text takes up in the first of the chosen fonts that is
+/// installed, when it flows over lines of at most `max_width`. Text can only be measured if
+/// one of the fonts is installed or folium has a builtin font.
+pub fn text_size(
+    font: &FontChoice,
+    text: &str,
+    font_size: f32,
+    max_width: u32,
+    flow: TextFlow,
+) -> Option<(u32, u32)> {
+    let font = font_for(font)?;
+    let text = shaping::shape(text);
+    let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+    flow.lay_out(&mut layout, &flow.layout_settings(max_width), |layout| {
+        layout.append(&[&*font], &TextStyle::new(&text, font_size, 0));
+    });
+
+    let width = tracked_width(&layout, flow.tracking);
+    Some((width.ceil() as u32, layout.height().ceil() as u32))
+}
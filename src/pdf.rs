@@ -0,0 +1,451 @@
+//! Writes slides to a PDF file, either one slide per page at the size of the slide, or several
+//! slides on every page of paper as a handout to print. Slides are embedded as the images they
+//! are drawn as.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+/// Points, the unit of PDF files, are 1/72 of an inch, and slides are drawn at 96 pixels per inch.
+const POINTS_PER_PIXEL: f32 = 0.75;
+/// The room kept free around the slides on a page of a handout, in points.
+const HANDOUT_MARGIN: f32 = 36.0;
+/// The room between the slides on a page of a handout, and between a slide and its notes.
+const HANDOUT_GAP: f32 = 18.0;
+/// The room between the lines for notes.
+const NOTE_LINE_SPACING: f32 = 18.0;
+
+/// A slide as it was drawn, as RGBA pixels row by row.
+pub struct SlideImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// The paper that handouts are printed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Paper {
+    A4,
+    Letter,
+}
+
+impl Paper {
+    /// The width and height of the paper in points.
+    fn size(self) -> (f32, f32) {
+        match self {
+            Paper::A4 => (595.0, 842.0),
+            Paper::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+impl FromStr for Paper {
+    type Err = String;
+
+    fn from_str(paper: &str) -> Result<Self, Self::Err> {
+        match paper.to_lowercase().as_str() {
+            "a4" => Ok(Paper::A4),
+            "letter" => Ok(Paper::Letter),
+            other => Err(format!("unknown paper {other}, expected a4 or letter")),
+        }
+    }
+}
+
+/// An area of a page, in points from its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Area {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+impl Area {
+    /// The largest area of the given aspect ratio, width over height, that fits in this one,
+    /// centred in it.
+    fn fit(self, aspect: f32) -> Area {
+        let (w, h) = if self.w / self.h > aspect {
+            (self.h * aspect, self.h)
+        } else {
+            (self.w, self.w / aspect)
+        };
+        Area {
+            x: self.x + (self.w - w) / 2.0,
+            y: self.y + (self.h - h) / 2.0,
+            w,
+            h,
+        }
+    }
+}
+
+/// What is on a page: slides, by their index, with a thin outline if they are `framed`, and
+/// lines to write on.
+struct Page {
+    width: f32,
+    height: f32,
+    slides: Vec<(usize, Area)>,
+    framed: bool,
+    lines: Vec<((f32, f32), (f32, f32))>,
+}
+
+/// Writes every slide on a page of its own, which is as large as the slide.
+pub fn slides_pdf(slides: &[SlideImage]) -> Vec<u8> {
+    let pages = slides
+        .iter()
+        .enumerate()
+        .map(|(idx, slide)| {
+            let (width, height) = (
+                slide.width as f32 * POINTS_PER_PIXEL,
+                slide.height as f32 * POINTS_PER_PIXEL,
+            );
+            Page {
+                width,
+                height,
+                slides: vec![(
+                    idx,
+                    Area {
+                        x: 0.0,
+                        y: 0.0,
+                        w: width,
+                        h: height,
+                    },
+                )],
+                framed: false,
+                lines: Vec::new(),
+            }
+        })
+        .collect::<Vec<_>>();
+    write_pdf(slides, &pages)
+}
+
+/// Writes the slides as a handout, with `per_page` of them on every page of paper, which is
+/// 2, 4 or 6. With `note_lines`, the slides are stacked down the left half of the page, with
+/// lines to take notes on to the right of every one of them.
+pub fn handout_pdf(
+    slides: &[SlideImage],
+    paper: Paper,
+    per_page: usize,
+    note_lines: bool,
+) -> Vec<u8> {
+    let (width, height) = paper.size();
+    let cells = handout_cells(paper, per_page, note_lines);
+    let pages = slides
+        .iter()
+        .enumerate()
+        .chunks(cells.len())
+        .into_iter()
+        .map(|on_page| {
+            let mut page = Page {
+                width,
+                height,
+                slides: Vec::new(),
+                framed: true,
+                lines: Vec::new(),
+            };
+            for ((idx, slide), (cell, notes)) in on_page.zip(&cells) {
+                let area = cell.fit(slide.width as f32 / slide.height as f32);
+                page.slides.push((idx, area));
+                let Some(notes) = notes else {
+                    continue;
+                };
+                let mut y = area.y + NOTE_LINE_SPACING;
+                while y <= area.y + area.h {
+                    page.lines.push(((notes.x, y), (notes.x + notes.w, y)));
+                    y += NOTE_LINE_SPACING;
+                }
+            }
+            page
+        })
+        .collect::<Vec<_>>();
+    write_pdf(slides, &pages)
+}
+
+/// The areas that the slides on a page of a handout are fitted into, in reading order, with the
+/// area of their notes if there are lines for them.
+fn handout_cells(paper: Paper, per_page: usize, note_lines: bool) -> Vec<(Area, Option<Area>)> {
+    let (width, height) = paper.size();
+    let (cols, rows) = match (per_page, note_lines) {
+        (_, true) => (1, per_page),
+        (2, false) => (1, 2),
+        (4, false) => (2, 2),
+        _ => (2, 3),
+    };
+    let cell_width = (width - 2.0 * HANDOUT_MARGIN - (cols - 1) as f32 * HANDOUT_GAP) / cols as f32;
+    let cell_height =
+        (height - 2.0 * HANDOUT_MARGIN - (rows - 1) as f32 * HANDOUT_GAP) / rows as f32;
+
+    (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            let cell = Area {
+                x: HANDOUT_MARGIN + col as f32 * (cell_width + HANDOUT_GAP),
+                y: HANDOUT_MARGIN + row as f32 * (cell_height + HANDOUT_GAP),
+                w: cell_width,
+                h: cell_height,
+            };
+            if !note_lines {
+                return (cell, None);
+            }
+            let half = (cell.w - HANDOUT_GAP) / 2.0;
+            let notes = Area {
+                x: cell.x + half + HANDOUT_GAP,
+                w: half,
+                ..cell
+            };
+            (Area { w: half, ..cell }, Some(notes))
+        })
+        .collect()
+}
+
+/// Writes the pages, with the slides on them, as a PDF file. Every slide is an image object,
+/// which the pages it is on draw.
+fn write_pdf(slides: &[SlideImage], pages: &[Page]) -> Vec<u8> {
+    // the catalog and the page tree come first, then the slides, then every page with its
+    // contents
+    let image_id = |slide: usize| 3 + slide;
+    let page_id = |page: usize| 3 + slides.len() + 2 * page;
+
+    let mut pdf = PdfWriter::new();
+    pdf.object(1, "<< /Type /Catalog /Pages 2 0 R >>", None);
+    let kids = (0..pages.len())
+        .map(|page| format!("{} 0 R", page_id(page)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    pdf.object(
+        2,
+        &format!("<< /Type /Pages /Kids [{kids}] /Count {} >>", pages.len()),
+        None,
+    );
+
+    for (idx, slide) in slides.iter().enumerate() {
+        // slides are opaque, so their alpha is left out
+        let rgb = slide
+            .rgba
+            .chunks(4)
+            .flat_map(|pixel| &pixel[..3])
+            .copied()
+            .collect::<Vec<_>>();
+        let data = compress_to_vec_zlib(&rgb, 6);
+        pdf.object(
+            image_id(idx),
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+                 /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+                slide.width,
+                slide.height,
+                data.len()
+            ),
+            Some(&data),
+        );
+    }
+
+    for (idx, page) in pages.iter().enumerate() {
+        // PDF measures from the bottom of the page upwards
+        let bottom = |area: &Area| page.height - area.y - area.h;
+        let mut contents = String::new();
+        let mut images = String::new();
+        for (slide, area) in &page.slides {
+            writeln!(
+                contents,
+                "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{slide} Do Q",
+                area.w,
+                area.h,
+                area.x,
+                bottom(area)
+            )
+            .unwrap();
+            if page.framed {
+                writeln!(
+                    contents,
+                    "0.5 w 0.6 G {:.2} {:.2} {:.2} {:.2} re S",
+                    area.x,
+                    bottom(area),
+                    area.w,
+                    area.h
+                )
+                .unwrap();
+            }
+            write!(images, "/Im{slide} {} 0 R ", image_id(*slide)).unwrap();
+        }
+        for ((x1, y1), (x2, y2)) in &page.lines {
+            writeln!(
+                contents,
+                "0.5 w 0.75 G {x1:.2} {:.2} m {x2:.2} {:.2} l S",
+                page.height - y1,
+                page.height - y2
+            )
+            .unwrap();
+        }
+
+        pdf.object(
+            page_id(idx),
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /XObject << {images}>> >> /Contents {} 0 R >>",
+                page.width,
+                page.height,
+                page_id(idx) + 1
+            ),
+            None,
+        );
+        pdf.object(
+            page_id(idx) + 1,
+            &format!("<< /Length {} >>", contents.len()),
+            Some(contents.as_bytes()),
+        );
+    }
+
+    pdf.finish()
+}
+
+/// The bytes of a PDF file as its objects are written, with where every object starts, for
+/// the cross-reference table at its end.
+struct PdfWriter {
+    bytes: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        // the comment with bytes above 127 marks the file as binary
+        Self {
+            bytes: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Writes the object with the given number, which is a dictionary, followed by a stream if
+    /// it has one.
+    fn object(&mut self, id: usize, dictionary: &str, stream: Option<&[u8]>) {
+        if self.offsets.len() < id {
+            self.offsets.resize(id, 0);
+        }
+        self.offsets[id - 1] = self.bytes.len();
+        self.bytes
+            .extend(format!("{id} 0 obj\n{dictionary}\n").as_bytes());
+        if let Some(stream) = stream {
+            self.bytes.extend(b"stream\n");
+            self.bytes.extend(stream);
+            self.bytes.extend(b"\nendstream\n");
+        }
+        self.bytes.extend(b"endobj\n");
+    }
+
+    /// Ends the file with its cross-reference table, whose entries are exactly 20 bytes long.
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.bytes.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            writeln!(table, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len() + 1
+        )
+        .unwrap();
+        self.bytes.extend(table.as_bytes());
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slide(width: u32, height: u32) -> SlideImage {
+        SlideImage {
+            width,
+            height,
+            rgba: [255, 255, 255, 255].repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn handout_layout() {
+        for paper in [Paper::A4, Paper::Letter] {
+            let (width, height) = paper.size();
+            for per_page in [2, 4, 6] {
+                for note_lines in [false, true] {
+                    let cells = handout_cells(paper, per_page, note_lines);
+                    assert_eq!(cells.len(), per_page);
+
+                    let areas = cells
+                        .iter()
+                        .flat_map(|(cell, notes)| std::iter::once(cell).chain(notes))
+                        .collect::<Vec<_>>();
+                    for area in &areas {
+                        assert!(area.x >= HANDOUT_MARGIN && area.y >= HANDOUT_MARGIN);
+                        assert!(area.x + area.w <= width - HANDOUT_MARGIN + 0.01);
+                        assert!(area.y + area.h <= height - HANDOUT_MARGIN + 0.01);
+                    }
+                    for (a, b) in areas
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(idx, a)| areas[idx + 1..].iter().map(move |b| (a, b)))
+                    {
+                        let apart = a.x + a.w <= b.x
+                            || b.x + b.w <= a.x
+                            || a.y + a.h <= b.y
+                            || b.y + b.h <= a.y;
+                        assert!(apart, "{a:?} overlaps {b:?}");
+                    }
+                }
+            }
+        }
+
+        let fitted = Area {
+            x: 0.0,
+            y: 0.0,
+            w: 200.0,
+            h: 200.0,
+        }
+        .fit(2.0);
+        assert_eq!(
+            fitted,
+            Area {
+                x: 0.0,
+                y: 50.0,
+                w: 200.0,
+                h: 100.0
+            }
+        );
+    }
+
+    #[test]
+    fn pdf_structure() {
+        let slides = (0..5).map(|_| slide(16, 9)).collect::<Vec<_>>();
+        let handout = handout_pdf(&slides, Paper::A4, 4, true);
+        let text = String::from_utf8_lossy(&handout);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Count 2"));
+        assert_eq!(text.matches("/Type /Page ").count(), 2);
+        assert_eq!(text.matches("/Subtype /Image").count(), 5);
+        assert!(text.contains(" l S"));
+
+        // every entry of the cross-reference table points at the start of its object
+        let xref = handout
+            .windows(6)
+            .rposition(|bytes| bytes == b"\nxref\n")
+            .unwrap();
+        let table = std::str::from_utf8(&handout[xref + 1..]).unwrap();
+        let entries = table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .collect::<Vec<_>>();
+        // the catalog, the page tree, the slides and every page with its contents
+        assert_eq!(entries.len(), 2 + 5 + 2 * 2);
+        for (id, entry) in entries.iter().enumerate() {
+            let offset = entry[..10].parse::<usize>().unwrap();
+            assert!(handout[offset..].starts_with(format!("{} 0 obj", id + 1).as_bytes()));
+        }
+
+        let pages = slides_pdf(&slides[..1]);
+        let text = String::from_utf8_lossy(&pages);
+        assert!(text.contains("/MediaBox [0 0 12.00 6.75]"));
+        assert!(!text.contains(" re S"));
+    }
+}
@@ -0,0 +1,441 @@
+use std::str::FromStr;
+
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    ExtendedColorType, ImageEncoder, ImageResult,
+};
+use sdl2::rect::Rect;
+
+// A software rasteriser, which draws slides into a buffer of pixels in memory instead of
+// through SDL's renderer, so that they can be rendered without a window or a renderer at all.
+// Everything that folium draws ends up as filled rectangles and images, which are blended
+// onto the buffer the way SDL blends them, so both look the same.
+
+/// How pixels are combined with the pixels that are already there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    /// Drawn over what is there, as far as they are opaque.
+    Over,
+    /// Every channel of what is there is multiplied by the colour of the pixel.
+    Multiply,
+    /// The colour of the pixel, times its alpha, is added to every channel.
+    Add,
+    /// What is there is replaced.
+    Replace,
+}
+
+/// An image to draw: RGBA pixels, row by row, with straight alpha.
+#[derive(Clone, Copy, Debug)]
+pub struct Image<'p> {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: &'p [u8],
+}
+
+/// How an image is drawn: the part of it that is drawn, where it ends up, rotated clockwise by
+/// `angle` degrees around its centre, and with its alpha multiplied by `alpha`.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub src: Rect,
+    pub dst: Rect,
+    pub angle: f64,
+    pub alpha: u8,
+    pub blend: Blend,
+    /// Whether the image is interpolated between its pixels when it is scaled, rather than
+    /// showing the nearest one.
+    pub smooth: bool,
+}
+
+/// A buffer of pixels that is drawn into.
+pub struct Pixmap {
+    width: u32,
+    height: u32,
+    /// RGBA pixels, row by row, with premultiplied alpha, which makes blending layers onto
+    /// each other straightforward.
+    pixels: Vec<u8>,
+    clip: Option<Rect>,
+}
+
+impl Pixmap {
+    /// A transparent pixmap.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+            clip: None,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Restricts drawing to a rect, or lifts the restriction.
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    /// The pixels in a rect that can be drawn in: the part of it that is inside of the pixmap
+    /// and the clip rect, as the ranges of its columns and rows.
+    fn drawable(&self, rect: Rect) -> (std::ops::Range<u32>, std::ops::Range<u32>) {
+        let mut left = rect.x().max(0) as i64;
+        let mut top = rect.y().max(0) as i64;
+        let mut right = (rect.x() as i64 + rect.width() as i64).min(self.width as i64);
+        let mut bottom = (rect.y() as i64 + rect.height() as i64).min(self.height as i64);
+        if let Some(clip) = self.clip {
+            left = left.max(clip.x() as i64);
+            top = top.max(clip.y() as i64);
+            right = right.min(clip.x() as i64 + clip.width() as i64);
+            bottom = bottom.min(clip.y() as i64 + clip.height() as i64);
+        }
+        let range = |from: i64, to: i64| from.max(0) as u32..to.max(from).max(0) as u32;
+        (range(left, right), range(top, bottom))
+    }
+
+    /// Fills a rect, or the whole pixmap, with a colour with straight alpha.
+    pub fn fill_rect(&mut self, rect: Option<Rect>, rgba: [u8; 4], mode: Blend) {
+        let rect = rect.unwrap_or(Rect::new(0, 0, self.width, self.height));
+        let source = premultiply(rgba);
+        let (columns, rows) = self.drawable(rect);
+        for y in rows {
+            for x in columns.clone() {
+                let idx = (y * self.width + x) as usize * 4;
+                blend(&mut self.pixels[idx..idx + 4], source, rgba, mode);
+            }
+        }
+    }
+
+    /// Draws (a part of) an image.
+    pub fn draw_image(&mut self, image: Image, placement: Placement) {
+        self.draw_with(placement, |x, y, smooth| {
+            let straight = sample(image, x, y, smooth);
+            (premultiply(straight), straight)
+        });
+    }
+
+    /// Draws (a part of) another pixmap, like a layer that was drawn separately.
+    pub fn draw_pixmap(&mut self, layer: &Pixmap, placement: Placement) {
+        let image = Image {
+            width: layer.width,
+            height: layer.height,
+            rgba: &layer.pixels,
+        };
+        self.draw_with(placement, |x, y, smooth| {
+            // the pixels of a pixmap are already premultiplied
+            let premultiplied = sample(image, x, y, smooth);
+            (premultiplied, unpremultiply(premultiplied))
+        });
+    }
+
+    /// Draws every pixel inside of the placement with the colour that `sample` gives for the
+    /// point of the source that ends up there, both premultiplied and straight.
+    fn draw_with(
+        &mut self,
+        placement: Placement,
+        sample: impl Fn(f32, f32, bool) -> ([u8; 4], [u8; 4]),
+    ) {
+        let Placement {
+            src,
+            dst,
+            angle,
+            alpha,
+            blend: mode,
+            smooth,
+        } = placement;
+        if dst.width() == 0 || dst.height() == 0 || src.width() == 0 || src.height() == 0 {
+            return;
+        }
+
+        let (sin, cos) = (angle.to_radians() as f32).sin_cos();
+        let (half_width, half_height) = (dst.width() as f32 / 2.0, dst.height() as f32 / 2.0);
+        let (centre_x, centre_y) = (dst.x() as f32 + half_width, dst.y() as f32 + half_height);
+        // the rotated image lies inside of a circle around its centre
+        let bounds = if angle == 0.0 {
+            dst
+        } else {
+            let reach = half_width.hypot(half_height).ceil() as i32;
+            Rect::new(
+                centre_x as i32 - reach,
+                centre_y as i32 - reach,
+                2 * reach as u32 + 1,
+                2 * reach as u32 + 1,
+            )
+        };
+
+        let scale_x = src.width() as f32 / dst.width() as f32;
+        let scale_y = src.height() as f32 / dst.height() as f32;
+        let (columns, rows) = self.drawable(bounds);
+        for y in rows {
+            for x in columns.clone() {
+                // the point of the unrotated destination that ends up at this pixel
+                let (dx, dy) = (x as f32 + 0.5 - centre_x, y as f32 + 0.5 - centre_y);
+                let u = dx * cos + dy * sin + half_width;
+                let v = -dx * sin + dy * cos + half_height;
+                if u < 0.0 || v < 0.0 || u >= dst.width() as f32 || v >= dst.height() as f32 {
+                    continue;
+                }
+
+                let (mut premultiplied, straight) = sample(
+                    src.x() as f32 + u * scale_x,
+                    src.y() as f32 + v * scale_y,
+                    smooth,
+                );
+                if alpha < u8::MAX {
+                    premultiplied = premultiplied.map(|channel| mul(channel, alpha));
+                }
+                let idx = (y * self.width + x) as usize * 4;
+                blend(
+                    &mut self.pixels[idx..idx + 4],
+                    premultiplied,
+                    straight,
+                    mode,
+                );
+            }
+        }
+    }
+
+    /// The pixels, row by row, as RGBA with straight alpha.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixels
+            .chunks(4)
+            .flat_map(|pixel| unpremultiply([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect()
+    }
+}
+
+/// Multiplies two values as if they were fractions of 255.
+fn mul(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+fn premultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    [mul(r, a), mul(g, a), mul(b, a), a]
+}
+
+fn unpremultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    if a == 0 {
+        return [0; 4];
+    }
+    let channel = |value: u8| ((value as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    [channel(r), channel(g), channel(b), a]
+}
+
+/// Blends a pixel onto a premultiplied pixel, like SDL does for each blend mode.
+fn blend(pixel: &mut [u8], premultiplied: [u8; 4], straight: [u8; 4], mode: Blend) {
+    match mode {
+        Blend::Over => {
+            let keep = 255 - premultiplied[3];
+            for (channel, value) in pixel.iter_mut().enumerate() {
+                *value = premultiplied[channel].saturating_add(mul(*value, keep));
+            }
+        }
+        Blend::Multiply => {
+            for (channel, value) in pixel.iter_mut().take(3).enumerate() {
+                *value = mul(*value, straight[channel]);
+            }
+        }
+        Blend::Add => {
+            for (channel, value) in pixel.iter_mut().take(3).enumerate() {
+                *value = value.saturating_add(premultiplied[channel]);
+            }
+        }
+        Blend::Replace => pixel.copy_from_slice(&premultiplied),
+    }
+}
+
+/// The colour of an image at a point, in pixels from its top left corner: that of the pixel the
+/// point is in, or interpolated between the four pixels around it.
+fn sample(image: Image, x: f32, y: f32, smooth: bool) -> [u8; 4] {
+    let pixel = |px: u32, py: u32| {
+        let idx = (py * image.width + px) as usize * 4;
+        [
+            image.rgba[idx],
+            image.rgba[idx + 1],
+            image.rgba[idx + 2],
+            image.rgba[idx + 3],
+        ]
+    };
+    let (max_x, max_y) = (image.width - 1, image.height - 1);
+    if !smooth {
+        return pixel((x as u32).min(max_x), (y as u32).min(max_y));
+    }
+
+    let x = (x - 0.5).clamp(0.0, max_x as f32);
+    let y = (y - 0.5).clamp(0.0, max_y as f32);
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let (right, bottom) = ((left + 1).min(max_x), (top + 1).min(max_y));
+    let (fx, fy) = (x.fract(), y.fract());
+
+    // colours are interpolated with their alpha, so that transparent pixels do not bleed into
+    // the edges of what is around them
+    let corners = [
+        (pixel(left, top), (1.0 - fx) * (1.0 - fy)),
+        (pixel(right, top), fx * (1.0 - fy)),
+        (pixel(left, bottom), (1.0 - fx) * fy),
+        (pixel(right, bottom), fx * fy),
+    ];
+    let alpha = corners
+        .iter()
+        .map(|(pixel, weight)| pixel[3] as f32 * weight)
+        .sum::<f32>();
+    if alpha == 0.0 {
+        return [0; 4];
+    }
+    let channel = |channel: usize| {
+        let sum = corners
+            .iter()
+            .map(|(pixel, weight)| pixel[channel] as f32 * pixel[3] as f32 * weight)
+            .sum::<f32>();
+        (sum / alpha).round().min(255.0) as u8
+    };
+    [channel(0), channel(1), channel(2), alpha.round() as u8]
+}
+
+/// Encodes RGBA pixels, row by row, as a PNG file.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> ImageResult<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(rgba, width, height, ExtendedColorType::Rgba8)?;
+    Ok(png)
+}
+
+/// The formats of the files that slides are rendered to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpeg),
+            "webp" => Ok(ImageFormat::Webp),
+            other => Err(format!("unknown format {other}, expected png, jpg or webp")),
+        }
+    }
+}
+
+/// Encodes RGBA pixels, row by row, as a file of the given format. JPEG files have no alpha and
+/// lose detail depending on `quality`, from 1 to 100; PNG and WebP files keep every pixel. Not
+/// every size fits in every format: WebP files are at most 16383 pixels wide and high.
+pub fn encode(
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    quality: u8,
+) -> ImageResult<Vec<u8>> {
+    let mut file = Vec::new();
+    match format {
+        ImageFormat::Png => return encode_png(width, height, rgba),
+        ImageFormat::Jpeg => {
+            let rgb = rgba
+                .chunks_exact(4)
+                .flat_map(|pixel| &pixel[..3])
+                .copied()
+                .collect::<Vec<_>>();
+            JpegEncoder::new_with_quality(&mut file, quality).encode(
+                &rgb,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+        }
+        ImageFormat::Webp => WebPEncoder::new_lossless(&mut file).encode(
+            rgba,
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        )?,
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blending_and_clipping() {
+        let mut pixmap = Pixmap::new(4, 2);
+        pixmap.fill_rect(None, [255, 255, 255, 255], Blend::Over);
+        pixmap.set_clip(Some(Rect::new(0, 0, 2, 2)));
+        pixmap.fill_rect(Some(Rect::new(1, 0, 3, 1)), [0, 0, 0, 128], Blend::Over);
+
+        let rgba = pixmap.to_rgba();
+        assert_eq!(&rgba[0..4], [255, 255, 255, 255]);
+        assert_eq!(&rgba[4..8], [127, 127, 127, 255]);
+        // outside of the clip rect
+        assert_eq!(&rgba[8..12], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn drawn_images() {
+        // a red and a green pixel, drawn at twice their size and turned upside down
+        let image = Image {
+            width: 2,
+            height: 1,
+            rgba: &[255, 0, 0, 255, 0, 255, 0, 255],
+        };
+        let mut pixmap = Pixmap::new(4, 2);
+        pixmap.draw_image(
+            image,
+            Placement {
+                src: Rect::new(0, 0, 2, 1),
+                dst: Rect::new(0, 0, 4, 2),
+                angle: 180.0,
+                alpha: u8::MAX,
+                blend: Blend::Over,
+                smooth: false,
+            },
+        );
+
+        let rgba = pixmap.to_rgba();
+        assert_eq!(&rgba[0..4], [0, 255, 0, 255]);
+        assert_eq!(&rgba[12..16], [255, 0, 0, 255]);
+        assert_eq!(&rgba[16..20], [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn png_encoding() {
+        let png = encode_png(2, 1, &[1, 2, 3, 4, 1, 2, 3, 4]).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xaeB`\x82");
+    }
+
+    #[test]
+    fn image_formats() {
+        assert_eq!("JPEG".parse(), Ok(ImageFormat::Jpeg));
+        assert_eq!("jpg".parse::<ImageFormat>().unwrap().extension(), "jpg");
+        assert!("gif".parse::<ImageFormat>().is_err());
+
+        let rgba = [200, 30, 30, 255].repeat(16 * 16);
+        let png = encode(ImageFormat::Png, 16, 16, &rgba, 90).unwrap();
+        assert_eq!(png, encode_png(16, 16, &rgba).unwrap());
+        let jpeg = encode(ImageFormat::Jpeg, 16, 16, &rgba, 90).unwrap();
+        assert_eq!(&jpeg[..2], b"\xff\xd8");
+        assert!(encode(ImageFormat::Jpeg, 16, 16, &rgba, 10).unwrap().len() < jpeg.len());
+        let webp = encode(ImageFormat::Webp, 16, 16, &rgba, 90).unwrap();
+        assert_eq!((&webp[..4], &webp[8..12]), (&b"RIFF"[..], &b"WEBP"[..]));
+
+        // too wide for a WebP file
+        let row = [200, 30, 30, 255].repeat(20000);
+        assert!(encode(ImageFormat::Webp, 20000, 1, &row, 90).is_err());
+    }
+}
@@ -1,62 +1,205 @@
-use std::collections::HashMap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
 
 use fontdue::{
-    layout::{LayoutSettings, TextStyle},
+    layout::{GlyphRasterConfig, HorizontalAlign, LayoutSettings, TextStyle},
     FontSettings,
 };
 use itertools::Itertools;
 use sdl2::{
-    image::LoadTexture,
-    render::{Canvas, RenderTarget, Texture},
+    image::{LoadSurface, LoadTexture},
+    pixels::{Color, PixelFormatEnum},
+    rect::Point,
+    render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator},
+    surface::{Surface, SurfaceContext},
+    video::{Window, WindowContext},
 };
 
 use crate::{
-    ast::{AbstractElementData, AbstractElementID, ElementType, GlobalState},
-    layout::{folium_to_sdl_rect, LayoutElement, Rect},
-    style::{extract_colour, extract_number, extract_string, StyleMap, StyleTarget},
-    SLIDE_HEIGHT, SLIDE_WIDTH,
+    animation::{Animation, Frame},
+    ast::{
+        AbstractElement, AbstractElementData, AbstractElementID, ElementType, GlobalState,
+        Watermark, WatermarkMark,
+    },
+    bidi,
+    chart::{self, ChartData, PlotData},
+    clock,
+    layout::{
+        fit_rects, folium_to_sdl_rect, list_item_rects, ConnectorSegment, LayoutElement, Rect,
+    },
+    markup::{self, SpanStyle},
+    math::{self, MathItem},
+    measure::{self, FontCache, FontChoice, TextFlow, TextWrap},
+    raster::{self, Blend, Pixmap},
+    shaping,
+    style::{
+        extract_boolean, extract_colour, extract_colour_list, extract_decimal, extract_line_ranges,
+        extract_number, extract_optional_colour, extract_optional_number, extract_paint,
+        extract_string, extract_string_list, Paint, PropertyValue, StyleMap, StyleTarget,
+    },
+    svg,
+    video::{self, VideoInfo},
 };
 
 pub struct RenderData<'a> {
-    texture_map: HashMap<AbstractElementID, Texture<'a>>,
+    /// The images of the image and video elements.
+    images: HashMap<AbstractElementID, ElementImage<'a>>,
+    /// The pixels of the images that are masked somewhere, see [`ImagePixels`].
+    image_pixels: HashMap<AbstractElementID, ImagePixels>,
     font_database: fontdb::Database,
     fonts_for_targets: HashMap<(AbstractElementID, StyleTarget), fontdue::Font>,
+    video_info: HashMap<AbstractElementID, VideoInfo>,
+    /// The font for every icon on every slide, keyed by the slide and the icon.
+    icon_fonts: HashMap<(AbstractElementID, AbstractElementID), fontdue::Font>,
+    /// The bold, italic and monospace variants of the font of a text style target, for the
+    /// spans of marked up text on a slide.
+    span_fonts: HashMap<(AbstractElementID, StyleTarget, SpanStyle), fontdue::Font>,
+    /// The laid out slides, with the size of the area they were laid out in. Layout does not
+    /// change between frames, so a slide is only laid out again when that size changes.
+    slide_data: RefCell<HashMap<AbstractElementID, SizedSlideData>>,
+    glyphs: GlyphAtlas,
+    /// The last image of every text and code element on every slide, keyed by the slide, the
+    /// element and the fingerprint of the text, style and area it was made for.
+    text_images: RefCell<HashMap<(AbstractElementID, AbstractElementID, u64), Rc<TextImage>>>,
+    /// When the slide that is being presented was shown, which is when its animations started.
+    /// Without it, as when slides are rendered to files, animations are drawn finished.
+    shown_at: Cell<Option<Instant>>,
+    /// Whether a copy of every frame that is drawn in full is kept, as it is while presenting.
+    keep_snapshots: bool,
+    snapshot: RefCell<Option<Snapshot>>,
+    /// Where the laser pointer is shown over the slide, if it is.
+    laser_pointer: Option<(i32, i32)>,
+    /// What has been drawn over every slide with the pen while presenting.
+    annotations: HashMap<usize, Vec<Stroke>>,
+    /// The image of the watermark of the deck, if it is one.
+    watermark_image: Option<ElementImage<'a>>,
+    /// The pixels of the images that backgrounds and shapes are filled with as a pattern.
+    patterns: HashMap<PathBuf, ImagePixels>,
+}
+
+/// A laid out slide, with the size of the area it was laid out in.
+type SizedSlideData = ((u32, u32), Rc<SlideData>);
+
+/// A line that is drawn over a slide with the pen, through the points of the target that the
+/// mouse went through.
+struct Stroke {
+    colour: (u8, u8, u8),
+    points: Vec<(i32, i32)>,
+}
+
+/// The pixels of the last frame that was drawn of a slide in full, at some size.
+struct Snapshot {
+    slide_idx: usize,
+    size: (u32, u32),
+    pixels: Vec<u8>,
 }
 
 pub struct SlideData {
     layout_rects: Vec<LayoutElement>,
-    background: (u8, u8, u8),
+    background: Paint,
     pub dimensions: (u32, u32),
     styles: StyleMap,
     slide_id: AbstractElementID,
+    connectors: Vec<ConnectorSegment>,
+    /// The alpha of the elements that are drawn faded, see [`crate::ast::Slide::opacities`].
+    opacities: HashMap<AbstractElementID, u8>,
+    /// The animations of the elements that are animated, with the centre of the element whose
+    /// animation it is, around which they are scaled.
+    animations: HashMap<AbstractElementID, (Animation, (f32, f32))>,
+    /// Why the slide could not be laid out, if it could not, which is shown instead of it.
+    layout_error: Option<String>,
+}
+
+/// The size of the area a slide is laid out in: the window it is presented in, if any, or the
+/// slide itself otherwise.
+fn viewport_size(global: &GlobalState, idx: usize, window: Option<(u32, u32)>) -> (u32, u32) {
+    window.unwrap_or_else(|| {
+        let slides = global.slides.borrow();
+        let all_styles = slides[idx].style_map();
+        let slide_styles = all_styles.styles_for_target(&StyleTarget::Slide).unwrap();
+        (
+            extract_number(slide_styles, "width"),
+            extract_number(slide_styles, "height"),
+        )
+    })
+}
+
+/// How long a frame of an animation is shown, for about 60 frames per second.
+const ANIMATION_FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// The SDL hint that sets the filter with which the textures that are created after it are
+/// scaled.
+const SCALE_QUALITY_HINT: &str = "SDL_RENDER_SCALE_QUALITY";
+
+/// The value of the scale quality hint for the `scaling` of an image.
+fn scale_quality(image_style: &HashMap<String, PropertyValue>) -> &'static str {
+    match extract_string(image_style, "scaling").as_str() {
+        "nearest" => "nearest",
+        "best" => "best",
+        // `linear`, as other values are reported when the style is parsed
+        _ => "linear",
+    }
 }
 
-pub fn generate_slide_data(global: &GlobalState, idx: usize, fullscreen: bool) -> SlideData {
+/// Lays out a slide, either in a window of the given size or, without one, at the size of the
+/// slide itself.
+pub fn generate_slide_data(
+    global: &GlobalState,
+    idx: usize,
+    window: Option<(u32, u32)>,
+) -> SlideData {
     let slides = global.slides.borrow();
     let all_styles = slides[idx].style_map();
     let slide_styles = all_styles.styles_for_target(&StyleTarget::Slide).unwrap();
 
-    let background = extract_colour(slide_styles, "bg");
+    let background =
+        extract_paint(slide_styles, "bg").expect("the background of a slide can not be none");
     let width = extract_number(slide_styles, "width");
     let height = extract_number(slide_styles, "height");
-    let margin = extract_number(slide_styles, "margin");
 
     let layout_rects = slides[idx].layout(
         global,
-        if fullscreen {
-            Some(Rect {
-                x: margin,
-                y: margin,
-                w: SLIDE_WIDTH - 2 * margin,
-                h: SLIDE_HEIGHT - 2 * margin,
-            })
-        } else {
-            None
-        },
+        window.map(|(window_width, window_height)| {
+            Rect::inside_slide_margins(slide_styles, window_width, window_height)
+        }),
     );
 
+    let (layout_rects, layout_error) = match layout_rects {
+        Ok(layout_rects) => (layout_rects, None),
+        Err(err) => (Vec::new(), Some(err.message(global))),
+    };
+    let connectors = slides[idx].layout_connectors(global, &layout_rects);
+    let layout_rects = slides[idx].in_draw_order(global, layout_rects);
+
+    // animated elements are scaled around the centre of everything that is inside of them
+    let animations = slides[idx]
+        .animations(global)
+        .into_iter()
+        .map(|(id, (animated, animation))| {
+            let inside = global.traverse(animated);
+            let bounds = layout_rects
+                .iter()
+                .filter(|layout_element| inside.contains(&layout_element.element))
+                .map(|layout_element| layout_element.max_bounds)
+                .reduce(|a, b| a.union(&b))
+                .unwrap_or_default();
+            (id, (animation, bounds.centre()))
+        })
+        .collect();
+
     SlideData {
         layout_rects,
+        connectors,
+        opacities: slides[idx].opacities(global),
+        animations,
+        layout_error,
         background,
         dimensions: (width, height),
         styles: all_styles.clone(), // TODO: don't clone here
@@ -64,9 +207,136 @@ pub fn generate_slide_data(global: &GlobalState, idx: usize, fullscreen: bool) -
     }
 }
 
-pub fn initialise_rendering_data<'a, T: LoadTexture>(
+/// Maps a font name to a fontdb family, treating the CSS generic family names specially.
+pub fn font_family(name: &str) -> fontdb::Family<'_> {
+    match name {
+        "serif" => fontdb::Family::Serif,
+        "sans-serif" => fontdb::Family::SansSerif,
+        "monospace" => fontdb::Family::Monospace,
+        "cursive" => fontdb::Family::Cursive,
+        "fantasy" => fontdb::Family::Fantasy,
+        other => fontdb::Family::Name(other),
+    }
+}
+
+/// Loads the face that best matches a query, if there is one.
+fn load_face(db: &fontdb::Database, query: &fontdb::Query) -> Option<fontdue::Font> {
+    face_font(db, db.query(query)?)
+}
+
+fn face_font(db: &fontdb::Database, face: fontdb::ID) -> Option<fontdue::Font> {
+    let font = db.with_face_data(face, |data, index| {
+        fontdue::Font::from_bytes(
+            data,
+            FontSettings {
+                collection_index: index,
+                ..Default::default()
+            },
+        )
+        .ok()
+    })??;
+    if let Some((fontdb::Source::File(path) | fontdb::Source::SharedFile(path, _), _)) =
+        db.face_source(face)
+    {
+        remember_font_file(&font, FontFile::Path(path));
+    }
+    Some(font)
+}
+
+/// Where the file of a font that is drawn with came from.
+#[derive(Clone)]
+enum FontFile {
+    Path(PathBuf),
+    Builtin,
+}
+
+/// The files of the fonts that are drawn with, by the hash of the file, which is all that a
+/// loaded font knows about it. Exported SVG files embed them.
+fn font_files() -> &'static Mutex<HashMap<usize, FontFile>> {
+    static FONT_FILES: OnceLock<Mutex<HashMap<usize, FontFile>>> = OnceLock::new();
+    FONT_FILES.get_or_init(Default::default)
+}
+
+fn remember_font_file(font: &fontdue::Font, file: FontFile) {
+    font_files().lock().unwrap().insert(font.file_hash(), file);
+}
+
+/// The contents of the file of a font, if it is known where it came from.
+fn font_file(font: &fontdue::Font) -> Option<Vec<u8>> {
+    let file = font_files()
+        .lock()
+        .unwrap()
+        .get(&font.file_hash())
+        .cloned()?;
+    match file {
+        FontFile::Path(path) => std::fs::read(path).ok(),
+        FontFile::Builtin => Some(include_bytes!("assets/newsreader.ttf").to_vec()),
+    }
+}
+
+pub fn initialise_rendering_data<'a, U>(
     global: &'a GlobalState,
-    texture_creator: &'a T,
+    texture_creator: &'a TextureCreator<U>,
+) -> RenderData<'a> {
+    // SDL picks the filter a texture is scaled with when it is created
+    let default_scale_quality = sdl2::hint::get(SCALE_QUALITY_HINT);
+    let render_data = load_rendering_data(
+        global,
+        |path, scale_quality| {
+            sdl2::hint::set(SCALE_QUALITY_HINT, scale_quality);
+            let texture = texture_creator
+                .load_texture(path)
+                .map_err(|err| panic!("{err}"))
+                .unwrap();
+            println!("{} has texture {:?}", path.display(), texture.query());
+            ElementImage::Texture(texture)
+        },
+        |info, frame| {
+            let mut texture = texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGBA32, info.width, info.height)
+                .unwrap();
+            texture
+                .update(None, &frame, info.width as usize * 4)
+                .unwrap();
+            ElementImage::Texture(texture)
+        },
+    );
+    sdl2::hint::set(
+        SCALE_QUALITY_HINT,
+        default_scale_quality.as_deref().unwrap_or("nearest"),
+    );
+    render_data
+}
+
+/// Loads everything that is needed to draw a presentation onto a [`SoftwareCanvas`], for which
+/// images and videos are kept as pixels rather than as textures.
+pub fn initialise_software_rendering_data(global: &GlobalState) -> RenderData<'static> {
+    load_rendering_data(
+        global,
+        |path, scale_quality| ElementImage::Pixels {
+            pixels: ImagePixels::load(path)
+                .map_err(|err| panic!("{err}"))
+                .unwrap(),
+            smooth: scale_quality != "nearest",
+        },
+        |info, frame| ElementImage::Pixels {
+            pixels: ImagePixels {
+                width: info.width,
+                height: info.height,
+                rgba: frame,
+            },
+            smooth: true,
+        },
+    )
+}
+
+/// Loads the fonts, images and videos of a presentation. Images are loaded by `load_image`,
+/// with the scale quality of the first slide they are on, and videos by `video_image`, with the
+/// pixels of their first frame.
+fn load_rendering_data<'a>(
+    global: &GlobalState,
+    mut load_image: impl FnMut(&Path, &'static str) -> ElementImage<'a>,
+    mut video_image: impl FnMut(&VideoInfo, Vec<u8>) -> ElementImage<'a>,
 ) -> RenderData<'a> {
     let mut db = fontdb::Database::new();
     db.load_system_fonts();
@@ -78,50 +348,68 @@ pub fn initialise_rendering_data<'a, T: LoadTexture>(
                 .get_slide_elements(slide)
                 .iter()
                 .filter(|elem| {
-                    elem.el_type() == ElementType::Text || elem.el_type() == ElementType::Code
-                })
-                .map(|elem| match elem.name() {
-                    Some(el_name) => StyleTarget::Named(el_name.to_owned()),
-                    None => StyleTarget::Anonymous(elem.el_type()),
+                    matches!(
+                        elem.el_type(),
+                        ElementType::Text
+                            | ElementType::Code
+                            | ElementType::List
+                            | ElementType::Math
+                            | ElementType::BarChart
+                            | ElementType::LineChart
+                            | ElementType::PieChart
+                            | ElementType::Plot
+                            | ElementType::SlideNumber
+                            | ElementType::Clock
+                            | ElementType::Link
+                            | ElementType::H1
+                            | ElementType::H2
+                            | ElementType::H3
+                    )
                 })
-                .sorted()
-                .dedup()
-                // .inspect(|st| {
+                .map(|elem| (StyleTarget::reify(elem), elem))
+                .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                .dedup_by(|(a, _), (b, _)| a == b)
+                // .inspect(|(st, _)| {
                 //     println!("generating font for style target {st:?} on slide {slide_idx}")
                 // })
-                .map(|st| {
-                    let ideal_font_name =
-                        extract_string(slide.style_map().styles_for_target(&st).unwrap(), "font");
-                    let acquired_font = db.query(&fontdb::Query {
-                        families: &[
-                            fontdb::Family::Name(&ideal_font_name),
-                            fontdb::Family::Serif,
-                        ],
-                        ..Default::default()
-                    });
+                .map(|(st, elem)| {
+                    let choice = FontChoice::from_style(
+                        &slide.style_map().styles_for_element(elem),
+                        "font",
+                    );
+                    let font_names = &choice.names;
+                    let families = font_names
+                        .iter()
+                        .map(|name| font_family(name))
+                        .collect::<Vec<_>>();
+                    let acquired_font = db.query(&choice.query(&families));
 
-                    let font_bytes = if let Some(font_id) = acquired_font {
+                    let (font_bytes, file) = if let Some(font_id) = acquired_font {
                         match db.face_source(font_id).unwrap().0 {
                             fontdb::Source::Binary(_) => {
                                 todo!("cannot handle binary font data loaded into fontdb yet")
                             }
                             fontdb::Source::File(ref path) => {
-                                std::fs::read(path).unwrap_or_else(|_| {
+                                let bytes = std::fs::read(path).unwrap_or_else(|_| {
                                     panic!(
                                         "got file path {} for font, but could not read it",
                                         path.display()
                                     )
-                                })
+                                });
+                                (bytes, FontFile::Path(path.clone()))
                             }
                             fontdb::Source::SharedFile(_, _) => {
                                 todo!("cannot handle shared files yet")
                             }
                         }
                     } else if cfg!(feature = "builtin-fonts") {
-                        eprintln!("warning: specified font '{ideal_font_name}' not found. Use the 'list-fonts' subcommand to see what fonts Folium can use. Falling back to default font");
-                        include_bytes!("assets/newsreader.ttf").to_vec()
+                        eprintln!("warning: none of the specified fonts {font_names:?} were found. Use the 'list-fonts' subcommand to see what fonts Folium can use. Falling back to default font");
+                        (
+                            include_bytes!("assets/newsreader.ttf").to_vec(),
+                            FontFile::Builtin,
+                        )
                     } else {
-                        panic!("Specified font '{ideal_font_name}' not found, exiting. Use the 'list-fonts' subcommand to see what fonts Folium can use.")
+                        panic!("None of the specified fonts {font_names:?} were found, exiting. Use the 'list-fonts' subcommand to see what fonts Folium can use.")
                     };
 
                     // SDL2's TTF rendering is pretty horrible and notably quite slow.
@@ -129,6 +417,7 @@ pub fn initialise_rendering_data<'a, T: LoadTexture>(
 
                     let font =
                         fontdue::Font::from_bytes(font_bytes, FontSettings::default()).unwrap();
+                    remember_font_file(&font, file);
 
                     ((slide.id(), st), font)
                 })
@@ -138,193 +427,5351 @@ pub fn initialise_rendering_data<'a, T: LoadTexture>(
         })
         .collect::<HashMap<(AbstractElementID, StyleTarget), fontdue::Font>>();
 
-    RenderData {
-        texture_map: (0..global.number_of_elements())
-            .flat_map(|idx| global.get_element_by_id(AbstractElementID(idx as u32)))
-            .filter(|elem| elem.el_type() == ElementType::Image)
-            .map(|img| {
-                (
-                    img.id(),
-                    texture_creator
-                        .load_texture(match img.data() {
-                            AbstractElementData::Image(path) => path,
-                            _ => unreachable!("image element did not have image data"),
-                        })
+    // every image is loaded with the scaling of the first slide it is on
+    let mut image_scaling = HashMap::new();
+    let mut image_pixels = HashMap::new();
+    for slide in global.slides.borrow().iter() {
+        for elem in global.get_slide_elements(slide) {
+            let AbstractElementData::Image(path) = elem.data() else {
+                continue;
+            };
+            let image_style = slide.style_map().styles_for_element(&elem);
+            image_scaling
+                .entry(elem.id())
+                .or_insert_with(|| scale_quality(&image_style));
+
+            let masked = extract_boolean(&image_style, "circle")
+                || extract_number(&image_style, "radius") > 0;
+            if masked && !image_pixels.contains_key(&elem.id()) {
+                let pixels = ImagePixels::load(path)
+                    .map_err(|err| panic!("{err}"))
+                    .unwrap();
+                image_pixels.insert(elem.id(), pixels);
+            }
+        }
+    }
+
+    let mut images: HashMap<AbstractElementID, ElementImage<'a>> = global
+        .all_elements()
+        .iter()
+        .filter(|elem| elem.el_type() == ElementType::Image)
+        .map(|img| {
+            let path = match img.data() {
+                AbstractElementData::Image(path) => path,
+                _ => unreachable!("image element did not have image data"),
+            };
+            let scale_quality = image_scaling.get(&img.id()).copied().unwrap_or("linear");
+            (img.id(), load_image(path, scale_quality))
+        })
+        .collect();
+
+    // videos start out showing their first frame, which is replaced while they are playing
+    let mut video_info = HashMap::new();
+    for elem in global.all_elements() {
+        let AbstractElementData::Video(path) = elem.data() else {
+            continue;
+        };
+
+        let info = match video::probe(path) {
+            Ok(info) => info,
+            Err(err) => {
+                eprintln!("warning: could not open video {}: {err}", path.display());
+                continue;
+            }
+        };
+
+        let frame = video::first_frame(path, &info).unwrap_or_else(|err| {
+            eprintln!(
+                "warning: could not decode the first frame of {}: {err}",
+                path.display()
+            );
+            [0, 0, 0, 255].repeat(info.width as usize * info.height as usize)
+        });
+
+        images.insert(elem.id(), video_image(&info, frame));
+        video_info.insert(elem.id(), info);
+    }
+
+    // every pattern is loaded once, however many backgrounds and shapes are filled with it
+    let mut patterns = HashMap::new();
+    for slide in global.slides.borrow().iter() {
+        let style_map = slide.style_map();
+        let elements = global.get_slide_elements(slide);
+        let styles = std::iter::once(
+            style_map
+                .styles_for_target(&StyleTarget::Slide)
+                .unwrap()
+                .clone(),
+        )
+        .chain(
+            elements
+                .iter()
+                .map(|elem| style_map.styles_for_element(elem)),
+        );
+        for style in styles {
+            for property in ["bg", "fill"] {
+                // only strings and lists can be patterns, the other fills are plain colours
+                if !matches!(
+                    style.get(property),
+                    Some(PropertyValue::String(_) | PropertyValue::List(_))
+                ) {
+                    continue;
+                }
+                let Some(Paint::Pattern { image, .. }) = extract_paint(&style, property) else {
+                    continue;
+                };
+                patterns.entry(image).or_insert_with_key(|image| {
+                    ImagePixels::load(image)
                         .map_err(|err| panic!("{err}"))
-                        .unwrap(),
-                )
-            })
-            .inspect(|(id, tex)| println!("{id} has texture {:?}", tex.query()))
-            .collect(),
+                        .unwrap()
+                });
+            }
+        }
+    }
+
+    let watermark_image = match global.deck().watermark {
+        Some(Watermark {
+            mark: WatermarkMark::Image(path),
+            ..
+        }) => Some(load_image(&path, "linear")),
+        _ => None,
+    };
+
+    // every icon gets the first font in its list that actually contains it
+    let mut icon_fonts = HashMap::new();
+    for slide in global.slides.borrow().iter() {
+        for elem in global.get_slide_elements(slide) {
+            let AbstractElementData::Icon(character) = elem.data() else {
+                continue;
+            };
+
+            let font_names =
+                extract_string_list(&slide.style_map().styles_for_element(&elem), "font");
+            let font = font_names.iter().find_map(|name| {
+                let font = load_face(
+                    &db,
+                    &fontdb::Query {
+                        families: &[font_family(name)],
+                        ..Default::default()
+                    },
+                )?;
+                (font.lookup_glyph_index(*character) != 0).then_some(font)
+            });
+
+            match font {
+                Some(font) => {
+                    icon_fonts.insert((slide.id(), elem.id()), font);
+                }
+                None => eprintln!(
+                    "warning: none of the fonts {font_names:?} contain the icon U+{:04X}",
+                    *character as u32
+                ),
+            }
+        }
+    }
+
+    // marked up text needs a font for every kind of span in it besides plain text, which
+    // uses the font of its style target
+    let mut span_fonts = HashMap::new();
+    for slide in global.slides.borrow().iter() {
+        for elem in global.get_slide_elements(slide) {
+            let (AbstractElementData::Text(text) | AbstractElementData::Heading(_, text)) =
+                elem.data()
+            else {
+                continue;
+            };
+
+            let text_style = slide.style_map().styles_for_element(&elem);
+            if !extract_boolean(&text_style, "markup") {
+                continue;
+            }
+
+            let target = StyleTarget::reify(&elem);
+            for span in markup::parse_markup(text) {
+                let key = (slide.id(), target.clone(), span.style);
+                if span.style == SpanStyle::default() || span_fonts.contains_key(&key) {
+                    continue;
+                }
+
+                // bold and italic spans are set in a heavier or slanted face of the font of the
+                // text, so text with `weight: bold` stays bold in an italic span
+                let mut choice = FontChoice::from_style(
+                    &text_style,
+                    if span.style.mono { "mono-font" } else { "font" },
+                );
+                if span.style.bold {
+                    choice.weight = choice.weight.max(fontdb::Weight::BOLD);
+                }
+                if span.style.italic {
+                    choice.style = fontdb::Style::Italic;
+                }
+                let font_names = &choice.names;
+                let families = font_names
+                    .iter()
+                    .map(|name| font_family(name))
+                    .collect::<Vec<_>>();
+
+                match load_face(&db, &choice.query(&families)) {
+                    Some(font) => {
+                        span_fonts.insert(key, font);
+                    }
+                    None => eprintln!(
+                        "warning: none of the fonts {font_names:?} were found for {:?} text",
+                        span.style
+                    ),
+                }
+            }
+        }
+    }
+
+    RenderData {
+        images,
+        image_pixels,
         font_database: db,
         fonts_for_targets,
+        video_info,
+        icon_fonts,
+        span_fonts,
+        slide_data: RefCell::new(HashMap::new()),
+        glyphs: GlyphAtlas::default(),
+        text_images: RefCell::new(HashMap::new()),
+        shown_at: Cell::new(None),
+        keep_snapshots: false,
+        snapshot: RefCell::new(None),
+        laser_pointer: None,
+        annotations: HashMap::new(),
+        watermark_image,
+        patterns,
     }
 }
 
-pub fn render<T: RenderTarget>(
-    global: &GlobalState,
-    target: &mut Canvas<T>,
-    slide_idx: usize,
-    fullscreen: bool,
-    render_data: &RenderData,
-    debug_rects: bool,
-) {
-    let slide_data = generate_slide_data(global, slide_idx, fullscreen);
+impl<'a> RenderData<'a> {
+    /// The laid out contents of a slide, which are only computed again when the slide is drawn
+    /// at another size than before. The layout for the old size is dropped then.
+    pub fn slide_data(
+        &self,
+        global: &GlobalState,
+        idx: usize,
+        window: Option<(u32, u32)>,
+    ) -> Rc<SlideData> {
+        let size = viewport_size(global, idx, window);
+        let id = global.slides.borrow()[idx].id();
+        let mut slide_data = self.slide_data.borrow_mut();
+        match slide_data.get(&id) {
+            Some((laid_out_at, data)) if *laid_out_at == size => data.clone(),
+            _ => {
+                let data = Rc::new(generate_slide_data(global, idx, window));
+                slide_data.insert(id, (size, data.clone()));
+                data
+            }
+        }
+    }
 
-    target.set_draw_color(slide_data.background);
-    target.clear();
+    /// The image of a text or code element on a slide. Text is only laid out and rasterised
+    /// again when the fingerprint of its content, style and area changes.
+    fn text_image(
+        &self,
+        slide_id: AbstractElementID,
+        element_id: AbstractElementID,
+        fingerprint: u64,
+        make: impl FnOnce() -> TextImage,
+    ) -> Rc<TextImage> {
+        let key = (slide_id, element_id, fingerprint);
+        if let Some(image) = self.text_images.borrow().get(&key) {
+            return image.clone();
+        }
 
-    if debug_rects {
-        target.set_draw_color((255, 0, 0));
-        target
-            .draw_rects(
-                &slide_data
-                    .layout_rects
-                    .iter()
-                    .map(|r| folium_to_sdl_rect(r.max_bounds))
-                    .collect::<Vec<_>>(),
-            )
-            .unwrap();
+        // the image that was made for an older fingerprint is not drawn again
+        let image = Rc::new(make());
+        let mut text_images = self.text_images.borrow_mut();
+        text_images.retain(|(slide, element, _), _| (*slide, *element) != (slide_id, element_id));
+        text_images.insert(key, image.clone());
+        image
     }
 
-    for rect in slide_data.layout_rects {
-        let element = global.get_element_by_id(rect.element).unwrap();
-        match element.data() {
-            AbstractElementData::Sized(_) => {
-                panic!("Sized should never have a layout element of its own")
-            }
-            AbstractElementData::Row(_) => {
-                panic!("Row should never have a layout element of its own")
-            }
-            AbstractElementData::Col(_) => {
-                panic!("Column should never have a layout element of its own")
+    /// Rasterises text for the red, green and blue subpixels of LCD screens from now on, which
+    /// makes it sharper on them, but gives it coloured edges on other screens and in images
+    /// that are scaled.
+    pub fn use_subpixel_text(&mut self) {
+        self.glyphs = GlyphAtlas {
+            subpixel: true,
+            ..Default::default()
+        };
+        self.text_images.borrow_mut().clear();
+    }
+
+    /// Shows the laser pointer at a point of the target over every frame that is drawn from
+    /// now on, or hides it. It is not part of the snapshots, so moving it only takes drawing
+    /// what changes, see [`render_changes`].
+    pub fn set_laser_pointer(&mut self, at: Option<(i32, i32)>) {
+        self.laser_pointer = at;
+    }
+
+    /// Starts a new line with the pen over a slide, at a point of the target. Like the laser
+    /// pointer, what is drawn with the pen is not part of the snapshots.
+    pub fn start_stroke(&mut self, slide_idx: usize, colour: (u8, u8, u8), at: (i32, i32)) {
+        self.annotations.entry(slide_idx).or_default().push(Stroke {
+            colour,
+            points: vec![at],
+        });
+    }
+
+    /// Continues the last line that was drawn with the pen over a slide to a point.
+    pub fn extend_stroke(&mut self, slide_idx: usize, to: (i32, i32)) {
+        if let Some(stroke) = self
+            .annotations
+            .get_mut(&slide_idx)
+            .and_then(|strokes| strokes.last_mut())
+        {
+            stroke.points.push(to);
+        }
+    }
+
+    /// Removes everything that was drawn with the pen over a slide.
+    pub fn clear_annotations(&mut self, slide_idx: usize) {
+        self.annotations.remove(&slide_idx);
+    }
+
+    /// Starts the animations of the slide that is shown next, from now on.
+    pub fn start_animations(&self) {
+        self.shown_at.set(Some(Instant::now()));
+    }
+
+    /// How long to wait before drawing the next frame of the animations on a slide, if any of
+    /// them are still playing.
+    pub fn time_until_animation_frame(
+        &self,
+        global: &GlobalState,
+        slide_idx: usize,
+        window: Option<(u32, u32)>,
+    ) -> Option<Duration> {
+        let elapsed = self.shown_at.get()?.elapsed();
+        let slide_data = self.slide_data(global, slide_idx, window);
+        slide_data
+            .animations
+            .values()
+            .any(|(animation, _)| !animation.is_finished(elapsed))
+            .then_some(ANIMATION_FRAME_TIME)
+    }
+
+    /// How an element is drawn when it is faded or in the middle of its animation: with what
+    /// alpha, and where the whole of `area` ends up. Other elements are drawn as usual.
+    fn compositing(
+        &self,
+        slide_data: &SlideData,
+        id: AbstractElementID,
+        area: Rect,
+    ) -> Option<(u8, sdl2::rect::Rect)> {
+        let alpha = slide_data.opacities.get(&id).copied();
+        let frame = self.shown_at.get().and_then(|shown_at| {
+            let elapsed = shown_at.elapsed();
+            let (animation, centre) = slide_data.animations.get(&id)?;
+            (!animation.is_finished(elapsed)).then(|| (animation.frame_at(elapsed), *centre))
+        });
+
+        match (alpha, frame) {
+            (None, None) => None,
+            (alpha, None) => Some((alpha.unwrap_or(u8::MAX), folium_to_sdl_rect(area))),
+            (alpha, Some((frame, centre))) => {
+                let alpha = alpha.unwrap_or(u8::MAX) as f32 * frame.opacity;
+                Some((alpha.round() as u8, frame_placement(area, frame, centre)))
             }
-            AbstractElementData::Padding(_) => {
-                panic!("Padding should never have a layout element of its own")
+        }
+    }
+
+    /// Keeps a copy of every frame that is drawn in full from now on, so that the parts of the
+    /// slide that change on their own can be drawn over it, see [`render_changes`].
+    pub fn keep_snapshots(&mut self) {
+        self.keep_snapshots = true;
+    }
+
+    /// The parts of a slide that change without anything happening: clocks, videos and the
+    /// elements that are in the middle of their animation, along with the area of their
+    /// shadows. An animation only covers the area between where it starts and where it ends.
+    pub fn changing_regions(
+        &self,
+        global: &GlobalState,
+        slide_idx: usize,
+        window: Option<(u32, u32)>,
+    ) -> Vec<sdl2::rect::Rect> {
+        let slide_data = self.slide_data(global, slide_idx, window);
+        let elapsed = self.shown_at.get().map(|shown_at| shown_at.elapsed());
+        // where an element is drawn over the whole of its animation, if it is animating
+        let animated = |id: AbstractElementID, area: Rect| {
+            let (animation, centre) = slide_data.animations.get(&id)?;
+            if elapsed.is_none_or(|elapsed| animation.is_finished(elapsed)) {
+                return None;
             }
-            AbstractElementData::Centre(_) => {} // TODO
-            AbstractElementData::Text(text_to_be_rendered) => {
-                let text_style_target = StyleTarget::reify(&element);
+            let start = frame_placement(area, animation.frame_at(Duration::ZERO), *centre);
+            Some(start.union(folium_to_sdl_rect(area)))
+        };
 
-                let text_style = slide_data
-                    .styles
-                    .styles_for_target(&text_style_target)
-                    .unwrap();
+        let elements = slide_data.layout_rects.iter().filter_map(|rect| {
+            let element = global.get_element_by_id(rect.element).unwrap();
+            let area = drawn_area(global, &slide_data, rect);
+            match element.el_type() {
+                ElementType::Clock | ElementType::Video => Some(folium_to_sdl_rect(area)),
+                _ => animated(rect.element, area),
+            }
+        });
+        let connectors = slide_data.connectors.iter().filter_map(|segment| {
+            animated(
+                segment.element,
+                connector_area(global, &slide_data, segment),
+            )
+        });
+        elements.chain(connectors).collect()
+    }
 
-                target.set_blend_mode(sdl2::render::BlendMode::Blend);
+    /// Where an element that draws over `area` ends up in this frame, which is farther than
+    /// `area` while it is moving in.
+    fn reach(&self, slide_data: &SlideData, id: AbstractElementID, area: Rect) -> sdl2::rect::Rect {
+        match self.compositing(slide_data, id, area) {
+            Some((_, placement)) => folium_to_sdl_rect(area).union(placement),
+            None => folium_to_sdl_rect(area),
+        }
+    }
 
-                let font = render_data
-                    .fonts_for_targets
-                    .get(&(slide_data.slide_id, text_style_target))
-                    .unwrap();
-                let font_size = extract_number(text_style, "size") as f32;
-                let text_colour = extract_colour(text_style, "fill");
+    /// The laid out elements of a slide that draw into `region`, in the order they are drawn.
+    /// The elements inside of a rotation or an opacity wrapper are drawn along with it, so they
+    /// are all kept when any of them, or the wrapper turned as far as it goes, reaches in.
+    fn layout_rects_reaching(
+        &self,
+        global: &GlobalState,
+        slide_data: &SlideData,
+        region: sdl2::rect::Rect,
+    ) -> Vec<LayoutElement> {
+        let reaches = |rect: &LayoutElement, area: Rect| {
+            overlap(self.reach(slide_data, rect.element, area), region)
+        };
 
-                let mut layout =
-                    fontdue::layout::Layout::new(fontdue::layout::CoordinateSystem::PositiveYDown);
-                layout.reset(&LayoutSettings {
-                    x: 0.0,
-                    y: 0.0,
-                    max_width: Some(rect.max_bounds.w as f32),
-                    max_height: Some(rect.max_bounds.h as f32),
-                    ..Default::default()
-                });
-                layout.append(
-                    &[font],
-                    &TextStyle::new(text_to_be_rendered, font_size, 0),
-                );
-                for glyph in layout.glyphs() {
-                    let (_, coverage) = font.rasterize(glyph.parent, font_size);
-
-                    for y_off in 0..glyph.height {
-                        for x_off in 0..glyph.width {
-                            let cov = coverage[y_off * glyph.width + x_off];
-                            target.set_draw_color(sdl2::pixels::Color::RGBA(
-                                text_colour.0,
-                                text_colour.1,
-                                text_colour.2,
-                                cov,
-                            ));
-                            target
-                                .draw_point((
-                                    glyph.x as i32 + x_off as i32 + rect.max_bounds.x as i32,
-                                    glyph.y as i32 + y_off as i32 + rect.max_bounds.y as i32,
-                                ))
-                                .unwrap();
-                        }
-                    }
+        let mut reaching = Vec::new();
+        let mut remaining = &slide_data.layout_rects[..];
+        while let Some((rect, rest)) = remaining.split_first() {
+            let element = global.get_element_by_id(rect.element).unwrap();
+            let (group_len, turned) = match element.data() {
+                AbstractElementData::Rotate(_, child) | AbstractElementData::Opacity(_, child) => {
+                    let child_ids = global.traverse(*child);
+                    let child_count = rest
+                        .iter()
+                        .take_while(|layout_element| child_ids.contains(&layout_element.element))
+                        .count();
+                    // turned, the corners of the wrapper stay within half of its longest side
+                    let bounds = rect.max_bounds;
+                    let turned = matches!(element.data(), AbstractElementData::Rotate(..))
+                        .then(|| bounds.expand(bounds.w.max(bounds.h) / 2));
+                    (1 + child_count, turned)
                 }
+                _ => (1, None),
+            };
+            let (group, rest) = remaining.split_at(group_len);
+            remaining = rest;
+
+            if turned.is_some_and(|area| reaches(rect, area))
+                || group
+                    .iter()
+                    .any(|rect| reaches(rect, drawn_area(global, slide_data, rect)))
+            {
+                reaching.extend_from_slice(group);
             }
-            AbstractElementData::Code(code_to_be_rendered) => {
-                let code_style_target = StyleTarget::reify(&element);
+        }
+        reaching
+    }
 
-                let code_style = slide_data
-                    .styles
-                    .styles_for_target(&code_style_target)
-                    .unwrap();
+    pub fn video_info(&self, id: AbstractElementID) -> Option<&VideoInfo> {
+        self.video_info.get(&id)
+    }
 
-                let bg_colour = extract_colour(code_style, "bg");
+    /// Replaces the frame that is shown for a video element.
+    pub fn update_video_frame(&mut self, id: AbstractElementID, frame: &[u8]) {
+        match (self.images.get_mut(&id), self.video_info.get(&id)) {
+            (Some(ElementImage::Texture(texture)), Some(info)) => texture
+                .update(None, frame, info.width as usize * 4)
+                .unwrap(),
+            (Some(ElementImage::Pixels { pixels, .. }), Some(_)) => {
+                pixels.rgba.copy_from_slice(frame)
+            }
+            _ => (),
+        }
+    }
+}
 
-                target.set_draw_color(bg_colour);
-                target
-                    .fill_rect(folium_to_sdl_rect(rect.max_bounds))
-                    .unwrap();
+/// Replaces `$slide` with the (one-based) number of the slide being rendered and `$total`
+/// with the number of slides in the presentation.
+fn expand_slide_placeholders(text: &str, slide_idx: usize, total: usize) -> String {
+    text.replace("$slide", &(slide_idx + 1).to_string())
+        .replace("$total", &total.to_string())
+}
 
-                let font = render_data
-                    .fonts_for_targets
-                    .get(&(slide_data.slide_id, code_style_target))
-                    .unwrap();
+/// Formats the marker of the `n`th item of a numbered list. The first character of the
+/// numbering format decides the style (`1`, `a`, `A`, `i` or `I`) and the rest is copied
+/// verbatim, so `a)` gives `a)`, `b)`, ... Any other format is used as-is for every item.
+fn list_marker(numbering: &str, n: usize) -> String {
+    let mut chars = numbering.chars();
+    let style = chars.next();
+    let suffix = chars.as_str();
+
+    let number = match style {
+        Some('1') => n.to_string(),
+        Some('a') => alphabetic_numeral(n),
+        Some('A') => alphabetic_numeral(n).to_uppercase(),
+        Some('i') => roman_numeral(n),
+        Some('I') => roman_numeral(n).to_uppercase(),
+        _ => return numbering.to_string(),
+    };
+
+    format!("{number}{suffix}")
+}
+
+/// a, b, ..., z, aa, ab, ...
+fn alphabetic_numeral(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+fn roman_numeral(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+
+    let mut numeral = String::new();
+    for (value, symbol) in NUMERALS {
+        while n >= value {
+            numeral.push_str(symbol);
+            n -= value;
+        }
+    }
+    numeral
+}
 
-                let font_size = extract_number(code_style, "size") as f32;
-                let text_colour = extract_colour(code_style, "fill");
+/// Fonts that are used for characters that the font of an element does not have, mostly
+/// emoji. fontdue can only draw outlines, so these are all monochrome fonts (or colour fonts
+/// that also contain outlines).
+const FALLBACK_FONTS: &[&str] = &["Noto Emoji", "Segoe UI Emoji", "Symbola", "DejaVu Sans"];
+
+/// The fonts that characters which the font of an element does not have are drawn with.
+struct Fallbacks {
+    db: fontdb::Database,
+    /// Every face of `db` that was loaded, also those that turned out not to have the
+    /// characters they were loaded for.
+    faces: FontCache<fontdb::ID>,
+    /// The installed fonts of `FALLBACK_FONTS`, followed by the fonts that were found for
+    /// scripts that none of those cover. Fonts are only ever added at the end, so their
+    /// positions stay the same.
+    fonts: Vec<Arc<fontdue::Font>>,
+    /// The scripts that the installed fonts were searched for.
+    searched: HashSet<&'static str>,
+}
 
-                let box_margin = extract_number(code_style, "margin");
-                let text_area = rect.max_bounds.with_margin(box_margin);
+impl Fallbacks {
+    fn face(&self, face: fontdb::ID) -> Option<Arc<fontdue::Font>> {
+        self.faces.get(face, || face_font(&self.db, face))
+    }
+}
 
-                let mut layout =
-                    fontdue::layout::Layout::new(fontdue::layout::CoordinateSystem::PositiveYDown);
-                layout.reset(&LayoutSettings {
-                    y: 0.0,
-                    x: 0.0,
-                    max_width: Some(text_area.w as f32),
-                    max_height: Some(text_area.h as f32),
+fn fallbacks() -> &'static Mutex<Fallbacks> {
+    static FALLBACKS: OnceLock<Mutex<Fallbacks>> = OnceLock::new();
+    FALLBACKS.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        let mut fallbacks = Fallbacks {
+            db,
+            faces: FontCache::default(),
+            fonts: Vec::new(),
+            searched: HashSet::new(),
+        };
+        fallbacks.fonts = FALLBACK_FONTS
+            .iter()
+            .filter_map(|name| {
+                fallbacks.face(fallbacks.db.query(&fontdb::Query {
+                    families: &[fontdb::Family::Name(name)],
                     ..Default::default()
-                });
-                layout.append(
-                    &[font],
-                    &TextStyle::new(code_to_be_rendered, font_size, 0),
-                );
-                for glyph in layout.glyphs() {
-                    let (_, coverage) = font.rasterize(glyph.parent, font_size);
-
-                    for y_off in 0..glyph.height {
-                        for x_off in 0..glyph.width {
-                            let cov = coverage[y_off * glyph.width + x_off];
-                            target.set_draw_color(sdl2::pixels::Color::RGBA(
-                                text_colour.0,
-                                text_colour.1,
-                                text_colour.2,
-                                cov,
-                            ));
-                            target
-                                .draw_point((
-                                    glyph.x as i32 + x_off as i32 + text_area.x as i32,
-                                    glyph.y as i32 + y_off as i32 + text_area.y as i32,
-                                ))
-                                .unwrap();
-                        }
-                    }
-                }
-            } // TODO: add code-specific features, like syntax highlighting etc
-            AbstractElementData::Image(..) => {
-                let texture = render_data.texture_map.get(&element.id()).unwrap();
-                target
-                    .copy(texture, None, folium_to_sdl_rect(rect.max_bounds))
-                    .unwrap();
-            }
-            AbstractElementData::None => {}
+                })?)
+            })
+            .collect();
+        Mutex::new(fallbacks)
+    })
+}
+
+/// The fallback fonts that are installed, loaded the first time they are needed.
+fn fallback_fonts() -> Vec<Arc<fontdue::Font>> {
+    fallbacks().lock().unwrap().fonts.clone()
+}
+
+/// A font that text was laid out in: one that the text is set in, or a fallback font for the
+/// characters that those do not have.
+#[derive(Clone)]
+enum TextFont<'f> {
+    Set(&'f fontdue::Font),
+    Fallback(Arc<fontdue::Font>),
+}
+
+impl std::ops::Deref for TextFont<'_> {
+    type Target = fontdue::Font;
+
+    fn deref(&self) -> &fontdue::Font {
+        match self {
+            TextFont::Set(font) => font,
+            TextFont::Fallback(font) => font,
+        }
+    }
+}
+
+impl std::borrow::Borrow<fontdue::Font> for TextFont<'_> {
+    fn borrow(&self) -> &fontdue::Font {
+        self
+    }
+}
+
+/// An installed font that is made for the script of a character, like Noto Sans Arabic, and
+/// has the character.
+fn script_font(fallbacks: &Fallbacks, character: char) -> Option<Arc<fontdue::Font>> {
+    let script = shaping::script(character)?.to_lowercase();
+    fallbacks
+        .db
+        .faces()
+        .filter(|face| {
+            face.families
+                .iter()
+                .any(|(family, _)| family.to_lowercase().contains(&script))
+        })
+        // the regular face, if there is one
+        .sorted_by_key(|face| {
+            (
+                face.style != fontdb::Style::Normal,
+                face.weight.0.abs_diff(fontdb::Weight::NORMAL.0),
+            )
+        })
+        .filter_map(|face| fallbacks.face(face.id))
+        .find(|font| font.lookup_glyph_index(character) != 0)
+}
+
+/// Picks the font to draw a character with: the given font if it has the character, otherwise
+/// the first fallback font that does. If none of them do, the installed fonts are searched for
+/// one that is made for the script of the character, which becomes a fallback font as well.
+/// Returns the index of the font in `[font, fallbacks...]`.
+fn font_index_for(font: &fontdue::Font, character: char) -> usize {
+    if character.is_whitespace() || font.lookup_glyph_index(character) != 0 {
+        return 0;
+    }
+
+    let mut fallbacks = fallbacks().lock().unwrap();
+    if let Some(idx) = fallbacks
+        .fonts
+        .iter()
+        .position(|fallback| fallback.lookup_glyph_index(character) != 0)
+    {
+        return idx + 1;
+    }
+
+    // every script is only searched for once, also when no font for it is installed
+    let Some(script) = shaping::script(character) else {
+        return 0;
+    };
+    if !fallbacks.searched.insert(script) {
+        return 0;
+    }
+    match script_font(&fallbacks, character) {
+        Some(found) => {
+            fallbacks.fonts.push(found);
+            fallbacks.fonts.len()
         }
+        None => 0,
     }
+}
 
-    target.present();
+/// Variation selectors and zero width joiners only change how the characters around them are
+/// drawn, which monochrome fonts cannot do, so they are left out rather than drawn as boxes.
+fn is_invisible_modifier(character: char) -> bool {
+    matches!(character, '\u{200d}' | '\u{fe00}'..='\u{fe0f}')
+}
+
+/// How the lines of a piece of text are aligned, from its `align` style.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum TextAlign {
+    #[default]
+    Left,
+    Centre,
+    Right,
+    /// Lines are stretched to the full width by widening their spaces, except for the last line
+    /// of every paragraph, which is aligned to the left.
+    Justify,
+}
+
+impl TextAlign {
+    fn from_style(style: &HashMap<String, PropertyValue>) -> Self {
+        match extract_string(style, "align").as_str() {
+            "centre" => Self::Centre,
+            "right" => Self::Right,
+            "justify" => Self::Justify,
+            // `left`, or the `top` or `baseline` of a row, as other values are reported when the
+            // style is parsed
+            _ => Self::Left,
+        }
+    }
+}
+
+/// How a piece of text is set, from the style of its element.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct TextSettings {
+    align: TextAlign,
+    /// Whether the paragraphs are read from right to left.
+    rtl: bool,
+    flow: TextFlow,
+    /// Whether a hyphen is drawn where a word is broken over two lines.
+    hyphenate: bool,
+    underline: bool,
+    strike: bool,
+}
+
+impl TextSettings {
+    /// Styles without a `direction`, `hyphenate`, `underline` or `strike`, like that of code,
+    /// are set from left to right without hyphens or lines.
+    fn from_style(style: &HashMap<String, PropertyValue>) -> Self {
+        Self {
+            align: TextAlign::from_style(style),
+            rtl: style
+                .get("direction")
+                .is_some_and(|direction| direction.to_string() == "rtl"),
+            flow: TextFlow::from_style(style),
+            hyphenate: matches!(style.get("hyphenate"), Some(PropertyValue::Boolean(true))),
+            underline: matches!(style.get("underline"), Some(PropertyValue::Boolean(true))),
+            strike: matches!(style.get("strike"), Some(PropertyValue::Boolean(true))),
+        }
+    }
+}
+
+/// Lays out a piece of text inside of the given area. Characters that the font does not have
+/// are taken from the fallback fonts; the glyphs refer to the returned list of fonts.
+fn layout_text<'f>(
+    font: &'f fontdue::Font,
+    text: &str,
+    font_size: f32,
+    area: Rect,
+    settings: TextSettings,
+) -> (fontdue::layout::Layout, Vec<TextFont<'f>>) {
+    layout_spans(&[(text, font)], font_size, area, settings)
+}
+
+/// Lays out pieces of text that are each set in their own font as a single paragraph, like
+/// `layout_text` does for a single piece. The text is shaped first, and brackets in runs that
+/// are read from right to left are mirrored.
+fn layout_spans<'f>(
+    spans: &[(&str, &'f fontdue::Font)],
+    font_size: f32,
+    area: Rect,
+    settings: TextSettings,
+) -> (fontdue::layout::Layout, Vec<TextFont<'f>>) {
+    let mut fonts: Vec<TextFont> = Vec::new();
+    for (_, font) in spans {
+        if !fonts.iter().any(|known| std::ptr::eq(&**known, *font)) {
+            fonts.push(TextFont::Set(font));
+        }
+    }
+    let span_font_count = fonts.len();
+
+    let spans = spans
+        .iter()
+        .map(|(text, font)| (shaping::shape(text), *font))
+        .collect::<Vec<_>>();
+    let all_chars = spans
+        .iter()
+        .flat_map(|(text, _)| text.chars())
+        .filter(|c| !is_invisible_modifier(*c))
+        .collect::<Vec<_>>();
+    let mut levels = bidi::levels(&all_chars, settings.rtl).into_iter();
+
+    // the text is added in runs of characters that are drawn with the same font
+    let mut runs = Vec::new();
+    let mut run = String::new();
+    let mut run_font = 0;
+    for (text, font) in &spans {
+        let span_font = fonts
+            .iter()
+            .position(|known| std::ptr::eq(&**known, *font))
+            .unwrap();
+        for character in text.chars().filter(|c| !is_invisible_modifier(*c)) {
+            let character = match levels.next() {
+                Some(level) if level % 2 == 1 => bidi::mirrored(character),
+                _ => character,
+            };
+            let font_index = match font_index_for(font, character) {
+                0 => span_font,
+                fallback => span_font_count + fallback - 1,
+            };
+            if font_index != run_font && !run.is_empty() {
+                runs.push((std::mem::take(&mut run), run_font));
+            }
+            run_font = font_index;
+            run.push(character);
+        }
+    }
+    runs.push((run, run_font));
+    // fonts for the scripts of the text may have been found while picking the fonts
+    fonts.extend(fallback_fonts().into_iter().map(TextFont::Fallback));
+
+    // text that is not wrapped has no width to be aligned in, so it is aligned when it is drawn
+    let mut layout = fontdue::layout::Layout::new(fontdue::layout::CoordinateSystem::PositiveYDown);
+    let layout_settings = LayoutSettings {
+        max_height: Some(area.h as f32),
+        horizontal_align: match settings.align {
+            TextAlign::Left | TextAlign::Justify => HorizontalAlign::Left,
+            TextAlign::Centre => HorizontalAlign::Center,
+            TextAlign::Right => HorizontalAlign::Right,
+        },
+        ..settings.flow.layout_settings(area.w)
+    };
+    settings
+        .flow
+        .lay_out(&mut layout, &layout_settings, |layout| {
+            for (run, run_font) in &runs {
+                layout.append(&fonts, &TextStyle::new(run, font_size, *run_font));
+            }
+        });
+
+    (layout, fonts)
+}
+
+/// The vertical extent of every line of the source text in a layout, relative to the top of
+/// the layout. A line that is wrapped covers several lines of the layout.
+fn source_line_extents(layout: &fontdue::layout::Layout) -> Vec<(f32, f32)> {
+    let glyphs = layout.glyphs();
+    let mut extents: Vec<(f32, f32)> = Vec::new();
+
+    for line in layout.lines().into_iter().flatten() {
+        let top = line.baseline_y - line.max_ascent;
+        let bottom = top + line.max_new_line_size;
+        let starts_source_line =
+            line.glyph_start == 0 || glyphs[line.glyph_start - 1].parent == '\n';
+        match extents.last_mut() {
+            Some(extent) if !starts_source_line => extent.1 = bottom,
+            _ => extents.push((top, bottom)),
+        }
+    }
+
+    extents
+}
+
+/// Lays out and draws a piece of text inside of the given area.
+fn draw_text<P: Painter>(
+    target: &mut P,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    text: &str,
+    font_size: f32,
+    colour: (u8, u8, u8),
+    area: Rect,
+) {
+    let mut batch = GlyphBatch::default();
+    batch_text(&mut batch, glyphs, font, text, font_size, colour, area);
+    batch.finish().draw(target);
+}
+
+/// Lays out a piece of text inside of the given area and adds its glyphs to a batch.
+fn batch_text(
+    batch: &mut GlyphBatch,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    text: &str,
+    font_size: f32,
+    colour: (u8, u8, u8),
+    area: Rect,
+) {
+    let (layout, fonts) = layout_text(font, text, font_size, area, TextSettings::default());
+    batch_layout(
+        batch,
+        glyphs,
+        &layout,
+        &fonts,
+        colour,
+        area,
+        TextSettings::default(),
+    );
+}
+
+/// Where every glyph of a layout is drawn horizontally once the characters of each line are put
+/// in the order they are read in. Lines that are read from left to right keep their positions.
+fn visual_positions(layout: &fontdue::layout::Layout, fonts: &[TextFont], rtl: bool) -> Vec<f32> {
+    let glyphs = layout.glyphs();
+    let mut positions = glyphs.iter().map(|glyph| glyph.x).collect::<Vec<_>>();
+    let chars = glyphs.iter().map(|glyph| glyph.parent).collect::<Vec<_>>();
+    if !bidi::has_right_to_left(&chars) {
+        return positions;
+    }
+
+    let levels = bidi::levels(&chars, rtl);
+    for line in layout.lines().into_iter().flatten() {
+        let range = line.glyph_start..=line.glyph_end;
+        let order = bidi::line_order(&chars[range.clone()], &levels[range]);
+
+        // the glyphs are placed again from the start of the line, one advance after another
+        let metrics = |idx: usize| {
+            let glyph = &glyphs[line.glyph_start + idx];
+            fonts[glyph.font_index].metrics_indexed(glyph.key.glyph_index, glyph.key.px)
+        };
+        let mut pen = glyphs[line.glyph_start].x - metrics(0).xmin as f32;
+        for idx in order {
+            let glyph_metrics = metrics(idx);
+            positions[line.glyph_start + idx] = pen + glyph_metrics.xmin as f32;
+            pen += glyph_metrics.advance_width;
+        }
+    }
+    positions
+}
+
+/// How far every glyph of a layout has to move to the right to justify it to the given width,
+/// given where the glyphs are drawn. The room that is left on a line is divided over the spaces
+/// between its words.
+fn justify_offsets(layout: &fontdue::layout::Layout, positions: &[f32], width: f32) -> Vec<f32> {
+    let glyphs = layout.glyphs();
+    let mut offsets = vec![0.0; glyphs.len()];
+
+    for line in layout.lines().into_iter().flatten() {
+        let ends_paragraph =
+            line.glyph_end + 1 >= glyphs.len() || glyphs[line.glyph_end].parent == '\n';
+        if ends_paragraph {
+            continue;
+        }
+
+        let line_glyphs = &glyphs[line.glyph_start..=line.glyph_end];
+        // whitespace at the end of a wrapped line is not stretched
+        let Some(last_word_end) = line_glyphs
+            .iter()
+            .rposition(|glyph| !glyph.parent.is_whitespace())
+        else {
+            continue;
+        };
+        let line_positions = &positions[line.glyph_start..=line.glyph_end];
+        let spaces = (0..last_word_end)
+            .filter(|idx| line_glyphs[*idx].parent.is_whitespace())
+            .map(|idx| line_positions[idx])
+            .collect::<Vec<_>>();
+        if spaces.is_empty() {
+            continue;
+        }
+
+        let start = line_positions[..=last_word_end]
+            .iter()
+            .copied()
+            .fold(f32::INFINITY, f32::min);
+        let end = (0..=last_word_end)
+            .map(|idx| line_positions[idx] + line_glyphs[idx].width as f32)
+            .fold(0.0, f32::max);
+        let per_space = (width - (end - start)).max(0.0) / spaces.len() as f32;
+
+        // every glyph moves along by the spaces that are drawn to its left
+        for (idx, position) in line_positions.iter().enumerate() {
+            let spaces_before = spaces.iter().filter(|space| *space < position).count();
+            offsets[line.glyph_start + idx] = spaces_before as f32 * per_space;
+        }
+    }
+
+    offsets
+}
+
+/// Moves the glyphs of every line of a layout apart by the tracking of the text, from left to
+/// right in the order they are drawn. Centred and right-aligned lines that were aligned by
+/// fontdue are moved back by half or all of the room that they gained.
+fn track_positions(
+    layout: &fontdue::layout::Layout,
+    positions: &mut [f32],
+    settings: TextSettings,
+) {
+    let tracking = settings.flow.tracking;
+    if tracking == 0.0 {
+        return;
+    }
+    let factor = match (settings.align, settings.flow.wrap) {
+        (_, TextWrap::None) | (TextAlign::Left | TextAlign::Justify, _) => 0.0,
+        (TextAlign::Centre, _) => 0.5,
+        (TextAlign::Right, _) => 1.0,
+    };
+
+    let glyphs = layout.glyphs();
+    for line in layout.lines().into_iter().flatten() {
+        let mut order = (line.glyph_start..=line.glyph_end).collect::<Vec<_>>();
+        order.sort_by(|a, b| positions[*a].total_cmp(&positions[*b]));
+        let gained = order
+            .iter()
+            .rposition(|idx| !glyphs[*idx].parent.is_whitespace())
+            .unwrap_or(0) as f32
+            * tracking;
+        for (rank, idx) in order.into_iter().enumerate() {
+            positions[idx] += rank as f32 * tracking - gained * factor;
+        }
+    }
+}
+
+/// How far every glyph of a layout that was not wrapped is moved to the right so that its line
+/// is centred or aligned to the right of an area of the given width.
+fn unwrapped_offsets(
+    layout: &fontdue::layout::Layout,
+    positions: &[f32],
+    width: f32,
+    align: TextAlign,
+) -> Vec<f32> {
+    let glyphs = layout.glyphs();
+    let mut offsets = vec![0.0; glyphs.len()];
+    let factor = match align {
+        TextAlign::Centre => 0.5,
+        TextAlign::Right => 1.0,
+        TextAlign::Left | TextAlign::Justify => return offsets,
+    };
+
+    for line in layout.lines().into_iter().flatten() {
+        let line_glyphs = &glyphs[line.glyph_start..=line.glyph_end];
+        let line_positions = &positions[line.glyph_start..=line.glyph_end];
+        let (start, end) = line_glyphs
+            .iter()
+            .zip(line_positions)
+            .filter(|(glyph, _)| !glyph.parent.is_whitespace())
+            .fold(
+                (f32::INFINITY, 0.0f32),
+                |(start, end), (glyph, position)| {
+                    (start.min(*position), end.max(position + glyph.width as f32))
+                },
+            );
+        if start > end {
+            continue;
+        }
+        let shift = (width - (end - start)) * factor - start;
+        offsets[line.glyph_start..=line.glyph_end].fill(shift);
+    }
+
+    offsets
+}
+
+/// Where a line ends in the middle of a word, so that a hyphen is drawn after its last glyph.
+/// Text that is read from right to left is never hyphenated.
+fn hyphenated_glyphs(layout: &fontdue::layout::Layout) -> Vec<usize> {
+    let glyphs = layout.glyphs();
+    let chars = glyphs.iter().map(|glyph| glyph.parent).collect::<Vec<_>>();
+    if bidi::has_right_to_left(&chars) {
+        return Vec::new();
+    }
+
+    layout
+        .lines()
+        .into_iter()
+        .flatten()
+        .map(|line| line.glyph_end)
+        .filter(|end| {
+            chars[*end].is_alphanumeric()
+                && chars
+                    .get(end + 1)
+                    .is_some_and(|next| next.is_alphanumeric())
+        })
+        .collect()
+}
+
+/// A hash of everything that the image of a text or code element depends on: its content, its
+/// style and the area it is drawn in.
+fn text_fingerprint(content: &str, style: &HashMap<String, PropertyValue>, area: Rect) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    for (property, value) in style.iter().sorted_by_key(|(property, _)| *property) {
+        property.hash(&mut hasher);
+        value.to_string().hash(&mut hasher);
+    }
+    area.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Lays out and rasterises the text of a code element, with the tints of a diff and the
+/// numbers in its gutter, inside of its box.
+fn code_image(
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    code: &str,
+    code_style: &HashMap<String, PropertyValue>,
+    bounds: Rect,
+) -> TextImage {
+    let text_colour = extract_colour(code_style, "fill");
+    let bg_colour = extract_paint(code_style, "bg").map_or(text_colour, |bg| bg.colour_at(0.5));
+    let box_margin = extract_number(code_style, "margin");
+    let mut text_area = bounds.with_margin(box_margin);
+    let font_size = measure::font_size(code_style, code, text_area.w, text_area.h);
+    let mut batch = GlyphBatch::default();
+
+    // the gutter with line numbers is as wide as the largest number, plus a space
+    let first_line = extract_number(code_style, "first-line") as usize;
+    let last_line = first_line + code.lines().count().max(1) - 1;
+    let gutter_width = if extract_boolean(code_style, "line-numbers") {
+        let digit_width = font.metrics('0', font_size).advance_width;
+        ((last_line.to_string().len() + 1) as f32 * digit_width).ceil() as u32
+    } else {
+        0
+    };
+    let gutter_area = Rect {
+        w: gutter_width.min(text_area.w),
+        ..text_area
+    };
+    text_area.x += gutter_area.w;
+    text_area.w -= gutter_area.w;
+
+    let settings = TextSettings::from_style(code_style);
+    let (layout, fonts) = layout_text(font, code, font_size, text_area, settings);
+
+    // in diff mode, added and removed lines are tinted across the whole box
+    if extract_boolean(code_style, "diff") {
+        let extents = source_line_extents(&layout);
+        for (line, (top, bottom)) in code.lines().zip(extents) {
+            let tint = match line.chars().next() {
+                Some('+') => extract_colour(code_style, "diff-added"),
+                Some('-') => extract_colour(code_style, "diff-removed"),
+                _ => continue,
+            };
+            batch.add_rect(
+                folium_to_sdl_rect(Rect {
+                    y: text_area.y + top as u32,
+                    h: (bottom - top).ceil() as u32,
+                    ..bounds
+                }),
+                tint,
+            );
+        }
+    }
+
+    let code_start = batch.glyphs.len();
+    batch_layout(
+        &mut batch,
+        glyphs,
+        &layout,
+        &fonts,
+        text_colour,
+        text_area,
+        settings,
+    );
+
+    // dimmed text is mixed with the background: line numbers half way, and the lines that are
+    // not highlighted two thirds of the way
+    let mix = |weight: u16| {
+        let channel = |fg: u8, bg: u8| ((fg as u16 + bg as u16 * weight) / (weight + 1)) as u8;
+        (
+            channel(text_colour.0, bg_colour.0),
+            channel(text_colour.1, bg_colour.1),
+            channel(text_colour.2, bg_colour.2),
+        )
+    };
+
+    if code_style.contains_key("highlight") {
+        let highlighted = extract_line_ranges(code_style, "highlight");
+        for (idx, (top, bottom)) in source_line_extents(&layout).into_iter().enumerate() {
+            if !highlighted
+                .iter()
+                .any(|lines| lines.contains(&(first_line + idx)))
+            {
+                let (top, bottom) = (text_area.y as f32 + top, text_area.y as f32 + bottom);
+                batch.recolour(code_start, top as i32..bottom.ceil() as i32, mix(2));
+            }
+        }
+    }
+
+    if gutter_width > 0 {
+        let number_colour = mix(1);
+        for (idx, (top, bottom)) in source_line_extents(&layout).into_iter().enumerate() {
+            batch_text(
+                &mut batch,
+                glyphs,
+                font,
+                &format!(
+                    "{:>width$}",
+                    first_line + idx,
+                    width = last_line.to_string().len()
+                ),
+                font_size,
+                number_colour,
+                Rect {
+                    y: gutter_area.y + top as u32,
+                    h: (bottom - top).ceil() as u32,
+                    ..gutter_area
+                },
+            );
+        }
+    }
+    batch.finish()
+}
+
+/// Draws text with `draw`, keeping it inside of its box unless its `overflow` style allows it
+/// to leave it. The text never leaves the clip rect that is already set, which is set again once
+/// the text is drawn.
+fn draw_clipped<P: Painter>(
+    target: &mut P,
+    style: &HashMap<String, PropertyValue>,
+    area: Rect,
+    draw: impl FnOnce(&mut P),
+) {
+    if extract_string(style, "overflow") == "visible" {
+        draw(target);
+        return;
+    }
+
+    let outer = target.clip_rect();
+    let area = folium_to_sdl_rect(area);
+    let clip = match outer {
+        Some(outer) => outer.intersection(area),
+        None => Some(area),
+    };
+    // a box outside of the clip rect has nothing in it to draw
+    if let Some(clip) = clip {
+        target.set_clip_rect(clip);
+        draw(target);
+        target.set_clip_rect(outer);
+    }
+}
+
+/// Adds the glyphs of text that has been laid out with `layout_text` to a batch, with the same
+/// alignment and direction.
+fn batch_layout(
+    batch: &mut GlyphBatch,
+    glyphs: &GlyphAtlas,
+    layout: &fontdue::layout::Layout,
+    fonts: &[TextFont],
+    colour: (u8, u8, u8),
+    area: Rect,
+    settings: TextSettings,
+) {
+    let xs = glyph_xs(layout, fonts, area, settings);
+    let line_ends = layout
+        .lines()
+        .into_iter()
+        .flatten()
+        .map(|line| (line.glyph_end, line.baseline_y))
+        .collect::<HashMap<_, _>>();
+    let hyphenated = if settings.hyphenate {
+        hyphenated_glyphs(layout)
+    } else {
+        Vec::new()
+    };
+
+    for (idx, (glyph, x)) in layout.glyphs().iter().zip(xs.iter().copied()).enumerate() {
+        let font = &fonts[glyph.font_index];
+
+        // a soft hyphen is only drawn where the word it is in is broken, as a hyphen
+        let hyphen_at = if glyph.parent == '\u{ad}' {
+            Some(x)
+        } else {
+            let rasterised = glyphs.glyph(font, glyph.key);
+            let metrics = rasterised.0;
+            batch.add(rasterised, x, glyph.y as i32 + area.y as i32, colour);
+            hyphenated
+                .contains(&idx)
+                .then(|| x - metrics.xmin + metrics.advance_width as i32)
+        };
+
+        if let (Some(hyphen_x), Some(baseline)) = (hyphen_at, line_ends.get(&idx)) {
+            let hyphen = glyphs.character(font, '-', glyph.key.px);
+            let metrics = hyphen.0;
+            batch.add(
+                hyphen,
+                hyphen_x + metrics.xmin,
+                (baseline - (metrics.height as i32 + metrics.ymin) as f32) as i32 + area.y as i32,
+                colour,
+            );
+        }
+    }
+
+    batch_decorations(batch, layout, fonts, &xs, colour, area, settings);
+}
+
+/// Lays out the text of a text element or a heading in `area`, the part of it inside of its
+/// padding, in the font of the element and in those of its styled spans.
+fn layout_text_element<'f>(
+    render_data: &'f RenderData,
+    slide_data: &SlideData,
+    element: &AbstractElement,
+    text: &str,
+    text_style: &HashMap<String, PropertyValue>,
+    area: Rect,
+) -> (fontdue::layout::Layout, Vec<TextFont<'f>>) {
+    let target = StyleTarget::reify(element);
+    let font = render_data
+        .fonts_for_targets
+        .get(&(slide_data.slide_id, target.clone()))
+        .unwrap();
+    let font_size = measure::font_size(text_style, text, area.w, area.h);
+
+    let spans = if extract_boolean(text_style, "markup") {
+        markup::parse_markup(text)
+    } else {
+        vec![markup::Span {
+            text: text.to_owned(),
+            style: SpanStyle::default(),
+        }]
+    };
+    let spans_with_fonts = spans
+        .iter()
+        .map(|span| {
+            let span_font = render_data
+                .span_fonts
+                .get(&(slide_data.slide_id, target.clone(), span.style))
+                .unwrap_or(font);
+            (span.text.as_str(), span_font)
+        })
+        .collect_vec();
+    layout_spans(
+        &spans_with_fonts,
+        font_size,
+        area,
+        TextSettings::from_style(text_style),
+    )
+}
+
+/// Where the glyphs of text that has been laid out with `layout_text` are drawn across its
+/// area, once they are put in their visual order, tracked and aligned.
+fn glyph_xs(
+    layout: &fontdue::layout::Layout,
+    fonts: &[TextFont],
+    area: Rect,
+    settings: TextSettings,
+) -> Vec<i32> {
+    let mut positions = visual_positions(layout, fonts, settings.rtl);
+    track_positions(layout, &mut positions, settings);
+    let offsets = match (settings.align, settings.flow.wrap) {
+        (align, TextWrap::None) => unwrapped_offsets(layout, &positions, area.w as f32, align),
+        (TextAlign::Justify, _) => justify_offsets(layout, &positions, area.w as f32),
+        _ => vec![0.0; layout.glyphs().len()],
+    };
+    positions
+        .into_iter()
+        .zip(offsets)
+        .map(|(position, offset)| (position + offset) as i32 + area.x as i32)
+        .collect()
+}
+
+/// Adds the lines under and through the lines of a layout to a batch, once its glyphs are
+/// placed at the given horizontal positions. Where the lines go and how thick they are follows
+/// from the metrics of the font of the first glyph on every line.
+fn batch_decorations(
+    batch: &mut GlyphBatch,
+    layout: &fontdue::layout::Layout,
+    fonts: &[TextFont],
+    xs: &[i32],
+    colour: (u8, u8, u8),
+    area: Rect,
+    settings: TextSettings,
+) {
+    if !settings.underline && !settings.strike {
+        return;
+    }
+
+    let glyphs = layout.glyphs();
+    for line in layout.lines().into_iter().flatten() {
+        let (start, end) = (line.glyph_start..=line.glyph_end)
+            .filter(|idx| !glyphs[*idx].parent.is_whitespace())
+            .fold((i32::MAX, i32::MIN), |(start, end), idx| {
+                (
+                    start.min(xs[idx]),
+                    end.max(xs[idx] + glyphs[idx].width as i32),
+                )
+            });
+        let first = &glyphs[line.glyph_start];
+        let Some(metrics) = fonts[first.font_index].horizontal_line_metrics(first.key.px) else {
+            continue;
+        };
+        if start >= end {
+            continue;
+        }
+
+        // the underline sits halfway down the descenders and the strike through the middle
+        // of the lowercase letters; the descent is negative
+        let thickness = (first.key.px / 16.0).round().max(1.0) as u32;
+        let baseline = line.baseline_y + area.y as f32;
+        let decorations = [
+            (settings.underline, baseline - metrics.descent * 0.5),
+            (settings.strike, baseline - metrics.ascent * 0.3),
+        ];
+        for (_, y) in decorations.into_iter().filter(|(drawn, _)| *drawn) {
+            batch.add_rect(
+                sdl2::rect::Rect::new(
+                    start,
+                    y as i32 - thickness as i32 / 2,
+                    (end - start) as u32,
+                    thickness,
+                ),
+                colour,
+            );
+        }
+    }
+}
+
+/// The width of a single line of text.
+fn text_width(font: &fontdue::Font, text: &str, font_size: f32) -> f32 {
+    shaping::shape(text)
+        .chars()
+        .filter(|c| !is_invisible_modifier(*c))
+        .map(|c| match font_index_for(font, c) {
+            0 => font.metrics(c, font_size).advance_width,
+            idx => {
+                fallback_fonts()[idx - 1]
+                    .metrics(c, font_size)
+                    .advance_width
+            }
+        })
+        .sum()
+}
+
+/// Where the data of a bar or line chart is drawn, after room has been made for the axes.
+struct PlotArea {
+    left: f32,
+    right: f32,
+    top: f32,
+    bottom: f32,
+    axis_max: f64,
+}
+
+impl PlotArea {
+    fn y_for(&self, value: f64) -> f32 {
+        self.bottom - (value.max(0.0) / self.axis_max) as f32 * (self.bottom - self.top)
+    }
+
+    /// The horizontal extent of the `idx`th of `count` categories.
+    fn slot(&self, idx: usize, count: usize) -> (f32, f32) {
+        let slot_width = (self.right - self.left) / count.max(1) as f32;
+        (self.left + idx as f32 * slot_width, slot_width)
+    }
+}
+
+/// Draws the value axis on the left and the category labels below a bar or line chart and
+/// returns the area that is left for the data. The axis runs from zero, so negative values are
+/// drawn as zero.
+fn draw_chart_axes<P: Painter>(
+    target: &mut P,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    data: &ChartData,
+    style: &HashMap<String, PropertyValue>,
+    area: Rect,
+) -> PlotArea {
+    let font_size = extract_number(style, "size") as f32;
+    let text_colour = extract_colour(style, "text-fill");
+    let axis_colour = extract_colour(style, "axis");
+
+    let ticks = chart::axis_ticks(data.largest_value());
+    let tick_labels = ticks
+        .iter()
+        .map(|tick| chart::format_tick(*tick))
+        .collect::<Vec<_>>();
+    let tick_label_width = tick_labels
+        .iter()
+        .map(|label| text_width(font, label, font_size))
+        .fold(0.0, f32::max);
+
+    let plot = PlotArea {
+        left: area.x as f32 + tick_label_width + font_size / 2.0,
+        right: (area.x + area.w) as f32,
+        top: area.y as f32 + font_size / 2.0,
+        bottom: (area.y + area.h) as f32 - font_size * 1.5,
+        axis_max: *ticks.last().unwrap(),
+    };
+
+    target.set_blend_mode(sdl2::render::BlendMode::Blend);
+
+    for (tick, label) in ticks.iter().zip(&tick_labels) {
+        let y = plot.y_for(*tick);
+        let width = text_width(font, label, font_size);
+        draw_text(
+            target,
+            glyphs,
+            font,
+            label,
+            font_size,
+            text_colour,
+            Rect {
+                x: (plot.left - font_size / 4.0 - width - 6.0).max(0.0) as u32,
+                y: (y - font_size / 2.0).max(0.0) as u32,
+                w: width.ceil() as u32 + 1,
+                h: font_size.ceil() as u32 * 2,
+            },
+        );
+        target.set_draw_color(axis_colour);
+        target
+            .fill_rect(sdl2::rect::Rect::new(
+                plot.left as i32 - 6,
+                y as i32 - 1,
+                6,
+                2,
+            ))
+            .unwrap();
+    }
+
+    for (idx, label) in data.labels.iter().enumerate() {
+        let (slot_x, slot_width) = plot.slot(idx, data.labels.len());
+        let width = text_width(font, label, font_size);
+        draw_text(
+            target,
+            glyphs,
+            font,
+            label,
+            font_size,
+            text_colour,
+            Rect {
+                x: (slot_x + (slot_width - width) / 2.0).max(0.0) as u32,
+                y: (plot.bottom + font_size / 4.0) as u32,
+                w: width.ceil() as u32 + 1,
+                h: font_size.ceil() as u32 * 2,
+            },
+        );
+    }
+
+    target.set_draw_color(axis_colour);
+    target
+        .fill_rect(sdl2::rect::Rect::new(
+            plot.left as i32 - 1,
+            plot.top as i32,
+            2,
+            (plot.bottom - plot.top).max(0.0) as u32 + 1,
+        ))
+        .unwrap();
+    target
+        .fill_rect(sdl2::rect::Rect::new(
+            plot.left as i32 - 1,
+            plot.bottom as i32 - 1,
+            (plot.right - plot.left) as u32 + 1,
+            2,
+        ))
+        .unwrap();
+
+    plot
+}
+
+/// Draws a bar chart. The colours in `fill` are used for the bars in turn.
+fn draw_bar_chart<P: Painter>(
+    target: &mut P,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    data: &ChartData,
+    style: &HashMap<String, PropertyValue>,
+    area: Rect,
+) {
+    let colours = extract_colour_list(style, "fill");
+    let gap = extract_number(style, "gap") as f32;
+
+    let plot = draw_chart_axes(target, glyphs, font, data, style, area);
+    for (idx, value) in data.values.iter().enumerate() {
+        let (slot_x, slot_width) = plot.slot(idx, data.values.len());
+        let bar_top = plot.y_for(*value);
+        target.set_draw_color(colours[idx % colours.len()]);
+        target
+            .fill_rect(sdl2::rect::Rect::new(
+                (slot_x + gap / 2.0) as i32,
+                bar_top as i32,
+                (slot_width - gap).max(1.0) as u32,
+                (plot.bottom - bar_top) as u32,
+            ))
+            .unwrap();
+    }
+}
+
+/// Draws a line chart: one point above the middle of every label, connected by a line. The line
+/// gets the first colour of `fill`.
+fn draw_line_chart<P: Painter>(
+    target: &mut P,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    data: &ChartData,
+    style: &HashMap<String, PropertyValue>,
+    area: Rect,
+) {
+    let colour = extract_colour_list(style, "fill")[0];
+    let width = extract_number(style, "stroke-width") as f32;
+
+    let plot = draw_chart_axes(target, glyphs, font, data, style, area);
+    let points = data
+        .values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let (slot_x, slot_width) = plot.slot(idx, data.values.len());
+            (slot_x + slot_width / 2.0, plot.y_for(*value))
+        })
+        .collect::<Vec<_>>();
+
+    draw_data_points(target, &points, colour, width, true);
+}
+
+/// Draws a dot for every point, a bit wider than `width`, and if `connected` is set, a line
+/// of that width through them.
+fn draw_data_points<P: Painter>(
+    target: &mut P,
+    points: &[(f32, f32)],
+    colour: (u8, u8, u8),
+    width: f32,
+    connected: bool,
+) {
+    let mut strokes = if connected {
+        points
+            .iter()
+            .tuple_windows()
+            .map(|(from, to)| segment_distance(*from, *to, width))
+            .collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+    strokes.extend(
+        points
+            .iter()
+            .map(|point| segment_distance(*point, *point, width * 2.5)),
+    );
+
+    let margin = width * 1.25 + 1.0;
+    let (left, right) = points
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), (x, _)| {
+            (min.min(*x), max.max(*x))
+        });
+    let (top, bottom) = points
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), (_, y)| {
+            (min.min(*y), max.max(*y))
+        });
+    let bounds = Rect {
+        x: (left - margin).max(0.0) as u32,
+        y: (top - margin).max(0.0) as u32,
+        w: (right - left + 2.0 * margin).max(0.0) as u32,
+        h: (bottom - top + 2.0 * margin).max(0.0) as u32,
+    };
+    draw_shape(target, bounds, colour, |x, y| {
+        strokes
+            .iter()
+            .map(|distance| distance(x, y))
+            .fold(f32::MAX, f32::min)
+    });
+}
+
+/// Draws a plot of numbers against numbers, with an axis with ticks on the left and at the
+/// bottom, named after the columns the values came from. With `mode: "scatter"`, the points
+/// are not connected.
+fn draw_plot<P: Painter>(
+    target: &mut P,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    data: &PlotData,
+    style: &HashMap<String, PropertyValue>,
+    area: Rect,
+) {
+    let font_size = extract_number(style, "size") as f32;
+    let text_colour = extract_colour(style, "text-fill");
+    let axis_colour = extract_colour(style, "axis");
+    let colour = extract_colour_list(style, "fill")[0];
+    let width = extract_number(style, "stroke-width") as f32;
+    let connected = extract_string(style, "mode") != "scatter";
+
+    let ((min_x, max_x), (min_y, max_y)) = data.ranges();
+    let x_ticks = chart::axis_ticks_between(min_x, max_x);
+    let y_ticks = chart::axis_ticks_between(min_y, max_y);
+    let label = |tick: &f64| {
+        let label = chart::format_tick(*tick);
+        let width = text_width(font, &label, font_size);
+        (label, width)
+    };
+    let x_labels = x_ticks.iter().map(label).collect::<Vec<_>>();
+    let y_labels = y_ticks.iter().map(label).collect::<Vec<_>>();
+    let y_label_width = y_labels.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+    let last_x_label_width = x_labels.last().map_or(0.0, |(_, width)| *width);
+
+    // the name of the y column goes above the axis, the name of the x column below the ticks
+    let left = area.x as f32 + y_label_width + font_size / 2.0;
+    let right = (area.x + area.w) as f32 - last_x_label_width / 2.0;
+    let top = area.y as f32 + font_size * 1.5;
+    let bottom = (area.y + area.h) as f32 - font_size * 2.75;
+    let (first_x, last_x) = (x_ticks[0], *x_ticks.last().unwrap());
+    let (first_y, last_y) = (y_ticks[0], *y_ticks.last().unwrap());
+    let x_for = |x: f64| left + ((x - first_x) / (last_x - first_x)) as f32 * (right - left);
+    let y_for = |y: f64| bottom - ((y - first_y) / (last_y - first_y)) as f32 * (bottom - top);
+
+    target.set_blend_mode(sdl2::render::BlendMode::Blend);
+
+    let mut draw_label = |text: &str, x: f32, y: f32, width: f32| {
+        draw_text(
+            target,
+            glyphs,
+            font,
+            text,
+            font_size,
+            text_colour,
+            Rect {
+                x: x.max(0.0) as u32,
+                y: y.max(0.0) as u32,
+                w: width.ceil() as u32 + 1,
+                h: font_size.ceil() as u32 * 2,
+            },
+        );
+    };
+    for (tick, (label, width)) in y_ticks.iter().zip(&y_labels) {
+        let y = y_for(*tick);
+        draw_label(
+            label,
+            left - font_size / 4.0 - width - 6.0,
+            y - font_size / 2.0,
+            *width,
+        );
+    }
+    for (tick, (label, width)) in x_ticks.iter().zip(&x_labels) {
+        draw_label(
+            label,
+            x_for(*tick) - width / 2.0,
+            bottom + font_size / 4.0,
+            *width,
+        );
+    }
+    let x_name_width = text_width(font, &data.x_name, font_size);
+    draw_label(
+        &data.x_name,
+        (left + right - x_name_width) / 2.0,
+        bottom + font_size * 1.5,
+        x_name_width,
+    );
+    let y_name_width = text_width(font, &data.y_name, font_size);
+    draw_label(&data.y_name, area.x as f32, area.y as f32, y_name_width);
+
+    target.set_draw_color(axis_colour);
+    for tick in &y_ticks {
+        target
+            .fill_rect(sdl2::rect::Rect::new(
+                left as i32 - 6,
+                y_for(*tick) as i32 - 1,
+                6,
+                2,
+            ))
+            .unwrap();
+    }
+    for tick in &x_ticks {
+        target
+            .fill_rect(sdl2::rect::Rect::new(
+                x_for(*tick) as i32 - 1,
+                bottom as i32,
+                2,
+                6,
+            ))
+            .unwrap();
+    }
+    target
+        .fill_rect(sdl2::rect::Rect::new(
+            left as i32 - 1,
+            top as i32,
+            2,
+            (bottom - top).max(0.0) as u32 + 1,
+        ))
+        .unwrap();
+    target
+        .fill_rect(sdl2::rect::Rect::new(
+            left as i32 - 1,
+            bottom as i32 - 1,
+            (right - left) as u32 + 1,
+            2,
+        ))
+        .unwrap();
+
+    let points = data
+        .points
+        .iter()
+        .map(|(x, y)| (x_for(*x), y_for(*y)))
+        .collect::<Vec<_>>();
+    draw_data_points(target, &points, colour, width, connected);
+}
+
+/// Signed distance to a slice of a pie with the given centre and radius, running clockwise
+/// from `start` to `end` (in radians, with 0 pointing right).
+fn pie_slice_distance(
+    centre: (f32, f32),
+    radius: f32,
+    start: f32,
+    end: f32,
+) -> impl Fn(f32, f32) -> f32 {
+    let edge = |angle: f32| {
+        (
+            centre.0 + radius * angle.cos(),
+            centre.1 + radius * angle.sin(),
+        )
+    };
+    let start_edge = segment_distance(centre, edge(start), 0.0);
+    let end_edge = segment_distance(centre, edge(end), 0.0);
+    let full_circle = end - start >= std::f32::consts::TAU - f32::EPSILON;
+
+    move |x, y| {
+        let circle = (x - centre.0).hypot(y - centre.1) - radius;
+        if full_circle {
+            return circle;
+        }
+
+        let angle = (y - centre.1).atan2(x - centre.0);
+        let angle = start + (angle - start).rem_euclid(std::f32::consts::TAU);
+        let edges = start_edge(x, y).min(end_edge(x, y));
+        let sides = if angle <= end { -edges } else { edges };
+        circle.max(sides)
+    }
+}
+
+/// Draws a pie chart with a legend on the right if `legend` is set. The colours in `fill` are
+/// used for the slices in turn, starting at the top and going clockwise.
+fn draw_pie_chart<P: Painter>(
+    target: &mut P,
+    glyphs: &GlyphAtlas,
+    font: &fontdue::Font,
+    data: &ChartData,
+    style: &HashMap<String, PropertyValue>,
+    area: Rect,
+) {
+    let colours = extract_colour_list(style, "fill");
+    let font_size = extract_number(style, "size") as f32;
+    let text_colour = extract_colour(style, "text-fill");
+
+    let legend_width = if extract_boolean(style, "legend") {
+        let label_width = data
+            .labels
+            .iter()
+            .map(|label| text_width(font, label, font_size))
+            .fold(0.0, f32::max);
+        let legend_width = label_width + font_size * 2.0;
+        let row_height = font_size * 1.5;
+        let legend_x = (area.x + area.w) as f32 - legend_width;
+        let legend_y =
+            area.y as f32 + (area.h as f32 - row_height * data.labels.len() as f32) / 2.0;
+
+        for (idx, label) in data.labels.iter().enumerate() {
+            let y = legend_y + idx as f32 * row_height;
+            target.set_draw_color(colours[idx % colours.len()]);
+            target
+                .fill_rect(sdl2::rect::Rect::new(
+                    legend_x as i32,
+                    (y + font_size * 0.25) as i32,
+                    font_size as u32,
+                    font_size as u32,
+                ))
+                .unwrap();
+            draw_text(
+                target,
+                glyphs,
+                font,
+                label,
+                font_size,
+                text_colour,
+                Rect {
+                    x: (legend_x + font_size * 1.5) as u32,
+                    y: y.max(0.0) as u32,
+                    w: label_width.ceil() as u32 + 1,
+                    h: row_height.ceil() as u32,
+                },
+            );
+        }
+
+        legend_width + font_size
+    } else {
+        0.0
+    };
+
+    let pie_width = (area.w as f32 - legend_width).max(0.0);
+    let radius = pie_width.min(area.h as f32) / 2.0;
+    let centre = (
+        area.x as f32 + pie_width / 2.0,
+        area.y as f32 + area.h as f32 / 2.0,
+    );
+    let bounds = Rect {
+        x: (centre.0 - radius - 1.0).max(0.0) as u32,
+        y: (centre.1 - radius - 1.0).max(0.0) as u32,
+        w: (2.0 * radius + 2.0) as u32,
+        h: (2.0 * radius + 2.0) as u32,
+    };
+
+    let total = data.values.iter().map(|value| value.max(0.0)).sum::<f64>();
+    if total <= 0.0 {
+        return;
+    }
+
+    target.set_blend_mode(sdl2::render::BlendMode::Blend);
+    let mut start = -std::f32::consts::FRAC_PI_2;
+    for (idx, value) in data.values.iter().enumerate() {
+        let sweep = (value.max(0.0) / total) as f32 * std::f32::consts::TAU;
+        if sweep > 0.0 {
+            draw_shape(
+                target,
+                bounds,
+                colours[idx % colours.len()],
+                pie_slice_distance(centre, radius, start, start + sweep),
+            );
+        }
+        start += sweep;
+    }
+}
+
+/// A glyph as it is rasterised by fontdue: its metrics and its coverage, row by row.
+type RasterisedGlyph = Rc<(fontdue::Metrics, Vec<u8>)>;
+
+/// Every glyph that has been drawn, in every font and at every size, so that each one is only
+/// rasterised once, however often it occurs on however many slides and redraws.
+#[derive(Default)]
+struct GlyphAtlas {
+    glyphs: RefCell<HashMap<GlyphRasterConfig, RasterisedGlyph>>,
+    /// Whether glyphs are rasterised for the red, green and blue subpixels of LCD screens, in
+    /// which case their coverage has three values per pixel.
+    subpixel: bool,
+}
+
+impl GlyphAtlas {
+    /// The glyph that a layout placed with the given key.
+    fn glyph(&self, font: &fontdue::Font, key: GlyphRasterConfig) -> RasterisedGlyph {
+        self.glyphs
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| {
+                if self.subpixel {
+                    let (metrics, mut coverage) = font.rasterize_config_subpixel(key);
+                    lcd_filter(&mut coverage, metrics.width * 3);
+                    Rc::new((metrics, coverage))
+                } else {
+                    Rc::new(font.rasterize_config(key))
+                }
+            })
+            .clone()
+    }
+
+    /// The glyph for a character in a font at a size.
+    fn character(&self, font: &fontdue::Font, character: char, px: f32) -> RasterisedGlyph {
+        self.glyph(
+            font,
+            GlyphRasterConfig {
+                glyph_index: font.lookup_glyph_index(character),
+                px,
+                font_hash: font.file_hash(),
+            },
+        )
+    }
+}
+
+/// Spreads the coverage of every subpixel over its neighbours, row by row, with the weights
+/// that FreeType uses by default. Without it, the edges of glyphs have coloured fringes.
+fn lcd_filter(coverage: &mut [u8], row_length: usize) {
+    const WEIGHTS: [u32; 5] = [8, 77, 86, 77, 8];
+    if row_length == 0 {
+        return;
+    }
+
+    for row in coverage.chunks_mut(row_length) {
+        let original = row.to_vec();
+        for (idx, value) in row.iter_mut().enumerate() {
+            let sum = WEIGHTS
+                .iter()
+                .enumerate()
+                .filter_map(|(tap, weight)| {
+                    let from = (idx + tap).checked_sub(2)?;
+                    Some(*original.get(from)? as u32 * weight)
+                })
+                .sum::<u32>();
+            *value = (sum / 256).min(255) as u8;
+        }
+    }
+}
+
+/// The gamma with which screens turn the values of colours into light.
+const GAMMA: f32 = 2.2;
+
+/// Blending glyphs onto the target mixes the values of the colours of the text and what is
+/// behind it, instead of the light they give off, so that dark text looks too heavy, light text
+/// too thin and the edges of text on a coloured background have a fringe. Glyphs are blended
+/// before what is behind them is known, so their coverage is corrected as if it were the
+/// opposite of the text: black behind light text and white behind dark text. This is a table
+/// of the corrected coverage for text of the given colour.
+fn gamma_table(colour: (u8, u8, u8)) -> [u8; 256] {
+    let linear = |channel: u8| (channel as f32 / 255.0).powf(GAMMA);
+    let luminance =
+        0.2126 * linear(colour.0) + 0.7152 * linear(colour.1) + 0.0722 * linear(colour.2);
+
+    let mut table = [0; 256];
+    for (coverage, corrected) in table.iter_mut().enumerate() {
+        let coverage = coverage as f32 / 255.0;
+        let on_black = coverage.powf(1.0 / GAMMA);
+        let on_white = 1.0 - (1.0 - coverage).powf(1.0 / GAMMA);
+        *corrected = ((on_white + (on_black - on_white) * luminance) * 255.0).round() as u8;
+    }
+    table
+}
+
+/// Rasterised glyphs that are drawn together, by copying them into a single image that is
+/// blended onto the target at once instead of drawing every pixel on its own.
+#[derive(Default)]
+struct GlyphBatch {
+    glyphs: Vec<PlacedGlyph>,
+}
+
+/// A glyph in a batch, with its top left corner at (x, y).
+struct PlacedGlyph {
+    glyph: RasterisedGlyph,
+    x: i32,
+    y: i32,
+    colour: (u8, u8, u8),
+}
+
+impl GlyphBatch {
+    /// Adds a glyph with its top left corner at the given point.
+    fn add(&mut self, glyph: RasterisedGlyph, x: i32, y: i32, colour: (u8, u8, u8)) {
+        if glyph.0.width > 0 && glyph.0.height > 0 {
+            self.glyphs.push(PlacedGlyph {
+                glyph,
+                x,
+                y,
+                colour,
+            });
+        }
+    }
+
+    /// Changes the colour of the glyphs from the `from`th one on whose top is in a range of
+    /// heights, which are those on a line.
+    fn recolour(&mut self, from: usize, tops: std::ops::Range<i32>, colour: (u8, u8, u8)) {
+        for glyph in &mut self.glyphs[from..] {
+            if tops.contains(&glyph.y) {
+                glyph.colour = colour;
+            }
+        }
+    }
+
+    /// Adds a filled rectangle, like an underline, as if it were a glyph.
+    fn add_rect(&mut self, rect: sdl2::rect::Rect, colour: (u8, u8, u8)) {
+        let (width, height) = (rect.width() as usize, rect.height() as usize);
+        let metrics = fontdue::Metrics {
+            xmin: 0,
+            ymin: 0,
+            width,
+            height,
+            advance_width: width as f32,
+            advance_height: 0.0,
+            bounds: fontdue::OutlineBounds::default(),
+        };
+        self.add(
+            Rc::new((metrics, vec![255; width * height])),
+            rect.x(),
+            rect.y(),
+            colour,
+        );
+    }
+
+    /// Composites the glyphs in the order they were added, so later glyphs are drawn on top.
+    fn finish(self) -> TextImage {
+        let Some(left) = self.glyphs.iter().map(|placed| placed.x).min() else {
+            return TextImage::default();
+        };
+        let top = self.glyphs.iter().map(|placed| placed.y).min().unwrap();
+        let right = self
+            .glyphs
+            .iter()
+            .map(|placed| placed.x + placed.glyph.0.width as i32)
+            .max()
+            .unwrap();
+        let bottom = self
+            .glyphs
+            .iter()
+            .map(|placed| placed.y + placed.glyph.0.height as i32)
+            .max()
+            .unwrap();
+        let (width, height) = ((right - left) as usize, (bottom - top) as usize);
+
+        let mut pixels = vec![0; width * height * 4];
+        // subpixel glyphs are also blended per channel, for targets that can show them
+        let subpixel = self
+            .glyphs
+            .iter()
+            .any(|placed| placed.glyph.1.len() > placed.glyph.0.width * placed.glyph.0.height);
+        let mut lcd = subpixel.then(|| LcdLayers {
+            keep: vec![255; width * height * 4],
+            add: [0, 0, 0, 255].repeat(width * height),
+        });
+        let mut tables: HashMap<(u8, u8, u8), [u8; 256]> = HashMap::new();
+
+        for PlacedGlyph {
+            glyph,
+            x,
+            y,
+            colour,
+        } in &self.glyphs
+        {
+            let (metrics, coverage) = &**glyph;
+            let table = tables
+                .entry(*colour)
+                .or_insert_with(|| gamma_table(*colour));
+            let channels = coverage.len() / (metrics.width * metrics.height);
+            for (idx, covs) in coverage.chunks(channels).enumerate() {
+                let px = (x - left) as usize + idx % metrics.width;
+                let py = (y - top) as usize + idx / metrics.width;
+                let offset = (py * width + px) * 4;
+                let covs = [0, 1, 2]
+                    .map(|channel| table[covs[channel.min(channels - 1)] as usize] as f32 / 255.0);
+
+                if let Some(LcdLayers { keep, add }) = &mut lcd {
+                    let colour = [colour.0, colour.1, colour.2];
+                    for channel in 0..3 {
+                        let cov = covs[channel];
+                        let kept = &mut keep[offset + channel];
+                        *kept = (*kept as f32 * (1.0 - cov)).round() as u8;
+                        let added = &mut add[offset + channel];
+                        *added = (*added as f32 * (1.0 - cov) + colour[channel] as f32 * cov)
+                            .round() as u8;
+                    }
+                }
+
+                let pixel = &mut pixels[offset..][..4];
+                // the glyph is blended over what is already there, with straight alpha
+                let src_alpha = covs.iter().sum::<f32>() / 3.0;
+                let dst_alpha = pixel[3] as f32 / 255.0;
+                let alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+                if alpha == 0.0 {
+                    continue;
+                }
+                for (channel, src) in [colour.0, colour.1, colour.2].into_iter().enumerate() {
+                    let dst = pixel[channel] as f32;
+                    pixel[channel] =
+                        ((src as f32 * src_alpha + dst * dst_alpha * (1.0 - src_alpha)) / alpha)
+                            .round() as u8;
+                }
+                pixel[3] = (alpha * 255.0).round() as u8;
+            }
+        }
+
+        TextImage {
+            x: left,
+            y: top,
+            width: width as u32,
+            height: height as u32,
+            pixels,
+            lcd,
+        }
+    }
+}
+
+/// Subpixel text as two images that are blended onto the target one after the other, which
+/// blends every channel with its own coverage: `keep` is multiplied with the target, which
+/// keeps as much of every channel as the text does not cover, and then `add` is added to it,
+/// which is the colour of the text times its coverage.
+struct LcdLayers {
+    keep: Vec<u8>,
+    add: Vec<u8>,
+}
+
+/// Text that has been laid out and rasterised, as an image that can be drawn again without
+/// doing either again.
+#[derive(Default)]
+struct TextImage {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    /// RGBA pixels, row by row.
+    pixels: Vec<u8>,
+    /// The same text for every subpixel, for text that is rasterised for them.
+    lcd: Option<LcdLayers>,
+}
+
+impl TextImage {
+    /// Blends the image onto the target. Subpixel text is only drawn as such straight onto the
+    /// window or surface: intermediate layers, like those for rotations and opacity, start
+    /// out transparent, which its layers can not blend with.
+    fn draw<P: Painter>(&self, target: &mut P) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let layers = match &self.lcd {
+            Some(LcdLayers { keep, add }) if !target.draws_to_layer() => vec![
+                (keep, sdl2::render::BlendMode::Mod),
+                (add, sdl2::render::BlendMode::Add),
+            ],
+            _ => vec![(&self.pixels, sdl2::render::BlendMode::Blend)],
+        };
+
+        for (pixels, blend_mode) in layers {
+            target.draw_pixels(
+                pixels,
+                (self.width, self.height),
+                sdl2::rect::Rect::new(self.x, self.y, self.width, self.height),
+                blend_mode,
+            );
+        }
+    }
+}
+
+/// Distance from a point to the edge of a rectangle with rounded corners,
+/// which is negative inside of it.
+fn rounded_rect_distance(rect: Rect, radius: f32) -> impl Fn(f32, f32) -> f32 {
+    let half_width = rect.w as f32 / 2.0;
+    let half_height = rect.h as f32 / 2.0;
+    let centre_x = rect.x as f32 + half_width;
+    let centre_y = rect.y as f32 + half_height;
+    let radius = radius.min(half_width).min(half_height);
+
+    move |x, y| {
+        let qx = (x - centre_x).abs() - (half_width - radius);
+        let qy = (y - centre_y).abs() - (half_height - radius);
+        qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
+    }
+}
+
+/// Approximate distance from a point to the edge of an ellipse with the given centre and radii,
+/// which is negative inside of it. The approximation is exact on the edge itself, which is all
+/// that matters for antialiasing.
+fn ellipse_distance(
+    centre_x: f32,
+    centre_y: f32,
+    radius_x: f32,
+    radius_y: f32,
+) -> impl Fn(f32, f32) -> f32 {
+    move |x, y| {
+        let dx = x - centre_x;
+        let dy = y - centre_y;
+        let k0 = (dx / radius_x).hypot(dy / radius_y);
+        let k1 = (dx / (radius_x * radius_x)).hypot(dy / (radius_y * radius_y));
+        if k1 == 0.0 {
+            -radius_x.min(radius_y)
+        } else {
+            k0 * (k0 - 1.0) / k1
+        }
+    }
+}
+
+/// Draws a shape given by the distance to its edge, which is negative inside of the shape.
+/// Pixels on the edge are antialiased; runs of fully covered pixels are filled in one go.
+fn draw_shape<P: Painter>(
+    target: &mut P,
+    bounds: Rect,
+    colour: (u8, u8, u8),
+    distance: impl Fn(f32, f32) -> f32,
+) {
+    for y in bounds.y..bounds.y + bounds.h {
+        let mut run_start = None;
+
+        for x in bounds.x..=bounds.x + bounds.w {
+            let coverage = if x < bounds.x + bounds.w {
+                (0.5 - distance(x as f32 + 0.5, y as f32 + 0.5)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            if coverage >= 1.0 {
+                run_start.get_or_insert(x);
+                continue;
+            }
+
+            if let Some(start) = run_start.take() {
+                target.set_draw_color(colour);
+                target
+                    .fill_rect(sdl2::rect::Rect::new(start as i32, y as i32, x - start, 1))
+                    .unwrap();
+            }
+            if coverage > 0.0 {
+                target.set_draw_color(sdl2::pixels::Color::RGBA(
+                    colour.0,
+                    colour.1,
+                    colour.2,
+                    (coverage * 255.0) as u8,
+                ));
+                target.draw_point((x as i32, y as i32)).unwrap();
+            }
+        }
+    }
+}
+
+/// Draws a shape like [`draw_shape`], but filled with a colour, a gradient or a pattern, whose
+/// image is looked up in `patterns`. Gradients and patterns are rasterised into an image first,
+/// with the antialiased edge of the shape in its alpha.
+fn draw_painted_shape<P: Painter>(
+    target: &mut P,
+    bounds: Rect,
+    paint: &Paint,
+    patterns: &HashMap<PathBuf, ImagePixels>,
+    distance: impl Fn(f32, f32) -> f32,
+) {
+    let colour_at: Box<dyn Fn(f32, f32) -> [u8; 4] + '_> = match *paint {
+        Paint::Solid(colour) => return draw_shape(target, bounds, colour, distance),
+        Paint::Gradient { angle, .. } => {
+            // the gradient runs across the box in its direction, from one corner to the
+            // opposite one
+            let (sin, cos) = (angle as f32).to_radians().sin_cos();
+            let extent = (bounds.w as f32 * cos.abs() + bounds.h as f32 * sin.abs()).max(1.0);
+            let centre_x = bounds.x as f32 + bounds.w as f32 / 2.0;
+            let centre_y = bounds.y as f32 + bounds.h as f32 / 2.0;
+            Box::new(move |px, py| {
+                let t = ((px - centre_x) * cos + (py - centre_y) * sin) / extent + 0.5;
+                let colour = paint.colour_at(t);
+                [colour.0, colour.1, colour.2, 255]
+            })
+        }
+        Paint::Pattern { ref image, scale } => {
+            let Some(tile) = patterns.get(image) else {
+                return;
+            };
+            Box::new(move |px, py| {
+                let x = ((px - bounds.x as f32) / scale).rem_euclid(tile.width as f32);
+                let y = ((py - bounds.y as f32) / scale).rem_euclid(tile.height as f32);
+                tile.sample(x, y)
+            })
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(bounds.w as usize * bounds.h as usize * 4);
+    for y in bounds.y..bounds.y + bounds.h {
+        for x in bounds.x..bounds.x + bounds.w {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let coverage = (0.5 - distance(px, py)).clamp(0.0, 1.0);
+            let [r, g, b, a] = colour_at(px, py);
+            pixels.extend([r, g, b, (a as f32 * coverage) as u8]);
+        }
+    }
+
+    TextImage {
+        x: bounds.x as i32,
+        y: bounds.y as i32,
+        width: bounds.w,
+        height: bounds.h,
+        pixels,
+        lcd: None,
+    }
+    .draw(target);
+}
+
+/// Distance from a point to a segment with round caps that is `width` thick.
+fn segment_distance(from: (f32, f32), to: (f32, f32), width: f32) -> impl Fn(f32, f32) -> f32 {
+    move |x, y| {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length_squared = dx * dx + dy * dy;
+        let t = if length_squared == 0.0 {
+            0.0
+        } else {
+            (((x - from.0) * dx + (y - from.1) * dy) / length_squared).clamp(0.0, 1.0)
+        };
+        (x - from.0 - t * dx).hypot(y - from.1 - t * dy) - width / 2.0
+    }
+}
+
+/// The strokes a line or an arrow is made of. The head of an arrow is made of two strokes at 30
+/// degrees from the line, `head` pixels long.
+fn connector_strokes(
+    segment: &ConnectorSegment,
+    head: f32,
+    with_head: bool,
+) -> Vec<((f32, f32), (f32, f32))> {
+    let mut strokes = vec![(segment.from, segment.to)];
+    let (dx, dy) = (segment.from.0 - segment.to.0, segment.from.1 - segment.to.1);
+    let length = dx.hypot(dy);
+    if with_head && length > 0.0 {
+        let (ux, uy) = (dx / length, dy / length);
+        let (sin, cos) = std::f32::consts::FRAC_PI_6.sin_cos();
+        for sin in [sin, -sin] {
+            let end = (
+                segment.to.0 + head * (ux * cos - uy * sin),
+                segment.to.1 + head * (ux * sin + uy * cos),
+            );
+            strokes.push((segment.to, end));
+        }
+    }
+    strokes
+}
+
+/// Draws a line or an arrow, see [`connector_strokes`].
+fn draw_connector<P: Painter>(
+    target: &mut P,
+    segment: &ConnectorSegment,
+    style: &HashMap<String, PropertyValue>,
+    with_head: bool,
+) {
+    let colour = extract_colour(style, "stroke");
+    let width = extract_number(style, "stroke-width") as f32;
+    let head = extract_number(style, "head") as f32;
+    let strokes = connector_strokes(segment, head, with_head);
+
+    let margin = width / 2.0 + 1.0;
+    let (min_x, min_y, max_x, max_y) = strokes.iter().flat_map(|(a, b)| [a, b]).fold(
+        (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+        |(min_x, min_y, max_x, max_y), (x, y)| {
+            (min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y))
+        },
+    );
+    let bounds = Rect {
+        x: (min_x - margin).max(0.0) as u32,
+        y: (min_y - margin).max(0.0) as u32,
+        w: (max_x - min_x + 2.0 * margin).ceil() as u32,
+        h: (max_y - min_y + 2.0 * margin).ceil() as u32,
+    };
+
+    let distances = strokes
+        .into_iter()
+        .map(|(from, to)| segment_distance(from, to, width))
+        .collect::<Vec<_>>();
+
+    target.set_blend_mode(sdl2::render::BlendMode::Blend);
+    draw_shape(target, bounds, colour, |x, y| {
+        distances
+            .iter()
+            .map(|distance| distance(x, y))
+            .fold(f32::MAX, f32::min)
+    });
+}
+
+/// Draws the fill and the outline of a shape. The outline lies on the inside of the edge.
+fn draw_filled_and_stroked<P: Painter>(
+    target: &mut P,
+    bounds: Rect,
+    style: &HashMap<String, PropertyValue>,
+    patterns: &HashMap<PathBuf, ImagePixels>,
+    distance: impl Fn(f32, f32) -> f32,
+) {
+    target.set_blend_mode(sdl2::render::BlendMode::Blend);
+
+    if let Some(fill) = extract_paint(style, "fill") {
+        draw_painted_shape(target, bounds, &fill, patterns, &distance);
+    }
+
+    let stroke_width = extract_number(style, "stroke-width") as f32;
+    if stroke_width > 0.0 {
+        if let Some(stroke) = extract_optional_colour(style, "stroke") {
+            draw_shape(target, bounds, stroke, |x, y| {
+                let d = distance(x, y);
+                d.max(-(d + stroke_width))
+            });
+        }
+    }
+}
+
+/// The decoded pixels of an image, which are kept for images with rounded corners or a
+/// circular mask, as those are drawn from them rather than from their texture.
+pub struct ImagePixels {
+    width: u32,
+    height: u32,
+    /// RGBA pixels, row by row.
+    rgba: Vec<u8>,
+}
+
+impl ImagePixels {
+    fn load(path: &Path) -> Result<Self, String> {
+        let surface = Surface::from_file(path)?.convert_format(PixelFormatEnum::RGBA32)?;
+        let (width, height) = (surface.width(), surface.height());
+        let pitch = surface.pitch() as usize;
+        let rgba = surface.with_lock(|pixels| {
+            pixels
+                .chunks(pitch)
+                .flat_map(|row| &row[..width as usize * 4])
+                .copied()
+                .collect()
+        });
+        Ok(ImagePixels {
+            width,
+            height,
+            rgba,
+        })
+    }
+
+    /// The colour at a point in the image, in pixels from its top left corner, interpolated
+    /// between the four pixels around it.
+    fn sample(&self, x: f32, y: f32) -> [u8; 4] {
+        let x = (x - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let y = (y - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (left, top) = (x.floor() as u32, y.floor() as u32);
+        let (right, bottom) = (
+            (left + 1).min(self.width - 1),
+            (top + 1).min(self.height - 1),
+        );
+        let (fx, fy) = (x.fract(), y.fract());
+
+        let pixel = |px: u32, py: u32| {
+            let idx = (py * self.width + px) as usize * 4;
+            &self.rgba[idx..idx + 4]
+        };
+        let mut colour = [0; 4];
+        for (channel, value) in colour.iter_mut().enumerate() {
+            let above = pixel(left, top)[channel] as f32 * (1.0 - fx)
+                + pixel(right, top)[channel] as f32 * fx;
+            let below = pixel(left, bottom)[channel] as f32 * (1.0 - fx)
+                + pixel(right, bottom)[channel] as f32 * fx;
+            *value = (above * (1.0 - fy) + below * fy).round() as u8;
+        }
+        colour
+    }
+}
+
+/// Scales the part `src` of an image to `dst` on the slide, with everything outside of a mask,
+/// given by the distance to its edge, made transparent.
+fn masked_image(
+    pixels: &ImagePixels,
+    src: Rect,
+    dst: Rect,
+    mask: impl Fn(f32, f32) -> f32,
+) -> TextImage {
+    if pixels.width == 0 || pixels.height == 0 {
+        return TextImage::default();
+    }
+
+    let scale_x = src.w as f32 / dst.w as f32;
+    let scale_y = src.h as f32 / dst.h as f32;
+    let mut rgba = Vec::with_capacity(dst.w as usize * dst.h as usize * 4);
+    for y in 0..dst.h {
+        for x in 0..dst.w {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let coverage = (0.5 - mask(dst.x as f32 + px, dst.y as f32 + py)).clamp(0.0, 1.0);
+            let [r, g, b, a] =
+                pixels.sample(src.x as f32 + px * scale_x, src.y as f32 + py * scale_y);
+            rgba.extend([r, g, b, (a as f32 * coverage) as u8]);
+        }
+    }
+
+    TextImage {
+        x: dst.x as i32,
+        y: dst.y as i32,
+        width: dst.w,
+        height: dst.h,
+        pixels: rgba,
+        lcd: None,
+    }
+}
+
+/// Draws the border that code, images and containers get with a `border-width`, on the inside
+/// of the edge of their shape and in their `border-colour` (black by default).
+fn draw_border<P: Painter>(
+    target: &mut P,
+    bounds: Rect,
+    style: &HashMap<String, PropertyValue>,
+    distance: impl Fn(f32, f32) -> f32,
+) {
+    let width = extract_optional_number(style, "border-width").unwrap_or(0) as f32;
+    if width <= 0.0 {
+        return;
+    }
+    let colour = if style.contains_key("border-colour") {
+        extract_colour(style, "border-colour")
+    } else {
+        (0, 0, 0)
+    };
+
+    target.set_blend_mode(sdl2::render::BlendMode::Blend);
+    draw_shape(target, bounds, colour, |x, y| {
+        let d = distance(x, y);
+        d.max(-(d + width))
+    });
+}
+
+/// The links on a slide and the areas they cover, so that they can be clicked on or exported.
+pub fn slide_links(
+    global: &GlobalState,
+    slide_idx: usize,
+    window: Option<(u32, u32)>,
+) -> Vec<(Rect, String)> {
+    generate_slide_data(global, slide_idx, window)
+        .layout_rects
+        .into_iter()
+        .filter_map(|layout_element| {
+            match global.get_element_by_id(layout_element.element)?.data() {
+                AbstractElementData::Link(_, url) => Some((layout_element.max_bounds, url.clone())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// How long it is until one of the clocks on a slide shows a different time, if it has any.
+pub fn time_until_clock_tick(global: &GlobalState, slide_idx: usize) -> Option<Duration> {
+    let slides = global.slides.borrow();
+    let slide = &slides[slide_idx];
+    global
+        .get_slide_elements(slide)
+        .iter()
+        .filter(|elem| elem.el_type() == ElementType::Clock)
+        .map(|elem| {
+            let format = extract_string(&slide.style_map().styles_for_element(elem), "format");
+            clock::time_until_next_tick(&format)
+        })
+        .min()
+}
+
+pub fn render<P: Painter>(
+    global: &GlobalState,
+    target: &mut P,
+    slide_idx: usize,
+    window: Option<(u32, u32)>,
+    render_data: &RenderData,
+    debug: DebugDrawing,
+) {
+    let slide_data = render_data.slide_data(global, slide_idx, window);
+    draw_slide(
+        global,
+        target,
+        slide_idx,
+        &slide_data,
+        render_data,
+        debug,
+        None,
+    );
+
+    if render_data.keep_snapshots {
+        let (width, height) = target.output_size().unwrap();
+        let pixels = target.pixels();
+        *render_data.snapshot.borrow_mut() = Some(Snapshot {
+            slide_idx,
+            size: (width, height),
+            pixels,
+        });
+    }
+
+    draw_overlay(target, slide_idx, render_data);
+    target.present();
+}
+
+/// Draws only the parts of a slide that change on their own again, see
+/// [`RenderData::changing_regions`], over the last frame that was drawn of it in full. Returns
+/// whether it could: without such a frame of the slide at the size of the target, nothing is
+/// drawn, and the slide has to be drawn in full instead.
+pub fn render_changes<P: Painter>(
+    global: &GlobalState,
+    target: &mut P,
+    slide_idx: usize,
+    window: Option<(u32, u32)>,
+    render_data: &RenderData,
+    debug: DebugDrawing,
+) -> bool {
+    let (width, height) = target.output_size().unwrap();
+    {
+        let snapshot = render_data.snapshot.borrow();
+        let Some(snapshot) = snapshot
+            .as_ref()
+            .filter(|snapshot| snapshot.slide_idx == slide_idx && snapshot.size == (width, height))
+        else {
+            return false;
+        };
+
+        // what is left in the target after it was presented can not be drawn over, and the
+        // overlay moves around, so the whole frame is put back
+        target.draw_pixels(
+            &snapshot.pixels,
+            (width, height),
+            sdl2::rect::Rect::new(0, 0, width, height),
+            sdl2::render::BlendMode::None,
+        );
+    }
+
+    let slide_data = render_data.slide_data(global, slide_idx, window);
+    for region in render_data.changing_regions(global, slide_idx, window) {
+        target.set_clip_rect(region);
+        let region = Some(region);
+        draw_slide(
+            global,
+            target,
+            slide_idx,
+            &slide_data,
+            render_data,
+            debug,
+            region,
+        );
+    }
+    target.set_clip_rect(None);
+
+    draw_overlay(target, slide_idx, render_data);
+    target.present();
+    true
+}
+
+/// Writes a slide as an SVG document, which can be edited further and stays sharp at any zoom.
+/// Backgrounds, shapes, borders, lines and images are written as vectors, and text as text in
+/// its fonts, which are embedded. What has no such form, like charts, code and formulas, is
+/// drawn like it is rendered and embedded as an image.
+pub fn render_svg(global: &GlobalState, slide_idx: usize, render_data: &RenderData) -> String {
+    let slide_data = render_data.slide_data(global, slide_idx, None);
+    let (width, height) = slide_data.dimensions;
+    let mut svg = svg::Document::new(width, height);
+
+    let whole = Rect {
+        x: 0,
+        y: 0,
+        w: width,
+        h: height,
+    };
+    let background = svg_paint(&mut svg, &slide_data.background, whole, render_data);
+    svg.push(&format!(
+        r#"<rect width="{width}" height="{height}" fill="{background}"/>"#
+    ));
+
+    if slide_data.layout_error.is_some() {
+        // the message that is shown instead of the slide is drawn like it is rendered
+        svg_raster(&mut svg, slide_data.dimensions, |canvas| {
+            draw_slide(
+                global,
+                canvas,
+                slide_idx,
+                &slide_data,
+                render_data,
+                DebugDrawing::default(),
+                None,
+            )
+        });
+        return svg.finish();
+    }
+
+    svg_elements(
+        global,
+        &mut svg,
+        &slide_data.layout_rects,
+        &slide_data,
+        render_data,
+        slide_idx,
+    );
+
+    // lines and arrows go on top of everything else but the watermark, like when rendering
+    for segment in &slide_data.connectors {
+        let element = global.get_element_by_id(segment.element).unwrap();
+        let style = slide_data.styles.styles_for_element(&element);
+        let with_head = matches!(element.data(), AbstractElementData::Arrow(..));
+        let strokes = connector_strokes(segment, extract_number(&style, "head") as f32, with_head);
+        let path = strokes
+            .iter()
+            .map(|(from, to)| {
+                format!(
+                    "M{} {}L{} {}",
+                    svg::number(from.0),
+                    svg::number(from.1),
+                    svg::number(to.0),
+                    svg::number(to.1)
+                )
+            })
+            .join("");
+        let opacity = match slide_data.opacities.get(&segment.element) {
+            Some(alpha) => format!(r#" opacity="{}""#, svg::number(*alpha as f32 / 255.0)),
+            None => String::new(),
+        };
+        svg.push(&format!(
+            r#"<path d="{path}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round"{opacity}/>"#,
+            svg::colour(extract_colour(&style, "stroke")),
+            extract_number(&style, "stroke-width")
+        ));
+    }
+
+    if let Some(watermark) = global.deck().watermark {
+        svg_raster(&mut svg, slide_data.dimensions, |canvas| {
+            draw_watermark(canvas, &watermark, render_data)
+        });
+    }
+
+    svg.finish()
+}
+
+/// Adds the elements of a slide to an SVG document, see [`render_svg`]. Elements that are
+/// faded and the children of `rotate` and `opacity` are put in groups.
+fn svg_elements(
+    global: &GlobalState,
+    svg: &mut svg::Document,
+    layout_elements: &[LayoutElement],
+    slide_data: &SlideData,
+    render_data: &RenderData,
+    slide_idx: usize,
+) {
+    let mut remaining = layout_elements;
+    while let Some((rect, rest)) = remaining.split_first() {
+        remaining = rest;
+        let element = global.get_element_by_id(rect.element).unwrap();
+        let style = slide_data.styles.styles_for_element(&element);
+        let bounds = rect.max_bounds;
+
+        let faded = slide_data.opacities.get(&rect.element);
+        if let Some(alpha) = faded {
+            svg.push(&format!(
+                r#"<g opacity="{}">"#,
+                svg::number(*alpha as f32 / 255.0)
+            ));
+        }
+
+        match element.data() {
+            AbstractElementData::Row(_)
+            | AbstractElementData::Col(_)
+            | AbstractElementData::Padding(_)
+            | AbstractElementData::Grid(..)
+            | AbstractElementData::Stack(_)
+            | AbstractElementData::Flow(_)
+            | AbstractElementData::Centre(_) => {
+                svg_border(
+                    svg,
+                    SvgShape::Rect {
+                        bounds,
+                        radius: 0.0,
+                    },
+                    &style,
+                );
+            }
+            AbstractElementData::Rotate(_, child) | AbstractElementData::Opacity(_, child) => {
+                let child_ids = global.traverse(*child);
+                let child_count = remaining
+                    .iter()
+                    .take_while(|layout_element| child_ids.contains(&layout_element.element))
+                    .count();
+                let (child_elements, rest) = remaining.split_at(child_count);
+                remaining = rest;
+
+                let group = match element.data() {
+                    AbstractElementData::Rotate(angle, _) => format!(
+                        r#"<g transform="rotate({angle} {} {})">"#,
+                        svg::number(bounds.x as f32 + bounds.w as f32 / 2.0),
+                        svg::number(bounds.y as f32 + bounds.h as f32 / 2.0)
+                    ),
+                    AbstractElementData::Opacity(alpha, _) => {
+                        format!(r#"<g opacity="{}">"#, svg::number(*alpha as f32 / 255.0))
+                    }
+                    _ => unreachable!(),
+                };
+                svg.push(&group);
+                svg_elements(
+                    global,
+                    svg,
+                    child_elements,
+                    slide_data,
+                    render_data,
+                    slide_idx,
+                );
+                svg.push("</g>");
+            }
+            AbstractElementData::Text(text) | AbstractElementData::Heading(_, text) => {
+                if let Some(bg) = extract_paint(&style, "bg") {
+                    let fill = svg_paint(svg, &bg, bounds, render_data);
+                    let radius = extract_number(&style, "radius") as f32;
+                    svg.push(
+                        &SvgShape::Rect { bounds, radius }
+                            .markup(0.0, &format!(r#"fill="{fill}""#)),
+                    );
+                }
+                let text_area = bounds.with_margin(extract_number(&style, "padding"));
+                let text = expand_slide_placeholders(text, slide_idx, global.number_of_slides());
+                let (layout, fonts) = layout_text_element(
+                    render_data,
+                    slide_data,
+                    &element,
+                    &text,
+                    &style,
+                    text_area,
+                );
+
+                let clipped = extract_string(&style, "overflow") != "visible";
+                if clipped {
+                    let clip = svg.clip((
+                        bounds.x as f32,
+                        bounds.y as f32,
+                        bounds.w as f32,
+                        bounds.h as f32,
+                    ));
+                    svg.push(&format!(r#"<g clip-path="{clip}">"#));
+                }
+                svg_text(
+                    svg,
+                    &layout,
+                    &fonts,
+                    extract_colour(&style, "fill"),
+                    text_area,
+                    TextSettings::from_style(&style),
+                );
+                if clipped {
+                    svg.push("</g>");
+                }
+            }
+            AbstractElementData::Rect(_) => {
+                let radius = extract_number(&style, "radius") as f32;
+                svg_filled_and_stroked(
+                    svg,
+                    SvgShape::Rect { bounds, radius },
+                    bounds,
+                    &style,
+                    render_data,
+                );
+            }
+            AbstractElementData::Ellipse(_) | AbstractElementData::Circle(_) => {
+                let radii = match element.data() {
+                    AbstractElementData::Circle(_) => {
+                        let radius = bounds.w.min(bounds.h) as f32 / 2.0;
+                        (radius, radius)
+                    }
+                    _ => (bounds.w as f32 / 2.0, bounds.h as f32 / 2.0),
+                };
+                let centre = (
+                    bounds.x as f32 + bounds.w as f32 / 2.0,
+                    bounds.y as f32 + bounds.h as f32 / 2.0,
+                );
+                svg_filled_and_stroked(
+                    svg,
+                    SvgShape::Ellipse { centre, radii },
+                    bounds,
+                    &style,
+                    render_data,
+                );
+            }
+            AbstractElementData::Frame(_) => {
+                let radius = extract_number(&style, "radius") as f32;
+                svg_outline(
+                    svg,
+                    SvgShape::Rect { bounds, radius },
+                    extract_colour(&style, "stroke"),
+                    extract_number(&style, "width") as f32,
+                );
+            }
+            // images with rounded corners or in a circle are drawn from their masked pixels
+            AbstractElementData::Image(path)
+                if extract_number(&style, "radius") == 0 && !extract_boolean(&style, "circle") =>
+            {
+                let image = render_data.images.get(&element.id()).unwrap();
+                let (src, dst) = fit_rects(&extract_string(&style, "fit"), image.size(), bounds);
+                match std::fs::read(path) {
+                    Ok(file) => {
+                        let (width, height) = image.size();
+                        svg.push(&format!(
+                            r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"><image width="{width}" height="{height}" href="{}"/></svg>"#,
+                            dst.x,
+                            dst.y,
+                            dst.w,
+                            dst.h,
+                            src.x,
+                            src.y,
+                            src.w,
+                            src.h,
+                            svg::data_uri(svg::image_mime_type(path), &file)
+                        ));
+                        svg_border(
+                            svg,
+                            SvgShape::Rect {
+                                bounds,
+                                radius: 0.0,
+                            },
+                            &style,
+                        );
+                    }
+                    Err(_) => svg_raster(svg, slide_data.dimensions, |canvas| {
+                        draw_elements(
+                            global,
+                            canvas,
+                            std::slice::from_ref(rect),
+                            slide_data,
+                            render_data,
+                            slide_idx,
+                            true,
+                        )
+                    }),
+                }
+            }
+            _ => svg_raster(svg, slide_data.dimensions, |canvas| {
+                draw_elements(
+                    global,
+                    canvas,
+                    std::slice::from_ref(rect),
+                    slide_data,
+                    render_data,
+                    slide_idx,
+                    true,
+                )
+            }),
+        }
+
+        if faded.is_some() {
+            svg.push("</g>");
+        }
+    }
+}
+
+/// The fill of an SVG shape that paints `bounds` like [`draw_painted_shape`] does. Patterns
+/// whose image could not be loaded are not painted.
+fn svg_paint(
+    svg: &mut svg::Document,
+    paint: &Paint,
+    bounds: Rect,
+    render_data: &RenderData,
+) -> String {
+    match *paint {
+        Paint::Solid(colour) => svg::colour(colour),
+        Paint::Gradient { from, to, angle } => {
+            let (sin, cos) = (angle as f32).to_radians().sin_cos();
+            let extent = (bounds.w as f32 * cos.abs() + bounds.h as f32 * sin.abs()).max(1.0);
+            let centre_x = bounds.x as f32 + bounds.w as f32 / 2.0;
+            let centre_y = bounds.y as f32 + bounds.h as f32 / 2.0;
+            let (half_x, half_y) = (cos * extent / 2.0, sin * extent / 2.0);
+            svg.linear_gradient(
+                from,
+                to,
+                (centre_x - half_x, centre_y - half_y),
+                (centre_x + half_x, centre_y + half_y),
+            )
+        }
+        Paint::Pattern { ref image, scale } => {
+            let (Some(tile), Ok(file)) = (render_data.patterns.get(image), std::fs::read(image))
+            else {
+                return String::from("none");
+            };
+            svg.pattern(
+                (bounds.x as f32, bounds.y as f32),
+                (tile.width as f32 * scale, tile.height as f32 * scale),
+                &svg::data_uri(svg::image_mime_type(image), &file),
+            )
+        }
+    }
+}
+
+/// The outline of a shape in an SVG document.
+#[derive(Clone, Copy)]
+enum SvgShape {
+    Rect {
+        bounds: Rect,
+        radius: f32,
+    },
+    Ellipse {
+        centre: (f32, f32),
+        radii: (f32, f32),
+    },
+}
+
+impl SvgShape {
+    /// The shape with the given attributes, shrunk by `inset` on every side, which puts a stroke
+    /// that is twice as wide on the inside of its edge, where it is drawn when rendering.
+    fn markup(self, inset: f32, attributes: &str) -> String {
+        match self {
+            SvgShape::Rect { bounds, radius } => {
+                let width = (bounds.w as f32 - 2.0 * inset).max(0.0);
+                let height = (bounds.h as f32 - 2.0 * inset).max(0.0);
+                let radius = (radius - inset).clamp(0.0, width.min(height) / 2.0);
+                format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {attributes}/>"#,
+                    svg::number(bounds.x as f32 + inset),
+                    svg::number(bounds.y as f32 + inset),
+                    svg::number(width),
+                    svg::number(height),
+                    svg::number(radius)
+                )
+            }
+            SvgShape::Ellipse { centre, radii } => format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {attributes}/>"#,
+                svg::number(centre.0),
+                svg::number(centre.1),
+                svg::number((radii.0 - inset).max(0.0)),
+                svg::number((radii.1 - inset).max(0.0))
+            ),
+        }
+    }
+}
+
+/// Adds an outline of the given width on the inside of the edge of a shape.
+fn svg_outline(svg: &mut svg::Document, shape: SvgShape, colour: (u8, u8, u8), width: f32) {
+    if width > 0.0 {
+        svg.push(&shape.markup(
+            width / 2.0,
+            &format!(
+                r#"fill="none" stroke="{}" stroke-width="{}""#,
+                svg::colour(colour),
+                svg::number(width)
+            ),
+        ));
+    }
+}
+
+/// Adds the fill and the outline of a shape, like [`draw_filled_and_stroked`] draws them.
+fn svg_filled_and_stroked(
+    svg: &mut svg::Document,
+    shape: SvgShape,
+    bounds: Rect,
+    style: &HashMap<String, PropertyValue>,
+    render_data: &RenderData,
+) {
+    if let Some(fill) = extract_paint(style, "fill") {
+        let fill = svg_paint(svg, &fill, bounds, render_data);
+        svg.push(&shape.markup(0.0, &format!(r#"fill="{fill}""#)));
+    }
+    if let Some(stroke) = extract_optional_colour(style, "stroke") {
+        svg_outline(
+            svg,
+            shape,
+            stroke,
+            extract_number(style, "stroke-width") as f32,
+        );
+    }
+}
+
+/// Adds the border of an element, like [`draw_border`] draws it.
+fn svg_border(svg: &mut svg::Document, shape: SvgShape, style: &HashMap<String, PropertyValue>) {
+    let colour = if style.contains_key("border-colour") {
+        extract_colour(style, "border-colour")
+    } else {
+        (0, 0, 0)
+    };
+    let width = extract_optional_number(style, "border-width").unwrap_or(0) as f32;
+    svg_outline(svg, shape, colour, width);
+}
+
+/// Adds text that has been laid out to an SVG document as text in the fonts it is set in, with
+/// every character where its glyph is drawn when rendering. Text in fonts whose file is not
+/// known falls back to a generic font.
+fn svg_text(
+    svg: &mut svg::Document,
+    layout: &fontdue::layout::Layout,
+    fonts: &[TextFont],
+    colour: (u8, u8, u8),
+    area: Rect,
+    settings: TextSettings,
+) {
+    let xs = glyph_xs(layout, fonts, area, settings);
+    let glyphs = layout.glyphs();
+    let hyphenated = if settings.hyphenate {
+        hyphenated_glyphs(layout)
+    } else {
+        Vec::new()
+    };
+    let decoration = match (settings.underline, settings.strike) {
+        (true, true) => r#" text-decoration="underline line-through""#,
+        (true, false) => r#" text-decoration="underline""#,
+        (false, true) => r#" text-decoration="line-through""#,
+        (false, false) => "",
+    };
+
+    for line in layout.lines().into_iter().flatten() {
+        // characters in the same font and size are set together, each at its own position
+        let mut runs: Vec<(usize, f32, String, Vec<String>)> = Vec::new();
+        for idx in line.glyph_start..=line.glyph_end {
+            let glyph = &glyphs[idx];
+            let metrics =
+                fonts[glyph.font_index].metrics_indexed(glyph.key.glyph_index, glyph.key.px);
+            let pen = (xs[idx] - metrics.xmin) as f32;
+
+            // like when rendering, hyphens are only drawn where words are broken
+            let characters = if glyph.parent == '\u{ad}' {
+                (idx == line.glyph_end)
+                    .then_some(('-', xs[idx] as f32))
+                    .into_iter()
+                    .collect_vec()
+            } else if glyph.parent.is_control() {
+                Vec::new()
+            } else if idx == line.glyph_end && hyphenated.contains(&idx) {
+                vec![(glyph.parent, pen), ('-', pen + metrics.advance_width)]
+            } else {
+                vec![(glyph.parent, pen)]
+            };
+
+            for (character, x) in characters {
+                match runs.last_mut() {
+                    Some((font_index, px, text, xs))
+                        if *font_index == glyph.font_index && *px == glyph.key.px =>
+                    {
+                        text.push(character);
+                        xs.push(svg::number(x));
+                    }
+                    _ => runs.push((
+                        glyph.font_index,
+                        glyph.key.px,
+                        character.to_string(),
+                        vec![svg::number(x)],
+                    )),
+                }
+            }
+        }
+
+        for (font_index, px, text, xs) in runs {
+            let font = &fonts[font_index];
+            let family = svg
+                .font(font.file_hash(), || font_file(font))
+                .unwrap_or_else(|| String::from("serif"));
+            svg.push(&format!(
+                r#"<text x="{}" y="{}" font-family="{family}" font-size="{}" fill="{}" xml:space="preserve"{decoration}>{}</text>"#,
+                xs.join(" "),
+                svg::number(line.baseline_y + area.y as f32),
+                svg::number(px),
+                svg::colour(colour),
+                svg::escape(&text)
+            ));
+        }
+    }
+}
+
+/// Draws what has no vector form to a transparent canvas the size of the slide, and adds the
+/// part of it that has been drawn to as an embedded image.
+fn svg_raster(
+    svg: &mut svg::Document,
+    (width, height): (u32, u32),
+    draw: impl FnOnce(&mut SoftwareCanvas),
+) {
+    let mut canvas = SoftwareCanvas::new(width, height);
+    draw(&mut canvas);
+    let pixels = canvas.pixels();
+
+    let row_length = width as usize;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+    for (idx, pixel) in pixels.chunks_exact(4).enumerate() {
+        if pixel[3] > 0 {
+            let (x, y) = (idx % row_length, idx / row_length);
+            (min_x, min_y) = (min_x.min(x), min_y.min(y));
+            (max_x, max_y) = (max_x.max(x), max_y.max(y));
+        }
+    }
+    if min_x > max_x {
+        return;
+    }
+
+    let (crop_width, crop_height) = (max_x - min_x + 1, max_y - min_y + 1);
+    let rgba = (min_y..=max_y)
+        .flat_map(|y| &pixels[(y * row_length + min_x) * 4..(y * row_length + max_x + 1) * 4])
+        .copied()
+        .collect_vec();
+    let png = raster::encode_png(crop_width as u32, crop_height as u32, &rgba)
+        .expect("PNG files hold any image that is not empty");
+    svg.image(
+        (
+            min_x as f32,
+            min_y as f32,
+            crop_width as f32,
+            crop_height as f32,
+        ),
+        &svg::data_uri("image/png", &png),
+    );
+}
+
+/// Draws what is shown over a slide while presenting: what has been drawn over it with the pen
+/// and the laser pointer. Strokes get thicker on larger targets.
+fn draw_overlay<P: Painter>(target: &mut P, slide_idx: usize, render_data: &RenderData) {
+    let (_, height) = target.output_size().unwrap();
+    let width = (height as f32 / 270.0).max(2.0);
+    let margin = width / 2.0 + 1.0;
+
+    target.set_blend_mode(BlendMode::Blend);
+    let strokes = render_data
+        .annotations
+        .get(&slide_idx)
+        .into_iter()
+        .flatten();
+    for stroke in strokes {
+        let points = stroke
+            .points
+            .iter()
+            .map(|(x, y)| (*x as f32, *y as f32))
+            .collect::<Vec<_>>();
+        // a stroke of a single point, from a click, is a dot
+        let segments = match points.as_slice() {
+            [point] => vec![(*point, *point)],
+            _ => points.iter().copied().tuple_windows().collect(),
+        };
+        for (from, to) in segments {
+            let bounds = Rect {
+                x: (from.0.min(to.0) - margin).max(0.0) as u32,
+                y: (from.1.min(to.1) - margin).max(0.0) as u32,
+                w: ((from.0 - to.0).abs() + 2.0 * margin).ceil() as u32,
+                h: ((from.1 - to.1).abs() + 2.0 * margin).ceil() as u32,
+            };
+            draw_shape(
+                target,
+                bounds,
+                stroke.colour,
+                segment_distance(from, to, width),
+            );
+        }
+    }
+
+    if let Some(at) = render_data.laser_pointer {
+        draw_laser_pointer(target, at);
+    }
+}
+
+/// Draws the laser pointer centred on a point: a red dot with a glow around it, which are
+/// larger on larger targets.
+fn draw_laser_pointer<P: Painter>(target: &mut P, at: (i32, i32)) {
+    let (_, height) = target.output_size().unwrap();
+    let radius = (height as f32 / 90.0).max(4.0);
+    let size = (radius * 4.0).ceil() as u32;
+    let centre = size as f32 / 2.0;
+
+    let pixels = (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let distance = (x as f32 + 0.5 - centre).hypot(y as f32 + 0.5 - centre);
+            // the dot is antialiased at its edge and the glow fades out to twice its radius
+            let dot = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            let glow = (1.0 - (distance - radius) / radius).clamp(0.0, 1.0).powi(2) * 0.4;
+            let alpha = dot.max(glow);
+            let light = (dot * 160.0) as u8;
+            [255, light, light, (alpha * 255.0) as u8]
+        })
+        .collect::<Vec<_>>();
+
+    let half = size as i32 / 2;
+    target.draw_pixels(
+        &pixels,
+        (size, size),
+        sdl2::rect::Rect::new(at.0 - half, at.1 - half, size, size),
+        BlendMode::Blend,
+    );
+}
+
+/// Draws the background of a slide and everything on it. Only what is inside of the clip
+/// rectangle of the target is drawn over, and with a `region`, which should lie inside of it,
+/// only the elements and the lines that draw into that are drawn at all.
+fn draw_slide<P: Painter>(
+    global: &GlobalState,
+    target: &mut P,
+    slide_idx: usize,
+    slide_data: &SlideData,
+    render_data: &RenderData,
+    debug: DebugDrawing,
+    region: Option<sdl2::rect::Rect>,
+) {
+    // unlike clearing, filling keeps to the clip rectangle
+    target.set_draw_color(slide_data.background.colour_at(0.0));
+    target.fill_rect(None).unwrap();
+    if !matches!(slide_data.background, Paint::Solid(_)) {
+        let (width, height) = target.output_size().unwrap();
+        let bounds = Rect {
+            x: 0,
+            y: 0,
+            w: width,
+            h: height,
+        };
+        draw_painted_shape(
+            target,
+            bounds,
+            &slide_data.background,
+            &render_data.patterns,
+            |_, _| f32::NEG_INFINITY,
+        );
+    }
+
+    if let Some(message) = &slide_data.layout_error {
+        let (width, height) = target.output_size().unwrap();
+        if let Some(font) = measure::font_for(&measure::FontChoice::regular(vec![String::from(
+            "sans-serif",
+        )])) {
+            draw_text(
+                target,
+                &render_data.glyphs,
+                &font,
+                message,
+                32.0,
+                (200, 30, 30),
+                Rect {
+                    x: 64,
+                    y: 64,
+                    w: width.saturating_sub(128),
+                    h: height.saturating_sub(128),
+                },
+            );
+        }
+    }
+
+    let reaching;
+    let layout_rects = match region {
+        Some(region) => {
+            reaching = render_data.layout_rects_reaching(global, slide_data, region);
+            &reaching[..]
+        }
+        None => &slide_data.layout_rects[..],
+    };
+
+    if debug.rects {
+        target.set_draw_color((255, 0, 0));
+        target
+            .draw_rects(
+                &layout_rects
+                    .iter()
+                    .map(|r| folium_to_sdl_rect(r.max_bounds))
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+    }
+
+    draw_elements(
+        global,
+        target,
+        layout_rects,
+        slide_data,
+        render_data,
+        slide_idx,
+        false,
+    );
+
+    // lines and arrows are drawn on top of everything else but the watermark
+    let connectors = slide_data.connectors.iter().filter(|segment| {
+        region.is_none_or(|region| {
+            let area = connector_area(global, slide_data, segment);
+            overlap(render_data.reach(slide_data, segment.element, area), region)
+        })
+    });
+    for segment in connectors {
+        let element = global.get_element_by_id(segment.element).unwrap();
+        let style = slide_data.styles.styles_for_element(&element);
+        let with_head = matches!(element.data(), AbstractElementData::Arrow(..));
+        let area = whole_target(target);
+        match render_data.compositing(slide_data, segment.element, area) {
+            Some((alpha, placement)) => {
+                target.composite(area, placement, 0, alpha, |canvas| {
+                    draw_connector(canvas, segment, &style, with_head)
+                });
+            }
+            None => draw_connector(target, segment, &style, with_head),
+        }
+    }
+
+    if let Some(watermark) = global.deck().watermark {
+        draw_watermark(target, &watermark, render_data);
+    }
+
+    if debug.guides {
+        draw_guides(global, target, slide_data, render_data);
+    }
+}
+
+/// What is drawn over slides to debug their layout.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugDrawing {
+    /// The bounds of every element, as red rectangles.
+    pub rects: bool,
+    /// The margins of the slide, the gaps between the elements of rows and columns and labels
+    /// with the name and ID of every element, see [`draw_guides`].
+    pub guides: bool,
+}
+
+/// Draws guides for the layout of a slide over it: the area inside of its margins in blue, the
+/// gaps between the elements of rows and columns in translucent magenta and a label with the
+/// name and ID of every element at its top left corner.
+fn draw_guides<P: Painter>(
+    global: &GlobalState,
+    target: &mut P,
+    slide_data: &SlideData,
+    render_data: &RenderData,
+) {
+    let (width, height) = target.output_size().unwrap();
+    let slide_styles = slide_data
+        .styles
+        .styles_for_target(&StyleTarget::Slide)
+        .unwrap();
+    let inside_margins = Rect::inside_slide_margins(slide_styles, width, height);
+    target.set_blend_mode(BlendMode::Blend);
+    target.set_draw_color((0, 120, 255));
+    target
+        .draw_rects(&[folium_to_sdl_rect(inside_margins)])
+        .unwrap();
+
+    // containers without a border are not laid out themselves, so they cover their contents
+    let laid_out = slide_data
+        .layout_rects
+        .iter()
+        .map(|layout_element| (layout_element.element, layout_element.max_bounds))
+        .collect::<HashMap<_, _>>();
+    let elements = global
+        .slides
+        .borrow()
+        .iter()
+        .find(|slide| slide.id() == slide_data.slide_id)
+        .map(|slide| global.get_slide_elements(slide))
+        .unwrap_or_default();
+    let bounds = elements
+        .iter()
+        .filter_map(|element| {
+            let area = global
+                .traverse(element.id())
+                .iter()
+                .filter_map(|id| laid_out.get(id))
+                .copied()
+                .reduce(|a, b| a.union(&b))?;
+            Some((element.id(), area))
+        })
+        .collect::<HashMap<_, _>>();
+
+    target.set_draw_color((255, 0, 255, 64));
+    for element in &elements {
+        let (children, horizontal) = match element.data() {
+            AbstractElementData::Row(children) => (children, true),
+            AbstractElementData::Col(children) => (children, false),
+            _ => continue,
+        };
+        let Some(&area) = bounds.get(&element.id()) else {
+            continue;
+        };
+        let children = children.iter().filter_map(|child| bounds.get(child));
+        for (before, after) in children.tuple_windows() {
+            let gap = if horizontal {
+                Rect {
+                    x: before.x + before.w,
+                    w: after.x.saturating_sub(before.x + before.w),
+                    ..area
+                }
+            } else {
+                Rect {
+                    y: before.y + before.h,
+                    h: after.y.saturating_sub(before.y + before.h),
+                    ..area
+                }
+            };
+            if gap.w > 0 && gap.h > 0 {
+                target.fill_rect(folium_to_sdl_rect(gap)).unwrap();
+            }
+        }
+    }
+
+    let Some(font) = measure::font_for(&FontChoice::regular(vec![String::from("sans-serif")]))
+    else {
+        return;
+    };
+    let font_size = (height as f32 / 80.0).max(10.0);
+    let line_height = font
+        .horizontal_line_metrics(font_size)
+        .map_or(font_size, |metrics| metrics.new_line_size)
+        .ceil() as u32;
+    // labels of elements sharing a corner, like a wrapper and its child, are stacked
+    let mut labels_at = HashMap::<(u32, u32), u32>::new();
+    for element in &elements {
+        let Some(&area) = bounds.get(&element.id()) else {
+            continue;
+        };
+        let label = match element.name() {
+            Some(name) => format!("{name} #{}", element.id().0),
+            None => format!("{:?} #{}", element.el_type(), element.id().0),
+        };
+        let stacked = labels_at.entry((area.x, area.y)).or_default();
+        let label_area = Rect {
+            y: area.y + *stacked * line_height,
+            w: text_width(&font, &label, font_size).ceil() as u32 + line_height,
+            h: line_height,
+            ..area
+        };
+        *stacked += 1;
+        target.set_draw_color((0, 0, 0, 160));
+        target.fill_rect(folium_to_sdl_rect(label_area)).unwrap();
+        draw_text(
+            target,
+            &render_data.glyphs,
+            &font,
+            &label,
+            font_size,
+            (255, 255, 255),
+            Rect {
+                x: label_area.x + line_height / 2,
+                ..label_area
+            },
+        );
+    }
+}
+
+/// Draws the watermark of the deck over the target, at its position, away from the edges.
+/// Text is set in a sans-serif font in grey, which shows on light and dark slides alike.
+fn draw_watermark<P: Painter>(target: &mut P, watermark: &Watermark, render_data: &RenderData) {
+    let area = whole_target(target);
+    let font = measure::font_for(&FontChoice::regular(vec![String::from("sans-serif")]));
+    let size = match (&watermark.mark, font.as_deref()) {
+        (WatermarkMark::Text { text, size }, Some(font)) => {
+            let metrics = font.horizontal_line_metrics(*size as f32).unwrap();
+            (
+                text_width(font, text, *size as f32).ceil() as u32,
+                metrics.new_line_size.ceil() as u32,
+            )
+        }
+        (WatermarkMark::Image(_), _) => match &render_data.watermark_image {
+            Some(image) => image.size(),
+            None => return,
+        },
+        _ => return,
+    };
+
+    let margin = (area.h / 20) as i32;
+    let place = |position: &str, start: &str, end: &str, size: u32, room: u32| {
+        if position.starts_with(start) || position.ends_with(start) {
+            margin
+        } else if position.starts_with(end) || position.ends_with(end) {
+            room as i32 - size as i32 - margin
+        } else {
+            (room as i32 - size as i32) / 2
+        }
+        .max(0) as u32
+    };
+    // text and images alike are drawn into this area, which is then faded as a whole
+    let mark = Rect {
+        x: place(&watermark.position, "left", "right", size.0, area.w),
+        y: place(&watermark.position, "top", "bottom", size.1, area.h),
+        w: size.0.max(1),
+        h: size.1.max(1),
+    };
+    // text gets some room to spare, as it can come out a little wider than it was measured,
+    // which would break it onto a line below the area
+    let layer = match watermark.mark {
+        WatermarkMark::Text { .. } => Rect {
+            w: mark.w + mark.h,
+            ..mark
+        },
+        WatermarkMark::Image(_) => mark,
+    };
+    target.composite(
+        layer,
+        folium_to_sdl_rect(layer),
+        0,
+        watermark.alpha,
+        |canvas| match (&watermark.mark, &render_data.watermark_image) {
+            (WatermarkMark::Text { text, size }, _) => draw_text(
+                canvas,
+                &render_data.glyphs,
+                &font.unwrap(),
+                text,
+                *size as f32,
+                (128, 128, 128),
+                layer,
+            ),
+            (WatermarkMark::Image(_), Some(image)) => {
+                canvas.draw_image(image, None, folium_to_sdl_rect(mark))
+            }
+            _ => (),
+        },
+    );
+}
+
+/// Canvases that textures can be created for. SDL only offers this for the canvases of windows
+/// and surfaces, rather than for any render target.
+pub trait TextureSource {
+    type Context;
+    fn texture_source(&self) -> TextureCreator<Self::Context>;
+}
+
+impl TextureSource for Canvas<Window> {
+    type Context = WindowContext;
+    fn texture_source(&self) -> TextureCreator<WindowContext> {
+        self.texture_creator()
+    }
+}
+
+impl<'s> TextureSource for Canvas<Surface<'s>> {
+    type Context = SurfaceContext<'s>;
+    fn texture_source(&self) -> TextureCreator<SurfaceContext<'s>> {
+        self.texture_creator()
+    }
+}
+
+/// What slides are drawn onto: the canvas of a window or a surface, which SDL draws onto, or a
+/// [`SoftwareCanvas`], which does not need SDL to draw onto. The methods that canvases have
+/// work like those of a canvas.
+pub trait Painter {
+    fn output_size(&self) -> Result<(u32, u32), String>;
+    fn set_draw_color<C: Into<Color>>(&mut self, colour: C);
+    fn set_blend_mode(&mut self, blend: BlendMode);
+    fn set_clip_rect<R: Into<Option<sdl2::rect::Rect>>>(&mut self, rect: R);
+    fn clip_rect(&self) -> Option<sdl2::rect::Rect>;
+    fn fill_rect<R: Into<Option<sdl2::rect::Rect>>>(&mut self, rect: R) -> Result<(), String>;
+
+    fn draw_point<P: Into<Point>>(&mut self, point: P) -> Result<(), String> {
+        let point = point.into();
+        self.fill_rect(sdl2::rect::Rect::new(point.x(), point.y(), 1, 1))
+    }
+
+    /// Draws the outlines of rects, one pixel wide.
+    fn draw_rects(&mut self, rects: &[sdl2::rect::Rect]) -> Result<(), String> {
+        for rect in rects {
+            let (x, y, width, height) = (rect.x(), rect.y(), rect.width(), rect.height());
+            self.fill_rect(sdl2::rect::Rect::new(x, y, width, 1))?;
+            self.fill_rect(sdl2::rect::Rect::new(x, rect.bottom() - 1, width, 1))?;
+            self.fill_rect(sdl2::rect::Rect::new(x, y, 1, height))?;
+            self.fill_rect(sdl2::rect::Rect::new(rect.right() - 1, y, 1, height))?;
+        }
+        Ok(())
+    }
+
+    /// Draws RGBA pixels, row by row, of an image of some size into `dst`.
+    fn draw_pixels(
+        &mut self,
+        pixels: &[u8],
+        size: (u32, u32),
+        dst: sdl2::rect::Rect,
+        blend: BlendMode,
+    );
+
+    /// Draws the part `src` of the image of an image or video element, or all of it, into `dst`.
+    fn draw_image(
+        &mut self,
+        image: &ElementImage,
+        src: Option<sdl2::rect::Rect>,
+        dst: sdl2::rect::Rect,
+    );
+
+    /// Draws to an intermediate layer as large as the target and copies the part of it inside
+    /// of `area` to `placement` on the target, rotated clockwise by `angle` degrees around its
+    /// centre and with its alpha multiplied by `alpha`.
+    fn composite(
+        &mut self,
+        area: Rect,
+        placement: sdl2::rect::Rect,
+        angle: u32,
+        alpha: u8,
+        draw: impl FnOnce(&mut Self),
+    );
+
+    /// Whether what is drawn goes to an intermediate layer, rather than straight to the target.
+    fn draws_to_layer(&self) -> bool;
+
+    /// The RGBA pixels of everything that has been drawn, row by row.
+    fn pixels(&self) -> Vec<u8>;
+
+    /// Shows what has been drawn, if the target is shown anywhere.
+    fn present(&mut self);
+}
+
+impl<T: RenderTarget> Painter for Canvas<T>
+where
+    Canvas<T>: TextureSource,
+{
+    fn output_size(&self) -> Result<(u32, u32), String> {
+        Canvas::output_size(self)
+    }
+
+    fn set_draw_color<C: Into<Color>>(&mut self, colour: C) {
+        Canvas::set_draw_color(self, colour)
+    }
+
+    fn set_blend_mode(&mut self, blend: BlendMode) {
+        Canvas::set_blend_mode(self, blend)
+    }
+
+    fn set_clip_rect<R: Into<Option<sdl2::rect::Rect>>>(&mut self, rect: R) {
+        Canvas::set_clip_rect(self, rect)
+    }
+
+    fn clip_rect(&self) -> Option<sdl2::rect::Rect> {
+        Canvas::clip_rect(self)
+    }
+
+    fn fill_rect<R: Into<Option<sdl2::rect::Rect>>>(&mut self, rect: R) -> Result<(), String> {
+        Canvas::fill_rect(self, rect)
+    }
+
+    fn draw_point<P: Into<Point>>(&mut self, point: P) -> Result<(), String> {
+        Canvas::draw_point(self, point)
+    }
+
+    fn draw_rects(&mut self, rects: &[sdl2::rect::Rect]) -> Result<(), String> {
+        Canvas::draw_rects(self, rects)
+    }
+
+    fn draw_pixels(
+        &mut self,
+        pixels: &[u8],
+        size: (u32, u32),
+        dst: sdl2::rect::Rect,
+        blend: BlendMode,
+    ) {
+        let texture_creator = self.texture_source();
+        let mut texture = texture_creator
+            .create_texture_static(PixelFormatEnum::RGBA32, size.0, size.1)
+            .unwrap();
+        texture.update(None, pixels, size.0 as usize * 4).unwrap();
+        texture.set_blend_mode(blend);
+        self.copy(&texture, None, dst).unwrap();
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &ElementImage,
+        src: Option<sdl2::rect::Rect>,
+        dst: sdl2::rect::Rect,
+    ) {
+        match image {
+            ElementImage::Texture(texture) => self.copy(texture, src, dst).unwrap(),
+            ElementImage::Pixels { pixels, .. } => {
+                let texture_creator = self.texture_source();
+                let mut texture = texture_creator
+                    .create_texture_static(PixelFormatEnum::RGBA32, pixels.width, pixels.height)
+                    .unwrap();
+                texture
+                    .update(None, &pixels.rgba, pixels.width as usize * 4)
+                    .unwrap();
+                texture.set_blend_mode(BlendMode::Blend);
+                self.copy(&texture, src, dst).unwrap();
+            }
+        }
+    }
+
+    fn composite(
+        &mut self,
+        area: Rect,
+        placement: sdl2::rect::Rect,
+        angle: u32,
+        alpha: u8,
+        draw: impl FnOnce(&mut Self),
+    ) {
+        let (width, height) = Canvas::output_size(self).unwrap();
+        let texture_creator = self.texture_source();
+        let mut texture = texture_creator
+            .create_texture_target(PixelFormatEnum::RGBA8888, width, height)
+            .unwrap();
+        texture.set_blend_mode(BlendMode::Blend);
+        texture.set_alpha_mod(alpha);
+
+        self.with_texture_canvas(&mut texture, |canvas| {
+            canvas.set_blend_mode(BlendMode::Blend);
+            canvas.set_draw_color((0, 0, 0, 0));
+            canvas.clear();
+            draw(canvas);
+        })
+        .unwrap();
+
+        self.copy_ex(
+            &texture,
+            folium_to_sdl_rect(area),
+            placement,
+            angle as f64,
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+    }
+
+    fn draws_to_layer(&self) -> bool {
+        // SAFETY: the renderer belongs to the canvas, which outlives the call
+        unsafe { !sdl2::sys::SDL_GetRenderTarget(self.raw()).is_null() }
+    }
+
+    fn pixels(&self) -> Vec<u8> {
+        self.read_pixels(None, PixelFormatEnum::RGBA32).unwrap()
+    }
+
+    fn present(&mut self) {
+        Canvas::present(self)
+    }
+}
+
+/// The image of an image or video element, as whatever it is drawn from.
+pub enum ElementImage<'a> {
+    /// A texture, for canvases.
+    Texture(Texture<'a>),
+    /// Pixels, for [`SoftwareCanvas`]es, with whether they are interpolated when scaled.
+    Pixels { pixels: ImagePixels, smooth: bool },
+}
+
+impl ElementImage<'_> {
+    fn size(&self) -> (u32, u32) {
+        match self {
+            ElementImage::Texture(texture) => {
+                let query = texture.query();
+                (query.width, query.height)
+            }
+            ElementImage::Pixels { pixels, .. } => (pixels.width, pixels.height),
+        }
+    }
+}
+
+/// A canvas in memory that is drawn onto without SDL, with [`raster::Pixmap`], for rendering
+/// slides without a video driver. It keeps the draw colour, blend mode and clip rect like a
+/// canvas does.
+pub struct SoftwareCanvas {
+    pixmap: Pixmap,
+    colour: Color,
+    blend: BlendMode,
+    clip: Option<sdl2::rect::Rect>,
+    layer: bool,
+}
+
+impl SoftwareCanvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            pixmap: Pixmap::new(width, height),
+            colour: Color::RGB(255, 255, 255),
+            blend: BlendMode::Blend,
+            clip: None,
+            layer: false,
+        }
+    }
+}
+
+fn raster_blend(blend: BlendMode) -> Blend {
+    match blend {
+        BlendMode::None => Blend::Replace,
+        BlendMode::Mod | BlendMode::Mul => Blend::Multiply,
+        BlendMode::Add => Blend::Add,
+        _ => Blend::Over,
+    }
+}
+
+impl Painter for SoftwareCanvas {
+    fn output_size(&self) -> Result<(u32, u32), String> {
+        Ok(self.pixmap.size())
+    }
+
+    fn set_draw_color<C: Into<Color>>(&mut self, colour: C) {
+        self.colour = colour.into();
+    }
+
+    fn set_blend_mode(&mut self, blend: BlendMode) {
+        self.blend = blend;
+    }
+
+    fn set_clip_rect<R: Into<Option<sdl2::rect::Rect>>>(&mut self, rect: R) {
+        self.clip = rect.into();
+        self.pixmap.set_clip(self.clip);
+    }
+
+    fn clip_rect(&self) -> Option<sdl2::rect::Rect> {
+        self.clip
+    }
+
+    fn fill_rect<R: Into<Option<sdl2::rect::Rect>>>(&mut self, rect: R) -> Result<(), String> {
+        let Color { r, g, b, a } = self.colour;
+        self.pixmap
+            .fill_rect(rect.into(), [r, g, b, a], raster_blend(self.blend));
+        Ok(())
+    }
+
+    fn draw_pixels(
+        &mut self,
+        pixels: &[u8],
+        size: (u32, u32),
+        dst: sdl2::rect::Rect,
+        blend: BlendMode,
+    ) {
+        let image = raster::Image {
+            width: size.0,
+            height: size.1,
+            rgba: pixels,
+        };
+        self.pixmap.draw_image(
+            image,
+            raster::Placement {
+                src: sdl2::rect::Rect::new(0, 0, size.0, size.1),
+                dst,
+                angle: 0.0,
+                alpha: u8::MAX,
+                blend: raster_blend(blend),
+                smooth: true,
+            },
+        );
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &ElementImage,
+        src: Option<sdl2::rect::Rect>,
+        dst: sdl2::rect::Rect,
+    ) {
+        let ElementImage::Pixels { pixels, smooth } = image else {
+            unreachable!("textures are only loaded for SDL canvases");
+        };
+        self.pixmap.draw_image(
+            raster::Image {
+                width: pixels.width,
+                height: pixels.height,
+                rgba: &pixels.rgba,
+            },
+            raster::Placement {
+                src: src.unwrap_or(sdl2::rect::Rect::new(0, 0, pixels.width, pixels.height)),
+                dst,
+                angle: 0.0,
+                alpha: u8::MAX,
+                blend: Blend::Over,
+                smooth: *smooth,
+            },
+        );
+    }
+
+    fn composite(
+        &mut self,
+        area: Rect,
+        placement: sdl2::rect::Rect,
+        angle: u32,
+        alpha: u8,
+        draw: impl FnOnce(&mut Self),
+    ) {
+        let (width, height) = self.pixmap.size();
+        let mut layer = SoftwareCanvas {
+            layer: true,
+            ..SoftwareCanvas::new(width, height)
+        };
+        draw(&mut layer);
+
+        self.pixmap.draw_pixmap(
+            &layer.pixmap,
+            raster::Placement {
+                src: folium_to_sdl_rect(area),
+                dst: placement,
+                angle: angle as f64,
+                alpha,
+                blend: Blend::Over,
+                smooth: true,
+            },
+        );
+    }
+
+    fn draws_to_layer(&self) -> bool {
+        self.layer
+    }
+
+    fn pixels(&self) -> Vec<u8> {
+        self.pixmap.to_rgba()
+    }
+
+    fn present(&mut self) {}
+}
+
+/// The area of the whole target, in which everything that is drawn to it ends up.
+fn whole_target<P: Painter>(target: &P) -> Rect {
+    let (width, height) = target.output_size().unwrap();
+    Rect {
+        x: 0,
+        y: 0,
+        w: width,
+        h: height,
+    }
+}
+
+/// The area that a laid out element draws over: its bounds, along with those of the shadow
+/// that it casts if it is a shadow.
+fn drawn_area(global: &GlobalState, slide_data: &SlideData, rect: &LayoutElement) -> Rect {
+    let element = global.get_element_by_id(rect.element).unwrap();
+    let style = slide_data.styles.styles_for_element(&element);
+    match element.data() {
+        AbstractElementData::Shadow(_) => rect
+            .max_bounds
+            .union(&Rect {
+                x: rect.max_bounds.x + extract_number(&style, "offset-x"),
+                y: rect.max_bounds.y + extract_number(&style, "offset-y"),
+                ..rect.max_bounds
+            })
+            .expand(extract_number(&style, "blur")),
+        _ => rect.max_bounds,
+    }
+}
+
+/// The area that a line or an arrow draws over, with its stroke and its head, as far as the top
+/// and the left of the slide.
+fn connector_area(
+    global: &GlobalState,
+    slide_data: &SlideData,
+    segment: &ConnectorSegment,
+) -> Rect {
+    let element = global.get_element_by_id(segment.element).unwrap();
+    let style = slide_data.styles.styles_for_element(&element);
+    let reach = extract_number(&style, "stroke-width")
+        + extract_optional_number(&style, "head").unwrap_or(0);
+    let (left, right) = (
+        segment.from.0.min(segment.to.0),
+        segment.from.0.max(segment.to.0),
+    );
+    let (top, bottom) = (
+        segment.from.1.min(segment.to.1),
+        segment.from.1.max(segment.to.1),
+    );
+    let (left, top) = (left.max(0.0), top.max(0.0));
+    Rect {
+        x: left as u32,
+        y: top as u32,
+        w: (right - left).max(0.0).ceil() as u32,
+        h: (bottom - top).max(0.0).ceil() as u32,
+    }
+    .expand(reach)
+}
+
+/// Whether two rectangles share any pixels.
+fn overlap(a: sdl2::rect::Rect, b: sdl2::rect::Rect) -> bool {
+    a.left() < b.right() && b.left() < a.right() && a.top() < b.bottom() && b.top() < a.bottom()
+}
+
+/// Where the whole of `area` is drawn in a frame of an animation, which scales it around
+/// `centre`.
+fn frame_placement(area: Rect, frame: Frame, centre: (f32, f32)) -> sdl2::rect::Rect {
+    let place = |start: u32, centre: f32, offset: f32| {
+        (centre + (start as f32 - centre) * frame.scale + offset).round() as i32
+    };
+    sdl2::rect::Rect::new(
+        place(area.x, centre.0, frame.offset.0),
+        place(area.y, centre.1, frame.offset.1),
+        (area.w as f32 * frame.scale).round().max(1.0) as u32,
+        (area.h as f32 * frame.scale).round().max(1.0) as u32,
+    )
+}
+
+/// Blurs a single channel image in place with three passes of a box blur in both directions,
+/// which comes close to a gaussian blur.
+fn blur(image: &mut [f32], width: usize, height: usize, radius: usize) {
+    let box_radius = radius / 3;
+    if box_radius == 0 {
+        return;
+    }
+
+    let window = (2 * box_radius + 1) as f32;
+    let mut line = Vec::new();
+    for _ in 0..3 {
+        for (count, stride, step) in [(height, width, 1), (width, 1, width)] {
+            let length = if step == 1 { width } else { height };
+            for start in (0..count).map(|idx| idx * stride) {
+                line.clear();
+                line.extend((0..length).map(|idx| image[start + idx * step]));
+
+                // a running sum over the window, where everything outside of the image is 0
+                let mut sum = line.iter().take(box_radius).sum::<f32>();
+                for idx in 0..length {
+                    if idx + box_radius < length {
+                        sum += line[idx + box_radius];
+                    }
+                    image[start + idx * step] = sum / window;
+                    if idx >= box_radius {
+                        sum -= line[idx - box_radius];
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws a soft shadow of a (rounded) rectangle. The shape is blurred offscreen and copied to
+/// the target as a texture.
+fn draw_shadow<P: Painter>(target: &mut P, area: Rect, style: &HashMap<String, PropertyValue>) {
+    let colour = extract_colour(style, "fill");
+    let opacity = extract_decimal(style, "opacity").clamp(0.0, 1.0);
+    let blur_radius = extract_number(style, "blur");
+    let radius = extract_number(style, "radius") as f32;
+
+    // the blur spreads the shape out by its radius on every side
+    let width = area.w + 2 * blur_radius;
+    let height = area.h + 2 * blur_radius;
+    let shape = rounded_rect_distance(
+        Rect {
+            x: blur_radius,
+            y: blur_radius,
+            ..area
+        },
+        radius,
+    );
+    let mut image = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| (0.5 - shape(x as f32 + 0.5, y as f32 + 0.5)).clamp(0.0, 1.0))
+        .collect::<Vec<_>>();
+    blur(
+        &mut image,
+        width as usize,
+        height as usize,
+        blur_radius as usize,
+    );
+
+    let pixels = image
+        .iter()
+        .flat_map(|coverage| {
+            [
+                colour.0,
+                colour.1,
+                colour.2,
+                (coverage * opacity * 255.0) as u8,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let x = area.x as i32 + extract_number(style, "offset-x") as i32 - blur_radius as i32;
+    let y = area.y as i32 + extract_number(style, "offset-y") as i32 - blur_radius as i32;
+    target.draw_pixels(
+        &pixels,
+        (width, height),
+        sdl2::rect::Rect::new(x, y, width, height),
+        sdl2::render::BlendMode::Blend,
+    );
+}
+
+/// Draws laid out elements in order. The elements inside of a rotation or an opacity wrapper
+/// are drawn separately and then copied to the target, see [`Painter::composite`]. So are elements
+/// with an `opacity` or in the middle of their animation, unless `composited` is set because
+/// that is what is being done; wrappers are not, as the elements inside of them follow them.
+fn draw_elements<P: Painter>(
+    global: &GlobalState,
+    target: &mut P,
+    layout_elements: &[LayoutElement],
+    slide_data: &SlideData,
+    render_data: &RenderData,
+    slide_idx: usize,
+    composited: bool,
+) {
+    let mut remaining = layout_elements;
+    while let Some((rect, rest)) = remaining.split_first() {
+        remaining = rest;
+        let element = global.get_element_by_id(rect.element).unwrap();
+
+        let is_wrapper = matches!(
+            element.data(),
+            AbstractElementData::Rotate(..) | AbstractElementData::Opacity(..)
+        );
+        let area = whole_target(target);
+        if let Some((alpha, placement)) = render_data.compositing(slide_data, rect.element, area) {
+            if !composited && !is_wrapper {
+                target.composite(area, placement, 0, alpha, |canvas| {
+                    draw_elements(
+                        global,
+                        canvas,
+                        std::slice::from_ref(rect),
+                        slide_data,
+                        render_data,
+                        slide_idx,
+                        true,
+                    )
+                });
+                continue;
+            }
+        }
+
+        match element.data() {
+            AbstractElementData::Sized(..) => {
+                panic!("Sized should never have a layout element of its own")
+            }
+            AbstractElementData::Place(..) => {
+                panic!("Place should never have a layout element of its own")
+            }
+            // containers only have a layout element of their own when they have a border
+            AbstractElementData::Row(_)
+            | AbstractElementData::Col(_)
+            | AbstractElementData::Padding(_)
+            | AbstractElementData::Grid(..)
+            | AbstractElementData::Stack(_)
+            | AbstractElementData::Flow(_)
+            | AbstractElementData::Centre(_) => {
+                let container_style = &slide_data.styles.styles_for_element(&element);
+                draw_border(
+                    target,
+                    rect.max_bounds,
+                    container_style,
+                    rounded_rect_distance(rect.max_bounds, 0.0),
+                );
+            }
+            AbstractElementData::Rotate(_, child) | AbstractElementData::Opacity(_, child) => {
+                // the elements of the child come right after the wrapper
+                let child_ids = global.traverse(*child);
+                let child_count = remaining
+                    .iter()
+                    .take_while(|layout_element| child_ids.contains(&layout_element.element))
+                    .count();
+                let (child_elements, rest) = remaining.split_at(child_count);
+                remaining = rest;
+
+                let (angle, alpha) = match element.data() {
+                    AbstractElementData::Rotate(angle, _) => (*angle, u8::MAX),
+                    AbstractElementData::Opacity(alpha, _) => (0, *alpha),
+                    _ => unreachable!(),
+                };
+                let placement = folium_to_sdl_rect(rect.max_bounds);
+                target.composite(rect.max_bounds, placement, angle, alpha, |canvas| {
+                    draw_elements(
+                        global,
+                        canvas,
+                        child_elements,
+                        slide_data,
+                        render_data,
+                        slide_idx,
+                        false,
+                    )
+                });
+            }
+            // headings are text with their own default style
+            AbstractElementData::Text(text_to_be_rendered)
+            | AbstractElementData::Heading(_, text_to_be_rendered) => {
+                let text_style = &slide_data.styles.styles_for_element(&element);
+
+                target.set_blend_mode(sdl2::render::BlendMode::Blend);
+                if let Some(bg) = extract_paint(text_style, "bg") {
+                    let radius = extract_number(text_style, "radius") as f32;
+                    draw_painted_shape(
+                        target,
+                        rect.max_bounds,
+                        &bg,
+                        &render_data.patterns,
+                        rounded_rect_distance(rect.max_bounds, radius),
+                    );
+                }
+                let text_area = rect
+                    .max_bounds
+                    .with_margin(extract_number(text_style, "padding"));
+
+                let text_colour = extract_colour(text_style, "fill");
+
+                let text_to_be_rendered = expand_slide_placeholders(
+                    text_to_be_rendered,
+                    slide_idx,
+                    global.number_of_slides(),
+                );
+                let fingerprint = text_fingerprint(&text_to_be_rendered, text_style, text_area);
+                let image =
+                    render_data.text_image(slide_data.slide_id, element.id(), fingerprint, || {
+                        let settings = TextSettings::from_style(text_style);
+                        let (layout, fonts) = layout_text_element(
+                            render_data,
+                            slide_data,
+                            &element,
+                            &text_to_be_rendered,
+                            text_style,
+                            text_area,
+                        );
+
+                        let mut batch = GlyphBatch::default();
+                        batch_layout(
+                            &mut batch,
+                            &render_data.glyphs,
+                            &layout,
+                            &fonts,
+                            text_colour,
+                            text_area,
+                            settings,
+                        );
+                        batch.finish()
+                    });
+                draw_clipped(target, text_style, rect.max_bounds, |target| {
+                    image.draw(target)
+                });
+            }
+            AbstractElementData::SlideNumber => {
+                let number_style_target = StyleTarget::reify(&element);
+                let number_style = &slide_data.styles.styles_for_element(&element);
+
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, number_style_target))
+                    .unwrap();
+
+                draw_text(
+                    target,
+                    &render_data.glyphs,
+                    font,
+                    &expand_slide_placeholders(
+                        &extract_string(number_style, "format"),
+                        slide_idx,
+                        global.number_of_slides(),
+                    ),
+                    extract_number(number_style, "size") as f32,
+                    extract_colour(number_style, "fill"),
+                    rect.max_bounds,
+                );
+            }
+            AbstractElementData::Clock => {
+                let clock_style_target = StyleTarget::reify(&element);
+                let clock_style = &slide_data.styles.styles_for_element(&element);
+
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, clock_style_target))
+                    .unwrap();
+
+                draw_text(
+                    target,
+                    &render_data.glyphs,
+                    font,
+                    &clock::format_time(
+                        &extract_string(clock_style, "format"),
+                        clock::local_time(),
+                    ),
+                    extract_number(clock_style, "size") as f32,
+                    extract_colour(clock_style, "fill"),
+                    rect.max_bounds,
+                );
+            }
+            AbstractElementData::Link(label, _) => {
+                let link_style_target = StyleTarget::reify(&element);
+                let link_style = &slide_data.styles.styles_for_element(&element);
+
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, link_style_target))
+                    .unwrap();
+                let font_size = extract_number(link_style, "size") as f32;
+                let colour = extract_colour(link_style, "fill");
+
+                draw_text(
+                    target,
+                    &render_data.glyphs,
+                    font,
+                    label,
+                    font_size,
+                    colour,
+                    rect.max_bounds,
+                );
+
+                if extract_boolean(link_style, "underline") {
+                    let ascent = font
+                        .horizontal_line_metrics(font_size)
+                        .map_or(font_size, |metrics| metrics.ascent);
+                    let width = text_width(font, label, font_size).min(rect.max_bounds.w as f32);
+                    target.set_draw_color(colour);
+                    target
+                        .fill_rect(sdl2::rect::Rect::new(
+                            rect.max_bounds.x as i32,
+                            (rect.max_bounds.y as f32 + ascent + font_size / 12.0) as i32,
+                            width as u32,
+                            (font_size / 16.0).max(1.0) as u32,
+                        ))
+                        .unwrap();
+                }
+            }
+            AbstractElementData::Icon(character) => {
+                // icons that are not in any of the fonts were reported when loading the fonts
+                if let Some(font) = render_data
+                    .icon_fonts
+                    .get(&(slide_data.slide_id, element.id()))
+                {
+                    let icon_style = &slide_data.styles.styles_for_element(&element);
+                    draw_text(
+                        target,
+                        &render_data.glyphs,
+                        font,
+                        &character.to_string(),
+                        extract_number(icon_style, "size") as f32,
+                        extract_colour(icon_style, "fill"),
+                        rect.max_bounds,
+                    );
+                }
+            }
+            AbstractElementData::Progress => {
+                let progress_style = &slide_data.styles.styles_for_element(&element);
+                let bounds = rect.max_bounds;
+                let thickness = extract_number(progress_style, "thickness").min(bounds.h);
+                let track = sdl2::rect::Rect::new(
+                    bounds.x as i32,
+                    (bounds.y + (bounds.h - thickness) / 2) as i32,
+                    bounds.w,
+                    thickness,
+                );
+
+                if let Some(bg) = extract_optional_colour(progress_style, "bg") {
+                    target.set_draw_color(bg);
+                    target.fill_rect(track).unwrap();
+                }
+
+                let filled_width =
+                    bounds.w as usize * (slide_idx + 1) / global.number_of_slides().max(1);
+                target.set_draw_color(extract_colour(progress_style, "fill"));
+                target
+                    .fill_rect(sdl2::rect::Rect::new(
+                        track.x(),
+                        track.y(),
+                        filled_width as u32,
+                        thickness,
+                    ))
+                    .unwrap();
+            }
+            AbstractElementData::Code(code_to_be_rendered) => {
+                let code_style_target = StyleTarget::reify(&element);
+                let code_style = &slide_data.styles.styles_for_element(&element);
+
+                let radius = extract_number(code_style, "radius") as f32;
+
+                target.set_blend_mode(sdl2::render::BlendMode::Blend);
+                if let Some(bg) = extract_paint(code_style, "bg") {
+                    draw_painted_shape(
+                        target,
+                        rect.max_bounds,
+                        &bg,
+                        &render_data.patterns,
+                        rounded_rect_distance(rect.max_bounds, radius),
+                    );
+                }
+
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, code_style_target))
+                    .unwrap();
+
+                let fingerprint =
+                    text_fingerprint(code_to_be_rendered, code_style, rect.max_bounds);
+                let image =
+                    render_data.text_image(slide_data.slide_id, element.id(), fingerprint, || {
+                        code_image(
+                            &render_data.glyphs,
+                            font,
+                            code_to_be_rendered,
+                            code_style,
+                            rect.max_bounds,
+                        )
+                    });
+                draw_clipped(target, code_style, rect.max_bounds, |target| {
+                    image.draw(target)
+                });
+                draw_border(
+                    target,
+                    rect.max_bounds,
+                    code_style,
+                    rounded_rect_distance(rect.max_bounds, radius),
+                );
+            } // TODO: add code-specific features, like syntax highlighting etc
+            AbstractElementData::List(items) => {
+                let list_style_target = StyleTarget::reify(&element);
+                let list_style = &slide_data.styles.styles_for_element(&element);
+
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, list_style_target))
+                    .unwrap();
+                let font_size = extract_number(list_style, "size") as f32;
+                let text_colour = extract_colour(list_style, "fill");
+                let numbering = extract_string(list_style, "numbering");
+
+                let item_rects = list_item_rects(
+                    rect.max_bounds,
+                    items.len() as u32,
+                    extract_number(list_style, "gap"),
+                    extract_number(list_style, "indent"),
+                );
+                for (idx, (marker_area, _)) in item_rects.into_iter().enumerate() {
+                    draw_text(
+                        target,
+                        &render_data.glyphs,
+                        font,
+                        &list_marker(&numbering, idx + 1),
+                        font_size,
+                        text_colour,
+                        marker_area,
+                    );
+                }
+            }
+            AbstractElementData::Math(formula) => {
+                let math_style_target = StyleTarget::reify(&element);
+                let math_style = &slide_data.styles.styles_for_element(&element);
+
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, math_style_target))
+                    .unwrap();
+                let font_size = extract_number(math_style, "size") as f32;
+                let colour = extract_colour(math_style, "fill");
+
+                let formula = math::parse_formula(formula)
+                    .expect("formulas are checked when the presentation is loaded");
+                let math_box = math::layout_formula(&formula, font, font_size);
+
+                let origin_x = rect.max_bounds.x as f32;
+                let baseline = rect.max_bounds.y as f32 + math_box.ascent;
+                let mut batch = GlyphBatch::default();
+                for item in math_box.items {
+                    match item {
+                        MathItem::Glyph {
+                            character,
+                            x,
+                            y,
+                            size,
+                        } => {
+                            let glyph = render_data.glyphs.character(font, character, size);
+                            let metrics = glyph.0;
+                            batch.add(
+                                glyph,
+                                (origin_x + x) as i32 + metrics.xmin,
+                                (baseline + y) as i32 - metrics.height as i32 - metrics.ymin,
+                                colour,
+                            );
+                        }
+                        MathItem::Rule { x, y, w, h } => {
+                            target.set_draw_color(colour);
+                            target
+                                .fill_rect(sdl2::rect::Rect::new(
+                                    (origin_x + x) as i32,
+                                    (baseline + y) as i32,
+                                    w.ceil() as u32,
+                                    h.ceil() as u32,
+                                ))
+                                .unwrap();
+                        }
+                    }
+                }
+                batch.finish().draw(target);
+            }
+            AbstractElementData::Image(..) => {
+                let image_style = &slide_data.styles.styles_for_element(&element);
+                let image = render_data.images.get(&element.id()).unwrap();
+                let (src, dst) = fit_rects(
+                    &extract_string(image_style, "fit"),
+                    image.size(),
+                    rect.max_bounds,
+                );
+
+                let radius = extract_number(image_style, "radius") as f32;
+                let circle = extract_boolean(image_style, "circle");
+                if circle || radius > 0.0 {
+                    // a circle is a square with corners that are as round as they can be
+                    let (mask_bounds, radius) = if circle {
+                        let side = dst.w.min(dst.h);
+                        let square = Rect {
+                            x: dst.x + (dst.w - side) / 2,
+                            y: dst.y + (dst.h - side) / 2,
+                            w: side,
+                            h: side,
+                        };
+                        (square, side as f32 / 2.0)
+                    } else {
+                        (dst, radius)
+                    };
+                    let mask = rounded_rect_distance(mask_bounds, radius);
+                    let fingerprint = text_fingerprint("", image_style, dst);
+                    let masked = render_data.text_image(
+                        slide_data.slide_id,
+                        element.id(),
+                        fingerprint,
+                        || {
+                            let pixels = render_data.image_pixels.get(&element.id()).unwrap();
+                            masked_image(pixels, src, dst, &mask)
+                        },
+                    );
+                    masked.draw(target);
+                    draw_border(target, mask_bounds, image_style, mask);
+                } else {
+                    target.draw_image(
+                        image,
+                        Some(folium_to_sdl_rect(src)),
+                        folium_to_sdl_rect(dst),
+                    );
+                    draw_border(
+                        target,
+                        rect.max_bounds,
+                        image_style,
+                        rounded_rect_distance(rect.max_bounds, 0.0),
+                    );
+                }
+            }
+            AbstractElementData::Rect(_) => {
+                let rect_style = &slide_data.styles.styles_for_element(&element);
+                let radius = extract_number(rect_style, "radius") as f32;
+
+                draw_filled_and_stroked(
+                    target,
+                    rect.max_bounds,
+                    rect_style,
+                    &render_data.patterns,
+                    rounded_rect_distance(rect.max_bounds, radius),
+                );
+            }
+            AbstractElementData::Shadow(_) => {
+                let shadow_style = &slide_data.styles.styles_for_element(&element);
+                draw_shadow(target, rect.max_bounds, shadow_style);
+            }
+            AbstractElementData::Frame(_) => {
+                let frame_style = &slide_data.styles.styles_for_element(&element);
+                let width = extract_number(frame_style, "width") as f32;
+                let radius = extract_number(frame_style, "radius") as f32;
+                let distance = rounded_rect_distance(rect.max_bounds, radius);
+
+                target.set_blend_mode(sdl2::render::BlendMode::Blend);
+                draw_shape(
+                    target,
+                    rect.max_bounds,
+                    extract_colour(frame_style, "stroke"),
+                    |x, y| {
+                        let d = distance(x, y);
+                        d.max(-(d + width))
+                    },
+                );
+            }
+            AbstractElementData::Ellipse(_) => {
+                let bounds = rect.max_bounds;
+                draw_filled_and_stroked(
+                    target,
+                    bounds,
+                    &slide_data.styles.styles_for_element(&element),
+                    &render_data.patterns,
+                    ellipse_distance(
+                        bounds.x as f32 + bounds.w as f32 / 2.0,
+                        bounds.y as f32 + bounds.h as f32 / 2.0,
+                        bounds.w as f32 / 2.0,
+                        bounds.h as f32 / 2.0,
+                    ),
+                );
+            }
+            AbstractElementData::Circle(_) => {
+                let bounds = rect.max_bounds;
+                let radius = bounds.w.min(bounds.h) as f32 / 2.0;
+                draw_filled_and_stroked(
+                    target,
+                    bounds,
+                    &slide_data.styles.styles_for_element(&element),
+                    &render_data.patterns,
+                    ellipse_distance(
+                        bounds.x as f32 + bounds.w as f32 / 2.0,
+                        bounds.y as f32 + bounds.h as f32 / 2.0,
+                        radius,
+                        radius,
+                    ),
+                );
+            }
+            AbstractElementData::Video(..) => {
+                // videos that could not be opened are shown as a black box
+                if let Some(image) = render_data.images.get(&element.id()) {
+                    target.draw_image(image, None, folium_to_sdl_rect(rect.max_bounds));
+                } else {
+                    target.set_draw_color((0, 0, 0));
+                    target
+                        .fill_rect(folium_to_sdl_rect(rect.max_bounds))
+                        .unwrap();
+                }
+            }
+            AbstractElementData::BarChart(data)
+            | AbstractElementData::LineChart(data)
+            | AbstractElementData::PieChart(data) => {
+                let chart_style_target = StyleTarget::reify(&element);
+                let chart_style = &slide_data.styles.styles_for_element(&element);
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, chart_style_target))
+                    .unwrap();
+
+                let draw_chart = match element.data() {
+                    AbstractElementData::BarChart(_) => draw_bar_chart,
+                    AbstractElementData::LineChart(_) => draw_line_chart,
+                    _ => draw_pie_chart,
+                };
+                draw_chart(
+                    target,
+                    &render_data.glyphs,
+                    font,
+                    data,
+                    chart_style,
+                    rect.max_bounds,
+                );
+            }
+            AbstractElementData::Plot(data) => {
+                let plot_style_target = StyleTarget::reify(&element);
+                let plot_style = &slide_data.styles.styles_for_element(&element);
+                let font = render_data
+                    .fonts_for_targets
+                    .get(&(slide_data.slide_id, plot_style_target))
+                    .unwrap();
+
+                draw_plot(
+                    target,
+                    &render_data.glyphs,
+                    font,
+                    data,
+                    plot_style,
+                    rect.max_bounds,
+                );
+            }
+            AbstractElementData::Line(..) | AbstractElementData::Arrow(..) => {
+                panic!("Lines and arrows should never have a layout element of their own")
+            }
+            AbstractElementData::Spacer(_)
+            | AbstractElementData::Audio(_)
+            | AbstractElementData::None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::load;
+
+    /// The pixels of a slide as it is rendered to a file, with the width of a row.
+    fn draw(global: &GlobalState, slide_idx: usize) -> (u32, Vec<u8>) {
+        let render_data = initialise_software_rendering_data(global);
+        let (width, height) = generate_slide_data(global, slide_idx, None).dimensions;
+        let mut canvas = SoftwareCanvas::new(width, height);
+        render(
+            global,
+            &mut canvas,
+            slide_idx,
+            None,
+            &render_data,
+            DebugDrawing::default(),
+        );
+        (width, canvas.pixels())
+    }
+
+    #[test]
+    fn resizing_lays_slides_out_again() {
+        let global = GlobalState::new();
+        load(&global, String::from("[ none() ]")).unwrap();
+        let render_data = initialise_software_rendering_data(&global);
+        let width_of_content = |slide_data: &SlideData| slide_data.layout_rects[0].max_bounds.w;
+
+        let small = render_data.slide_data(&global, 0, Some((640, 360)));
+        assert!(Rc::ptr_eq(
+            &small,
+            &render_data.slide_data(&global, 0, Some((640, 360)))
+        ));
+
+        let large = render_data.slide_data(&global, 0, Some((1280, 720)));
+        assert!(!Rc::ptr_eq(&small, &large));
+        assert!(width_of_content(&large) > width_of_content(&small));
+        assert!(width_of_content(&large) <= 1280);
+        // the layout for the old size is no longer kept
+        assert_eq!(Rc::strong_count(&small), 1);
+        assert_eq!(render_data.slide_data.borrow().len(), 1);
+    }
+
+    #[test]
+    fn slide_placeholders_are_expanded() {
+        let global = GlobalState::new();
+        let source = r#"[ text("page $slide of $total") ] [ text("page $slide of $total") ]"#;
+        load(&global, String::from(source)).unwrap();
+        let render_data = initialise_software_rendering_data(&global);
+
+        let svg = render_svg(&global, 1, &render_data);
+        assert!(svg.contains("page 2 of 2"), "{svg}");
+        assert!(!svg.contains('$'));
+    }
+
+    #[test]
+    fn progress_bars_fill_up_over_the_presentation() {
+        let global = GlobalState::new();
+        let source = "deck { width: 400, height: 200 }\n[ progress() progress { fill: #ff0000 } ]\n[ progress() progress { fill: #ff0000 } ]";
+        load(&global, String::from(source)).unwrap();
+
+        // the bar spans the slide inside of its margins, from x 64 to 336
+        let is_filled = |(width, pixels): &(u32, Vec<u8>), x: u32| {
+            let idx = (100 * width + x) as usize * 4;
+            pixels[idx..idx + 3] == [255, 0, 0]
+        };
+        let first = draw(&global, 0);
+        assert!(is_filled(&first, 70) && is_filled(&first, 190));
+        assert!(!is_filled(&first, 210) && !is_filled(&first, 330));
+        let last = draw(&global, 1);
+        assert!(is_filled(&last, 70) && is_filled(&last, 330));
+        assert!(!is_filled(&last, 340));
+    }
+
+    #[test]
+    fn missing_glyphs_come_from_fallback_fonts() {
+        let font = measure::font_for(&FontChoice::regular(vec![String::from("Liberation Serif")]))
+            .unwrap();
+        assert_eq!(font.lookup_glyph_index('☃'), 0);
+
+        let area = Rect {
+            x: 0,
+            y: 0,
+            w: 400,
+            h: 100,
+        };
+        let (layout, fonts) = layout_text(&font, "a☃", 32.0, area, TextSettings::default());
+        let glyphs = layout.glyphs();
+        assert_eq!(glyphs[0].font_index, 0);
+        assert_ne!(glyphs[1].font_index, 0);
+        assert_ne!(fonts[glyphs[1].font_index].lookup_glyph_index('☃'), 0);
+    }
+
+    /// The box of the element with the given ID on the first slide.
+    fn bounds_of(global: &GlobalState, id: u32) -> Rect {
+        generate_slide_data(global, 0, None)
+            .layout_rects
+            .into_iter()
+            .find(|rect| rect.element == AbstractElementID(id))
+            .unwrap()
+            .max_bounds
+    }
+
+    /// How bright the first text from the left in the box of the element with the given ID is,
+    /// on a black background, as the brightest red in the first columns of it.
+    fn brightness_of_first_text(global: &GlobalState, id: u32) -> u8 {
+        let (width, pixels) = draw(global, 0);
+        let bounds = bounds_of(global, id);
+        let column = |x: u32| {
+            (bounds.y..bounds.y + bounds.h)
+                .map(|y| pixels[(y * width + x) as usize * 4])
+                .max()
+                .unwrap()
+        };
+        let first = (bounds.x..bounds.x + bounds.w)
+            .find(|x| column(*x) > 16)
+            .unwrap();
+        (first..first + 8).map(column).max().unwrap()
+    }
+
+    #[test]
+    fn line_numbers_are_dimmed_in_a_gutter() {
+        let numbered = |line_numbers| {
+            let global = GlobalState::new();
+            let source = format!(
+                "[ code(\"let x = 1;\nlet y = 2;\") code {{ fill: #ffffff, bg: #000000, line-numbers: {line_numbers}, first-line: 9 }} ]"
+            );
+            load(&global, source).unwrap();
+            brightness_of_first_text(&global, 1)
+        };
+        // the numbers are drawn half way between the text and the background
+        assert!((100..=140).contains(&numbered(true)));
+        assert_eq!(numbered(false), 255);
+    }
+
+    #[test]
+    fn glyphs_are_rasterised_once() {
+        let global = GlobalState::new();
+        let source = r#"[ text("the same text") ] [ text("the same text") ]"#;
+        load(&global, String::from(source)).unwrap();
+        let render_data = initialise_software_rendering_data(&global);
+        let draw_slide = |slide_idx| {
+            let mut canvas = SoftwareCanvas::new(1920, 1080);
+            render(
+                &global,
+                &mut canvas,
+                slide_idx,
+                None,
+                &render_data,
+                DebugDrawing::default(),
+            );
+        };
+
+        draw_slide(0);
+        // t, h, e, s, a, m and x, with the space
+        let rasterised = render_data.glyphs.glyphs.borrow().len();
+        assert_eq!(rasterised, 8);
+        draw_slide(1);
+        draw_slide(0);
+        assert_eq!(render_data.glyphs.glyphs.borrow().len(), rasterised);
+
+        let font = render_data.fonts_for_targets.values().next().unwrap();
+        assert!(Rc::ptr_eq(
+            &render_data.glyphs.character(font, 't', 32.0),
+            &render_data.glyphs.character(font, 't', 32.0)
+        ));
+    }
+
+    #[test]
+    fn text_images_are_kept_until_the_text_changes() {
+        let global = GlobalState::new();
+        load(&global, String::from(r#"[ text("kept") ]"#)).unwrap();
+        let render_data = initialise_software_rendering_data(&global);
+        let draw_at = |(width, height)| {
+            let mut canvas = SoftwareCanvas::new(width, height);
+            render(
+                &global,
+                &mut canvas,
+                0,
+                Some((width, height)),
+                &render_data,
+                DebugDrawing::default(),
+            );
+            let text_images = render_data.text_images.borrow();
+            assert_eq!(text_images.len(), 1);
+            text_images.values().next().unwrap().clone()
+        };
+
+        let first = draw_at((1280, 720));
+        assert!(Rc::ptr_eq(&first, &draw_at((1280, 720))));
+        // at another size the text has another area, and the old image is dropped
+        let resized = draw_at((640, 360));
+        assert!(!Rc::ptr_eq(&first, &resized));
+        assert_eq!(Rc::strong_count(&first), 1);
+    }
+
+    #[test]
+    fn only_changing_regions_are_redrawn() {
+        let global = GlobalState::new();
+        // a clock with a format that never changes, so that redrawing it gives the same pixels
+        let source = r#"[ row(text("still"), clock()) clock { format: "now" } ] [ text("still") ]"#;
+        load(&global, String::from(source)).unwrap();
+        let mut render_data = initialise_software_rendering_data(&global);
+        render_data.keep_snapshots();
+
+        let clock = bounds_of(&global, 2);
+        assert_eq!(
+            render_data.changing_regions(&global, 0, None),
+            vec![folium_to_sdl_rect(clock)]
+        );
+        assert!(render_data.changing_regions(&global, 1, None).is_empty());
+        // the text next to the clock is not drawn again
+        let slide_data = render_data.slide_data(&global, 0, None);
+        let reaching =
+            render_data.layout_rects_reaching(&global, &slide_data, folium_to_sdl_rect(clock));
+        assert_eq!(
+            reaching.iter().map(|rect| rect.element).collect::<Vec<_>>(),
+            vec![AbstractElementID(2)]
+        );
+
+        let mut canvas = SoftwareCanvas::new(1920, 1080);
+        let debug = DebugDrawing::default();
+        // without a full frame of the slide there is nothing to draw the changes over
+        assert!(!render_changes(
+            &global,
+            &mut canvas,
+            0,
+            None,
+            &render_data,
+            debug
+        ));
+        render(&global, &mut canvas, 0, None, &render_data, debug);
+        let full = canvas.pixels();
+
+        let mut redrawn = SoftwareCanvas::new(1920, 1080);
+        assert!(render_changes(
+            &global,
+            &mut redrawn,
+            0,
+            None,
+            &render_data,
+            debug
+        ));
+        assert_eq!(redrawn.pixels(), full);
+        assert!(!render_changes(
+            &global,
+            &mut redrawn,
+            1,
+            None,
+            &render_data,
+            debug
+        ));
+        let mut resized = SoftwareCanvas::new(960, 540);
+        assert!(!render_changes(
+            &global,
+            &mut resized,
+            0,
+            None,
+            &render_data,
+            debug
+        ));
+    }
+
+    #[test]
+    fn lines_off_the_slide_reach_as_far_as_its_edge() {
+        let global = GlobalState::new();
+        load(
+            &global,
+            String::from("[ none() line(from: [0, 10], to: [30, 10]) ]"),
+        )
+        .unwrap();
+        let slide_data = generate_slide_data(&global, 0, None);
+        let on_the_slide = slide_data.connectors[0];
+        let off_the_slide = ConnectorSegment {
+            from: (-20.0, 10.0),
+            ..on_the_slide
+        };
+        assert_eq!(
+            connector_area(&global, &slide_data, &off_the_slide),
+            connector_area(&global, &slide_data, &on_the_slide)
+        );
+    }
+
+    #[test]
+    fn the_laser_pointer_is_drawn_over_the_slide() {
+        let global = GlobalState::new();
+        load(&global, String::from("[ none() ]")).unwrap();
+        let mut render_data = initialise_software_rendering_data(&global);
+        render_data.keep_snapshots();
+        let debug = DebugDrawing::default();
+        let pixel_at = |pixels: &[u8], (x, y): (usize, usize)| {
+            let idx = (y * 1920 + x) * 4;
+            pixels[idx..idx + 3].to_vec()
+        };
+
+        render_data.set_laser_pointer(Some((500, 400)));
+        let mut canvas = SoftwareCanvas::new(1920, 1080);
+        render(&global, &mut canvas, 0, None, &render_data, debug);
+        let pixels = canvas.pixels();
+        assert_eq!(pixel_at(&pixels, (500, 400)), [255, 160, 160]);
+        assert_eq!(pixel_at(&pixels, (900, 400)), [235, 218, 199]);
+        // the pointer is not part of the snapshot, so moving it only takes drawing the changes
+        let snapshot = render_data
+            .snapshot
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .pixels
+            .clone();
+        assert_eq!(pixel_at(&snapshot, (500, 400)), [235, 218, 199]);
+
+        render_data.set_laser_pointer(Some((900, 400)));
+        assert!(render_changes(
+            &global,
+            &mut canvas,
+            0,
+            None,
+            &render_data,
+            debug
+        ));
+        let pixels = canvas.pixels();
+        assert_eq!(pixel_at(&pixels, (500, 400)), [235, 218, 199]);
+        assert_eq!(pixel_at(&pixels, (900, 400)), [255, 160, 160]);
+
+        render_data.set_laser_pointer(None);
+        render(&global, &mut canvas, 0, None, &render_data, debug);
+        assert_eq!(canvas.pixels(), snapshot);
+    }
+
+    #[test]
+    fn pen_strokes_are_drawn_over_their_slide() {
+        let global = GlobalState::new();
+        load(&global, String::from("[ none() ] [ none() ]")).unwrap();
+        let mut render_data = initialise_software_rendering_data(&global);
+        let debug = DebugDrawing::default();
+        let draw = |render_data: &RenderData, slide_idx| {
+            let mut canvas = SoftwareCanvas::new(1920, 1080);
+            render(&global, &mut canvas, slide_idx, None, render_data, debug);
+            canvas.pixels()
+        };
+        let pixel_at = |pixels: &[u8], (x, y): (usize, usize)| {
+            let idx = (y * 1920 + x) * 4;
+            pixels[idx..idx + 3].to_vec()
+        };
+        let blank = draw(&render_data, 0);
+
+        render_data.start_stroke(0, (0, 0, 255), (200, 300));
+        render_data.extend_stroke(0, (600, 300));
+        render_data.extend_stroke(0, (600, 700));
+        // a click without moving leaves a dot
+        render_data.start_stroke(0, (255, 0, 0), (1000, 500));
+        let pixels = draw(&render_data, 0);
+        for point in [(200, 300), (400, 300), (600, 500), (600, 700)] {
+            assert_eq!(pixel_at(&pixels, point), [0, 0, 255]);
+        }
+        assert_eq!(pixel_at(&pixels, (1000, 500)), [255, 0, 0]);
+        assert_eq!(pixel_at(&pixels, (400, 500)), [235, 218, 199]);
+        assert_eq!(draw(&render_data, 1), blank);
+
+        render_data.clear_annotations(0);
+        assert_eq!(draw(&render_data, 0), blank);
+    }
+
+    #[test]
+    fn guides_show_the_margins_gaps_and_elements() {
+        let global = GlobalState::new();
+        load(&global, String::from("[ row(text(\"a\"), text(\"b\")) ]")).unwrap();
+        let render_data = initialise_software_rendering_data(&global);
+        let draw = |guides| {
+            let mut canvas = SoftwareCanvas::new(1920, 1080);
+            let debug = DebugDrawing {
+                guides,
+                ..Default::default()
+            };
+            render(&global, &mut canvas, 0, None, &render_data, debug);
+            canvas.pixels()
+        };
+        let pixel_at = |pixels: &[u8], (x, y): (u32, u32)| {
+            let idx = ((y * 1920 + x) * 4) as usize;
+            pixels[idx..idx + 3].to_vec()
+        };
+        let (plain, guided) = (draw(false), draw(true));
+
+        // the area inside of the margins is outlined in blue
+        assert_eq!(pixel_at(&plain, (64, 900)), [235, 218, 199]);
+        assert_eq!(pixel_at(&guided, (64, 900)), [0, 120, 255]);
+        assert_eq!(pixel_at(&guided, (63, 900)), [235, 218, 199]);
+
+        // the gap between the texts is tinted magenta
+        let (a, b) = (bounds_of(&global, 1), bounds_of(&global, 2));
+        assert!(a.x + a.w < b.x);
+        let gap = ((a.x + a.w + b.x) / 2, b.y + b.h / 2);
+        let [red, green, _] = pixel_at(&guided, gap)[..] else {
+            unreachable!()
+        };
+        assert!(red > 235 && green < 218);
+
+        // every element has a label on a dark background at its top left corner
+        let brightness = |pixels: &[u8]| {
+            (b.y + 2..b.y + 12)
+                .flat_map(|y| (b.x + 5..b.x + 50).map(move |x| (x, y)))
+                .map(|point| pixel_at(pixels, point)[0] as u32)
+                .sum::<u32>()
+        };
+        assert!(brightness(&guided) < brightness(&plain) * 2 / 3);
+    }
+
+    #[test]
+    fn glyph_coverage_is_gamma_corrected() {
+        let dark = gamma_table((0, 0, 0));
+        let light = gamma_table((255, 255, 255));
+        for table in [dark, light] {
+            assert_eq!((table[0], table[255]), (0, 255));
+            assert!(table.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+        // dark text is made thinner and light text heavier at its edges
+        assert!(dark[128] < 100 && light[128] > 160);
+    }
+
+    #[test]
+    fn subpixel_text_has_coloured_edges() {
+        let global = GlobalState::new();
+        let source = r#"[ text("subpixel") slide { bg: #ffffff } ]"#;
+        load(&global, String::from(source)).unwrap();
+        let coloured_pixels = |subpixel| {
+            let mut render_data = initialise_software_rendering_data(&global);
+            if subpixel {
+                render_data.use_subpixel_text();
+            }
+            let mut canvas = SoftwareCanvas::new(1920, 1080);
+            render(
+                &global,
+                &mut canvas,
+                0,
+                None,
+                &render_data,
+                DebugDrawing::default(),
+            );
+            canvas
+                .pixels()
+                .chunks(4)
+                .filter(|pixel| pixel[0] != pixel[2])
+                .count()
+        };
+
+        assert_eq!(coloured_pixels(false), 0);
+        assert!(coloured_pixels(true) > 50);
+    }
+
+    #[test]
+    fn batched_glyphs_look_like_glyphs_drawn_one_by_one() {
+        let font = measure::font_for(&FontChoice::regular(vec![String::from("Liberation Serif")]))
+            .unwrap();
+        let glyphs = GlyphAtlas::default();
+        let area = Rect {
+            x: 10,
+            y: 10,
+            w: 300,
+            h: 200,
+        };
+        let mut batch = GlyphBatch::default();
+        let text = "Batched glyphs, drawn over two lines of text";
+        batch_text(&mut batch, &glyphs, &font, text, 32.0, (20, 40, 160), area);
+        assert!(batch.glyphs.len() > 30);
+
+        let canvas = || {
+            let mut canvas = SoftwareCanvas::new(320, 220);
+            canvas.set_draw_color((250, 240, 220));
+            canvas.fill_rect(None).unwrap();
+            canvas
+        };
+        let mut one_by_one = canvas();
+        for placed in std::mem::take(&mut batch.glyphs) {
+            let mut single = GlyphBatch::default();
+            single.glyphs.push(placed);
+            single.finish().draw(&mut one_by_one);
+        }
+        let mut batched = canvas();
+        batch_text(&mut batch, &glyphs, &font, text, 32.0, (20, 40, 160), area);
+        batch.finish().draw(&mut batched);
+
+        let (batched, one_by_one) = (batched.pixels(), one_by_one.pixels());
+        assert_ne!(batched, canvas().pixels());
+        // glyphs that overlap are only rounded differently
+        assert!(batched
+            .iter()
+            .zip(&one_by_one)
+            .all(|(a, b)| a.abs_diff(*b) <= 1));
+    }
+
+    #[test]
+    fn text_decorations_run_along_the_text() {
+        // the row of the box with the longest run of dark pixels, and how long the run is
+        let longest_run = |style| {
+            let global = GlobalState::new();
+            let source = format!(r#"[ text("a b c d e") text {{ {style} }} ]"#);
+            load(&global, source).unwrap();
+            let (width, pixels) = draw(&global, 0);
+            let bounds = bounds_of(&global, 1);
+            (0..bounds.h)
+                .map(|y| {
+                    let run = (0..bounds.w)
+                        .map(|x| pixels[((bounds.y + y) * width + bounds.x + x) as usize * 4] < 128)
+                        .dedup_with_count()
+                        .filter(|(_, dark)| *dark)
+                        .map(|(count, _)| count)
+                        .max()
+                        .unwrap_or(0);
+                    (y, run)
+                })
+                .max_by_key(|(_, run)| *run)
+                .unwrap()
+        };
+
+        let (_, plain) = longest_run("");
+        assert!(plain < 30);
+        let (underline_row, underline) = longest_run("underline: true");
+        let (strike_row, strike) = longest_run("strike: true");
+        assert!(underline > 100 && strike > 100);
+        // the line through the text is above the line under it
+        assert!(strike_row + 5 < underline_row);
+    }
+
+    #[test]
+    fn text_is_aligned_in_its_box() {
+        // the first and last columns of the box with dark text in them, from its left edge
+        let inked = |align| {
+            let global = GlobalState::new();
+            let source = format!(r#"[ text("aligned") text {{ align: {align} }} ]"#);
+            load(&global, source).unwrap();
+            let (width, pixels) = draw(&global, 0);
+            let bounds = bounds_of(&global, 1);
+            let columns = (0..bounds.w)
+                .filter(|x| {
+                    (bounds.y..bounds.y + bounds.h)
+                        .any(|y| pixels[(y * width + bounds.x + x) as usize * 4] < 128)
+                })
+                .collect_vec();
+            (columns[0], bounds.w - 1 - columns[columns.len() - 1])
+        };
+
+        let (left, _) = inked("left");
+        assert!(left < 4);
+        let (_, right) = inked("right");
+        assert!(right < 4);
+        let (before, after) = inked("centre");
+        assert!(before.abs_diff(after) <= 2 && before > 100);
+    }
+
+    #[test]
+    fn code_backgrounds_have_rounded_corners() {
+        // the colours in the top left corner of the box and half way down its left edge
+        let corners = |radius| {
+            let global = GlobalState::new();
+            let source = format!(r#"[ code("x") code {{ bg: #000000, radius: {radius} }} ]"#);
+            load(&global, source).unwrap();
+            let (width, pixels) = draw(&global, 0);
+            let bounds = bounds_of(&global, 1);
+            let at = |x: u32, y: u32| {
+                let idx = ((bounds.y + y) * width + bounds.x + x) as usize * 4;
+                (pixels[idx], pixels[idx + 1], pixels[idx + 2])
+            };
+            (at(1, 1), at(1, bounds.h / 2))
+        };
+
+        let slide_bg = (235, 218, 199);
+        assert_eq!(corners(0), ((0, 0, 0), (0, 0, 0)));
+        assert_eq!(corners(16), (slide_bg, (0, 0, 0)));
+    }
+
+    #[test]
+    fn images_can_be_masked_to_rounded_corners_and_circles() {
+        let pixels = ImagePixels {
+            width: 64,
+            height: 64,
+            rgba: [255, 0, 0, 255].repeat(64 * 64),
+        };
+        let whole = Rect {
+            x: 0,
+            y: 0,
+            w: 64,
+            h: 64,
+        };
+        let dst = Rect {
+            x: 8,
+            y: 8,
+            w: 128,
+            h: 128,
+        };
+
+        // which of the corners and the centre of the image are drawn, when it is masked to a
+        // rounded rectangle with the given radius
+        let drawn = |radius| {
+            let mut canvas = SoftwareCanvas::new(144, 144);
+            masked_image(&pixels, whole, dst, rounded_rect_distance(dst, radius)).draw(&mut canvas);
+            let canvas = canvas.pixels();
+            let red = |x: usize, y: usize| canvas[(y * 144 + x) * 4..][..4] == [255, 0, 0, 255];
+            [(10, 10), (133, 10), (10, 133), (133, 133), (72, 72)].map(|(x, y)| red(x, y))
+        };
+
+        assert_eq!(drawn(0.0), [true; 5]);
+        assert_eq!(drawn(24.0), [false, false, false, false, true]);
+        // a circle is a square with corners that are as round as they can be
+        assert_eq!(drawn(64.0), [false, false, false, false, true]);
+    }
+
+    #[test]
+    fn diff_lines_are_tinted_across_the_box() {
+        let global = GlobalState::new();
+        let source = "[ code(\"+added\n-removed\n kept\") code { bg: #000000, diff: true, diff-added: #00ff00, diff-removed: #ff0000 } ]";
+        load(&global, String::from(source)).unwrap();
+        let (width, pixels) = draw(&global, 0);
+
+        // the colours down the right edge of the box, where there is no text
+        let bounds = bounds_of(&global, 1);
+        let x = bounds.x + bounds.w - 2;
+        let colours = (bounds.y..bounds.y + bounds.h)
+            .map(|y| {
+                let idx = (y * width + x) as usize * 4;
+                (pixels[idx], pixels[idx + 1], pixels[idx + 2])
+            })
+            .dedup()
+            .collect_vec();
+        assert_eq!(
+            colours,
+            vec![(0, 0, 0), (0, 255, 0), (255, 0, 0), (0, 0, 0)]
+        );
+    }
 }
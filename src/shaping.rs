@@ -0,0 +1,298 @@
+// fontdue draws every character with the glyph the font maps it to, without the contextual
+// forms and reordering that some scripts need. The most important of those are done here, on
+// the characters themselves, before the text is laid out:
+//
+// - Arabic letters take the form that joins them to their neighbours, using the presentation
+//   forms that Unicode has for them, and lam followed by alef becomes a ligature.
+// - In the scripts of India, a vowel sign that is written before its consonant, like the short
+//   i of Devanagari, is moved in front of the consonant (or conjunct) it belongs to, and the
+//   two part vowels of Bengali are split into their parts.
+//
+// Conjuncts and marks that depend on the font are not formed, so consonants with a virama are
+// shown with a visible virama.
+
+/// The presentation forms of the Arabic letters: isolated, final, initial and medial. Letters
+/// that only join to the letter before them have no initial and medial forms, and hamza joins
+/// to neither side.
+const ARABIC_FORMS: &[(char, [u32; 4])] = &[
+    ('\u{0621}', [0xfe80, 0, 0, 0]),
+    ('\u{0622}', [0xfe81, 0xfe82, 0, 0]),
+    ('\u{0623}', [0xfe83, 0xfe84, 0, 0]),
+    ('\u{0624}', [0xfe85, 0xfe86, 0, 0]),
+    ('\u{0625}', [0xfe87, 0xfe88, 0, 0]),
+    ('\u{0626}', [0xfe89, 0xfe8a, 0xfe8b, 0xfe8c]),
+    ('\u{0627}', [0xfe8d, 0xfe8e, 0, 0]),
+    ('\u{0628}', [0xfe8f, 0xfe90, 0xfe91, 0xfe92]),
+    ('\u{0629}', [0xfe93, 0xfe94, 0, 0]),
+    ('\u{062a}', [0xfe95, 0xfe96, 0xfe97, 0xfe98]),
+    ('\u{062b}', [0xfe99, 0xfe9a, 0xfe9b, 0xfe9c]),
+    ('\u{062c}', [0xfe9d, 0xfe9e, 0xfe9f, 0xfea0]),
+    ('\u{062d}', [0xfea1, 0xfea2, 0xfea3, 0xfea4]),
+    ('\u{062e}', [0xfea5, 0xfea6, 0xfea7, 0xfea8]),
+    ('\u{062f}', [0xfea9, 0xfeaa, 0, 0]),
+    ('\u{0630}', [0xfeab, 0xfeac, 0, 0]),
+    ('\u{0631}', [0xfead, 0xfeae, 0, 0]),
+    ('\u{0632}', [0xfeaf, 0xfeb0, 0, 0]),
+    ('\u{0633}', [0xfeb1, 0xfeb2, 0xfeb3, 0xfeb4]),
+    ('\u{0634}', [0xfeb5, 0xfeb6, 0xfeb7, 0xfeb8]),
+    ('\u{0635}', [0xfeb9, 0xfeba, 0xfebb, 0xfebc]),
+    ('\u{0636}', [0xfebd, 0xfebe, 0xfebf, 0xfec0]),
+    ('\u{0637}', [0xfec1, 0xfec2, 0xfec3, 0xfec4]),
+    ('\u{0638}', [0xfec5, 0xfec6, 0xfec7, 0xfec8]),
+    ('\u{0639}', [0xfec9, 0xfeca, 0xfecb, 0xfecc]),
+    ('\u{063a}', [0xfecd, 0xfece, 0xfecf, 0xfed0]),
+    ('\u{0641}', [0xfed1, 0xfed2, 0xfed3, 0xfed4]),
+    ('\u{0642}', [0xfed5, 0xfed6, 0xfed7, 0xfed8]),
+    ('\u{0643}', [0xfed9, 0xfeda, 0xfedb, 0xfedc]),
+    ('\u{0644}', [0xfedd, 0xfede, 0xfedf, 0xfee0]),
+    ('\u{0645}', [0xfee1, 0xfee2, 0xfee3, 0xfee4]),
+    ('\u{0646}', [0xfee5, 0xfee6, 0xfee7, 0xfee8]),
+    ('\u{0647}', [0xfee9, 0xfeea, 0xfeeb, 0xfeec]),
+    ('\u{0648}', [0xfeed, 0xfeee, 0, 0]),
+    ('\u{0649}', [0xfeef, 0xfef0, 0, 0]),
+    ('\u{064a}', [0xfef1, 0xfef2, 0xfef3, 0xfef4]),
+    // the letters that Persian and Urdu add
+    ('\u{067e}', [0xfb56, 0xfb57, 0xfb58, 0xfb59]),
+    ('\u{0686}', [0xfb7a, 0xfb7b, 0xfb7c, 0xfb7d]),
+    ('\u{0698}', [0xfb8a, 0xfb8b, 0, 0]),
+    ('\u{06a9}', [0xfb8e, 0xfb8f, 0xfb90, 0xfb91]),
+    ('\u{06af}', [0xfb92, 0xfb93, 0xfb94, 0xfb95]),
+    ('\u{06cc}', [0xfbfc, 0xfbfd, 0xfbfe, 0xfbff]),
+];
+
+/// The isolated and final forms of lam followed by the alefs.
+const LAM_ALEF: &[(char, [u32; 2])] = &[
+    ('\u{0622}', [0xfef5, 0xfef6]),
+    ('\u{0623}', [0xfef7, 0xfef8]),
+    ('\u{0625}', [0xfef9, 0xfefa]),
+    ('\u{0627}', [0xfefb, 0xfefc]),
+];
+
+const TATWEEL: char = '\u{0640}';
+const LAM: char = '\u{0644}';
+
+/// How an Arabic character joins to the characters around it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Joining {
+    /// Joins to the characters on both sides.
+    Dual,
+    /// Only joins to the character before it.
+    Right,
+    /// Vowel marks, which are skipped when looking for the neighbours of a letter.
+    Transparent,
+    None,
+}
+
+fn joining(character: char) -> Joining {
+    match character {
+        TATWEEL => Joining::Dual,
+        '\u{064b}'..='\u{065f}'
+        | '\u{0670}'
+        | '\u{06d6}'..='\u{06dc}'
+        | '\u{06df}'..='\u{06e4}'
+        | '\u{06e7}'
+        | '\u{06e8}'
+        | '\u{06ea}'..='\u{06ed}' => Joining::Transparent,
+        c => match arabic_forms(c) {
+            Some([_, 0, _, _]) => Joining::None,
+            Some([_, _, 0, _]) => Joining::Right,
+            Some(_) => Joining::Dual,
+            None => Joining::None,
+        },
+    }
+}
+
+fn arabic_forms(character: char) -> Option<[u32; 4]> {
+    ARABIC_FORMS
+        .iter()
+        .find(|(letter, _)| *letter == character)
+        .map(|(_, forms)| *forms)
+}
+
+/// Gives the Arabic letters in a piece of text the forms that join them together.
+fn join_arabic(chars: &[char]) -> Vec<char> {
+    // the neighbours of a letter that it can join to, skipping vowel marks
+    let neighbour = |range: &mut dyn Iterator<Item = usize>| {
+        range
+            .map(|idx| chars[idx])
+            .find(|c| joining(*c) != Joining::Transparent)
+    };
+
+    let mut shaped = Vec::with_capacity(chars.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        let character = chars[idx];
+        let Some(forms) = arabic_forms(character) else {
+            shaped.push(character);
+            idx += 1;
+            continue;
+        };
+
+        let joins_before =
+            neighbour(&mut (0..idx).rev()).is_some_and(|c| joining(c) == Joining::Dual);
+
+        // lam and the alef right after it are drawn as one ligature
+        if character == LAM {
+            let alef = chars
+                .get(idx + 1)
+                .and_then(|next| LAM_ALEF.iter().find(|(alef, _)| alef == next));
+            if let Some((_, [isolated, last])) = alef {
+                let form = if joins_before { *last } else { *isolated };
+                shaped.push(char::from_u32(form).unwrap());
+                idx += 2;
+                continue;
+            }
+        }
+
+        let joins_after = joining(character) == Joining::Dual
+            && neighbour(&mut (idx + 1..chars.len()))
+                .is_some_and(|c| matches!(joining(c), Joining::Dual | Joining::Right));
+        let form = match (joins_before, joins_after) {
+            (false, false) => forms[0],
+            (true, false) => forms[1],
+            (false, true) => forms[2],
+            (true, true) => forms[3],
+        };
+        shaped.push(char::from_u32(form).unwrap_or(character));
+        idx += 1;
+    }
+    shaped
+}
+
+fn is_indic_consonant(character: char) -> bool {
+    matches!(
+        character,
+        '\u{0915}'..='\u{0939}'
+            | '\u{0958}'..='\u{095f}'
+            | '\u{0978}'..='\u{097f}'
+            | '\u{0995}'..='\u{09b9}'
+            | '\u{09dc}'..='\u{09df}'
+            | '\u{0a15}'..='\u{0a39}'
+            | '\u{0a59}'..='\u{0a5e}'
+            | '\u{0a95}'..='\u{0ab9}'
+    )
+}
+
+fn is_virama(character: char) -> bool {
+    matches!(character, '\u{094d}' | '\u{09cd}' | '\u{0a4d}' | '\u{0acd}')
+}
+
+fn is_nukta(character: char) -> bool {
+    matches!(character, '\u{093c}' | '\u{09bc}' | '\u{0a3c}' | '\u{0abc}')
+}
+
+/// Vowel signs that are written after their consonant, but drawn before it.
+fn is_pre_base_vowel(character: char) -> bool {
+    matches!(
+        character,
+        '\u{093f}' | '\u{094e}' | '\u{09bf}' | '\u{09c7}' | '\u{09c8}' | '\u{0a3f}' | '\u{0abf}'
+    )
+}
+
+/// Moves the vowel signs that are drawn before their consonant in front of it, or in front of
+/// the whole conjunct if the consonant is joined to others with viramas.
+fn reorder_indic(chars: &[char]) -> Vec<char> {
+    let mut shaped = Vec::with_capacity(chars.len());
+    for character in chars.iter().copied() {
+        // the two part vowels of Bengali consist of a vowel sign before and one after
+        let parts: &[char] = match character {
+            '\u{09cb}' => &['\u{09c7}', '\u{09be}'],
+            '\u{09cc}' => &['\u{09c7}', '\u{09d7}'],
+            _ => &[character],
+        };
+
+        for part in parts.iter().copied() {
+            if !is_pre_base_vowel(part) {
+                shaped.push(part);
+                continue;
+            }
+
+            // the start of the consonant, or conjunct, that the vowel belongs to
+            let mut start = shaped.len();
+            while start > 0 && is_nukta(shaped[start - 1]) {
+                start -= 1;
+            }
+            if start > 0 && is_indic_consonant(shaped[start - 1]) {
+                start -= 1;
+                while start >= 2 && is_virama(shaped[start - 1]) {
+                    let mut before = start - 2;
+                    while before > 0 && is_nukta(shaped[before]) {
+                        before -= 1;
+                    }
+                    if !is_indic_consonant(shaped[before]) {
+                        break;
+                    }
+                    start = before;
+                }
+            }
+            shaped.insert(start, part);
+        }
+    }
+    shaped
+}
+
+/// Gives the characters of a piece of text the forms and the order they are drawn in.
+pub fn shape(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let chars = join_arabic(&chars);
+    reorder_indic(&chars).into_iter().collect()
+}
+
+/// The name of the script a character belongs to, for the scripts that a font is usually made
+/// for specifically, as it appears in the names of such fonts.
+pub fn script(character: char) -> Option<&'static str> {
+    let script = match character {
+        '\u{0370}'..='\u{03ff}' => "Greek",
+        '\u{0400}'..='\u{04ff}' => "Cyrillic",
+        '\u{0530}'..='\u{058f}' => "Armenian",
+        '\u{0590}'..='\u{05ff}' | '\u{fb1d}'..='\u{fb4f}' => "Hebrew",
+        '\u{0600}'..='\u{06ff}'
+        | '\u{0750}'..='\u{077f}'
+        | '\u{08a0}'..='\u{08ff}'
+        | '\u{fb50}'..='\u{fdff}'
+        | '\u{fe70}'..='\u{feff}' => "Arabic",
+        '\u{0900}'..='\u{097f}' => "Devanagari",
+        '\u{0980}'..='\u{09ff}' => "Bengali",
+        '\u{0a00}'..='\u{0a7f}' => "Gurmukhi",
+        '\u{0a80}'..='\u{0aff}' => "Gujarati",
+        '\u{0b80}'..='\u{0bff}' => "Tamil",
+        '\u{0c00}'..='\u{0c7f}' => "Telugu",
+        '\u{0c80}'..='\u{0cff}' => "Kannada",
+        '\u{0d00}'..='\u{0d7f}' => "Malayalam",
+        '\u{0e00}'..='\u{0e7f}' => "Thai",
+        '\u{10a0}'..='\u{10ff}' => "Georgian",
+        '\u{1200}'..='\u{137f}' => "Ethiopic",
+        '\u{3040}'..='\u{30ff}' => "JP",
+        '\u{ac00}'..='\u{d7af}' => "KR",
+        '\u{4e00}'..='\u{9fff}' => "CJK",
+        _ => return None,
+    };
+    Some(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arabic_joining() {
+        // kaf, teh and beh join into initial, medial and final forms
+        assert_eq!(shape("كتب"), "\u{fedb}\u{fe98}\u{fe90}");
+        // lam and alef form a ligature, after which meem stands on its own
+        assert_eq!(shape("سلام"), "\u{feb3}\u{fefc}\u{fee1}");
+        // vowel marks do not break the joining
+        assert_eq!(shape("بَب"), "\u{fe91}\u{064e}\u{fe90}");
+        assert_eq!(shape("ب ب"), "\u{fe8f} \u{fe8f}");
+    }
+
+    #[test]
+    fn indic_reordering() {
+        // ki: the vowel sign is drawn before ka
+        assert_eq!(shape("कि"), "\u{093f}\u{0915}");
+        // the vowel sign goes before the whole conjunct of sa, virama and ta
+        assert_eq!(shape("स्ति"), "\u{093f}\u{0938}\u{094d}\u{0924}");
+        // Bengali o is split around its consonant
+        assert_eq!(shape("কো"), "\u{09c7}\u{0995}\u{09be}");
+        assert_eq!(shape("plain"), "plain");
+    }
+}
@@ -1,21 +1,113 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::hash::Hash;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
+use itertools::Itertools;
 use strum::IntoEnumIterator;
 
-use crate::ast::{AbstractElement, ElementType};
+use crate::ast::{AbstractElement, DeckSettings, ElementType};
 use crate::layout::SizeSpec;
-use crate::{SLIDE_HEIGHT, SLIDE_WIDTH};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum PropertyValue {
     Number(u32),
-    // Size(u32),
+    /// A number with a fractional part, like `0.5`.
+    Decimal(f32),
+    Length(Length),
     String(String),
     Boolean(bool),
     Colour(u8, u8, u8),
     SizeSpec(SizeSpec),
+    List(Vec<PropertyValue>),
+}
+
+impl PropertyValue {
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            PropertyValue::Number(_) => "Number",
+            PropertyValue::Decimal(_) => "Decimal",
+            PropertyValue::Length(_) => "Length",
+            PropertyValue::String(_) => "String",
+            PropertyValue::Boolean(_) => "Boolean",
+            PropertyValue::Colour(..) => "Colour",
+            PropertyValue::SizeSpec(_) => "SizeSpec",
+            PropertyValue::List(_) => "List",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Px,
+    Pt,
+    Em,
+}
+
+impl Unit {
+    pub const fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Pt => "pt",
+            Unit::Em => "em",
+        }
+    }
+}
+
+/// A number with a unit suffix, like `24pt`. Lengths only exist while parsing: once a slide's
+/// style map is complete, they are converted to pixels by [`StyleMap::resolve_units`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Length {
+    pub value: f32,
+    pub unit: Unit,
+}
+
+impl Length {
+    pub fn parse(s: &str) -> Option<Self> {
+        [Unit::Px, Unit::Pt, Unit::Em].into_iter().find_map(|unit| {
+            let number = s.strip_suffix(unit.suffix())?;
+            if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                return None;
+            }
+
+            number
+                .parse::<f32>()
+                .ok()
+                .map(|value| Length { value, unit })
+        })
+    }
+
+    /// Points are converted using the slide's dpi, ems are relative to the slide's default text size.
+    pub fn to_pixels(self, dpi: u32, em_size: u32) -> u32 {
+        let pixels = match self.unit {
+            Unit::Px => self.value,
+            Unit::Pt => self.value * dpi as f32 / 72.0,
+            Unit::Em => self.value * em_size as f32,
+        };
+
+        pixels.round() as u32
+    }
+}
+
+impl Display for PropertyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyValue::Number(val) => write!(f, "{val}"),
+            PropertyValue::Decimal(val) => write!(f, "{val}"),
+            PropertyValue::Length(length) => write!(f, "{}{}", length.value, length.unit.suffix()),
+            PropertyValue::String(val) => write!(f, "{val}"),
+            PropertyValue::Boolean(val) => write!(f, "{val}"),
+            PropertyValue::Colour(r, g, b) => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+            PropertyValue::SizeSpec(spec) => write!(
+                f,
+                "<{};{}>",
+                spec.width.map_or(String::from("_"), |w| w.to_string()),
+                spec.height.map_or(String::from("_"), |h| h.to_string())
+            ),
+            PropertyValue::List(values) => write!(f, "[{}]", values.iter().join(", ")),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -30,29 +122,129 @@ impl StyleTarget {
         match self {
             StyleTarget::Named(..) => HashMap::new(),
             StyleTarget::Anonymous(el_type) => match el_type {
-                ElementType::Sized => HashMap::new(),
+                ElementType::Sized => HashMap::from([(
+                    String::from("size"),
+                    PropertyValue::SizeSpec(SizeSpec {
+                        width: None,
+                        height: None,
+                    }),
+                )]),
                 ElementType::Padding => {
                     HashMap::from([(String::from("amount"), PropertyValue::Number(12))])
                 }
-                ElementType::Row => {
-                    HashMap::from([(String::from("gap"), PropertyValue::Number(32))])
-                }
-                ElementType::Col => {
-                    HashMap::from([(String::from("gap"), PropertyValue::Number(32))])
-                }
+                ElementType::Row => HashMap::from([
+                    (String::from("gap"), PropertyValue::Number(32)),
+                    (
+                        String::from("sizing"),
+                        PropertyValue::String(String::from("equal")),
+                    ),
+                    (
+                        String::from("align"),
+                        PropertyValue::String(String::from("top")),
+                    ),
+                    (
+                        String::from("direction"),
+                        PropertyValue::String(String::from("ltr")),
+                    ),
+                ]),
+                ElementType::Col => HashMap::from([
+                    (String::from("gap"), PropertyValue::Number(32)),
+                    (
+                        String::from("sizing"),
+                        PropertyValue::String(String::from("equal")),
+                    ),
+                ]),
                 ElementType::Centre => HashMap::new(),
                 ElementType::Text => HashMap::from([
                     (String::from("size"), PropertyValue::Number(32)),
+                    (String::from("min-size"), PropertyValue::Number(8)),
+                    (String::from("max-size"), PropertyValue::Number(200)),
+                    (
+                        String::from("overflow"),
+                        PropertyValue::String(String::from("clip")),
+                    ),
                     (
                         String::from("font"),
                         PropertyValue::String(String::from("Liberation Serif")),
                     ),
                     (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                    (
+                        String::from("bg"),
+                        PropertyValue::String(String::from("none")),
+                    ),
+                    (String::from("padding"), PropertyValue::Number(0)),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                    (String::from("markup"), PropertyValue::Boolean(true)),
+                    (
+                        String::from("align"),
+                        PropertyValue::String(String::from("left")),
+                    ),
+                    (
+                        String::from("direction"),
+                        PropertyValue::String(String::from("ltr")),
+                    ),
+                    (
+                        String::from("wrap"),
+                        PropertyValue::String(String::from("word")),
+                    ),
+                    (String::from("hyphenate"), PropertyValue::Boolean(false)),
+                    (
+                        String::from("weight"),
+                        PropertyValue::String(String::from("normal")),
+                    ),
+                    (
+                        String::from("style"),
+                        PropertyValue::String(String::from("normal")),
+                    ),
+                    (String::from("line-height"), PropertyValue::Decimal(1.0)),
+                    (String::from("tracking"), PropertyValue::Number(0)),
+                    (String::from("underline"), PropertyValue::Boolean(false)),
+                    (String::from("strike"), PropertyValue::Boolean(false)),
+                    (
+                        String::from("mono-font"),
+                        PropertyValue::String(String::from("Liberation Mono")),
+                    ),
                 ]),
                 ElementType::Code => HashMap::from([
                     (String::from("bg"), PropertyValue::Colour(30, 30, 30)),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                    (String::from("min-size"), PropertyValue::Number(8)),
+                    (String::from("max-size"), PropertyValue::Number(200)),
+                    (
+                        String::from("overflow"),
+                        PropertyValue::String(String::from("clip")),
+                    ),
                     (String::from("fill"), PropertyValue::Colour(255, 255, 255)),
                     (String::from("margin"), PropertyValue::Number(20)),
+                    (String::from("line-numbers"), PropertyValue::Boolean(false)),
+                    (String::from("first-line"), PropertyValue::Number(1)),
+                    (
+                        String::from("align"),
+                        PropertyValue::String(String::from("left")),
+                    ),
+                    (
+                        String::from("wrap"),
+                        PropertyValue::String(String::from("word")),
+                    ),
+                    (String::from("hyphenate"), PropertyValue::Boolean(false)),
+                    (
+                        String::from("weight"),
+                        PropertyValue::String(String::from("normal")),
+                    ),
+                    (
+                        String::from("style"),
+                        PropertyValue::String(String::from("normal")),
+                    ),
+                    (String::from("line-height"), PropertyValue::Decimal(1.0)),
+                    (String::from("diff"), PropertyValue::Boolean(false)),
+                    (
+                        String::from("diff-added"),
+                        PropertyValue::Colour(35, 75, 40),
+                    ),
+                    (
+                        String::from("diff-removed"),
+                        PropertyValue::Colour(95, 35, 35),
+                    ),
                     (String::from("size"), PropertyValue::Number(32)),
                     (
                         String::from("font"),
@@ -63,14 +255,280 @@ impl StyleTarget {
                         PropertyValue::String(String::from("rs")),
                     ),
                 ]),
-                ElementType::Image => HashMap::new(),
+                ElementType::Image => HashMap::from([
+                    (
+                        String::from("fit"),
+                        PropertyValue::String(String::from("contain")),
+                    ),
+                    (
+                        String::from("scaling"),
+                        PropertyValue::String(String::from("linear")),
+                    ),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                    (String::from("circle"), PropertyValue::Boolean(false)),
+                ]),
+                ElementType::List => HashMap::from([
+                    (String::from("gap"), PropertyValue::Number(16)),
+                    (String::from("indent"), PropertyValue::Number(64)),
+                    (
+                        String::from("numbering"),
+                        PropertyValue::String(String::from("1.")),
+                    ),
+                    (String::from("size"), PropertyValue::Number(32)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Math => HashMap::from([
+                    (String::from("size"), PropertyValue::Number(40)),
+                    (
+                        String::from("font"),
+                        PropertyValue::List(vec![
+                            PropertyValue::String(String::from("STIX Two Math")),
+                            PropertyValue::String(String::from("Latin Modern Math")),
+                            PropertyValue::String(String::from("DejaVu Serif")),
+                            PropertyValue::String(String::from("serif")),
+                        ]),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Video => HashMap::new(),
+                ElementType::Rect => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(200, 200, 200)),
+                    (String::from("stroke"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("stroke-width"), PropertyValue::Number(0)),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                ]),
+                ElementType::Ellipse | ElementType::Circle => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(200, 200, 200)),
+                    (String::from("stroke"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("stroke-width"), PropertyValue::Number(0)),
+                ]),
+                ElementType::BarChart => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(70, 110, 160)),
+                    (String::from("axis"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("gap"), PropertyValue::Number(24)),
+                    (String::from("size"), PropertyValue::Number(24)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("text-fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::LineChart => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(70, 110, 160)),
+                    (String::from("stroke-width"), PropertyValue::Number(4)),
+                    (String::from("axis"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("size"), PropertyValue::Number(24)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("text-fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Plot => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(70, 110, 160)),
+                    (String::from("stroke-width"), PropertyValue::Number(4)),
+                    (
+                        String::from("mode"),
+                        PropertyValue::String(String::from("line")),
+                    ),
+                    (String::from("axis"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("size"), PropertyValue::Number(24)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("text-fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::PieChart => HashMap::from([
+                    (
+                        String::from("fill"),
+                        PropertyValue::List(vec![
+                            PropertyValue::Colour(70, 110, 160),
+                            PropertyValue::Colour(220, 130, 50),
+                            PropertyValue::Colour(90, 160, 90),
+                            PropertyValue::Colour(200, 70, 70),
+                            PropertyValue::Colour(140, 110, 180),
+                            PropertyValue::Colour(150, 150, 150),
+                        ]),
+                    ),
+                    (String::from("legend"), PropertyValue::Boolean(true)),
+                    (String::from("size"), PropertyValue::Number(24)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("text-fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Line | ElementType::Arrow => HashMap::from([
+                    (String::from("stroke"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("stroke-width"), PropertyValue::Number(4)),
+                    (String::from("head"), PropertyValue::Number(20)),
+                ]),
+                ElementType::Spacer => HashMap::new(),
+                ElementType::Rotate => HashMap::new(),
+                ElementType::Opacity => HashMap::new(),
+                ElementType::Place => HashMap::new(),
+                ElementType::Flow => HashMap::from([
+                    (String::from("gap"), PropertyValue::Number(16)),
+                    (String::from("line-gap"), PropertyValue::Number(16)),
+                ]),
+                ElementType::Shadow => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                    (String::from("opacity"), PropertyValue::Decimal(0.4)),
+                    (String::from("blur"), PropertyValue::Number(16)),
+                    (String::from("offset-x"), PropertyValue::Number(8)),
+                    (String::from("offset-y"), PropertyValue::Number(8)),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                ]),
+                ElementType::Frame => HashMap::from([
+                    (String::from("stroke"), PropertyValue::Colour(60, 60, 60)),
+                    (String::from("width"), PropertyValue::Number(4)),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                    (String::from("gap"), PropertyValue::Number(0)),
+                ]),
+                ElementType::Audio => {
+                    HashMap::from([(String::from("autoplay"), PropertyValue::Boolean(true))])
+                }
+                ElementType::Stack => HashMap::new(),
+                ElementType::H1 | ElementType::H2 | ElementType::H3 => HashMap::from([
+                    (
+                        String::from("size"),
+                        PropertyValue::Number(match el_type {
+                            ElementType::H1 => 72,
+                            ElementType::H2 => 56,
+                            _ => 44,
+                        }),
+                    ),
+                    (String::from("min-size"), PropertyValue::Number(8)),
+                    (String::from("max-size"), PropertyValue::Number(200)),
+                    (
+                        String::from("overflow"),
+                        PropertyValue::String(String::from("clip")),
+                    ),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Sans")),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                    (
+                        String::from("bg"),
+                        PropertyValue::String(String::from("none")),
+                    ),
+                    (String::from("padding"), PropertyValue::Number(0)),
+                    (String::from("radius"), PropertyValue::Number(0)),
+                    (String::from("markup"), PropertyValue::Boolean(true)),
+                    (
+                        String::from("align"),
+                        PropertyValue::String(String::from("left")),
+                    ),
+                    (
+                        String::from("direction"),
+                        PropertyValue::String(String::from("ltr")),
+                    ),
+                    (
+                        String::from("wrap"),
+                        PropertyValue::String(String::from("word")),
+                    ),
+                    (String::from("hyphenate"), PropertyValue::Boolean(false)),
+                    (
+                        String::from("weight"),
+                        PropertyValue::String(String::from("normal")),
+                    ),
+                    (
+                        String::from("style"),
+                        PropertyValue::String(String::from("normal")),
+                    ),
+                    (String::from("line-height"), PropertyValue::Decimal(1.0)),
+                    (String::from("tracking"), PropertyValue::Number(0)),
+                    (String::from("underline"), PropertyValue::Boolean(false)),
+                    (String::from("strike"), PropertyValue::Boolean(false)),
+                    (
+                        String::from("mono-font"),
+                        PropertyValue::String(String::from("Liberation Mono")),
+                    ),
+                ]),
+                ElementType::Link => HashMap::from([
+                    (String::from("size"), PropertyValue::Number(32)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(30, 90, 200)),
+                    (String::from("underline"), PropertyValue::Boolean(true)),
+                ]),
+                ElementType::Icon => HashMap::from([
+                    (String::from("size"), PropertyValue::Number(32)),
+                    (
+                        String::from("font"),
+                        PropertyValue::List(vec![
+                            PropertyValue::String(String::from("Font Awesome 6 Free")),
+                            PropertyValue::String(String::from("Font Awesome 6 Brands")),
+                            PropertyValue::String(String::from("Font Awesome 5 Free")),
+                            PropertyValue::String(String::from("Font Awesome 5 Brands")),
+                        ]),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Progress => HashMap::from([
+                    (String::from("fill"), PropertyValue::Colour(70, 110, 160)),
+                    (
+                        String::from("bg"),
+                        PropertyValue::String(String::from("none")),
+                    ),
+                    (String::from("thickness"), PropertyValue::Number(8)),
+                ]),
+                ElementType::SlideNumber => HashMap::from([
+                    (
+                        String::from("format"),
+                        PropertyValue::String(String::from("$slide / $total")),
+                    ),
+                    (String::from("size"), PropertyValue::Number(24)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Clock => HashMap::from([
+                    (
+                        String::from("format"),
+                        PropertyValue::String(String::from("%H:%M")),
+                    ),
+                    (String::from("size"), PropertyValue::Number(24)),
+                    (
+                        String::from("font"),
+                        PropertyValue::String(String::from("Liberation Serif")),
+                    ),
+                    (String::from("fill"), PropertyValue::Colour(0, 0, 0)),
+                ]),
+                ElementType::Grid => HashMap::from([
+                    (String::from("row-gap"), PropertyValue::Number(32)),
+                    (String::from("col-gap"), PropertyValue::Number(32)),
+                    (String::from("widths"), PropertyValue::List(Vec::new())),
+                    (String::from("heights"), PropertyValue::List(Vec::new())),
+                ]),
                 ElementType::ElNone => HashMap::new(),
             },
             StyleTarget::Slide => HashMap::from([
-                (String::from("width"), PropertyValue::Number(SLIDE_WIDTH)),
-                (String::from("height"), PropertyValue::Number(SLIDE_HEIGHT)),
+                (
+                    String::from("width"),
+                    PropertyValue::Number(DeckSettings::default().width),
+                ),
+                (
+                    String::from("height"),
+                    PropertyValue::Number(DeckSettings::default().height),
+                ),
                 (String::from("margin"), PropertyValue::Number(64)),
                 (String::from("bg"), PropertyValue::Colour(235, 218, 199)),
+                (String::from("dpi"), PropertyValue::Number(96)),
+                (String::from("header"), PropertyValue::Boolean(true)),
+                (String::from("footer"), PropertyValue::Boolean(true)),
+                (String::from("header-height"), PropertyValue::Number(64)),
+                (String::from("footer-height"), PropertyValue::Number(64)),
             ]),
         }
     }
@@ -88,6 +546,87 @@ pub struct StyleMap {
     styles: HashMap<StyleTarget, HashMap<String, PropertyValue>>,
 }
 
+/// The properties that are sizes in pixels, as opposed to counts, durations and the like.
+const PIXEL_PROPERTIES: &[&str] = &[
+    "amount",
+    "blur",
+    "border-width",
+    "col-gap",
+    "enter-distance",
+    "footer-height",
+    "gap",
+    "gap-after",
+    "gap-before",
+    "head",
+    "header-height",
+    "height",
+    "indent",
+    "line-gap",
+    "margin",
+    "margin-bottom",
+    "margin-left",
+    "margin-right",
+    "margin-top",
+    "max-size",
+    "min-size",
+    "offset-x",
+    "offset-y",
+    "padding",
+    "radius",
+    "row-gap",
+    "size",
+    "stroke-width",
+    "thickness",
+    "tracking",
+    "width",
+];
+
+/// The properties that take one of a few words, with those words. A typo in them is reported
+/// when the style is parsed, see [`check_keyword`], rather than when the slide is drawn.
+const KEYWORD_PROPERTIES: &[(&str, &[&str])] = &[
+    (
+        "align",
+        &["left", "centre", "right", "justify", "top", "baseline"],
+    ),
+    (
+        "anchor",
+        &[
+            "top-left",
+            "top",
+            "top-right",
+            "left",
+            "centre",
+            "right",
+            "bottom-left",
+            "bottom",
+            "bottom-right",
+        ],
+    ),
+    (
+        "enter-easing",
+        &["linear", "ease-in", "ease-out", "ease-in-out"],
+    ),
+    ("enter-from", &["left", "right", "top", "bottom"]),
+    ("fit", &["contain", "cover", "crop", "stretch"]),
+    ("scaling", &["nearest", "linear", "best"]),
+    ("style", &["normal", "italic", "oblique"]),
+    (
+        "weight",
+        &[
+            "thin",
+            "extra-light",
+            "light",
+            "normal",
+            "medium",
+            "semibold",
+            "bold",
+            "extra-bold",
+            "black",
+        ],
+    ),
+    ("wrap", &["word", "char", "none"]),
+];
+
 impl StyleMap {
     pub fn new() -> Self {
         Self {
@@ -99,18 +638,142 @@ impl StyleMap {
         self.styles.insert(target, properties);
     }
 
+    /// Adds the properties of `other` that are not set in this map yet. `other` is expected to
+    /// contain the default styles, which it may have changed, like [`StyleMap::for_deck`] does.
     pub fn fill_in(&mut self, other: Self) {
         for (target, properties) in other.styles {
-            let existing_styles = self
-                .styles
-                .entry(target.clone())
-                .or_insert(target.default_style());
+            let existing_styles = self.styles.entry(target).or_default();
             for (prop_name, prop_value) in properties {
                 existing_styles.entry(prop_name).or_insert(prop_value);
             }
         }
     }
 
+    /// Converts every [`Length`] in the map to a plain number of pixels. This should be called
+    /// once all styles (including defaults) are present, because it depends on the slide's `dpi`
+    /// and on the default text size, which is what `em` is relative to.
+    pub fn resolve_units(&mut self) {
+        let dpi = self
+            .styles
+            .get(&StyleTarget::Slide)
+            .map_or(96, |styles| extract_number(styles, "dpi"));
+
+        let default_em_size = extract_number(
+            &StyleTarget::Anonymous(ElementType::Text).default_style(),
+            "size",
+        );
+        let em_size = match self
+            .styles
+            .get(&StyleTarget::Anonymous(ElementType::Text))
+            .and_then(|styles| styles.get("size"))
+        {
+            Some(PropertyValue::Number(size)) => *size,
+            Some(PropertyValue::Length(length)) => length.to_pixels(dpi, default_em_size),
+            _ => default_em_size,
+        };
+
+        fn resolve(value: &mut PropertyValue, dpi: u32, em_size: u32) {
+            match value {
+                PropertyValue::Length(length) => {
+                    *value = PropertyValue::Number(length.to_pixels(dpi, em_size))
+                }
+                PropertyValue::List(values) => {
+                    values.iter_mut().for_each(|v| resolve(v, dpi, em_size))
+                }
+                _ => {}
+            }
+        }
+
+        for value in self
+            .styles
+            .values_mut()
+            .flat_map(|styles| styles.values_mut())
+        {
+            resolve(value, dpi, em_size);
+        }
+    }
+
+    /// Multiplies every size in pixels in the map by a factor, as for rendering a presentation at
+    /// a higher resolution. Like [`Self::resolve_units`], this should be called once all styles
+    /// are present.
+    pub fn scale(&mut self, factor: f32) {
+        fn scale_value(value: &mut PropertyValue, factor: f32) {
+            match value {
+                PropertyValue::Number(pixels) => *pixels = (*pixels as f32 * factor).round() as u32,
+                PropertyValue::Decimal(pixels) => *pixels *= factor,
+                PropertyValue::SizeSpec(SizeSpec { width, height }) => {
+                    for pixels in [width, height].into_iter().flatten() {
+                        *pixels = (*pixels as f32 * factor).round() as u32;
+                    }
+                }
+                PropertyValue::List(values) => values
+                    .iter_mut()
+                    .for_each(|value| scale_value(value, factor)),
+                _ => {}
+            }
+        }
+
+        if factor == 1.0 {
+            return;
+        }
+        for (property, value) in self
+            .styles
+            .values_mut()
+            .flat_map(|styles| styles.iter_mut())
+        {
+            if PIXEL_PROPERTIES.contains(&property.as_str()) {
+                scale_value(value, factor);
+            }
+        }
+    }
+
+    /// Replaces every colour in the map, also those in lists like gradients, by the colour that
+    /// a palette has for it.
+    pub fn recolour(&mut self, palette: &Palette) {
+        fn recolour_value(value: &mut PropertyValue, palette: &Palette) {
+            match value {
+                PropertyValue::Colour(r, g, b) => {
+                    let (r, g, b) = palette.colour_for((*r, *g, *b));
+                    *value = PropertyValue::Colour(r, g, b);
+                }
+                PropertyValue::List(values) => values
+                    .iter_mut()
+                    .for_each(|value| recolour_value(value, palette)),
+                _ => {}
+            }
+        }
+
+        for value in self
+            .styles
+            .values_mut()
+            .flat_map(|styles| styles.values_mut())
+        {
+            recolour_value(value, palette);
+        }
+    }
+
+    /// Looks up the styles that apply to an element. Styles cascade from the element's name,
+    /// to the anonymous target of its type, to the default style of that type; so a named
+    /// text element only needs to specify the properties in which it differs from other text.
+    pub fn styles_for_element(&self, elem: &AbstractElement) -> HashMap<String, PropertyValue> {
+        let anonymous_target = StyleTarget::Anonymous(elem.el_type());
+        let mut styles = anonymous_target.default_style();
+
+        if let Some(anonymous_styles) = self.styles.get(&anonymous_target) {
+            styles.extend(anonymous_styles.clone());
+        }
+
+        if let Some(named_styles) = elem
+            .name()
+            .as_ref()
+            .and_then(|name| self.styles.get(&StyleTarget::Named(name.to_owned())))
+        {
+            styles.extend(named_styles.clone());
+        }
+
+        styles
+    }
+
     pub fn styles_for_target(
         &self,
         target: &StyleTarget,
@@ -119,6 +782,26 @@ impl StyleMap {
     }
 }
 
+impl StyleMap {
+    /// The default styles, but with the slide dimensions and the scaling of images taken from
+    /// the deck preamble.
+    pub fn for_deck(deck: &DeckSettings) -> Self {
+        let mut style_map = Self::default();
+        let slide_styles = style_map.styles.get_mut(&StyleTarget::Slide).unwrap();
+        slide_styles.insert(String::from("width"), PropertyValue::Number(deck.width));
+        slide_styles.insert(String::from("height"), PropertyValue::Number(deck.height));
+        let image_styles = style_map
+            .styles
+            .get_mut(&StyleTarget::Anonymous(ElementType::Image))
+            .unwrap();
+        image_styles.insert(
+            String::from("scaling"),
+            PropertyValue::String(deck.image_scaling.clone()),
+        );
+        style_map
+    }
+}
+
 impl Default for StyleMap {
     fn default() -> Self {
         let mut style_map = StyleMap::new();
@@ -145,16 +828,25 @@ pub fn extract_number<S: Into<String> + Display>(
         .unwrap_or_else(|| panic!("Property {property} was not found in style."))
     {
         PropertyValue::Number(val) => *val,
-        PropertyValue::String(_) => panic!("Property {property} was found, but is of type String"),
-        PropertyValue::Boolean(_) => {
-            panic!("Property {property} was found, but is of type Boolean")
-        }
-        PropertyValue::Colour(..) => {
-            panic!("Property {property} was found, but is of type Colour")
-        }
-        PropertyValue::SizeSpec(_) => {
-            panic!("Property {property} was found, but is of type SizeSpec")
-        }
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+/// Extracts a number that does not have to be set, like the properties that any element can
+/// have, which are not in the default styles.
+pub fn extract_optional_number<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Option<u32> {
+    match map.get(&property.to_string())? {
+        PropertyValue::Number(val) => Some(*val),
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
     }
 }
 
@@ -166,17 +858,11 @@ pub fn extract_string<S: Into<String> + Display>(
         .get(&property.to_string())
         .unwrap_or_else(|| panic!("Property {property} was not found in style."))
     {
-        PropertyValue::Number(_) => panic!("Property {property} was found, but is of type Number"),
         PropertyValue::String(val) => val.to_owned(),
-        PropertyValue::Boolean(_) => {
-            panic!("Property {property} was found, but is of type Boolean")
-        }
-        PropertyValue::Colour(..) => {
-            panic!("Property {property} was found, but is of type Colour")
-        }
-        PropertyValue::SizeSpec(_) => {
-            panic!("Property {property} was found, but is of type SizeSpec")
-        }
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
     }
 }
 
@@ -188,15 +874,11 @@ pub fn extract_boolean<S: Into<String> + Display>(
         .get(&property.to_string())
         .unwrap_or_else(|| panic!("Property {property} was not found in style."))
     {
-        PropertyValue::Number(_) => panic!("Property {property} was found, but is of type Number"),
-        PropertyValue::String(_) => panic!("Property {property} was found, but is of type String"),
         PropertyValue::Boolean(val) => *val,
-        PropertyValue::Colour(..) => {
-            panic!("Property {property} was found, but is of type Colour")
-        }
-        PropertyValue::SizeSpec(_) => {
-            panic!("Property {property} was found, but is of type SizeSpec")
-        }
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
     }
 }
 
@@ -208,15 +890,29 @@ pub fn extract_colour<S: Into<String> + Display>(
         .get(&property.to_string())
         .unwrap_or_else(|| panic!("Property {property} was not found in style."))
     {
-        PropertyValue::Number(_) => panic!("Property {property} was found, but is of type Number"),
-        PropertyValue::String(_) => panic!("Property {property} was found, but is of type String"),
-        PropertyValue::Boolean(_) => {
-            panic!("Property {property} was found, but is of type Boolean")
-        }
         PropertyValue::Colour(r, g, b) => (*r, *g, *b),
-        PropertyValue::SizeSpec(_) => {
-            panic!("Property {property} was found, but is of type SizeSpec")
-        }
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+/// Extracts a colour that can also be turned off with `none`, like the fill of a shape.
+pub fn extract_optional_colour<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Option<(u8, u8, u8)> {
+    match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::Colour(r, g, b) => Some((*r, *g, *b)),
+        PropertyValue::String(val) if val == "none" => None,
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
     }
 }
 
@@ -228,14 +924,327 @@ pub fn extract_size_spec<S: Into<String> + Display>(
         .get(&property.to_string())
         .unwrap_or_else(|| panic!("Property {property} was not found in style."))
     {
-        PropertyValue::Number(_) => panic!("Property {property} was found, but is of type Number"),
-        PropertyValue::String(_) => panic!("Property {property} was found, but is of type String"),
-        PropertyValue::Boolean(_) => {
-            panic!("Property {property} was found, but is of type Boolean")
+        PropertyValue::SizeSpec(spec) => *spec,
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+pub fn extract_list<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Vec<PropertyValue> {
+    match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::List(values) => values.clone(),
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+pub fn extract_number_list<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Vec<u32> {
+    extract_list(map, property.to_string())
+        .iter()
+        .map(|value| match value {
+            PropertyValue::Number(val) => *val,
+            other => panic!(
+                "Property {property} contains a value of type {}, expected Number",
+                other.type_name()
+            ),
+        })
+        .collect()
+}
+
+/// Extracts a property that is either a single string or a list of strings, like a font
+/// fallback chain.
+/// Extracts line numbers, which are given as a list of single lines and ranges of them, like
+/// `[3, 7-9]`, or as a single line.
+pub fn extract_line_ranges<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Vec<RangeInclusive<usize>> {
+    let values = match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::List(values) => values.clone(),
+        value => vec![value.clone()],
+    };
+    values
+        .iter()
+        .map(|value| match value {
+            PropertyValue::Number(line) => *line as usize..=*line as usize,
+            PropertyValue::String(range) => range
+                .split_once('-')
+                .and_then(|(first, last)| Some(first.parse().ok()?..=last.parse().ok()?))
+                .unwrap_or_else(|| {
+                    panic!("Property {property} contains {range}, expected a line or a range of lines like 7-9")
+                }),
+            other => panic!(
+                "Property {property} contains a value of type {}, expected a line or a range of lines",
+                other.type_name()
+            ),
+        })
+        .collect()
+}
+
+pub fn extract_string_list<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Vec<String> {
+    match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::String(val) => vec![val.to_owned()],
+        PropertyValue::List(values) => values
+            .iter()
+            .map(|value| match value {
+                PropertyValue::String(val) => val.to_owned(),
+                other => panic!(
+                    "Property {property} contains a value of type {}, expected String",
+                    other.type_name()
+                ),
+            })
+            .collect(),
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+/// Extracts a fraction like an opacity, which can also be written as the whole number 0 or 1.
+pub fn extract_decimal<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> f32 {
+    match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::Decimal(val) => *val,
+        PropertyValue::Number(val) => *val as f32,
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+/// Extracts a property that is either a single colour or a list of colours, like the colour
+/// cycle of a chart.
+pub fn extract_colour_list<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Vec<(u8, u8, u8)> {
+    match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::Colour(r, g, b) => vec![(*r, *g, *b)],
+        PropertyValue::List(values) => values
+            .iter()
+            .map(|value| match value {
+                PropertyValue::Colour(r, g, b) => (*r, *g, *b),
+                other => panic!(
+                    "Property {property} contains a value of type {}, expected Colour",
+                    other.type_name()
+                ),
+            })
+            .collect(),
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+/// What a background or a shape is filled with: a single colour, a linear gradient between
+/// two colours, written as a list like `[#fff, #000, 90]`, or an image that is repeated like
+/// tiles, written as its path or a list like `["paper.png", 0.5]`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Solid((u8, u8, u8)),
+    /// The angle is in degrees, clockwise: 0 runs from left to right and 90 from top to bottom.
+    Gradient {
+        from: (u8, u8, u8),
+        to: (u8, u8, u8),
+        angle: u32,
+    },
+    /// The tiles start at the top left corner of what is filled, and are the size of the image
+    /// times the scale.
+    Pattern {
+        image: PathBuf,
+        scale: f32,
+    },
+}
+
+impl Paint {
+    /// The colour at `t` along the gradient, from 0 at its start to 1 at its end. Patterns
+    /// count as white, as their colours are not known before their image is loaded.
+    pub fn colour_at(&self, t: f32) -> (u8, u8, u8) {
+        match *self {
+            Paint::Solid(colour) => colour,
+            Paint::Gradient { from, to, .. } => {
+                let t = t.clamp(0.0, 1.0);
+                let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+                (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+            }
+            Paint::Pattern { .. } => (255, 255, 255),
         }
-        PropertyValue::Colour(..) => {
-            panic!("Property {property} was found, but is of type Colour")
+    }
+}
+
+/// The colours of a variant of a presentation, like a dark one for projection, as they differ
+/// from the colours of the presentation itself.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Palette {
+    /// Colours that are replaced by other colours.
+    pub swaps: HashMap<(u8, u8, u8), (u8, u8, u8)>,
+    /// Whether the colours that are not swapped are made as light as they were dark, keeping
+    /// their hue.
+    pub invert: bool,
+}
+
+impl Palette {
+    /// The palette of the `dark` variant when a presentation does not define one.
+    pub fn dark() -> Self {
+        Self {
+            swaps: HashMap::new(),
+            invert: true,
+        }
+    }
+
+    pub fn colour_for(&self, colour: (u8, u8, u8)) -> (u8, u8, u8) {
+        if let Some(swapped) = self.swaps.get(&colour) {
+            return *swapped;
+        }
+        if !self.invert {
+            return colour;
+        }
+
+        // inverting every channel inverts the lightness, but also turns the hue around, which
+        // reflecting every channel between the largest and the smallest one turns back
+        let (r, g, b) = colour;
+        let extremes = r.max(g).max(b) as i32 + r.min(g).min(b) as i32;
+        let shift = |channel: u8| (channel as i32 + 255 - extremes) as u8;
+        (shift(r), shift(g), shift(b))
+    }
+}
+
+/// Extracts a colour, a gradient or a pattern, which can also be turned off with `none`. A
+/// gradient without an angle runs from top to bottom and a pattern without a scale has tiles
+/// the size of its image.
+pub fn extract_paint<S: Into<String> + Display>(
+    map: &HashMap<String, PropertyValue>,
+    property: S,
+) -> Option<Paint> {
+    match map
+        .get(&property.to_string())
+        .unwrap_or_else(|| panic!("Property {property} was not found in style."))
+    {
+        PropertyValue::Colour(r, g, b) => Some(Paint::Solid((*r, *g, *b))),
+        PropertyValue::String(val) if val == "none" => None,
+        PropertyValue::String(path) => Some(Paint::Pattern {
+            image: PathBuf::from(path),
+            scale: 1.0,
+        }),
+        PropertyValue::List(values) => match &values[..] {
+            [PropertyValue::String(path), PropertyValue::Decimal(scale)]
+                if *scale > 0.0 => {
+                Some(Paint::Pattern {
+                    image: PathBuf::from(path),
+                    scale: *scale,
+                })
+            }
+            [PropertyValue::String(path), PropertyValue::Number(scale)]
+                if *scale > 0 => {
+                Some(Paint::Pattern {
+                    image: PathBuf::from(path),
+                    scale: *scale as f32,
+                })
+            }
+            [PropertyValue::Colour(r1, g1, b1), PropertyValue::Colour(r2, g2, b2)] => {
+                Some(Paint::Gradient {
+                    from: (*r1, *g1, *b1),
+                    to: (*r2, *g2, *b2),
+                    angle: 90,
+                })
+            }
+            [PropertyValue::Colour(r1, g1, b1), PropertyValue::Colour(r2, g2, b2), PropertyValue::Number(angle)] => {
+                Some(Paint::Gradient {
+                    from: (*r1, *g1, *b1),
+                    to: (*r2, *g2, *b2),
+                    angle: angle % 360,
+                })
+            }
+            _ => panic!(
+                "Property {property} is a list, but a gradient is written as [start colour, end colour, angle] and a pattern as [image, scale]"
+            ),
+        },
+        other => panic!(
+            "Property {property} was found, but is of type {}",
+            other.type_name()
+        ),
+    }
+}
+
+/// Checks that a property that takes one of a few words, like `fit`, is set to one of them. If
+/// it is not, the words it can be are listed, as in `contain, cover, crop or stretch`.
+pub fn check_keyword(key: &str, value: &PropertyValue) -> Result<(), String> {
+    let Some((_, words)) = KEYWORD_PROPERTIES
+        .iter()
+        .find(|(property, _)| *property == key)
+    else {
+        return Ok(());
+    };
+    // a weight can also be given as a number
+    let number = matches!(value, PropertyValue::Number(_)) && key == "weight";
+    if number || words.contains(&value.to_string().as_str()) {
+        return Ok(());
+    }
+
+    let (last, rest) = words.split_last().unwrap();
+    let expected = format!("{} or {last}", rest.join(", "));
+    match key {
+        "weight" => Err(format!("a number from 1 to 1000, {expected}")),
+        _ => Err(expected),
+    }
+}
+
+/// Parses an aspect ratio like `16:9` or `4:3`.
+pub fn parse_aspect_ratio(ratio: &str) -> Option<(u32, u32)> {
+    let (width, height) = ratio.split_once(':')?;
+    let ratio = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (ratio.0 > 0 && ratio.1 > 0).then_some(ratio)
+}
+
+/// The size of a slide with an aspect ratio. A given width is kept and the height follows from
+/// it, or the other way around; if neither is given, the height is `base_height`. There is no
+/// size when the side that follows is too large to be a number of pixels.
+pub fn size_for_aspect_ratio(
+    (ratio_width, ratio_height): (u32, u32),
+    width: Option<u32>,
+    height: Option<u32>,
+    base_height: u32,
+) -> Option<(u32, u32)> {
+    let scale =
+        |side: u32, to: u32, from: u32| u32::try_from(side as u64 * to as u64 / from as u64).ok();
+    match (width, height) {
+        (Some(width), _) => Some((width, scale(width, ratio_height, ratio_width)?)),
+        (None, height) => {
+            let height = height.unwrap_or(base_height);
+            Some((scale(height, ratio_width, ratio_height)?, height))
         }
-        PropertyValue::SizeSpec(spec) => *spec,
     }
 }
@@ -0,0 +1,245 @@
+//! Builds SVG documents, for slides that are exported as vectors. What is drawn is added as
+//! markup, and what it refers to, like gradients and fonts, is collected in its definitions.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// An SVG document that is being written.
+pub struct Document {
+    width: u32,
+    height: u32,
+    defs: String,
+    body: String,
+    /// The fonts that are embedded, by the hash of their file, with the family they go by.
+    fonts: HashMap<usize, String>,
+    next_id: usize,
+}
+
+impl Document {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            defs: String::new(),
+            body: String::new(),
+            fonts: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Adds an element, or the start or the end of a group.
+    pub fn push(&mut self, markup: &str) {
+        self.body.push_str(markup);
+        self.body.push('\n');
+    }
+
+    fn id(&mut self, kind: &str) -> String {
+        self.next_id += 1;
+        format!("{kind}{}", self.next_id)
+    }
+
+    /// Defines a linear gradient from one point to another, and returns the fill that paints
+    /// with it.
+    pub fn linear_gradient(
+        &mut self,
+        from: (u8, u8, u8),
+        to: (u8, u8, u8),
+        start: (f32, f32),
+        end: (f32, f32),
+    ) -> String {
+        let id = self.id("gradient");
+        writeln!(
+            self.defs,
+            r#"<linearGradient id="{id}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}"><stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/></linearGradient>"#,
+            number(start.0),
+            number(start.1),
+            number(end.0),
+            number(end.1),
+            colour(from),
+            colour(to)
+        )
+        .unwrap();
+        format!("url(#{id})")
+    }
+
+    /// Defines a pattern of tiles with the given size, starting at `origin`, that show an
+    /// image, and returns the fill that paints with it.
+    pub fn pattern(&mut self, origin: (f32, f32), tile: (f32, f32), href: &str) -> String {
+        let id = self.id("pattern");
+        let (width, height) = (number(tile.0), number(tile.1));
+        writeln!(
+            self.defs,
+            r#"<pattern id="{id}" patternUnits="userSpaceOnUse" x="{}" y="{}" width="{width}" height="{height}"><image width="{width}" height="{height}" preserveAspectRatio="none" href="{href}"/></pattern>"#,
+            number(origin.0),
+            number(origin.1),
+        )
+        .unwrap();
+        format!("url(#{id})")
+    }
+
+    /// Defines a rectangle to clip to, and returns the clip path that clips to it.
+    pub fn clip(&mut self, area: (f32, f32, f32, f32)) -> String {
+        let id = self.id("clip");
+        writeln!(
+            self.defs,
+            r#"<clipPath id="{id}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+            number(area.0),
+            number(area.1),
+            number(area.2),
+            number(area.3)
+        )
+        .unwrap();
+        format!("url(#{id})")
+    }
+
+    /// Embeds a font file, once for every file, and returns the family that text set in it
+    /// goes by. The file is only read the first time, and not at all if it is not there.
+    pub fn font(&mut self, hash: usize, read: impl FnOnce() -> Option<Vec<u8>>) -> Option<String> {
+        if let Some(family) = self.fonts.get(&hash) {
+            return Some(family.clone());
+        }
+        let file = read()?;
+        let family = format!("folium-font-{}", self.fonts.len() + 1);
+        let (mime, format) = if file.starts_with(b"OTTO") {
+            ("font/otf", "opentype")
+        } else {
+            ("font/ttf", "truetype")
+        };
+        writeln!(
+            self.defs,
+            r#"<style>@font-face {{ font-family: "{family}"; src: url("{}") format("{format}"); }}</style>"#,
+            data_uri(mime, &file)
+        )
+        .unwrap();
+        self.fonts.insert(hash, family.clone());
+        Some(family)
+    }
+
+    /// Adds an image that covers an area.
+    pub fn image(&mut self, area: (f32, f32, f32, f32), href: &str) {
+        self.push(&format!(
+            r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="{href}"/>"#,
+            number(area.0),
+            number(area.1),
+            number(area.2),
+            number(area.3)
+        ));
+    }
+
+    pub fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n<defs>\n{2}</defs>\n{3}</svg>\n",
+            self.width, self.height, self.defs, self.body
+        )
+    }
+}
+
+/// A colour as it is written in SVG.
+pub fn colour((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// A coordinate, with no more digits than are needed.
+pub fn number(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    if rounded == rounded.trunc() {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{rounded}")
+    }
+}
+
+/// Text with the characters that have a meaning in markup escaped.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+/// The type of an image file, from its extension.
+pub fn image_mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        _ => "image/png",
+    }
+}
+
+/// A file of the given type, embedded in a URI.
+pub fn data_uri(mime: &str, bytes: &[u8]) -> String {
+    format!("data:{mime};base64,{}", base64(bytes))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |triple, (idx, byte)| {
+            triple | (*byte as u32) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * idx) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(escape(r#"<a & "b">"#), "&lt;a &amp; &quot;b&quot;&gt;");
+        assert_eq!(number(12.0), "12");
+        assert_eq!(number(0.125), "0.13");
+        assert_eq!(colour((255, 0, 16)), "#ff0010");
+    }
+
+    #[test]
+    fn documents() {
+        let mut document = Document::new(40, 30);
+        let fill = document.linear_gradient((0, 0, 0), (255, 255, 255), (0.0, 0.0), (40.0, 0.0));
+        document.push(&format!(r#"<rect width="40" height="30" fill="{fill}"/>"#));
+        let family = document.font(7, || Some(b"\0\x01\0\0".to_vec()));
+        assert!(family.is_some());
+        assert_eq!(document.font(7, || unreachable!()), family);
+        assert_eq!(document.font(8, || None), None);
+        let clip = document.clip((0.0, 0.0, 20.0, 10.0));
+        document.push(&format!(r#"<g clip-path="{clip}"></g>"#));
+
+        let svg = document.finish();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\""));
+        assert!(svg.contains(r#"<linearGradient id="gradient1""#));
+        assert!(svg.contains(r#"fill="url(#gradient1)""#));
+        assert_eq!(svg.matches("@font-face").count(), 1);
+        assert!(svg.contains(r#"<clipPath id="clip2"><rect x="0" y="0" width="20""#));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    io::Read,
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Videos are decoded by the `ffmpeg` and `ffprobe` command line tools, which need to be
+// installed separately. Frames are always decoded to tightly packed RGBA.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub frame_duration: Duration,
+}
+
+impl VideoInfo {
+    pub fn frame_size(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+pub fn probe(path: &Path) -> Result<VideoInfo, String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height,r_frame_rate"])
+        .args(["-of", "csv=p=0"])
+        .arg(path)
+        .output()
+        .map_err(|err| format!("could not run ffprobe: {err}"))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    // the output looks like `1920,1080,30000/1001`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields = stdout.trim().split(',').collect::<Vec<_>>();
+    let [width, height, frame_rate] = fields[..] else {
+        return Err(format!("unexpected ffprobe output {stdout:?}"));
+    };
+
+    let frame_rate = match frame_rate.split_once('/') {
+        Some((num, den)) => num.parse::<f64>().unwrap_or(0.0) / den.parse::<f64>().unwrap_or(1.0),
+        None => frame_rate.parse::<f64>().unwrap_or(0.0),
+    };
+
+    Ok(VideoInfo {
+        width: width.parse().map_err(|_| "invalid video width")?,
+        height: height.parse().map_err(|_| "invalid video height")?,
+        frame_duration: Duration::from_secs_f64(1.0 / frame_rate.max(1.0)),
+    })
+}
+
+fn decoder(path: &Path) -> Command {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"]);
+    command
+}
+
+pub fn first_frame(path: &Path, info: &VideoInfo) -> Result<Vec<u8>, String> {
+    let output = decoder(path)
+        .args(["-frames:v", "1"])
+        .output()
+        .map_err(|err| format!("could not run ffmpeg: {err}"))?;
+
+    if output.stdout.len() < info.frame_size() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(output.stdout[..info.frame_size()].to_vec())
+}
+
+/// Plays a video by decoding it in the background. Frames are handed out at the frame rate
+/// of the video; while the player is paused, the decoder is stalled by the full channel.
+pub struct VideoPlayer {
+    decoder: Child,
+    frames: Receiver<Vec<u8>>,
+    frame_duration: Duration,
+    next_frame: Instant,
+    paused: bool,
+    finished: bool,
+}
+
+impl VideoPlayer {
+    pub fn start(path: &Path, info: &VideoInfo) -> Result<Self, String> {
+        let mut decoder = decoder(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("could not run ffmpeg: {err}"))?;
+
+        let mut stdout = decoder.stdout.take().unwrap();
+        let frame_size = info.frame_size();
+        let (sender, frames) = mpsc::sync_channel(2);
+
+        thread::spawn(move || loop {
+            let mut frame = vec![0; frame_size];
+            if stdout.read_exact(&mut frame).is_err() || sender.send(frame).is_err() {
+                break;
+            }
+        });
+
+        Ok(Self {
+            decoder,
+            frames,
+            frame_duration: info.frame_duration,
+            next_frame: Instant::now(),
+            paused: false,
+            finished: false,
+        })
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.next_frame = Instant::now();
+    }
+
+    /// Returns the next frame if it is due.
+    pub fn poll(&mut self) -> Option<Vec<u8>> {
+        if self.paused || self.finished || Instant::now() < self.next_frame {
+            return None;
+        }
+
+        match self.frames.try_recv() {
+            Ok(frame) => {
+                self.next_frame += self.frame_duration;
+                Some(frame)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.finished = true;
+                None
+            }
+        }
+    }
+
+    /// How long the viewer can wait for events before the next frame should be shown,
+    /// or `None` if the video is not playing.
+    pub fn time_until_next_frame(&self) -> Option<Duration> {
+        if self.paused || self.finished {
+            None
+        } else {
+            Some(self.next_frame.saturating_duration_since(Instant::now()))
+        }
+    }
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        let _ = self.decoder.kill();
+        let _ = self.decoder.wait();
+    }
+}